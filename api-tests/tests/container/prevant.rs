@@ -1,4 +1,4 @@
-use crate::common::Service;
+use prevant_client::ServiceConfig;
 use reqwest::{Client, Response, StatusCode};
 use std::collections::HashMap;
 use testcontainers::{Container, Docker, Image, WaitForMessage};
@@ -48,7 +48,7 @@ impl Image for PREvant {
 
 pub async fn deploy_app<D>(
     prevant: &Container<'_, D, PREvant>,
-    services: &Vec<Service>,
+    services: &Vec<ServiceConfig>,
 ) -> Result<Uuid, Response>
 where
     D: Docker,
@@ -90,7 +90,7 @@ where
             "http://localhost:{}/api/apps/{}?replicateFrom={}",
             port, app_name, from_app_name
         ))
-        .json(&Vec::<Service>::new())
+        .json(&Vec::<ServiceConfig>::new())
         .send()
         .await
         .unwrap();
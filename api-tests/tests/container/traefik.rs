@@ -4,6 +4,8 @@ use std::time::Duration;
 use testcontainers::{Container, Docker, Image, WaitForMessage};
 use uuid::Uuid;
 
+/// Traefik labelled as reverse proxy of PREvant, built from `api-tests/traefik/Dockerfile` with
+/// `docker build -t prevant-test-traefik api-tests/traefik`.
 #[derive(Default)]
 pub struct Traefik;
 
@@ -13,7 +15,7 @@ impl Image for Traefik {
     type Volumes = HashMap<String, String>;
 
     fn descriptor(&self) -> String {
-        "traefik:v1.7-alpine".to_string()
+        "prevant-test-traefik".to_string()
     }
 
     fn wait_until_ready<D: Docker>(&self, container: &Container<D, Self>) {
@@ -1,10 +1,11 @@
 mod common;
 mod container;
 
-use crate::common::{docker, Service};
+use crate::common::docker;
 use crate::container::{
     delete_app, deploy_app, logs, make_request, replicate_app, PREvant, Traefik,
 };
+use prevant_client::ServiceConfig;
 use std::time::Duration;
 use testcontainers::Docker;
 
@@ -14,15 +15,9 @@ async fn should_deploy_nginx() {
     let traefik = docker.run(Traefik::default());
     let prevant = docker.run(PREvant::default());
 
-    let app_name = deploy_app(
-        &prevant,
-        &vec![Service::new(
-            String::from("nginx"),
-            String::from("nginx:alpine"),
-        )],
-    )
-    .await
-    .expect("Should be able to deploy app");
+    let app_name = deploy_app(&prevant, &vec![ServiceConfig::new("nginx", "nginx:alpine")])
+        .await
+        .expect("Should be able to deploy app");
 
     let mut i = 0;
     loop {
@@ -48,11 +43,8 @@ async fn should_replicate_mariadb_with_replicated_env() {
     let _traefik = docker.run(Traefik::default());
     let prevant = docker.run(PREvant::default());
 
-    let db_service = Service::new(String::from("db"), String::from("mariadb:10.3.17"))
-        .with_replicated_env(
-            String::from("MYSQL_RANDOM_ROOT_PASSWORD"),
-            String::from("yes"),
-        );
+    let db_service = ServiceConfig::new("db", "mariadb:10.3.17")
+        .with_replicated_env("MYSQL_RANDOM_ROOT_PASSWORD", "yes");
 
     let app_name = deploy_app(&prevant, &vec![db_service])
         .await
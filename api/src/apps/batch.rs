@@ -0,0 +1,402 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::routes::{spawn_with_options, CreateAppPayload, RunOptions};
+use crate::apps::{Apps, AppsError};
+use crate::auth::{AuthenticationError, User};
+use crate::config::Companion;
+use crate::http_result::{HttpApiError, HttpResult};
+use crate::models::service::Service;
+use crate::models::{AppName, AppStatusChangeId, ServiceConfig};
+use futures::stream::{self, StreamExt};
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{Responder, Response};
+use rocket::serde::json::{Json, Value};
+use rocket::State;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::Poll;
+
+pub fn apps_batch_routes() -> Vec<rocket::Route> {
+    rocket::routes![deploy_apps]
+}
+
+/// Deploys several apps with one request, e.g. for a nightly refresh of all branch apps. All
+/// deployments are parsed, planned, and checked against the image policy upfront, i.e. nothing
+/// will be deployed if one of them is invalid. Then, they are executed concurrently, at most
+/// `batchParallelism` at a time. Problems that only arise while deploying, e.g. freezes or an
+/// exhausted host capacity, are reported for each app.
+#[post("/apps:batch", format = "application/json", data = "<payload>")]
+async fn deploy_apps(
+    apps: &State<Arc<Apps>>,
+    payload: Json<Value>,
+    options: RunOptions,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<BatchResponse> {
+    let owner = user?.name().cloned();
+    let deployments =
+        BatchDeployment::parse_all(payload.into_inner(), apps.config().strict_payloads())?;
+
+    for deployment in &deployments {
+        apps.validate_deployment(
+            &deployment.app_name,
+            deployment.replicate_from.clone(),
+            &deployment.service_configs,
+            &deployment.user_defined_companions,
+            &deployment.skipped_companions,
+        )
+        .await
+        .map_err(|err| {
+            HttpApiError::from(err)
+                .with_context(&format!("Invalid deployment of {}", deployment.app_name))
+        })?;
+    }
+
+    let pending_report = deployments
+        .iter()
+        .map(|deployment| {
+            (
+                deployment.app_name.to_string(),
+                BatchDeploymentStatus::Pending {
+                    status_change: format!(
+                        "/api/apps/{}/status-changes/{}",
+                        deployment.app_name, deployment.status_id
+                    ),
+                },
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let parallelism = apps.config().batch_parallelism();
+    let apps = (**apps).clone();
+    let future = async move {
+        let results = stream::iter(deployments.iter().map(|deployment| {
+            apps.create_or_update(
+                &deployment.app_name,
+                &deployment.status_id,
+                deployment.replicate_from.clone(),
+                &deployment.service_configs,
+                &deployment.user_defined_companions,
+                &deployment.skipped_companions,
+                owner.clone(),
+            )
+        }))
+        .buffered(parallelism)
+        .collect::<Vec<_>>()
+        .await;
+
+        deployments
+            .iter()
+            .zip(results.into_iter())
+            .map(|(deployment, result)| {
+                (
+                    deployment.app_name.to_string(),
+                    BatchDeploymentStatus::from(result),
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    match spawn_with_options(options, future).await? {
+        Poll::Pending => Ok(BatchResponse::Pending(Json(pending_report))),
+        Poll::Ready(report) => Ok(BatchResponse::Ready(Json(report))),
+    }
+}
+
+struct BatchDeployment {
+    app_name: AppName,
+    status_id: AppStatusChangeId,
+    replicate_from: Option<AppName>,
+    service_configs: Vec<ServiceConfig>,
+    user_defined_companions: Vec<Companion>,
+    skipped_companions: Vec<String>,
+}
+
+impl BatchDeployment {
+    /// Parses a list of deployments, such as
+    /// `[{ "appName": "feature-a", "replicateFrom": "master", "services": [], "companions": {},
+    /// "skipCompanions": ["kafka"] }]`.
+    fn parse_all(value: Value, strict: bool) -> Result<Vec<Self>, HttpApiError> {
+        let values = match value {
+            Value::Array(values) => values,
+            _ => {
+                return Err(bad_request(String::from(
+                    "The payload must be a list of deployments.",
+                )))
+            }
+        };
+
+        let mut deployments: Vec<BatchDeployment> = Vec::with_capacity(values.len());
+        for (index, value) in values.into_iter().enumerate() {
+            let deployment = BatchDeployment::parse(value, strict).map_err(|detail| {
+                bad_request(format!("Invalid deployment at index {}: {}", index, detail))
+            })?;
+
+            if deployments
+                .iter()
+                .any(|d| d.app_name == deployment.app_name)
+            {
+                return Err(bad_request(format!(
+                    "The app {} must not be deployed more than once.",
+                    deployment.app_name
+                )));
+            }
+            deployments.push(deployment);
+        }
+
+        Ok(deployments)
+    }
+
+    fn parse(value: Value, strict: bool) -> Result<Self, String> {
+        let mut deployment = match value {
+            Value::Object(deployment) => deployment,
+            _ => return Err(String::from("The deployment must be an object.")),
+        };
+
+        let app_name = match deployment.remove("appName") {
+            Some(Value::String(app_name)) => {
+                AppName::from_str(&app_name).map_err(|err| err.to_string())?
+            }
+            _ => return Err(String::from("appName is a required string.")),
+        };
+
+        let replicate_from = match deployment.remove("replicateFrom") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(replicate_from)) => {
+                Some(AppName::from_str(&replicate_from).map_err(|err| err.to_string())?)
+            }
+            Some(_) => return Err(String::from("replicateFrom must be a string.")),
+        };
+
+        let skipped_companions = match deployment.remove("skipCompanions") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(names)) => names
+                .into_iter()
+                .map(|name| match name {
+                    Value::String(name) => Ok(name),
+                    _ => Err(String::from("skipCompanions must be a list of strings.")),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(String::from("skipCompanions must be a list of strings.")),
+        };
+
+        let (service_configs, user_defined_companions) =
+            CreateAppPayload::from_value(Value::Object(deployment), strict)
+                .map_err(|err| err.detail().cloned().unwrap_or_default())?
+                .into_parts();
+
+        Ok(BatchDeployment {
+            app_name,
+            status_id: AppStatusChangeId::new(),
+            replicate_from,
+            service_configs,
+            user_defined_companions,
+            skipped_companions,
+        })
+    }
+}
+
+fn bad_request(detail: String) -> HttpApiError {
+    HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+        .detail(detail)
+        .into()
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum BatchDeploymentStatus {
+    #[serde(rename_all = "camelCase")]
+    Pending {
+        status_change: String,
+    },
+    Deployed {
+        services: Vec<Service>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl From<Result<Vec<Service>, AppsError>> for BatchDeploymentStatus {
+    fn from(result: Result<Vec<Service>, AppsError>) -> Self {
+        match result {
+            Ok(services) => BatchDeploymentStatus::Deployed { services },
+            Err(err) => BatchDeploymentStatus::Failed {
+                error: err.to_string(),
+            },
+        }
+    }
+}
+
+enum BatchResponse {
+    Pending(Json<BTreeMap<String, BatchDeploymentStatus>>),
+    Ready(Json<BTreeMap<String, BatchDeploymentStatus>>),
+}
+
+impl<'r> Responder<'r, 'static> for BatchResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        match self {
+            BatchResponse::Pending(report) => Response::build_from(report.respond_to(request)?)
+                .status(Status::Accepted)
+                .ok(),
+            BatchResponse::Ready(report) => report.respond_to(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use rocket::http::ContentType;
+    use rocket::local::asynchronous::Client;
+
+    async fn client() -> Client {
+        let apps = Apps::new(Config::default(), Box::new(Dummy::new())).unwrap();
+        let rocket = rocket::build()
+            .manage(Arc::new(apps))
+            .mount("/api", apps_batch_routes());
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[tokio::test]
+    async fn should_deploy_several_apps() {
+        let client = client().await;
+
+        let response = client
+            .post("/api/apps:batch")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!([
+                    {
+                        "appName": "feature-a",
+                        "services": [ { "serviceName": "nginx", "image": "nginx:latest" } ]
+                    },
+                    {
+                        "appName": "feature-b",
+                        "replicateFrom": "master",
+                        "services": [ { "serviceName": "httpd", "image": "httpd:latest" } ]
+                    }
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+        let report = response.into_json::<Value>().await.unwrap();
+        assert_eq!(report["feature-a"]["status"], "deployed");
+        assert_eq!(
+            report["feature-a"]["services"][0]["name"],
+            serde_json::json!("nginx")
+        );
+        assert_eq!(report["feature-b"]["status"], "deployed");
+    }
+
+    #[tokio::test]
+    async fn should_not_deploy_anything_if_one_deployment_is_invalid() {
+        let client = client().await;
+
+        let response = client
+            .post("/api/apps:batch")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!([
+                    {
+                        "appName": "feature-a",
+                        "services": [ { "serviceName": "nginx", "image": "nginx:latest" } ]
+                    },
+                    {
+                        "services": [ { "serviceName": "httpd", "image": "httpd:latest" } ]
+                    }
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn should_not_deploy_anything_if_one_deployment_violates_image_policy() {
+        let config = crate::config_from_str!(
+            r#"
+            [images.policy]
+            forbiddenTags = [ 'latest' ]
+            "#
+        );
+        let apps = Arc::new(Apps::new(config, Box::new(Dummy::new())).unwrap());
+        let rocket = rocket::build()
+            .manage(apps.clone())
+            .mount("/api", apps_batch_routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client
+            .post("/api/apps:batch")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!([
+                    {
+                        "appName": "feature-a",
+                        "services": [ { "serviceName": "nginx", "image": "nginx:1.21" } ]
+                    },
+                    {
+                        "appName": "feature-b",
+                        "services": [ { "serviceName": "httpd", "image": "httpd:latest" } ]
+                    }
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Forbidden);
+        let problem = response.into_json::<Value>().await.unwrap();
+        assert!(problem["detail"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid deployment of feature-b"));
+        assert!(apps.get_apps().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_reject_duplicate_apps() {
+        let deployments = BatchDeployment::parse_all(
+            serde_json::json!([
+                { "appName": "feature-a", "services": [] },
+                { "appName": "feature-a", "services": [] }
+            ]),
+            false,
+        );
+
+        assert!(deployments.is_err());
+    }
+}
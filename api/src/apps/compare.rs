@@ -0,0 +1,265 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::service::Service;
+use crate::models::AppName;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The differences between the services of two apps, e.g. between a review app and master.
+/// Services without any difference are omitted.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppComparison {
+    app: String,
+    other_app: String,
+    services: Vec<ServiceDifference>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceDifference {
+    service_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Change>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<Change>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_commit: Option<Change>,
+    #[serde(skip_serializing_if = "EnvDifference::is_empty")]
+    env: EnvDifference,
+}
+
+/// A value that differs between both apps. `None` means that the app does not have such a
+/// service or that the value is unknown.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    app: Option<String>,
+    other_app: Option<String>,
+}
+
+/// Lists the names of the differing environment variables. The values are omitted because they
+/// might contain credentials.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EnvDifference {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    only_in_app: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    only_in_other_app: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    changed: Vec<String>,
+}
+
+impl EnvDifference {
+    fn new(env: &BTreeMap<&String, &str>, other_env: &BTreeMap<&String, &str>) -> Self {
+        let mut difference = EnvDifference::default();
+
+        for (key, value) in env {
+            match other_env.get(key) {
+                None => difference.only_in_app.push((*key).clone()),
+                Some(other_value) if other_value != value => {
+                    difference.changed.push((*key).clone())
+                }
+                Some(_) => {}
+            }
+        }
+        for key in other_env.keys() {
+            if !env.contains_key(key) {
+                difference.only_in_other_app.push((*key).clone());
+            }
+        }
+
+        difference
+    }
+
+    fn is_empty(&self) -> bool {
+        self.only_in_app.is_empty() && self.only_in_other_app.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Change {
+    fn of(app: Option<String>, other_app: Option<String>) -> Option<Self> {
+        if app == other_app {
+            None
+        } else {
+            Some(Change { app, other_app })
+        }
+    }
+}
+
+impl AppComparison {
+    pub fn new(
+        app: AppName,
+        services: &[Service],
+        other_app: AppName,
+        other_services: &[Service],
+    ) -> Self {
+        let service_names = services
+            .iter()
+            .chain(other_services.iter())
+            .map(|service| service.service_name())
+            .collect::<BTreeSet<_>>();
+
+        fn find<'a>(services: &'a [Service], service_name: &str) -> Option<&'a Service> {
+            services
+                .iter()
+                .find(|service| service.service_name() == service_name)
+        }
+
+        let differences = service_names
+            .into_iter()
+            .map(|service_name| {
+                ServiceDifference::new(
+                    service_name,
+                    find(services, service_name),
+                    find(other_services, service_name),
+                )
+            })
+            .filter(|difference| !difference.is_empty())
+            .collect();
+
+        AppComparison {
+            app: app.to_string(),
+            other_app: other_app.to_string(),
+            services: differences,
+        }
+    }
+}
+
+impl ServiceDifference {
+    fn new(service_name: &String, service: Option<&Service>, other: Option<&Service>) -> Self {
+        let image = |service: Option<&Service>| service.map(|s| s.image().to_string());
+        let version = |service: Option<&Service>| {
+            service
+                .and_then(|s| s.web_host_meta())
+                .and_then(|meta| meta.version())
+        };
+        let git_commit = |service: Option<&Service>| {
+            service
+                .and_then(|s| s.web_host_meta())
+                .and_then(|meta| meta.commit())
+        };
+        let env = |service: Option<&Service>| {
+            service
+                .and_then(|s| s.config().env())
+                .map(|env| {
+                    env.iter()
+                        .map(|variable| (variable.key(), variable.value().unsecure()))
+                        .collect::<BTreeMap<_, _>>()
+                })
+                .unwrap_or_default()
+        };
+
+        ServiceDifference {
+            service_name: service_name.clone(),
+            image: Change::of(image(service), image(other)),
+            version: Change::of(version(service), version(other)),
+            git_commit: Change::of(git_commit(service), git_commit(other)),
+            env: EnvDifference::new(&env(service), &env(other)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.image.is_none()
+            && self.version.is_none()
+            && self.git_commit.is_none()
+            && self.env.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ServiceBuilder, ServiceConfig};
+    use crate::sc;
+    use std::str::FromStr;
+
+    fn service(app_name: &str, config: ServiceConfig) -> Service {
+        ServiceBuilder::new()
+            .id(format!("{}-{}", app_name, config.service_name()))
+            .app_name(String::from(app_name))
+            .config(config)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_compare_images_and_env_of_services() {
+        let services = vec![
+            service("branch", sc!("service-a", "service-a:feature")),
+            service(
+                "branch",
+                sc!("service-b", labels = (), env = ("LOG_LEVEL" => "debug", "FEATURE" => "on"), volumes = ()),
+            ),
+            service("branch", sc!("service-c", "service-c:1.0")),
+        ];
+        let other_services = vec![
+            service("master", sc!("service-a", "service-a:latest")),
+            service(
+                "master",
+                sc!("service-b", labels = (), env = ("LOG_LEVEL" => "info", "LEGACY" => "on"), volumes = ()),
+            ),
+            service("master", sc!("service-c", "service-c:1.0")),
+            service("master", sc!("service-d", "service-d:1.0")),
+        ];
+
+        let comparison = AppComparison::new(
+            AppName::from_str("branch").unwrap(),
+            &services,
+            AppName::from_str("master").unwrap(),
+            &other_services,
+        );
+
+        assert_eq!(
+            serde_json::to_value(comparison).unwrap(),
+            serde_json::json!({
+                "app": "branch",
+                "otherApp": "master",
+                "services": [{
+                    "serviceName": "service-a",
+                    "image": {
+                        "app": "docker.io/library/service-a:feature",
+                        "otherApp": "docker.io/library/service-a:latest"
+                    }
+                }, {
+                    "serviceName": "service-b",
+                    "env": {
+                        "onlyInApp": [ "FEATURE" ],
+                        "onlyInOtherApp": [ "LEGACY" ],
+                        "changed": [ "LOG_LEVEL" ]
+                    }
+                }, {
+                    "serviceName": "service-d",
+                    "image": {
+                        "app": null,
+                        "otherApp": "docker.io/library/service-d:1.0"
+                    }
+                }]
+            })
+        );
+    }
+}
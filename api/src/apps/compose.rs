@@ -0,0 +1,327 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! Parses the subset of docker-compose (version 3) files that can be mapped to the service
+//! configurations of PREvant: the services with their `image`, `environment`, and `ports`. Files
+//! that declare `volumes` are refused because PREvant cannot mount paths or volumes of the host
+//! that the file has been written for. All other keys are ignored.
+
+use crate::http_result::HttpApiError;
+use crate::models::{Environment, EnvironmentVariable, Image, Port, ServiceConfig, HTTP_PORT_NAME};
+use http_api_problem::{HttpApiProblem, StatusCode};
+use secstr::SecUtf8;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    services: BTreeMap<String, ComposeService>,
+}
+
+#[derive(Deserialize)]
+struct ComposeService {
+    image: Option<String>,
+    environment: Option<ComposeEnvironment>,
+    #[serde(default)]
+    volumes: Vec<Value>,
+    #[serde(default)]
+    ports: Vec<ComposePort>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ComposeEnvironment {
+    List(Vec<String>),
+    Map(BTreeMap<String, Value>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ComposePort {
+    Long { target: u16 },
+    Short(Value),
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum ComposeError {
+    #[fail(display = "Invalid docker-compose file: {}", err)]
+    InvalidFile { err: String },
+    #[fail(display = "The service {} does not declare an image.", service_name)]
+    MissingImage { service_name: String },
+    #[fail(display = "The service {} has an invalid image: {}", service_name, err)]
+    InvalidImage { service_name: String, err: String },
+    #[fail(display = "The service {} has an invalid port: {}", service_name, port)]
+    InvalidPort { service_name: String, port: String },
+    #[fail(
+        display = "The service {} declares volumes, which are not supported because PREvant cannot mount paths or volumes of the docker-compose host. Provide the file contents through the volumes of the JSON payload instead.",
+        service_name
+    )]
+    UnsupportedVolumes { service_name: String },
+}
+
+/// Converts the services of the docker-compose file into service configurations.
+pub fn parse_compose_file(content: &str) -> Result<Vec<ServiceConfig>, ComposeError> {
+    let compose_file =
+        serde_yaml::from_str::<ComposeFile>(content).map_err(|err| ComposeError::InvalidFile {
+            err: err.to_string(),
+        })?;
+
+    let mut configs = Vec::with_capacity(compose_file.services.len());
+    for (service_name, service) in compose_file.services {
+        let image = service.image.ok_or_else(|| ComposeError::MissingImage {
+            service_name: service_name.clone(),
+        })?;
+        let image = Image::from_str(&image).map_err(|err| ComposeError::InvalidImage {
+            service_name: service_name.clone(),
+            err: err.to_string(),
+        })?;
+
+        let mut config = ServiceConfig::new(service_name.clone(), image);
+
+        if let Some(environment) = service.environment {
+            config.set_env(Some(environment.into_environment()));
+        }
+
+        if !service.volumes.is_empty() {
+            return Err(ComposeError::UnsupportedVolumes { service_name });
+        }
+
+        let mut ports = Vec::with_capacity(service.ports.len());
+        for port in service.ports {
+            let port = port.container_port(&service_name)?;
+            let name = if ports.is_empty() {
+                String::from(HTTP_PORT_NAME)
+            } else {
+                format!("port-{}", port)
+            };
+            ports.push(Port::new(name, port));
+        }
+        if !ports.is_empty() {
+            config.set_ports(ports);
+        }
+
+        configs.push(config);
+    }
+
+    Ok(configs)
+}
+
+impl ComposeEnvironment {
+    /// Variables without a value refer to the environment of the docker-compose host and are
+    /// skipped because that environment is not available to PREvant.
+    fn into_environment(self) -> Environment {
+        let variables = match self {
+            ComposeEnvironment::List(variables) => variables
+                .into_iter()
+                .filter_map(|variable| {
+                    let mut key_and_value = variable.splitn(2, '=');
+                    match (key_and_value.next(), key_and_value.next()) {
+                        (Some(key), Some(value)) => Some(EnvironmentVariable::new(
+                            String::from(key),
+                            SecUtf8::from(value),
+                        )),
+                        _ => None,
+                    }
+                })
+                .collect(),
+            ComposeEnvironment::Map(variables) => variables
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value,
+                        Value::Bool(value) => value.to_string(),
+                        Value::Number(value) => value.to_string(),
+                        _ => return None,
+                    };
+                    Some(EnvironmentVariable::new(key, SecUtf8::from(value)))
+                })
+                .collect(),
+        };
+
+        Environment::new(variables)
+    }
+}
+
+impl ComposePort {
+    /// Resolves the port of the container from the short syntax, such as `80`, `8080:80`, or
+    /// `127.0.0.1:8080:80/tcp`, or from the long syntax.
+    fn container_port(&self, service_name: &str) -> Result<u16, ComposeError> {
+        let port = match self {
+            ComposePort::Long { target } => return Ok(*target),
+            ComposePort::Short(Value::Number(port)) => port.to_string(),
+            ComposePort::Short(Value::String(port)) => port.clone(),
+            ComposePort::Short(port) => format!("{:?}", port),
+        };
+
+        port.rsplit(':')
+            .next()
+            .and_then(|container_port| container_port.split('/').next())
+            .and_then(|container_port| container_port.parse::<u16>().ok())
+            .ok_or_else(|| ComposeError::InvalidPort {
+                service_name: String::from(service_name),
+                port,
+            })
+    }
+}
+
+impl From<ComposeError> for HttpApiError {
+    fn from(err: ComposeError) -> Self {
+        HttpApiProblem::with_title_and_type(StatusCode::UNPROCESSABLE_ENTITY)
+            .detail(format!("{}", err))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_services_of_compose_file() {
+        let configs = parse_compose_file(
+            r#"
+version: '3'
+services:
+  db:
+    image: mariadb:10.3
+    environment:
+      MYSQL_USER: admin
+      MYSQL_PORT: 3306
+      MYSQL_PASSWORD:
+  web:
+    image: nginx
+    environment:
+      - LOG_LEVEL=debug
+      - HOST_ONLY
+    ports:
+      - "8080:80"
+      - target: 443
+        published: 8443
+      - 127.0.0.1:9000:9000/tcp
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(configs.len(), 2);
+
+        let db = &configs[0];
+        assert_eq!(db.service_name(), "db");
+        assert_eq!(&db.image().to_string(), "docker.io/library/mariadb:10.3");
+        let env = db.env().unwrap();
+        assert_eq!(
+            env.variable("MYSQL_USER").unwrap().value().unsecure(),
+            "admin"
+        );
+        assert_eq!(
+            env.variable("MYSQL_PORT").unwrap().value().unsecure(),
+            "3306"
+        );
+        assert_eq!(env.variable("MYSQL_PASSWORD"), None);
+        assert!(db.ports().is_empty());
+
+        let web = &configs[1];
+        assert_eq!(web.service_name(), "web");
+        let env = web.env().unwrap();
+        assert_eq!(
+            env.variable("LOG_LEVEL").unwrap().value().unsecure(),
+            "debug"
+        );
+        assert_eq!(env.variable("HOST_ONLY"), None);
+        assert_eq!(
+            web.ports(),
+            &[
+                Port::new(String::from("http"), 80),
+                Port::new(String::from("port-443"), 443),
+                Port::new(String::from("port-9000"), 9000),
+            ]
+        );
+        assert_eq!(web.port(), 80);
+    }
+
+    #[test]
+    fn should_not_parse_compose_file_without_image() {
+        let err = parse_compose_file(
+            r#"
+services:
+  web:
+    build: .
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            ComposeError::MissingImage {
+                service_name: String::from("web")
+            }
+        );
+    }
+
+    #[test]
+    fn should_not_parse_compose_file_with_port_range() {
+        let err = parse_compose_file(
+            r#"
+services:
+  web:
+    image: nginx
+    ports:
+      - "3000-3005"
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            ComposeError::InvalidPort {
+                service_name: String::from("web"),
+                port: String::from("3000-3005"),
+            }
+        );
+    }
+
+    #[test]
+    fn should_not_parse_compose_file_with_volumes() {
+        let err = parse_compose_file(
+            r#"
+services:
+  web:
+    image: nginx
+    volumes:
+      - ./html:/usr/share/nginx/html
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            ComposeError::UnsupportedVolumes {
+                service_name: String::from("web"),
+            }
+        );
+    }
+}
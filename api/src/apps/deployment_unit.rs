@@ -23,11 +23,13 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
-use crate::config::Config;
+use super::trace::{DeploymentTrace, TraceStep};
+use crate::config::{Companion, CompanionType, Config, ImagesConfig};
 use crate::models::{AppName, ContainerType, Image, ServiceConfig};
 use handlebars::TemplateRenderError;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::path::PathBuf;
 
 pub(super) struct DeploymentUnit {
     app_name: AppName,
@@ -35,35 +37,115 @@ pub(super) struct DeploymentUnit {
     service_companions: Vec<ServiceConfig>,
     app_companions: Vec<ServiceConfig>,
     templating_only_service_configs: Vec<ServiceConfig>,
+    user_defined_companions: HashSet<String>,
+    trace: DeploymentTrace,
+    /// Steps that have been applied to companion templates before they have been rendered, keyed
+    /// by the template's service name.
+    companion_steps: HashMap<String, Vec<TraceStep>>,
 }
 
 impl DeploymentUnit {
     pub fn new(app_name: AppName, configs: Vec<ServiceConfig>) -> Self {
+        let mut trace = DeploymentTrace::default();
+        for config in configs.iter() {
+            trace.record(config.service_name(), TraceStep::Payload);
+        }
+
         DeploymentUnit {
             app_name,
             configs,
             service_companions: Vec::new(),
             app_companions: Vec::new(),
             templating_only_service_configs: Vec::new(),
+            user_defined_companions: HashSet::new(),
+            trace,
+            companion_steps: HashMap::new(),
+        }
+    }
+
+    /// Extends the `DeploymentUnit` with the services that have been replicated from the app
+    /// `replicated_from`.
+    pub fn extend_with_replicated_service_configs<ServiceConfigIter>(
+        &mut self,
+        replicated_from: &str,
+        configs: ServiceConfigIter,
+    ) where
+        ServiceConfigIter: IntoIterator<Item = ServiceConfig>,
+    {
+        for config in configs {
+            self.trace.record(
+                config.service_name(),
+                TraceStep::Replicated {
+                    from: replicated_from.to_string(),
+                },
+            );
+            self.configs.push(config);
         }
     }
 
     /// Extends the `DeploymentUnit` with configuration options, such as:
     ///
     /// - secrets
-    /// - application and service companions
-    pub fn extend_with_config(&mut self, config: &Config) {
+    /// - application and service companions, except for the companions named in
+    ///   `skipped_companions`
+    pub fn extend_with_config(&mut self, config: &Config, skipped_companions: &[String]) {
         for service_config in self.configs.iter_mut() {
+            let existing_paths = volume_paths(service_config);
             config.add_secrets_to(service_config, &self.app_name);
+
+            let secret_paths = volume_paths(service_config)
+                .into_iter()
+                .filter(|path| !existing_paths.contains(path))
+                .collect::<Vec<_>>();
+            if !secret_paths.is_empty() {
+                self.trace.record(
+                    service_config.service_name(),
+                    TraceStep::Secrets {
+                        paths: secret_paths,
+                    },
+                );
+            }
         }
 
-        let service_companions = config.service_companion_configs(&self.app_name);
+        let service_companions =
+            config.service_companion_configs(&self.app_name, skipped_companions);
         self.service_companions.extend(service_companions);
 
-        let app_companions = config.application_companion_configs(&self.app_name);
+        let app_companions =
+            config.application_companion_configs(&self.app_name, skipped_companions);
         self.app_companions.extend(app_companions);
     }
 
+    /// Extends the `DeploymentUnit` with the companions that have been provided by the deployment
+    /// request. If a companion of the server configuration has the same service name, the
+    /// user-defined companion takes precedence and the server's companion fills the gaps.
+    pub fn extend_with_user_defined_companions(&mut self, companions: &[Companion]) {
+        for companion in companions
+            .iter()
+            .filter(|companion| companion.matches_app_name(&self.app_name))
+        {
+            let companion_configs = match companion.companion_type() {
+                CompanionType::Application => &mut self.app_companions,
+                CompanionType::Service => &mut self.service_companions,
+            };
+
+            let mut companion_config = ServiceConfig::from(companion.clone());
+            companion_config.set_companion_definition(serde_json::to_string(companion).ok());
+            self.user_defined_companions
+                .insert(companion_config.service_name().clone());
+            match companion_configs
+                .iter_mut()
+                .find(|config| config.service_name() == companion_config.service_name())
+            {
+                Some(existing_config) => {
+                    companion_config.merge_with(existing_config);
+                    *existing_config = companion_config;
+                }
+                None => companion_configs.push(companion_config),
+            }
+        }
+    }
+
     /// Extends the `DeploymentUnit` with service configuration that are only required for templating
     pub fn extend_with_templating_only_service_configs<ServiceConfigIter>(
         &mut self,
@@ -97,39 +179,109 @@ impl DeploymentUnit {
         images
     }
 
+    /// Applies the image rewrite rules of the configuration to all services and companions.
+    pub fn rewrite_images(&mut self, images_config: &ImagesConfig) {
+        for (service_name, from) in
+            Self::rewrite_images_impl(self.configs.iter_mut(), images_config)
+        {
+            self.trace
+                .record(&service_name, TraceStep::ImageRewritten { from });
+        }
+        for (template, from) in Self::rewrite_images_impl(
+            self.service_companions
+                .iter_mut()
+                .chain(self.app_companions.iter_mut()),
+            images_config,
+        ) {
+            self.companion_steps
+                .entry(template)
+                .or_insert_with(Vec::new)
+                .push(TraceStep::ImageRewritten { from });
+        }
+        Self::rewrite_images_impl(
+            self.templating_only_service_configs.iter_mut(),
+            images_config,
+        );
+    }
+
+    /// Rewrites the images and returns the service names and the original images of the rewritten
+    /// configurations.
+    fn rewrite_images_impl<'a, Iter>(
+        configs: Iter,
+        images_config: &ImagesConfig,
+    ) -> Vec<(String, String)>
+    where
+        Iter: Iterator<Item = &'a mut ServiceConfig>,
+    {
+        let mut rewritten_images = Vec::new();
+        for config in configs {
+            if let Some(image) = images_config.rewrite(config.image()) {
+                rewritten_images.push((config.service_name().clone(), config.image().to_string()));
+                config.set_image(image);
+            }
+        }
+        rewritten_images
+    }
+
     pub fn assign_port_mappings(&mut self, port_mappings: &HashMap<Image, u16>) {
-        Self::assign_port_mappings_impl(self.configs.iter_mut(), port_mappings);
-        Self::assign_port_mappings_impl(self.service_companions.iter_mut(), port_mappings);
-        Self::assign_port_mappings_impl(self.app_companions.iter_mut(), port_mappings);
+        for (service_name, port) in
+            Self::assign_port_mappings_impl(self.configs.iter_mut(), port_mappings)
+        {
+            self.trace
+                .record(&service_name, TraceStep::ImagePort { port });
+        }
+        for (template, port) in Self::assign_port_mappings_impl(
+            self.service_companions
+                .iter_mut()
+                .chain(self.app_companions.iter_mut()),
+            port_mappings,
+        ) {
+            self.companion_steps
+                .entry(template)
+                .or_insert_with(Vec::new)
+                .push(TraceStep::ImagePort { port });
+        }
         Self::assign_port_mappings_impl(
             self.templating_only_service_configs.iter_mut(),
             port_mappings,
         );
     }
 
-    fn assign_port_mappings_impl<'a, Iter>(configs: Iter, port_mappings: &HashMap<Image, u16>)
+    /// Assigns the ports of the images and returns the service names and ports that have been
+    /// assigned.
+    fn assign_port_mappings_impl<'a, Iter>(
+        configs: Iter,
+        port_mappings: &HashMap<Image, u16>,
+    ) -> Vec<(String, u16)>
     where
         Iter: Iterator<Item = &'a mut ServiceConfig>,
     {
+        let mut assigned_ports = Vec::new();
         for config in configs {
             if let Some(port) = port_mappings.get(config.image()) {
                 config.set_port(port.clone());
+                assigned_ports.push((config.service_name().clone(), port.clone()));
             }
         }
+        assigned_ports
     }
-}
 
-impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
-    type Error = TemplateRenderError;
-
-    fn try_into(self) -> Result<Vec<ServiceConfig>, Self::Error> {
+    /// Resolves the service configurations like `try_into` and additionally returns the trace of
+    /// how each of them has been produced.
+    pub fn try_into_with_trace(
+        self,
+    ) -> Result<(Vec<ServiceConfig>, DeploymentTrace), TemplateRenderError> {
+        let mut trace = self.trace;
+        let companion_steps = self.companion_steps;
+        let user_defined_companions = self.user_defined_companions;
         let mut services = HashMap::new();
 
         for config in self.configs.into_iter() {
-            services.insert(
-                config.service_name().clone(),
-                config.apply_templating(&self.app_name)?,
-            );
+            let templated_config = config.apply_templating(&self.app_name)?;
+            if templated_config != config {
+                trace.record(config.service_name(), TraceStep::Templated);
+            }
+            services.insert(config.service_name().clone(), templated_config);
         }
 
         // If the user wants to deploy a service that has the same name as a companion,
@@ -139,6 +291,7 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
 
         struct ServiceCompanion {
             templated_companion: ServiceConfig,
+            template: String,
             for_service_name: String,
         }
 
@@ -150,6 +303,7 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
 
                 service_companions.push(ServiceCompanion {
                     templated_companion,
+                    template: service_companion.service_name().clone(),
                     for_service_name: service.service_name().clone(),
                 });
             }
@@ -169,6 +323,12 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
                 .get_mut(companion.templated_companion.service_name())
                 .unwrap()
                 .merge_with(&companion.templated_companion);
+            trace.record(
+                companion.templated_companion.service_name(),
+                TraceStep::MergedWithCompanion {
+                    template: companion.template.clone(),
+                },
+            );
         }
 
         // Exclude service_companions that are included in the request
@@ -185,20 +345,37 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
                         .is_none()
                 })
                 .map(|service_companion| {
-                    (
-                        service_companion.templated_companion.service_name().clone(),
-                        service_companion.templated_companion,
-                    )
+                    let service_name = service_companion.templated_companion.service_name();
+                    trace.record(
+                        service_name,
+                        TraceStep::ServiceCompanion {
+                            template: service_companion.template.clone(),
+                            for_service: service_companion.for_service_name.clone(),
+                            user_defined: user_defined_companions
+                                .contains(&service_companion.template),
+                        },
+                    );
+                    trace.record_all(
+                        service_name,
+                        companion_steps
+                            .get(&service_companion.template)
+                            .cloned()
+                            .unwrap_or_default(),
+                    );
+                    trace.record(service_name, TraceStep::Templated);
+
+                    (service_name.clone(), service_companion.templated_companion)
                 }),
         );
 
         let mut templating_only_service_configs = self.templating_only_service_configs;
         templating_only_service_configs.extend(services.values().cloned());
-        for companion_config in self.app_companions.into_iter() {
-            let companion_config = companion_config.apply_templating_for_application_companion(
+        for companion_template in self.app_companions.into_iter() {
+            let companion_config = companion_template.apply_templating_for_application_companion(
                 &self.app_name,
                 &templating_only_service_configs,
             )?;
+            let template = companion_template.service_name();
 
             // If a custom application companion was deployed, its config needs to be merged
             // with the companion config
@@ -206,8 +383,27 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
 
             if let Some(existing_config) = existing_config {
                 existing_config.merge_with(&companion_config);
+                trace.record(
+                    companion_config.service_name(),
+                    TraceStep::MergedWithCompanion {
+                        template: template.clone(),
+                    },
+                );
             } else {
-                services.insert(companion_config.service_name().clone(), companion_config);
+                let service_name = companion_config.service_name();
+                trace.record(
+                    service_name,
+                    TraceStep::ApplicationCompanion {
+                        template: template.clone(),
+                        user_defined: user_defined_companions.contains(template),
+                    },
+                );
+                trace.record_all(
+                    service_name,
+                    companion_steps.get(template).cloned().unwrap_or_default(),
+                );
+                trace.record(service_name, TraceStep::Templated);
+                services.insert(service_name.clone(), companion_config);
             }
         }
 
@@ -218,6 +414,24 @@ impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
             index1.cmp(&index2)
         });
 
+        trace.retain_services(configs.iter().map(|config| config.service_name()));
+
+        Ok((configs, trace))
+    }
+}
+
+fn volume_paths(service_config: &ServiceConfig) -> Vec<PathBuf> {
+    service_config
+        .volumes()
+        .map(|volumes| volumes.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+impl TryInto<Vec<ServiceConfig>> for DeploymentUnit {
+    type Error = TemplateRenderError;
+
+    fn try_into(self) -> Result<Vec<ServiceConfig>, Self::Error> {
+        let (configs, _trace) = self.try_into_with_trace()?;
         Ok(configs)
     }
 }
@@ -270,7 +484,7 @@ mod tests {
             AppName::from_str("master").unwrap(),
             vec![sc!("http1", "nginx:1.13")],
         );
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let mut port_mappings = HashMap::new();
         port_mappings.insert(Image::from_str("nginx:1.13").unwrap(), 4711);
@@ -283,6 +497,137 @@ mod tests {
         assert_eq!(configs[2].port(), 4711);
     }
 
+    #[test]
+    fn should_rewrite_images() {
+        let config = config_from_str!(
+            r#"
+            [companions.http2]
+            serviceName = 'http2'
+            type = 'application'
+            image = 'nginx:1.13'
+
+            [[images.rewrites]]
+            from = 'docker.io/*'
+            to = 'mirror.example.com/*'
+        "#
+        );
+
+        let mut unit = DeploymentUnit::new(
+            AppName::from_str("master").unwrap(),
+            vec![sc!("http1", "nginx:1.13")],
+        );
+        unit.extend_with_config(&config, &[]);
+        unit.rewrite_images(&config.images_config());
+
+        let (configs, trace) = unit.try_into_with_trace().unwrap();
+        for config in configs.iter() {
+            assert_eq!(
+                &config.image().to_string(),
+                "mirror.example.com/library/nginx:1.13"
+            );
+        }
+        let trace = serde_json::to_value(&trace).unwrap();
+        assert!(trace["http1"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({
+                "step": "imageRewritten",
+                "from": "docker.io/library/nginx:1.13"
+            })));
+    }
+
+    #[test]
+    fn should_deploy_user_defined_companions() {
+        let companions = vec![toml::from_str::<Companion>(
+            r#"
+                serviceName = '{{service.name}}-mock'
+                type = 'service'
+                image = 'mockserver/mockserver:latest'
+                env = [ "SERVICE={{service.name}}" ]
+                "#,
+        )
+        .unwrap()];
+
+        let mut unit = DeploymentUnit::new(
+            AppName::from_str("feature-xxx").unwrap(),
+            vec![sc!("wordpress", "wordpress:alpine")],
+        );
+        unit.extend_with_config(&Config::default(), &[]);
+        unit.extend_with_user_defined_companions(&companions);
+
+        let configs: Vec<_> = unit.try_into().unwrap();
+        assert_eq!(configs.len(), 2);
+
+        let mock = configs
+            .iter()
+            .find(|config| config.service_name() == "wordpress-mock")
+            .unwrap();
+        assert_eq!(mock.container_type(), &ContainerType::ServiceCompanion);
+        assert_eq!(
+            mock.env().unwrap().variable("SERVICE"),
+            Some(&EnvironmentVariable::new(
+                String::from("SERVICE"),
+                SecUtf8::from("wordpress")
+            ))
+        );
+    }
+
+    #[test]
+    fn should_merge_user_defined_companions_with_companions_of_config() {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            env = [ "VAR_1=abcd", "VAR_2=1234" ]
+        "#
+        );
+        let companions = vec![toml::from_str::<Companion>(
+            r#"
+                serviceName = 'openid'
+                type = 'application'
+                image = 'private.example.com/library/openid:feature-xxx'
+                env = [ "VAR_1=efg" ]
+                "#,
+        )
+        .unwrap()];
+
+        let mut unit = DeploymentUnit::new(
+            AppName::from_str("feature-xxx").unwrap(),
+            vec![sc!("wordpress", "wordpress:alpine")],
+        );
+        unit.extend_with_config(&config, &[]);
+        unit.extend_with_user_defined_companions(&companions);
+
+        let configs: Vec<_> = unit.try_into().unwrap();
+        assert_eq!(configs.len(), 2);
+
+        let openid = configs
+            .iter()
+            .find(|config| config.service_name() == "openid")
+            .unwrap();
+        assert_eq!(
+            openid.image().to_string(),
+            "private.example.com/library/openid:feature-xxx"
+        );
+        let openid_env = openid.env().unwrap();
+        assert_eq!(
+            openid_env.variable("VAR_1"),
+            Some(&EnvironmentVariable::new(
+                String::from("VAR_1"),
+                SecUtf8::from("efg")
+            ))
+        );
+        assert_eq!(
+            openid_env.variable("VAR_2"),
+            Some(&EnvironmentVariable::new(
+                String::from("VAR_2"),
+                SecUtf8::from("1234")
+            ))
+        );
+    }
+
     #[test]
     fn should_merge_with_application_companion_if_services_contain_same_service_name() {
         let config = config_from_str!(
@@ -305,7 +650,7 @@ mod tests {
             )],
         );
 
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let openid_configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(openid_configs.len(), 1);
@@ -353,7 +698,7 @@ mod tests {
             )],
         );
 
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let openid_configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(openid_configs.len(), 1);
@@ -393,7 +738,7 @@ mod tests {
                 sc!("nextcloud", "nextcloud:alpine"),
             ],
         );
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(configs.len(), 4);
@@ -478,7 +823,7 @@ mod tests {
             AppName::from_str("master").unwrap(),
             vec![sc!("wordpress", "wordpress:alpine")],
         );
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(configs.len(), 2);
@@ -523,7 +868,7 @@ mod tests {
             AppName::from_str("master").unwrap(),
             vec![sc!("wordpress", "wordpress:alpine")],
         );
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
         unit.extend_with_templating_only_service_configs(vec![sc!("postgres", "postgres:alpine")]);
 
         let configs: Vec<_> = unit.try_into().unwrap();
@@ -570,7 +915,7 @@ mod tests {
             vec![sc!("openid", "private.example.com/library/openid:backup")],
         );
 
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let openid_configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(openid_configs.len(), 1);
@@ -614,7 +959,7 @@ mod tests {
             ],
         );
 
-        unit.extend_with_config(&config);
+        unit.extend_with_config(&config, &[]);
 
         let configs: Vec<_> = unit.try_into().unwrap();
         assert_eq!(configs.len(), 3);
@@ -644,4 +989,72 @@ mod tests {
             Some("docker.io/library/kafka:latest".to_string())
         );
     }
+
+    #[test]
+    fn should_trace_origin_of_service_configs() {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+        "#
+        );
+        let companions = vec![toml::from_str::<Companion>(
+            r#"
+                serviceName = '{{service.name}}-mock'
+                type = 'service'
+                image = 'mockserver/mockserver:latest'
+                "#,
+        )
+        .unwrap()];
+
+        let mut unit = DeploymentUnit::new(
+            AppName::from_str("feature-xxx").unwrap(),
+            vec![sc!("wordpress", "wordpress:alpine")],
+        );
+        unit.extend_with_replicated_service_configs("master", vec![sc!("mariadb", "mariadb:10.3")]);
+        unit.extend_with_config(&config, &[]);
+        unit.extend_with_user_defined_companions(&companions);
+
+        let mut port_mappings = HashMap::new();
+        port_mappings.insert(
+            Image::from_str("private.example.com/library/openid:latest").unwrap(),
+            8080,
+        );
+        unit.assign_port_mappings(&port_mappings);
+
+        let (configs, trace) = unit.try_into_with_trace().unwrap();
+        assert_eq!(configs.len(), 5);
+
+        assert_eq!(trace.steps_of("wordpress"), &[TraceStep::Payload]);
+        assert_eq!(
+            trace.steps_of("mariadb"),
+            &[TraceStep::Replicated {
+                from: String::from("master")
+            }]
+        );
+        assert_eq!(
+            trace.steps_of("openid"),
+            &[
+                TraceStep::ApplicationCompanion {
+                    template: String::from("openid"),
+                    user_defined: false
+                },
+                TraceStep::ImagePort { port: 8080 },
+                TraceStep::Templated
+            ]
+        );
+        assert_eq!(
+            trace.steps_of("wordpress-mock"),
+            &[
+                TraceStep::ServiceCompanion {
+                    template: String::from("{{service.name}}-mock"),
+                    for_service: String::from("wordpress"),
+                    user_defined: true
+                },
+                TraceStep::Templated
+            ]
+        );
+    }
 }
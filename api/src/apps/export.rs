@@ -0,0 +1,305 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! The portable document of an app that `GET /apps/{app}/export` returns and that
+//! `POST /apps/{app}?import=true` recreates the app from, e.g. to move a review app to another
+//! PREvant host.
+
+use crate::config::Companion;
+use crate::models::service::{ContainerType, Service, ServiceStatus};
+use crate::models::{AppName, Image, ServiceConfig};
+use std::str::FromStr;
+
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppExport {
+    version: u32,
+    app_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replicated_from: Option<String>,
+    services: Vec<ServiceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    paused_services: Vec<String>,
+    #[serde(default)]
+    companions: Vec<ExportedCompanion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    user_defined_companions: Vec<Companion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_companions: Vec<String>,
+}
+
+/// The companions are resolved again by the configuration of the importing host, thus, only their
+/// state is exported. The definitions of the user-defined companions are exported separately.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedCompanion {
+    service_name: String,
+    #[serde(rename = "type")]
+    container_type: ContainerType,
+    image: Image,
+    status: ServiceStatus,
+}
+
+impl AppExport {
+    /// Exports the instances of the app with the configuration that the infrastructure reports for
+    /// them. Replicas are not exported because they are replicated again on import.
+    pub fn new(app_name: &AppName, services: &[Service]) -> Self {
+        let instances = services
+            .iter()
+            .filter(|service| service.container_type() == &ContainerType::Instance)
+            .collect::<Vec<_>>();
+
+        let replicated_from = services
+            .iter()
+            .find_map(|service| service.config().replicated_from())
+            .cloned();
+
+        let paused_services = instances
+            .iter()
+            .filter(|service| service.status() == &ServiceStatus::Paused)
+            .map(|service| service.service_name().clone())
+            .collect();
+
+        let services_configs = instances
+            .iter()
+            .map(|service| service.config().clone())
+            .collect();
+
+        let companions = services
+            .iter()
+            .filter(|service| {
+                service.container_type() == &ContainerType::ApplicationCompanion
+                    || service.container_type() == &ContainerType::ServiceCompanion
+            })
+            .map(|service| ExportedCompanion {
+                service_name: service.service_name().clone(),
+                container_type: service.container_type().clone(),
+                image: service.image().clone(),
+                status: service.status().clone(),
+            })
+            .collect();
+
+        let (user_defined_companions, skipped_companions) = requested_companions(services);
+
+        AppExport {
+            version: EXPORT_VERSION,
+            app_name: app_name.to_string(),
+            replicated_from,
+            services: services_configs,
+            paused_services,
+            companions,
+            user_defined_companions,
+            skipped_companions,
+        }
+    }
+
+    /// The app that the replicas of the exported app have been replicated from.
+    pub fn replicated_from(&self) -> Option<AppName> {
+        self.replicated_from
+            .as_ref()
+            .and_then(|app_name| AppName::from_str(app_name).ok())
+    }
+
+    /// The service configurations for the app to import into. Routes that refer to the exported
+    /// app are moved to the new app.
+    pub fn service_configs(&self, app_name: &AppName) -> Vec<ServiceConfig> {
+        self.services
+            .iter()
+            .cloned()
+            .map(|mut config| {
+                config.move_routes(&self.app_name, app_name);
+                config
+            })
+            .collect()
+    }
+
+    /// The names of the services and companions that have been paused when the app was exported.
+    pub fn paused_services(&self) -> Vec<&String> {
+        self.paused_services
+            .iter()
+            .chain(
+                self.companions
+                    .iter()
+                    .filter(|companion| companion.status == ServiceStatus::Paused)
+                    .map(|companion| &companion.service_name),
+            )
+            .collect()
+    }
+
+    /// The companions that the deployments of the exported app defined themselves.
+    pub fn user_defined_companions(&self) -> &[Companion] {
+        &self.user_defined_companions
+    }
+
+    /// The names of the companions of the configuration that the deployments of the exported app
+    /// skipped.
+    pub fn skipped_companions(&self) -> &[String] {
+        &self.skipped_companions
+    }
+
+    pub fn is_supported_version(&self) -> bool {
+        self.version == EXPORT_VERSION
+    }
+}
+
+/// Restores the user-defined companions and the skipped companions that the deployment requests
+/// of the given services of an app contained, so that they can be requested again, e.g. for a
+/// clone or an export of the app. Companions that have been skipped by a request but that are
+/// running have been requested by a later one.
+pub(super) fn requested_companions(services: &[Service]) -> (Vec<Companion>, Vec<String>) {
+    let user_defined_companions = services
+        .iter()
+        .filter_map(|service| service.config().companion_definition())
+        .filter_map(
+            |definition| match serde_json::from_str::<Companion>(definition) {
+                Ok(companion) => Some(companion),
+                Err(err) => {
+                    warn!("Cannot restore user-defined companion: {}", err);
+                    None
+                }
+            },
+        )
+        .collect();
+
+    let mut skipped_companions = Vec::new();
+    for service_name in services
+        .iter()
+        .flat_map(|service| service.config().skipped_companions())
+    {
+        let is_running = services
+            .iter()
+            .any(|service| service.service_name() == service_name);
+        if !is_running && !skipped_companions.contains(service_name) {
+            skipped_companions.push(service_name.clone());
+        }
+    }
+
+    (user_defined_companions, skipped_companions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::service::ServiceBuilder;
+    use crate::sc;
+
+    fn service(app_name: &str, config: ServiceConfig, status: ServiceStatus) -> Service {
+        ServiceBuilder::new()
+            .id(format!("{}-{}", app_name, config.service_name()))
+            .app_name(app_name.to_string())
+            .config(config)
+            .service_status(status)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_export_instances_and_companion_states() {
+        let mut replica = sc!("db", "mariadb:10.3");
+        replica.set_container_type(ContainerType::Replica);
+        replica.set_replicated_from(Some(String::from("master")), None);
+        let mut companion = sc!("openid", "private.example.com/library/openid:latest");
+        companion.set_container_type(ContainerType::ApplicationCompanion);
+
+        let services = vec![
+            service(
+                "branch",
+                sc!("backend", "backend:1.0"),
+                ServiceStatus::Paused,
+            ),
+            service("branch", replica, ServiceStatus::Running),
+            service("branch", companion, ServiceStatus::Paused),
+        ];
+
+        let export = AppExport::new(&AppName::from_str("branch").unwrap(), &services);
+
+        assert_eq!(
+            serde_json::to_value(&export).unwrap(),
+            serde_json::json!({
+                "version": 1,
+                "appName": "branch",
+                "replicatedFrom": "master",
+                "services": [{
+                    "serviceName": "backend",
+                    "image": "docker.io/library/backend:1.0",
+                    "type": "instance"
+                }],
+                "pausedServices": [ "backend" ],
+                "companions": [{
+                    "serviceName": "openid",
+                    "type": "app-companion",
+                    "image": "private.example.com/library/openid:latest",
+                    "status": "paused"
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn should_import_exported_document() {
+        let export = serde_json::from_value::<AppExport>(serde_json::json!({
+            "version": 1,
+            "appName": "branch",
+            "replicatedFrom": "master",
+            "services": [{
+                "serviceName": "backend",
+                "image": "docker.io/library/backend:1.0",
+                "type": "instance",
+                "env": { "LOG_LEVEL": "debug" }
+            }],
+            "companions": [{
+                "serviceName": "openid",
+                "type": "app-companion",
+                "image": "private.example.com/library/openid:latest",
+                "status": "paused"
+            }]
+        }))
+        .unwrap();
+
+        let configs = export.service_configs(&AppName::from_str("other-branch").unwrap());
+
+        assert!(export.is_supported_version());
+        assert_eq!(
+            export.replicated_from(),
+            Some(AppName::from_str("master").unwrap())
+        );
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].service_name(), "backend");
+        assert_eq!(
+            configs[0]
+                .env()
+                .unwrap()
+                .variable("LOG_LEVEL")
+                .unwrap()
+                .value()
+                .unsecure(),
+            "debug"
+        );
+        assert_eq!(export.paused_services(), vec!["openid"]);
+    }
+}
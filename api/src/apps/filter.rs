@@ -0,0 +1,331 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::service::{ContainerType, Service};
+use multimap::MultiMap;
+use regex::Regex;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::str::FromStr;
+
+/// Selects the apps and the services that `GET /apps` returns.
+pub struct AppsFilter {
+    name: Option<Regex>,
+    container_type: Option<ContainerType>,
+    image: Option<String>,
+    owner: Option<String>,
+}
+
+impl AppsFilter {
+    /// The name pattern has to match the whole app name and the image has to be contained in the
+    /// fully qualified image of a service.
+    pub fn new(
+        name: Option<&str>,
+        container_type: Option<ContainerType>,
+        image: Option<String>,
+        owner: Option<String>,
+    ) -> Result<Self, regex::Error> {
+        let name = match name {
+            Some(name) => Some(Regex::new(&format!("^(?:{})$", name))?),
+            None => None,
+        };
+
+        Ok(AppsFilter {
+            name,
+            container_type,
+            image,
+            owner,
+        })
+    }
+
+    /// Keeps the apps whose name matches and that have a service of the owner. Within these apps,
+    /// only the services of the container type and the image are kept, and apps without such
+    /// services are omitted.
+    pub fn apply(&self, apps: MultiMap<String, Service>) -> MultiMap<String, Service> {
+        let mut filtered_apps = MultiMap::new();
+
+        for (app_name, services) in apps.into_iter() {
+            if !self.matches_app(&app_name, &services) {
+                continue;
+            }
+
+            let services = services
+                .into_iter()
+                .filter(|service| self.matches_service(service))
+                .collect::<Vec<_>>();
+            if !services.is_empty() {
+                filtered_apps.insert_many(app_name, services);
+            }
+        }
+
+        filtered_apps
+    }
+
+    fn matches_app(&self, app_name: &str, services: &[Service]) -> bool {
+        let name_matches = self
+            .name
+            .as_ref()
+            .map_or(true, |name| name.is_match(app_name));
+        let owner_matches = self.owner.as_ref().map_or(true, |owner| {
+            services
+                .iter()
+                .any(|service| service.owner() == Some(owner))
+        });
+
+        name_matches && owner_matches
+    }
+
+    fn matches_service(&self, service: &Service) -> bool {
+        let type_matches = self.container_type.as_ref().map_or(true, |container_type| {
+            service.container_type() == container_type
+        });
+        let image_matches = self.image.as_ref().map_or(true, |image| {
+            service.image().to_string().contains(image.as_str())
+        });
+
+        type_matches && image_matches
+    }
+}
+
+/// The order of the apps in the response of `GET /apps`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AppsOrder {
+    Name,
+    NameDescending,
+    /// By the start of the oldest service of the app
+    Created,
+    CreatedDescending,
+}
+
+impl Default for AppsOrder {
+    fn default() -> Self {
+        AppsOrder::Name
+    }
+}
+
+impl FromStr for AppsOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(AppsOrder::Name),
+            "-name" => Ok(AppsOrder::NameDescending),
+            "created" => Ok(AppsOrder::Created),
+            "-created" => Ok(AppsOrder::CreatedDescending),
+            _ => Err(format!(
+                "Unknown sort order {:?}, expected name, -name, created, or -created.",
+                s
+            )),
+        }
+    }
+}
+
+/// A page of the sorted apps. It serializes as an object of the app names and their services,
+/// like the complete list, whose keys are in the requested order.
+pub struct AppsPage {
+    apps: Vec<(String, Vec<Service>)>,
+    total: usize,
+    next_offset: Option<usize>,
+}
+
+impl AppsPage {
+    pub fn new(
+        apps: MultiMap<String, Service>,
+        order: AppsOrder,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Self {
+        let mut apps = apps.into_iter().collect::<Vec<_>>();
+        match order {
+            AppsOrder::Name => apps.sort_by(|(a, _), (b, _)| a.cmp(b)),
+            AppsOrder::NameDescending => apps.sort_by(|(a, _), (b, _)| b.cmp(a)),
+            AppsOrder::Created => {
+                apps.sort_by_key(|(app_name, services)| (created_at(services), app_name.clone()))
+            }
+            AppsOrder::CreatedDescending => apps.sort_by(|(a, a_services), (b, b_services)| {
+                (created_at(b_services), b).cmp(&(created_at(a_services), a))
+            }),
+        }
+
+        let total = apps.len();
+        let apps = apps
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        let next_offset = Some(offset + apps.len()).filter(|next_offset| *next_offset < total);
+
+        AppsPage {
+            apps,
+            total,
+            next_offset,
+        }
+    }
+
+    /// The number of apps that match the filter, regardless of the pagination.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The offset of the next page, if there are more apps.
+    pub fn next_offset(&self) -> Option<usize> {
+        self.next_offset
+    }
+}
+
+fn created_at(services: &[Service]) -> Option<chrono::DateTime<chrono::Utc>> {
+    services.iter().map(|service| *service.started_at()).min()
+}
+
+impl Serialize for AppsPage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.apps.len()))?;
+        for (app_name, services) in &self.apps {
+            map.serialize_entry(app_name, services)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::service::ServiceBuilder;
+    use crate::models::ServiceConfig;
+    use chrono::{TimeZone, Utc};
+
+    fn apps() -> MultiMap<String, Service> {
+        let mut apps = MultiMap::new();
+        for (app_name, service_name, image, container_type, hour) in &[
+            ("master", "db", "mariadb:10.3", ContainerType::Instance, 8),
+            (
+                "master",
+                "backend",
+                "backend:1.0",
+                ContainerType::Instance,
+                8,
+            ),
+            (
+                "feature-a",
+                "db",
+                "mariadb:10.3",
+                ContainerType::Replica,
+                10,
+            ),
+            (
+                "feature-a",
+                "backend",
+                "backend:2.0",
+                ContainerType::Instance,
+                10,
+            ),
+            (
+                "feature-b",
+                "backend",
+                "backend:3.0",
+                ContainerType::Instance,
+                9,
+            ),
+        ] {
+            let mut config = crate::sc!(*service_name, *image);
+            config.set_container_type(container_type.clone());
+            if *app_name == "feature-b" {
+                config.set_owner(Some(String::from("jane")));
+            }
+
+            apps.insert(
+                app_name.to_string(),
+                ServiceBuilder::new()
+                    .id(format!("{}-{}", app_name, service_name))
+                    .app_name(app_name.to_string())
+                    .config(config)
+                    .started_at(Utc.ymd(2021, 7, 1).and_hms(*hour, 0, 0))
+                    .build()
+                    .unwrap(),
+            );
+        }
+        apps
+    }
+
+    fn app_names(page: &AppsPage) -> Vec<&str> {
+        page.apps
+            .iter()
+            .map(|(app_name, _)| app_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn should_filter_apps_by_name_and_owner() {
+        let filter = AppsFilter::new(Some("feature-.*"), None, None, None).unwrap();
+        let page = AppsPage::new(filter.apply(apps()), AppsOrder::Name, 0, None);
+        assert_eq!(app_names(&page), vec!["feature-a", "feature-b"]);
+
+        let filter = AppsFilter::new(Some("feature"), None, None, None).unwrap();
+        assert!(filter.apply(apps()).is_empty());
+
+        let filter = AppsFilter::new(None, None, None, Some(String::from("jane"))).unwrap();
+        let page = AppsPage::new(filter.apply(apps()), AppsOrder::Name, 0, None);
+        assert_eq!(app_names(&page), vec!["feature-b"]);
+    }
+
+    #[test]
+    fn should_filter_services_by_type_and_image() {
+        let filter = AppsFilter::new(None, Some(ContainerType::Replica), None, None).unwrap();
+        let filtered_apps = filter.apply(apps());
+        assert_eq!(filtered_apps.len(), 1);
+        assert_eq!(filtered_apps.get_vec("feature-a").unwrap().len(), 1);
+
+        let filter = AppsFilter::new(None, None, Some(String::from("mariadb")), None).unwrap();
+        let page = AppsPage::new(filter.apply(apps()), AppsOrder::Name, 0, None);
+        assert_eq!(app_names(&page), vec!["feature-a", "master"]);
+    }
+
+    #[test]
+    fn should_sort_and_paginate_apps() {
+        let page = AppsPage::new(apps(), AppsOrder::Created, 0, Some(2));
+        assert_eq!(app_names(&page), vec!["master", "feature-b"]);
+        assert_eq!(page.total(), 3);
+        assert_eq!(page.next_offset(), Some(2));
+
+        let page = AppsPage::new(apps(), AppsOrder::CreatedDescending, 2, Some(2));
+        assert_eq!(app_names(&page), vec!["master"]);
+        assert_eq!(page.next_offset(), None);
+
+        let page = AppsPage::new(apps(), AppsOrder::NameDescending, 0, None);
+        assert_eq!(app_names(&page), vec!["master", "feature-b", "feature-a"]);
+    }
+
+    #[test]
+    fn should_serialize_apps_in_order() {
+        let page = AppsPage::new(apps(), AppsOrder::NameDescending, 1, Some(1));
+
+        let json = serde_json::to_string(&page).unwrap();
+
+        assert!(json.starts_with(r#"{"feature-b":[{"#));
+    }
+}
@@ -42,7 +42,8 @@ impl Apps {
         app_name: &AppName,
         configs: Vec<ServiceConfig>,
     ) -> Result<Vec<ServiceConfig>, AppsServiceError> {
-        match self.config.hook("deployment") {
+        let config = self.config();
+        match config.hook("deployment") {
             None => Ok(configs),
             Some(hook_path) => self.parse_and_run_hook(app_name, configs, hook_path).await,
         }
@@ -263,6 +264,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[crate::sc!("service-a"), crate::sc!("service-b")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -299,6 +303,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[crate::sc!("service-a")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -351,6 +358,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[service_config],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -392,6 +402,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[crate::sc!("service-a")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -443,6 +456,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[service_config],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -487,6 +503,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[crate::sc!("service-a")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -524,6 +543,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &[crate::sc!("service-a")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -552,6 +574,9 @@ mod tests {
                 &AppStatusChangeId::new(),
                 None,
                 &[crate::sc!("service-a"), crate::sc!("service-b")],
+                &[],
+                &[],
+                None,
             )
             .await
             .unwrap_err()
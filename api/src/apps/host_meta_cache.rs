@@ -257,7 +257,7 @@ impl HostMetaCrawler {
             Some(endpoint_url) => endpoint_url.join(".well-known/host-meta.json").unwrap(),
         };
 
-        let get_request = reqwest::Client::builder()
+        let mut get_request = reqwest::Client::builder()
             .connect_timeout(Duration::from_millis(500))
             .timeout(Duration::from_millis(750))
             .user_agent(format!("PREvant/{}", crate_version!()))
@@ -265,13 +265,19 @@ impl HostMetaCrawler {
             .unwrap()
             .get(&url.to_string())
             .header("Forwarded", "host=www.prevant.example.com;proto=http")
-            .header(
+            .header("Accept", "application/json");
+
+        // Services which are routed without stripping the path prefix see the full path and,
+        // therefore, do not need to know the prefix.
+        if service.config().strips_routing_path(service.app_name()) {
+            let prefix = service.config().routing_path(service.app_name());
+            get_request = get_request.header(
                 "X-Forwarded-Prefix",
-                format!("/{}/{}", service.app_name(), service.service_name()),
-            )
-            .header("Accept", "application/json")
-            .send()
-            .await;
+                prefix.trim_end_matches('/').to_string(),
+            );
+        }
+
+        let get_request = get_request.send().await;
 
         let meta = match get_request {
             Ok(response) => match response.json::<WebHostMeta>().await {
@@ -23,40 +23,95 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
+mod batch;
+mod compare;
+mod compose;
 mod deployment_unit;
+mod export;
+mod filter;
 mod hooks;
 mod host_meta_cache;
+mod operation_scheduler;
+mod restart_scheduler;
 mod routes;
+mod trace;
 
 pub use crate::apps::AppsService as Apps;
 pub use crate::apps::AppsServiceError as AppsError;
-use crate::config::{Config, ConfigError};
-use crate::infrastructure::Infrastructure;
-use crate::models::service::{ContainerType, Service, ServiceStatus};
-use crate::models::{AppName, AppStatusChangeId, LogChunk, ServiceConfig};
+use crate::config::{Companion, Config, ConfigError, SizeLimitAction};
+use crate::infrastructure::{
+    Capabilities, Infrastructure, ServiceDeploymentError, ServicesDeploymentError,
+    TransientInfrastructureError,
+};
+use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
+use crate::models::{
+    deployment_waves, AppName, AppStatusChangeId, BasicAuthCredentials, DependencyCycleError,
+    DeploymentStrategy, DiagnosticCheck, DiagnosticsReport, ExecOutput, LogChunk, Orphan,
+    ServiceBuilder, ServiceConfig, ServiceStats,
+};
+use crate::services::app_events::{AppEvent, AppEvents};
+use crate::services::audit_log::{AuditAction, AuditEntry, AuditLog};
+use crate::services::desired_state::DesiredState;
+use crate::services::freezes::Freezes;
+use crate::services::idempotent_requests::{IdempotentRequest, IdempotentRequests};
 use crate::services::images_service::{ImagesService, ImagesServiceError};
-use chrono::{DateTime, FixedOffset};
+use crate::services::scheduled_operations::{
+    ScheduledChange, ScheduledOperation, ScheduledOperations,
+};
+use crate::services::webhook_deliveries::{DeploymentEvent, WebhookDeliveries};
+pub use batch::apps_batch_routes;
+use chrono::{DateTime, FixedOffset, Utc};
+pub(self) use compare::AppComparison;
 pub(self) use deployment_unit::DeploymentUnit;
+pub(self) use export::{requested_companions, AppExport};
+pub(self) use filter::{AppsFilter, AppsOrder, AppsPage};
 use handlebars::TemplateRenderError;
 pub use host_meta_cache::new as host_meta_crawling;
 pub use host_meta_cache::HostMetaCache;
 pub use host_meta_cache::HostMetaCrawler;
 use multimap::MultiMap;
+pub use operation_scheduler::OperationScheduler;
+pub use restart_scheduler::RestartScheduler;
 pub use routes::{apps_routes, delete_app_sync};
 use std::collections::{HashMap, HashSet};
-use std::convert::{From, TryInto};
+use std::convert::From;
+use std::future::Future;
 use std::str::FromStr;
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
+pub use trace::{DeploymentTrace, TraceStep};
 
 pub struct AppsService {
-    config: Config,
+    config: RwLock<Arc<Config>>,
     infrastructure: Box<dyn Infrastructure>,
     app_guards: Mutex<HashMap<AppName, Arc<AppGuard>>>,
+    webhook_deliveries: WebhookDeliveries,
+    freezes: Freezes,
+    desired_state: DesiredState,
+    audit_log: AuditLog,
+    diagnostics: Mutex<Option<DiagnosticsReport>>,
+    shutting_down: AtomicBool,
+    idempotent_requests: IdempotentRequests<IdempotentOutcome>,
+    events: AppEvents,
+    scheduled_operations: ScheduledOperations,
 }
 
+/// How long the results of deployment requests are remembered for replays of their idempotency
+/// keys.
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
 type GuardedResult = Result<Vec<Service>, AppsServiceError>;
 
+/// The outcome of a deployment request that replays of its idempotency key are answered with.
+#[derive(Clone)]
+pub enum IdempotentOutcome {
+    Deployed(Vec<Service>),
+    Scheduled(ScheduledOperation),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum AppGuardKind {
     Deployment,
@@ -72,16 +127,20 @@ struct AppGuard {
     app_name: AppName,
     kind: AppGuardKind,
     process_mutex: Mutex<(bool, Option<GuardedResult>)>,
-    condvar: Condvar,
+    /// Signals waiting tasks that the result is available without blocking the executor's threads.
+    done_sender: watch::Sender<bool>,
+    done_receiver: watch::Receiver<bool>,
 }
 
 impl AppGuard {
     fn new(app_name: AppName, kind: AppGuardKind) -> Self {
+        let (done_sender, done_receiver) = watch::channel(false);
         AppGuard {
             app_name,
             kind,
             process_mutex: Mutex::new((false, None)),
-            condvar: Condvar::new(),
+            done_sender,
+            done_receiver,
         }
     }
 
@@ -95,12 +154,16 @@ impl AppGuard {
         }
     }
 
-    fn wait_for_result(&self) -> GuardedResult {
-        let mut guard = self.process_mutex.lock().unwrap();
-        while (*guard).1.is_none() {
+    async fn wait_for_result(&self) -> GuardedResult {
+        let mut done_receiver = self.done_receiver.clone();
+        while !*done_receiver.borrow() {
             trace!("waiting for the result of {}", self.app_name);
-            guard = self.condvar.wait(guard).unwrap();
+            if done_receiver.changed().await.is_err() {
+                break;
+            }
         }
+
+        let guard = self.process_mutex.lock().unwrap();
         (*guard)
             .1
             .as_ref()
@@ -113,9 +176,12 @@ impl AppGuard {
         apps_service: &AppsService,
         result: GuardedResult,
     ) -> GuardedResult {
-        let mut guard = self.process_mutex.lock().unwrap();
-        (*guard).1 = Some(result.clone());
-        self.condvar.notify_all();
+        {
+            let mut guard = self.process_mutex.lock().unwrap();
+            (*guard).1 = Some(result.clone());
+        }
+        // The guard holds a receiver itself, thus sending cannot fail.
+        let _ = self.done_sender.send(true);
 
         let mut apps_in_deletion = apps_service.app_guards.lock().unwrap();
         let removed_guard = apps_in_deletion.remove(&self.app_name);
@@ -133,17 +199,471 @@ impl AppsService {
         config: Config,
         infrastructure: Box<dyn Infrastructure>,
     ) -> Result<AppsService, AppsServiceError> {
+        let webhook_deliveries = WebhookDeliveries::new(
+            config.notification_sinks(),
+            config.notifications_base_url().cloned(),
+        );
+        let freezes = Freezes::new(config.freeze_windows());
+        let desired_state = DesiredState::load(config.state_file());
+        let audit_log = AuditLog::load(config.audit_file());
+        let scheduled_operations =
+            ScheduledOperations::load(config.scheduled_operations_file().as_ref());
         Ok(AppsService {
-            config,
+            config: RwLock::new(Arc::new(config)),
             infrastructure,
             app_guards: Mutex::new(HashMap::new()),
+            webhook_deliveries,
+            freezes,
+            desired_state,
+            audit_log,
+            diagnostics: Mutex::new(None),
+            shutting_down: AtomicBool::new(false),
+            idempotent_requests: IdempotentRequests::new(IDEMPOTENCY_KEY_TTL),
+            events: AppEvents::new(),
+            scheduled_operations,
         })
     }
 
+    /// Returns the current configuration. The configuration might be replaced by
+    /// [`reload_config`](AppsService::reload_config) at any time, thus, callers should hold on to
+    /// the returned configuration while they are processing a request.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Reloads the configuration from the file it has been loaded from, so that changes of
+    /// companions, secrets, hooks, freezes, notification sinks, etc. apply to subsequent
+    /// deployments without restarting PREvant. Returns `false` if the configuration has not been loaded from a file.
+    pub async fn reload_config(&self) -> Result<bool, ConfigError> {
+        let file = match self.config().file() {
+            Some(file) => file.clone(),
+            None => return Ok(false),
+        };
+
+        let config = Config::load(&file.to_string_lossy()).await?;
+
+        // The configured parts of the services are rebuilt whereas their runtime state, e.g. the
+        // freezes declared by administrators or the dead letters, is kept.
+        self.freezes.reconfigure(config.freeze_windows());
+        self.webhook_deliveries.reconfigure(
+            config.notification_sinks(),
+            config.notifications_base_url().cloned(),
+        );
+        self.desired_state.relocate(config.state_file());
+        self.audit_log.relocate(config.audit_file());
+        self.scheduled_operations
+            .relocate(config.scheduled_operations_file().as_ref());
+
+        *self.config.write().unwrap() = Arc::new(config);
+        info!("Reloaded configuration from {}", file.display());
+        Ok(true)
+    }
+
     /// Analyzes running containers and returns a map of `app-name` with the
     /// corresponding list of `Service`s.
     pub async fn get_apps(&self) -> Result<MultiMap<String, Service>, AppsServiceError> {
-        Ok(self.infrastructure.get_services().await?)
+        let now = Utc::now();
+
+        let running_services = self.infrastructure.get_services().await?;
+
+        let mut apps = MultiMap::new();
+        for (app_name, services) in running_services.iter_all() {
+            for service in services {
+                let next_restart = self.next_restart(&app_name, service.service_name(), &now);
+                let stale = AppsService::is_stale_replica(service, &running_services);
+                let service = ServiceBuilder::from(service.clone())
+                    .next_restart(next_restart)
+                    .stale(stale)
+                    .build()
+                    .expect("Rebuilding an existing service must not fail");
+                apps.insert(app_name.clone(), service);
+            }
+        }
+
+        Ok(apps)
+    }
+
+    /// Returns the apps that match the filter with the matching services, see
+    /// [`AppsFilter::apply`].
+    pub async fn get_filtered_apps(
+        &self,
+        filter: &AppsFilter,
+    ) -> Result<MultiMap<String, Service>, AppsServiceError> {
+        Ok(filter.apply(self.get_apps().await?))
+    }
+
+    /// A replica is stale if the service that it has been replicated from runs with a different
+    /// image digest by now, e.g. because master has been redeployed with a new image.
+    fn is_stale_replica(service: &Service, running_services: &MultiMap<String, Service>) -> bool {
+        let config = service.config();
+        let (replicated_from, replicated_image_digest) =
+            match (config.replicated_from(), config.replicated_image_digest()) {
+                (Some(replicated_from), Some(image_digest)) => (replicated_from, image_digest),
+                _ => return false,
+            };
+
+        running_services
+            .get_vec(replicated_from)
+            .and_then(|services| {
+                services
+                    .iter()
+                    .find(|s| s.service_name() == service.service_name())
+            })
+            .and_then(|source| source.config().deployed_image_digest())
+            .map_or(false, |image_digest| {
+                image_digest != replicated_image_digest
+            })
+    }
+
+    /// Replicates the services of the app again from the app that its stale replicas have been
+    /// replicated from. Returns the redeployed services or an empty list if no replica is stale.
+    pub async fn refresh_stale_replicas(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        owner: Option<String>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let apps = self.get_apps().await?;
+        let services =
+            apps.get_vec(app_name.as_str())
+                .ok_or_else(|| AppsServiceError::AppNotFound {
+                    app_name: app_name.clone(),
+                })?;
+
+        let replicate_from = services
+            .iter()
+            .filter(|service| service.is_stale())
+            .filter_map(|service| service.config().replicated_from())
+            .find_map(|replicated_from| AppName::from_str(replicated_from).ok());
+
+        match replicate_from {
+            Some(replicate_from) => {
+                self.create_or_update(
+                    app_name,
+                    status_id,
+                    Some(replicate_from),
+                    &[],
+                    &[],
+                    &[],
+                    owner,
+                )
+                .await
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Deploys the services of an existing app under a new name, e.g. to fork the review app of a
+    /// colleague for testing a follow-up change. Only the instances of the source app are cloned
+    /// because the replicas and companions are resolved again for the new app, so that their
+    /// names and routes match the new app.
+    pub async fn clone_app(
+        &self,
+        app_name: &AppName,
+        source_app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        owner: Option<String>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let source_services = self
+            .infrastructure
+            .get_services()
+            .await?
+            .remove(source_app_name.as_str())
+            .unwrap_or_default();
+        if source_services.is_empty() {
+            return Err(AppsServiceError::AppNotFound {
+                app_name: source_app_name.clone(),
+            });
+        }
+
+        let replicate_from = source_services
+            .iter()
+            .filter_map(|service| service.config().replicated_from())
+            .find_map(|replicated_from| AppName::from_str(replicated_from).ok());
+        let (user_defined_companions, mut skipped_companions) =
+            requested_companions(&source_services);
+        let config = self.config();
+        skipped_companions.retain(|service_name| config.has_companion(service_name));
+
+        // The credentials and the tickets of the source app are derived for the new app by
+        // create_or_update, so they must not be taken over.
+        let service_configs = source_services
+            .into_iter()
+            .map(|service| service.config().clone())
+            .filter(|config| config.container_type() == &ContainerType::Instance)
+            .map(|mut config| {
+                config.set_deployed_fingerprint(None);
+                config.set_deployed_image_digest(None);
+                config.set_basic_auth(None);
+                config.set_tickets(Vec::new());
+                config.move_routes(source_app_name, app_name);
+                config
+            })
+            .collect::<Vec<_>>();
+
+        // The app must not exist while holding its guard, so that concurrent requests cannot
+        // create it in the meantime.
+        self.create_or_update_guarded(
+            app_name,
+            status_id,
+            replicate_from,
+            &service_configs,
+            &user_defined_companions,
+            &skipped_companions,
+            owner,
+            true,
+        )
+        .await
+    }
+
+    /// Recreates an app from the document of `GET /apps/{app}/export`, e.g. on another PREvant host.
+    /// The companions are resolved by the configuration of this host, together with the
+    /// user-defined companions and the skipped companions of the export, and the services and
+    /// companions that have been paused when the app was exported are paused again.
+    pub async fn import_app(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        export: &AppExport,
+        owner: Option<String>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        // Companions that this host does not know cannot be skipped, they are not deployed anyway.
+        let config = self.config();
+        let skipped_companions = export
+            .skipped_companions()
+            .iter()
+            .filter(|service_name| config.has_companion(service_name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut services = self
+            .create_or_update(
+                app_name,
+                status_id,
+                export.replicated_from(),
+                &export.service_configs(app_name),
+                export.user_defined_companions(),
+                &skipped_companions,
+                owner,
+            )
+            .await?;
+
+        for service_name in export.paused_services() {
+            let index = match services
+                .iter()
+                .position(|service| service.service_name() == service_name)
+            {
+                Some(index) => index,
+                None => continue,
+            };
+
+            if let Some(paused_service) = self
+                .change_status(app_name, service_name, ServiceStatus::Paused)
+                .await?
+            {
+                services[index] = paused_service;
+            }
+        }
+
+        Ok(services)
+    }
+
+    /// Returns the earliest restart of the service after the given timestamp according to the
+    /// configured restart schedules.
+    pub fn next_restart(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        after: &DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        self.config()
+            .restart_schedules(app_name, service_name)
+            .filter_map(|schedule| schedule.next_restart_after(after))
+            .min()
+    }
+
+    /// Returns `true` if any restart schedule of the service was due within `(since, until]`.
+    pub fn is_restart_due(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        since: &DateTime<Utc>,
+        until: &DateTime<Utc>,
+    ) -> bool {
+        self.config()
+            .restart_schedules(app_name, service_name)
+            .any(|schedule| schedule.is_due(since, until))
+    }
+
+    /// Restarts the service of the given app unless the app is currently deployed or deleted.
+    pub async fn restart_service(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.change_service(app_name, async {
+            let service = self
+                .infrastructure
+                .restart_service(app_name, service_name)
+                .await?;
+            if service.is_some() {
+                self.events
+                    .publish(AppEvent::service_restarted(app_name, service_name));
+            }
+            Ok(service)
+        })
+        .await
+    }
+
+    /// Applies the change to a single service of the app while holding the guard of the app, so
+    /// that the change cannot interleave with a deployment or a deletion of the same app.
+    async fn change_service<F>(
+        &self,
+        app_name: &AppName,
+        change: F,
+    ) -> Result<Option<Service>, AppsServiceError>
+    where
+        F: Future<Output = Result<Option<Service>, AppsServiceError>>,
+    {
+        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
+        if !guard.is_first() {
+            return Err(AppsServiceError::AppIsInDeployment {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let result = change.await;
+        guard.notify_with_result(
+            self,
+            result
+                .clone()
+                .map(|service| service.into_iter().collect::<Vec<_>>()),
+        );
+        result
+    }
+
+    pub fn webhook_deliveries(&self) -> &WebhookDeliveries {
+        &self.webhook_deliveries
+    }
+
+    pub fn freezes(&self) -> &Freezes {
+        &self.freezes
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    /// The lifecycle events of the apps, e.g. started deployments, changed service states, or
+    /// deletions.
+    pub fn events(&self) -> &AppEvents {
+        &self.events
+    }
+
+    /// The deployments and deletions that have been scheduled for later.
+    pub fn scheduled_operations(&self) -> &ScheduledOperations {
+        &self.scheduled_operations
+    }
+
+    /// Rejects changes of the given app while a freeze window is in effect for it. Apps that are
+    /// not running yet can be deployed anyway if the window allows new apps.
+    async fn ensure_not_frozen(&self, app_name: &str) -> Result<(), AppsServiceError> {
+        let now = Utc::now();
+        if self.freezes.active_freeze(app_name, &now, false).is_none() {
+            return Ok(());
+        }
+
+        let new_app = !self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name);
+        match self.freezes.active_freeze(app_name, &now, new_app) {
+            None => Ok(()),
+            Some((until, window)) => Err(AppsServiceError::AppIsFrozen {
+                app_name: String::from(app_name),
+                until,
+                reason: window
+                    .reason()
+                    .cloned()
+                    .unwrap_or_else(|| String::from("No reason given.")),
+            }),
+        }
+    }
+
+    async fn ensure_app_does_not_exist(&self, app_name: &AppName) -> Result<(), AppsServiceError> {
+        if self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name.as_str())
+        {
+            return Err(AppsServiceError::AppAlreadyExists {
+                app_name: app_name.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the features that the underlying infrastructure supports.
+    pub fn infrastructure_capabilities(&self) -> Capabilities {
+        self.infrastructure.capabilities()
+    }
+
+    /// Registers the idempotency key of a deployment request that will be processed under the
+    /// given status change. Replays of the key are answered by the returned outcome.
+    pub fn begin_idempotent_request(
+        &self,
+        key: &str,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> IdempotentRequest<IdempotentOutcome> {
+        self.idempotent_requests.begin(key, app_name, status_id)
+    }
+
+    /// Remembers the outcome of the request for replays of the idempotency key.
+    pub fn complete_idempotent_request(&self, key: &str, outcome: IdempotentOutcome) {
+        self.idempotent_requests.complete(key, outcome);
+    }
+
+    /// Forgets the idempotency key of a failed request, so that a replay processes the request
+    /// again.
+    pub fn forget_idempotent_request(&self, key: &str) {
+        self.idempotent_requests.forget(key);
+    }
+
+    /// Rejects all subsequent deployments and deletions, so that PREvant can shut down as soon as
+    /// the running ones have been [drained](AppsService::drain).
+    pub fn begin_shutdown(&self) {
+        // The flag is set while holding the guards so that no deployment can slip in after the
+        // guards have been checked by `drain`.
+        let _app_guards = self.app_guards.lock().unwrap();
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits until all running deployments and deletions have finished, and thus, until their
+    /// results have been recorded in the desired state. Returns `false` if they did not finish
+    /// within the given timeout.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        let drained = tokio::time::timeout(timeout, async {
+            loop {
+                let pending_apps = self
+                    .app_guards
+                    .lock()
+                    .unwrap()
+                    .keys()
+                    .map(|app_name| app_name.to_string())
+                    .collect::<Vec<_>>();
+                if pending_apps.is_empty() {
+                    break;
+                }
+
+                debug!("Waiting for the changes of {:?}", pending_apps);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await;
+        drained.is_ok()
     }
 
     fn create_or_get_app_guard(
@@ -152,6 +672,10 @@ impl AppsService {
         kind: AppGuardKind,
     ) -> Result<Arc<AppGuard>, AppsServiceError> {
         let mut apps_in_deletion = self.app_guards.lock().unwrap();
+        if self.shutting_down.load(Ordering::SeqCst) && !apps_in_deletion.contains_key(&app_name) {
+            return Err(AppsServiceError::ShuttingDown);
+        }
+
         let guard = &*apps_in_deletion
             .entry(app_name.clone())
             .or_insert_with(|| Arc::new(AppGuard::new(app_name.clone(), kind)));
@@ -192,8 +716,12 @@ impl AppsService {
             .filter(|config| !service_names.contains(config.service_name()))
             .filter(|config| !running_service_names.contains(config.service_name()))
             .map(|config| {
+                let image_digest = config.deployed_image_digest().cloned();
                 let mut replicated_config = config;
                 replicated_config.set_container_type(ContainerType::Replica);
+                replicated_config.set_deployed_image_digest(None);
+                replicated_config
+                    .set_replicated_from(Some(replicate_from_app_name.clone()), image_digest);
                 replicated_config
             })
             .collect::<Vec<ServiceConfig>>())
@@ -221,16 +749,53 @@ impl AppsService {
     /// - the replications from the running template application (e.g. master)
     /// - the application companions (see README)
     /// - the service companions (see README)
+    /// - the user-defined companions of the request
     ///
     /// # Arguments
     /// * `replicate_from` - The application name that is used as a template.
+    /// * `user_defined_companions` - Companions that are only deployed for this app and that
+    ///   take precedence over the companions of the server configuration.
+    /// * `skipped_companions` - Names of companions of the server configuration that must not be
+    ///   deployed for this app.
     pub async fn create_or_update(
         &self,
         app_name: &AppName,
         status_id: &AppStatusChangeId,
         replicate_from: Option<AppName>,
         service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+        owner: Option<String>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.create_or_update_guarded(
+            app_name,
+            status_id,
+            replicate_from,
+            service_configs,
+            user_defined_companions,
+            skipped_companions,
+            owner,
+            false,
+        )
+        .await
+    }
+
+    /// Performs [`create_or_update`](AppsService::create_or_update) while holding the guard of
+    /// the app. If `must_not_exist` is set, the deployment is refused if the app is running
+    /// already.
+    async fn create_or_update_guarded(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+        owner: Option<String>,
+        must_not_exist: bool,
     ) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_not_frozen(app_name).await?;
+
         let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
 
         if !guard.is_first() {
@@ -239,11 +804,57 @@ impl AppsService {
             });
         }
 
-        guard.notify_with_result(
+        if must_not_exist {
+            if let Err(err) = self.ensure_app_does_not_exist(app_name).await {
+                return guard.notify_with_result(self, Err(err));
+            }
+        }
+
+        self.events.publish(AppEvent::deployment_started(app_name));
+
+        let result = guard.notify_with_result(
             self,
-            self.create_or_update_impl(app_name, status_id, replicate_from, service_configs)
-                .await,
-        )
+            self.create_or_update_impl(
+                app_name,
+                status_id,
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+                owner.clone(),
+            )
+            .await,
+        );
+
+        self.webhook_deliveries.notify(match &result {
+            Ok(services) => DeploymentEvent::deployed(app_name, services),
+            Err(err) => DeploymentEvent::deployment_failed(app_name, err.to_string()),
+        });
+        self.events.publish(match &result {
+            Ok(services) => AppEvent::deployment_finished(app_name, services),
+            Err(err) => AppEvent::deployment_failed(app_name, err.to_string()),
+        });
+
+        self.audit_log.record(match &result {
+            Ok(services) => AuditEntry::new(
+                owner,
+                AuditAction::Deployment,
+                app_name,
+                services.iter().map(|s| s.service_name().clone()).collect(),
+            ),
+            Err(err) => AuditEntry::new(
+                owner,
+                AuditAction::Deployment,
+                app_name,
+                service_configs
+                    .iter()
+                    .map(|config| config.service_name().clone())
+                    .collect(),
+            )
+            .failed(err.to_string()),
+        });
+
+        result
     }
 
     async fn create_or_update_impl(
@@ -252,84 +863,609 @@ impl AppsService {
         status_id: &AppStatusChangeId,
         replicate_from: Option<AppName>,
         service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+        owner: Option<String>,
     ) -> Result<Vec<Service>, AppsServiceError> {
-        let mut configs = service_configs.iter().cloned().collect::<Vec<_>>();
+        let (mut configs, trace) = self
+            .plan_deployment_with_trace(
+                app_name,
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            )
+            .await?;
+        self.check_image_policy(&configs, &trace)?;
 
-        let replicate_from_app_name =
-            replicate_from.unwrap_or_else(|| AppName::from_str("master").unwrap());
-        if &replicate_from_app_name != app_name {
-            configs.extend(
-                self.configs_to_replicate(service_configs, app_name, &replicate_from_app_name)
-                    .await?,
-            );
+        let mut tickets = self.config().tickets_of(app_name);
+        for ticket in service_configs
+            .iter()
+            .chain(configs.iter())
+            .flat_map(|config| config.tickets())
+        {
+            if !tickets.contains(ticket) {
+                tickets.push(ticket.clone());
+            }
+        }
+
+        for config in configs.iter_mut() {
+            config.set_owner(owner.clone());
+            config.set_tickets(tickets.clone());
+            config.set_skipped_companions(skipped_companions.to_vec());
         }
 
-        let mut deployment_unit = DeploymentUnit::new(app_name.clone(), configs);
-        deployment_unit.extend_with_config(&self.config);
+        self.check_image_size_limit(app_name, &configs).await?;
 
-        let configs_for_templating = self
+        let running_services = self
             .infrastructure
-            .get_configs_of_app(app_name)
+            .get_services()
             .await?
+            .remove(app_name.as_str())
+            .unwrap_or_default();
+        let credentials =
+            self.protect_with_basic_auth(app_name, &mut configs, &running_services)?;
+        let (kept_companions, configs): (Vec<_>, Vec<_>) = configs
             .into_iter()
-            .filter(|config| config.container_type() == &ContainerType::Instance)
-            .filter(|config| {
-                !service_configs
+            .partition(|config| AppsService::is_kept_companion(config, &running_services));
+        if !kept_companions.is_empty() {
+            debug!(
+                "Keeping running companions of {}: {:?}",
+                app_name,
+                kept_companions
                     .iter()
-                    .any(|c| c.service_name() == config.service_name())
-            })
-            .collect::<Vec<_>>();
-        deployment_unit.extend_with_templating_only_service_configs(configs_for_templating);
-
-        let images = deployment_unit.images();
-        let port_mappings = ImagesService::new().resolve_image_ports(&images).await?;
-        deployment_unit.assign_port_mappings(&port_mappings);
+                    .map(|config| config.service_name())
+                    .collect::<Vec<_>>()
+            );
+        }
 
-        let configs: Vec<_> = deployment_unit.try_into()?;
-        let configs = self.apply_deployment_hook(app_name, configs).await?;
+        self.check_host_capacity(app_name, &configs, &running_services)
+            .await?;
 
-        let services = self
+        let mut services = self
             .infrastructure
             .deploy_services(
                 &status_id.to_string(),
                 app_name,
                 &configs,
-                &self.config.container_config(),
+                &self.config().container_config(),
             )
             .await?;
+        self.desired_state.record_deployment(
+            app_name,
+            configs
+                .iter()
+                .chain(kept_companions.iter())
+                .map(|config| config.service_name()),
+        );
+
+        for service in running_services {
+            let is_kept = kept_companions
+                .iter()
+                .any(|config| config.service_name() == service.service_name());
+            let is_listed = services
+                .iter()
+                .any(|s| s.service_name() == service.service_name());
+            if is_kept && !is_listed {
+                services.push(service);
+            }
+        }
+
+        if credentials.is_some() {
+            services = services
+                .into_iter()
+                .map(|service| {
+                    ServiceBuilder::from(service)
+                        .credentials(credentials.clone())
+                        .build()
+                        .expect("Rebuilding an existing service must not fail")
+                })
+                .collect();
+        }
 
         Ok(services)
     }
 
-    /// Deletes all services for the given `app_name`.
-    pub async fn delete_app(
+    /// Protects the routes of the services with basic auth if it is configured for the app. The
+    /// htpasswd entry of the running services is kept so that the users do not lose access on
+    /// updates. Otherwise, new credentials are generated and returned because their password is
+    /// only known right now.
+    fn protect_with_basic_auth(
         &self,
         app_name: &AppName,
-        status_id: &AppStatusChangeId,
-    ) -> Result<Vec<Service>, AppsServiceError> {
-        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deletion)?;
+        configs: &mut [ServiceConfig],
+        running_services: &[Service],
+    ) -> Result<Option<BasicAuthCredentials>, AppsServiceError> {
+        let config = self.config();
+        let basic_auth = match config.basic_auth_for(app_name) {
+            Some(basic_auth) => basic_auth,
+            None => return Ok(None),
+        };
 
-        if !guard.is_first() {
-            guard.wait_for_result()
-        } else {
-            guard.notify_with_result(self, self.delete_app_impl(app_name, status_id).await)
+        if !self.infrastructure.capabilities().basic_auth() {
+            return Err(AppsServiceError::BasicAuthNotSupported {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let (htpasswd, credentials) = match running_services
+            .iter()
+            .find_map(|service| service.config().basic_auth())
+        {
+            Some(htpasswd) => (htpasswd.clone(), None),
+            None => {
+                let credentials = BasicAuthCredentials::generate(basic_auth.username());
+                (credentials.htpasswd(), Some(credentials))
+            }
+        };
+
+        for config in configs.iter_mut() {
+            config.set_basic_auth(Some(htpasswd.clone()));
         }
+
+        Ok(credentials)
     }
 
-    async fn delete_app_impl(
+    /// Refuses the deployment if the images of the planned services violate the image policy, i.e.
+    /// after the images have been rewritten and the deployment hook has been applied. The
+    /// companions of the server configuration are trusted.
+    fn check_image_policy(
         &self,
-        app_name: &AppName,
-        status_id: &AppStatusChangeId,
-    ) -> Result<Vec<Service>, AppsServiceError> {
-        let services = self
-            .infrastructure
-            .stop_services(&status_id.to_string(), app_name)
-            .await?;
-        if services.is_empty() {
-            Err(AppsServiceError::AppNotFound {
-                app_name: app_name.clone(),
-            })
-        } else {
+        configs: &[ServiceConfig],
+        trace: &DeploymentTrace,
+    ) -> Result<(), AppsServiceError> {
+        let images_config = self.config().images_config();
+        let policy = images_config.policy();
+
+        let violations = configs
+            .iter()
+            .filter(|config| !AppsService::is_companion_of_config(config, trace))
+            .flat_map(|config| {
+                policy
+                    .violations(config.image())
+                    .into_iter()
+                    .map(move |violation| format!("{}: {}", config.service_name(), violation))
+            })
+            .collect::<Vec<_>>();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AppsServiceError::ImagePolicyViolation { violations })
+        }
+    }
+
+    /// Returns `true` if the service has been created from a companion of the server configuration
+    /// and has not been modified by the request or the deployment hook afterwards.
+    fn is_companion_of_config(config: &ServiceConfig, trace: &DeploymentTrace) -> bool {
+        let steps = trace.steps_of(config.service_name());
+        let from_config = steps.iter().any(|step| {
+            matches!(
+                step,
+                TraceStep::ApplicationCompanion {
+                    user_defined: false,
+                    ..
+                } | TraceStep::ServiceCompanion {
+                    user_defined: false,
+                    ..
+                }
+            )
+        });
+        let modified = steps.iter().any(|step| {
+            matches!(
+                step,
+                TraceStep::Payload
+                    | TraceStep::MergedWithCompanion { .. }
+                    | TraceStep::DeploymentHook
+            )
+        });
+        from_config && !modified
+    }
+
+    /// Resolves the total size of the images of the app, if a limit is configured, and warns about
+    /// or refuses apps that exceed the limit. Images that are shared by multiple services are
+    /// counted once because they are only stored once on the host.
+    async fn check_image_size_limit(
+        &self,
+        app_name: &AppName,
+        configs: &[ServiceConfig],
+    ) -> Result<(), AppsServiceError> {
+        let images_config = self.config().images_config();
+        let max_app_size = match images_config.max_app_size() {
+            Some(max_app_size) => max_app_size,
+            None => return Ok(()),
+        };
+
+        let images = configs
+            .iter()
+            .map(|config| config.image().clone())
+            .collect::<HashSet<_>>();
+        let image_sizes = ImagesService::new().resolve_image_sizes(&images).await?;
+        let app_size = image_sizes.values().sum::<u64>();
+        debug!(
+            "The images of {} have a size of {} bytes",
+            app_name, app_size
+        );
+
+        if app_size <= max_app_size {
+            return Ok(());
+        }
+
+        match images_config.size_limit_action() {
+            SizeLimitAction::Warn => {
+                warn!(
+                    "The images of {} have a size of {} bytes which exceeds the limit of {} bytes",
+                    app_name, app_size, max_app_size
+                );
+                Ok(())
+            }
+            SizeLimitAction::Refuse => Err(AppsServiceError::AppExceedsImageSizeLimit {
+                app_name: app_name.clone(),
+                size: app_size,
+                limit: max_app_size,
+            }),
+        }
+    }
+
+    /// Refuses deployments that add containers to a host whose capacity is exhausted. Updates of
+    /// running services are not refused because they replace the existing containers.
+    async fn check_host_capacity(
+        &self,
+        app_name: &String,
+        configs: &[ServiceConfig],
+        running_services: &[Service],
+    ) -> Result<(), AppsServiceError> {
+        let capacity_config = self.config().capacity_config();
+        if !capacity_config.is_limited() {
+            return Ok(());
+        }
+
+        let additional_containers = configs
+            .iter()
+            .filter(|config| {
+                !running_services
+                    .iter()
+                    .any(|service| service.service_name() == config.service_name())
+            })
+            .count();
+        if additional_containers == 0 {
+            return Ok(());
+        }
+
+        let capacity = match self.infrastructure.get_host_capacity(app_name).await? {
+            Some(capacity) => capacity,
+            None => return Ok(()),
+        };
+        debug!("Capacity of the host: {:?}", capacity);
+
+        let violations = capacity_config.violations(&capacity, additional_containers);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AppsServiceError::InsufficientCapacity { violations })
+        }
+    }
+
+    /// Decides, based on the deployment strategy of the companion, if a running companion is kept
+    /// as is. By default, companions are only redeployed if their rendered configuration differs
+    /// from the configuration of the running companion. Thus, deployments that only change the
+    /// services of the user do not restart heavy companions, such as databases.
+    fn is_kept_companion(config: &ServiceConfig, running_services: &[Service]) -> bool {
+        match config.container_type() {
+            ContainerType::ApplicationCompanion | ContainerType::ServiceCompanion => {}
+            _ => return false,
+        }
+
+        let running_companion = running_services.iter().find(|service| {
+            service.service_name() == config.service_name()
+                && service.status() == &ServiceStatus::Running
+        });
+        let running_companion = match running_companion {
+            Some(running_companion) => running_companion,
+            None => return false,
+        };
+
+        match config.deployment_strategy() {
+            DeploymentStrategy::RedeployAlways => false,
+            DeploymentStrategy::RedeployOnImageUpdate => {
+                running_companion.config().deployed_fingerprint() == Some(&config.fingerprint())
+            }
+            DeploymentStrategy::RedeployNever => true,
+        }
+    }
+
+    /// Resolves the service configurations that would be deployed for the given app, i.e. it
+    /// replicates services, applies companions, templating, and the deployment hook, but it does not
+    /// touch the infrastructure.
+    pub async fn plan_deployment(
+        &self,
+        app_name: &AppName,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+    ) -> Result<Vec<ServiceConfig>, AppsServiceError> {
+        let (configs, _trace) = self
+            .plan_deployment_with_trace(
+                app_name,
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            )
+            .await?;
+        Ok(configs)
+    }
+
+    /// Plans the deployment and checks the planned services against the image policy without
+    /// touching the infrastructure, e.g. to validate a deployment before it is scheduled.
+    pub async fn validate_deployment(
+        &self,
+        app_name: &AppName,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+    ) -> Result<(), AppsServiceError> {
+        let (configs, trace) = self
+            .plan_deployment_with_trace(
+                app_name,
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            )
+            .await?;
+        self.check_image_policy(&configs, &trace)
+    }
+
+    /// Like `plan_deployment` but additionally returns a trace of how each service configuration
+    /// has been produced, in order to debug surprising deployments.
+    pub async fn plan_deployment_with_trace(
+        &self,
+        app_name: &AppName,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+    ) -> Result<(Vec<ServiceConfig>, DeploymentTrace), AppsServiceError> {
+        let apps_config = self.config();
+        let unknown_companions = skipped_companions
+            .iter()
+            .filter(|name| !apps_config.has_companion(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown_companions.is_empty() {
+            return Err(AppsServiceError::UnknownCompanions {
+                names: unknown_companions.join(", "),
+            });
+        }
+
+        let mut deployment_unit =
+            DeploymentUnit::new(app_name.clone(), service_configs.iter().cloned().collect());
+
+        let replicate_from_app_name =
+            replicate_from.unwrap_or_else(|| AppName::from_str("master").unwrap());
+        if &replicate_from_app_name != app_name {
+            deployment_unit.extend_with_replicated_service_configs(
+                &replicate_from_app_name,
+                self.configs_to_replicate(service_configs, app_name, &replicate_from_app_name)
+                    .await?,
+            );
+        }
+
+        deployment_unit.extend_with_config(&apps_config, skipped_companions);
+        deployment_unit.extend_with_user_defined_companions(user_defined_companions);
+
+        let configs_for_templating = self
+            .infrastructure
+            .get_configs_of_app(app_name)
+            .await?
+            .into_iter()
+            .filter(|config| config.container_type() == &ContainerType::Instance)
+            .filter(|config| {
+                !service_configs
+                    .iter()
+                    .any(|c| c.service_name() == config.service_name())
+            })
+            .collect::<Vec<_>>();
+        deployment_unit.extend_with_templating_only_service_configs(configs_for_templating);
+
+        deployment_unit.rewrite_images(&apps_config.images_config());
+
+        let images = deployment_unit.images();
+        let port_mappings = ImagesService::new().resolve_image_ports(&images).await?;
+        deployment_unit.assign_port_mappings(&port_mappings);
+
+        let (mut configs, mut trace) = deployment_unit.try_into_with_trace()?;
+        for config in configs.iter_mut() {
+            let labels = config.labels().cloned();
+            apps_config.add_labels_to(config);
+            if config.labels() != labels.as_ref() {
+                trace.record(config.service_name(), TraceStep::GlobalLabels);
+            }
+
+            let routing = config.routing().cloned();
+            apps_config.add_routing_to(config);
+            if config.routing() != routing.as_ref() {
+                trace.record(config.service_name(), TraceStep::GlobalRouting);
+            }
+        }
+
+        let configs_before_hook = configs.clone();
+        let configs = self.apply_deployment_hook(app_name, configs).await?;
+        for config in configs.iter() {
+            if !configs_before_hook.contains(config) {
+                trace.record(config.service_name(), TraceStep::DeploymentHook);
+            }
+        }
+        trace.retain_services(configs.iter().map(|config| config.service_name()));
+
+        deployment_waves(&configs)?;
+        Ok((configs, trace))
+    }
+
+    /// Queues the deployment of the app so that it will be performed at the given point in time
+    /// (see [`create_or_update`](AppsService::create_or_update)). The image policy is checked
+    /// upfront on the planned deployment, so that invalid deployments are rejected right away.
+    pub async fn schedule_deployment(
+        &self,
+        app_name: &AppName,
+        run_at: DateTime<Utc>,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        user_defined_companions: &[Companion],
+        skipped_companions: &[String],
+        owner: Option<String>,
+    ) -> Result<ScheduledOperation, AppsServiceError> {
+        self.validate_deployment(
+            app_name,
+            replicate_from.clone(),
+            service_configs,
+            user_defined_companions,
+            skipped_companions,
+        )
+        .await?;
+
+        let operation = ScheduledOperation::new(
+            app_name.clone(),
+            ScheduledChange::Deployment {
+                replicate_from,
+                service_configs: service_configs.to_vec(),
+                user_defined_companions: user_defined_companions.to_vec(),
+                skipped_companions: skipped_companions.to_vec(),
+            },
+            run_at,
+            owner,
+        );
+        info!("Scheduled deployment of {} at {}", app_name, run_at);
+        self.scheduled_operations.schedule(operation.clone());
+        Ok(operation)
+    }
+
+    /// Queues the deletion of the app so that it will be performed at the given point in time,
+    /// e.g. during off-hours.
+    pub fn schedule_deletion(
+        &self,
+        app_name: &AppName,
+        run_at: DateTime<Utc>,
+        owner: Option<String>,
+    ) -> ScheduledOperation {
+        let operation =
+            ScheduledOperation::new(app_name.clone(), ScheduledChange::Deletion, run_at, owner);
+        info!("Scheduled deletion of {} at {}", app_name, run_at);
+        self.scheduled_operations.schedule(operation.clone());
+        operation
+    }
+
+    /// Performs the scheduled operations that are due at the given point in time. Operations that
+    /// are blocked by a freeze window are postponed until the end of the window's current period.
+    pub async fn run_scheduled_operations(&self, now: &DateTime<Utc>) {
+        for operation in self.scheduled_operations.take_due(now) {
+            let app_name = operation.app_name();
+            let status_id = AppStatusChangeId::new();
+            let owner = operation.owner().cloned();
+
+            let result = match operation.change() {
+                ScheduledChange::Deployment {
+                    replicate_from,
+                    service_configs,
+                    user_defined_companions,
+                    skipped_companions,
+                } => {
+                    info!("Performing scheduled deployment of {}", app_name);
+                    self.create_or_update(
+                        app_name,
+                        &status_id,
+                        replicate_from.clone(),
+                        service_configs,
+                        user_defined_companions,
+                        skipped_companions,
+                        owner,
+                    )
+                    .await
+                }
+                ScheduledChange::Deletion => {
+                    info!("Performing scheduled deletion of {}", app_name);
+                    self.delete_app(app_name, &status_id, owner).await
+                }
+            };
+
+            match result {
+                Ok(_) => {}
+                Err(AppsServiceError::AppIsFrozen { until, .. }) => {
+                    info!(
+                        "Postponing scheduled operation {} of {} until {}",
+                        operation.id(),
+                        app_name,
+                        until
+                    );
+                    self.scheduled_operations
+                        .schedule(operation.postponed(until));
+                }
+                Err(err) => error!(
+                    "Scheduled operation {} of {} failed: {}",
+                    operation.id(),
+                    app_name,
+                    err
+                ),
+            }
+        }
+    }
+
+    /// Deletes all services for the given `app_name`.
+    pub async fn delete_app(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        user: Option<String>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_not_frozen(app_name).await?;
+
+        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deletion)?;
+
+        if !guard.is_first() {
+            guard.wait_for_result().await
+        } else {
+            let result =
+                guard.notify_with_result(self, self.delete_app_impl(app_name, status_id).await);
+
+            if let Ok(services) = &result {
+                self.desired_state.record_deletion(app_name);
+                self.webhook_deliveries
+                    .notify(DeploymentEvent::deleted(app_name, services));
+                self.events.publish(AppEvent::deleted(app_name, services));
+            }
+
+            self.audit_log.record(match &result {
+                Ok(services) => AuditEntry::new(
+                    user,
+                    AuditAction::Deletion,
+                    app_name,
+                    services.iter().map(|s| s.service_name().clone()).collect(),
+                ),
+                Err(err) => AuditEntry::new(user, AuditAction::Deletion, app_name, Vec::new())
+                    .failed(err.to_string()),
+            });
+
+            result
+        }
+    }
+
+    async fn delete_app_impl(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let services = self
+            .infrastructure
+            .stop_services(&status_id.to_string(), app_name)
+            .await?;
+        if services.is_empty() {
+            Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            })
+        } else {
             Ok(services)
         }
     }
@@ -352,17 +1488,202 @@ impl AppsService {
         }
     }
 
-    pub async fn change_status(
+    /// Executes the command within the container of the service and returns the channel that
+    /// provides the output of the command, or `None` if there is no such service.
+    pub async fn exec(
         &self,
-        app_name: &String,
+        app_name: &AppName,
         service_name: &String,
-        status: ServiceStatus,
-    ) -> Result<Option<Service>, AppsServiceError> {
+        command: &[String],
+    ) -> Result<Option<Receiver<Result<ExecOutput, failure::Error>>>, AppsServiceError> {
+        if !self.infrastructure.capabilities().exec() {
+            return Err(AppsServiceError::ExecNotSupported);
+        }
+
         Ok(self
             .infrastructure
-            .change_status(app_name, service_name, status)
+            .exec(app_name, service_name, command)
             .await?)
     }
+
+    /// Returns the resource usage of the services of the given app or `None` if there is no such
+    /// app.
+    pub async fn get_stats(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Option<Vec<ServiceStats>>, AppsServiceError> {
+        if !self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name.as_str())
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(self.infrastructure.get_stats(app_name).await?))
+    }
+
+    pub async fn change_status(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+        status: ServiceStatus,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.ensure_not_frozen(app_name).await?;
+
+        self.change_service(app_name, async {
+            let service = self
+                .infrastructure
+                .change_status(app_name, service_name, status.clone())
+                .await?;
+            if service.is_some() {
+                self.events.publish(AppEvent::service_status_changed(
+                    app_name,
+                    service_name,
+                    status.clone(),
+                ));
+                self.desired_state
+                    .record_status(app_name, service_name, status);
+            }
+            Ok(service)
+        })
+        .await
+    }
+
+    /// Returns the leftovers on the infrastructure, e.g. containers of interrupted deployments.
+    /// Apps that are currently deployed or deleted are skipped because their resources are
+    /// incomplete until the operation finishes.
+    pub async fn get_orphans(&self) -> Result<Vec<Orphan>, AppsServiceError> {
+        let orphans = self.infrastructure.get_orphans().await?;
+        let app_guards = self.app_guards.lock().unwrap();
+        Ok(orphans
+            .into_iter()
+            .filter(|orphan| {
+                !app_guards
+                    .keys()
+                    .any(|app_name| app_name.as_str() == orphan.app_name())
+            })
+            .collect())
+    }
+
+    /// Removes the current orphans, see [`get_orphans`](AppsService::get_orphans), and returns the
+    /// removed ones.
+    pub async fn remove_orphans(&self) -> Result<Vec<Orphan>, AppsServiceError> {
+        let orphans = self.get_orphans().await?;
+        if orphans.is_empty() {
+            return Ok(orphans);
+        }
+        Ok(self.infrastructure.remove_orphans(&orphans).await?)
+    }
+
+    /// Checks whether PREvant is able to operate properly: whether the configuration can be
+    /// applied, the infrastructure is reachable, the infrastructure specific requirements are
+    /// met, and the app `master`, which serves as the default source of replicas, exists. The
+    /// infrastructure may apply repairs, thus, this should only run while PREvant starts.
+    pub async fn run_diagnostics(&self) -> DiagnosticsReport {
+        let mut checks = Vec::new();
+
+        let master = AppName::from_str("master").unwrap();
+        checks.push(
+            match self.plan_deployment(&master, None, &[], &[], &[]).await {
+                Ok(_) => DiagnosticCheck::passed(
+                    "configuration",
+                    String::from("Companions and templates of the configuration can be applied."),
+                ),
+                Err(err) => DiagnosticCheck::failed("configuration", err.to_string())
+                    .with_suggested_repair(String::from(
+                        "Fix the configuration and restart PREvant.",
+                    )),
+            },
+        );
+
+        match self.infrastructure.get_services().await {
+            Ok(services) => {
+                checks.push(DiagnosticCheck::passed(
+                    "infrastructure",
+                    format!(
+                        "The infrastructure is reachable and runs {} apps.",
+                        services.len()
+                    ),
+                ));
+
+                match self.infrastructure.diagnose().await {
+                    Ok(infrastructure_checks) => checks.extend(infrastructure_checks),
+                    Err(err) => checks.push(DiagnosticCheck::failed(
+                        "infrastructure-requirements",
+                        err.to_string(),
+                    )),
+                }
+
+                checks.push(if services.contains_key(master.as_str()) {
+                    DiagnosticCheck::passed("base-app", String::from("The app master exists."))
+                } else {
+                    DiagnosticCheck::warning(
+                        "base-app",
+                        String::from("The app master does not exist, thus, other apps cannot replicate its services."),
+                    )
+                    .with_suggested_repair(String::from("Deploy the app master."))
+                });
+            }
+            Err(err) => checks.push(
+                DiagnosticCheck::failed("infrastructure", err.to_string()).with_suggested_repair(
+                    String::from("Check the connection to the infrastructure and restart PREvant."),
+                ),
+            ),
+        }
+
+        let report = DiagnosticsReport::new(checks);
+        *self.diagnostics.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Returns the report of the last diagnostic pass, if any.
+    pub fn diagnostics(&self) -> Option<DiagnosticsReport> {
+        self.diagnostics.lock().unwrap().clone()
+    }
+
+    /// Compares the recorded desired state with the services of the infrastructure and starts the
+    /// services that are supposed to run but have been stopped, e.g. because the host has been
+    /// restarted. Returns the started services.
+    pub async fn reconcile_desired_state(&self) -> Result<Vec<Service>, AppsServiceError> {
+        let running_services = self.infrastructure.get_services().await?;
+
+        let mut started_services = Vec::new();
+        for (app_name, desired_services) in self.desired_state.apps() {
+            let services = running_services.get_vec(&app_name);
+            for (service_name, desired_status) in desired_services {
+                if desired_status != ServiceStatus::Running {
+                    continue;
+                }
+
+                let service = services.and_then(|services| {
+                    services
+                        .iter()
+                        .find(|service| service.service_name() == &service_name)
+                });
+                match service {
+                    None => warn!(
+                        "Cannot restore {} of {} because it does not exist anymore",
+                        service_name, app_name
+                    ),
+                    Some(service) if service.status() == &ServiceStatus::Running => {}
+                    Some(_) => {
+                        info!("Starting {} of {} again", service_name, app_name);
+                        if let Some(service) = self
+                            .infrastructure
+                            .change_status(&app_name, &service_name, ServiceStatus::Running)
+                            .await?
+                        {
+                            started_services.push(service);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(started_services)
+    }
 }
 
 /// Defines error cases for the `AppService`
@@ -371,6 +1692,8 @@ pub enum AppsServiceError {
     /// Will be used when no app with a given name is found
     #[fail(display = "Cannot find app {}.", app_name)]
     AppNotFound { app_name: AppName },
+    #[fail(display = "The app {} already exists.", app_name)]
+    AppAlreadyExists { app_name: AppName },
     #[fail(
         display = "The app {} is currently within deployment by another request.",
         app_name
@@ -381,9 +1704,23 @@ pub enum AppsServiceError {
         app_name
     )]
     AppIsInDeletion { app_name: AppName },
+    /// Will be used when the app must not be changed due to a freeze window.
+    #[fail(
+        display = "The app {} cannot be changed until {} due to a deployment freeze: {}",
+        app_name, until, reason
+    )]
+    AppIsFrozen {
+        app_name: String,
+        until: DateTime<Utc>,
+        reason: String,
+    },
     /// Will be used when the service cannot interact correctly with the infrastructure.
     #[fail(display = "Cannot interact with infrastructure: {}", error)]
     InfrastructureError { error: Arc<failure::Error> },
+    /// Will be used when the interaction with the infrastructure failed due to a transient cause,
+    /// e.g. a registry timeout, even after retrying. Thus, the request may be retried later.
+    #[fail(display = "Temporarily cannot interact with infrastructure: {}", error)]
+    TransientInfrastructureError { error: Arc<failure::Error> },
     /// Will be used if the service configuration cannot be loaded.
     #[fail(display = "Invalid configuration: {}", error)]
     InvalidServerConfiguration { error: Arc<ConfigError> },
@@ -391,8 +1728,52 @@ pub enum AppsServiceError {
     InvalidTemplateFormat { error: Arc<TemplateRenderError> },
     #[fail(display = "Unable to resolve information about image: {}", error)]
     UnableToResolveImage { error: ImagesServiceError },
+    #[fail(display = "Invalid service dependencies: {}", error)]
+    InvalidServiceDependencies { error: DependencyCycleError },
     #[fail(display = "Invalid deployment hook.")]
     InvalidDeploymentHook,
+    #[fail(
+        display = "The images of {} have a size of {} bytes which exceeds the limit of {} bytes.",
+        app_name, size, limit
+    )]
+    AppExceedsImageSizeLimit {
+        app_name: AppName,
+        size: u64,
+        limit: u64,
+    },
+    /// Will be used if images of a deployment are not allowed by the image policy.
+    #[fail(display = "The images violate the image policy: {:?}", violations)]
+    ImagePolicyViolation { violations: Vec<String> },
+    #[fail(display = "Cannot skip unknown companions: {}", names)]
+    UnknownCompanions { names: String },
+    /// Will be used if the deployment would exceed the configured capacity of the host.
+    #[fail(display = "The host has not enough capacity: {:?}", violations)]
+    InsufficientCapacity { violations: Vec<String> },
+    /// Will be used if basic auth is configured for an app but the infrastructure cannot protect
+    /// the routes of its services.
+    #[fail(
+        display = "The infrastructure cannot protect the app {} with basic auth.",
+        app_name
+    )]
+    BasicAuthNotSupported { app_name: AppName },
+    /// Will be used if the infrastructure cannot execute commands within containers.
+    #[fail(display = "The infrastructure cannot execute commands within containers.")]
+    ExecNotSupported,
+    /// Will be used if PREvant is shutting down and waits for the running deployments.
+    #[fail(display = "PREvant is shutting down and does not accept changes of apps.")]
+    ShuttingDown,
+    /// Will be used if the payload describes services that cannot be deployed, e.g. due to invalid
+    /// image references.
+    #[fail(display = "Invalid service: {}", error)]
+    InvalidServiceModel { error: Arc<ServiceError> },
+}
+
+impl From<ServiceError> for AppsServiceError {
+    fn from(error: ServiceError) -> Self {
+        AppsServiceError::InvalidServiceModel {
+            error: Arc::new(error),
+        }
+    }
 }
 
 impl From<ConfigError> for AppsServiceError {
@@ -405,8 +1786,24 @@ impl From<ConfigError> for AppsServiceError {
 
 impl From<failure::Error> for AppsServiceError {
     fn from(error: failure::Error) -> Self {
-        AppsServiceError::InfrastructureError {
-            error: Arc::new(error),
+        let transient = error
+            .downcast_ref::<TransientInfrastructureError>()
+            .is_some()
+            || error
+                .downcast_ref::<ServiceDeploymentError>()
+                .map_or(false, |err| err.is_transient())
+            || error
+                .downcast_ref::<ServicesDeploymentError>()
+                .map_or(false, |err| err.is_transient());
+
+        if transient {
+            AppsServiceError::TransientInfrastructureError {
+                error: Arc::new(error),
+            }
+        } else {
+            AppsServiceError::InfrastructureError {
+                error: Arc::new(error),
+            }
         }
     }
 }
@@ -419,6 +1816,12 @@ impl From<TemplateRenderError> for AppsServiceError {
     }
 }
 
+impl From<DependencyCycleError> for AppsServiceError {
+    fn from(error: DependencyCycleError) -> Self {
+        AppsServiceError::InvalidServiceDependencies { error }
+    }
+}
+
 impl From<ImagesServiceError> for AppsServiceError {
     fn from(error: ImagesServiceError) -> Self {
         AppsServiceError::UnableToResolveImage { error }
@@ -430,7 +1833,7 @@ mod tests {
 
     use super::*;
     use crate::infrastructure::Dummy;
-    use crate::models::{EnvironmentVariable, Image, ServiceBuilder};
+    use crate::models::{CheckStatus, EnvironmentVariable, Image, ServiceBuilder};
     use chrono::Utc;
     use sha2::{Digest, Sha256};
     use std::path::PathBuf;
@@ -488,6 +1891,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -501,33 +1907,1148 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn should_replication_from_master() -> Result<(), AppsServiceError> {
+    async fn should_publish_lifecycle_events() -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
+        let mut events = apps.events().subscribe();
 
+        let app_name = AppName::from_str("master").unwrap();
         apps.create_or_update(
-            &AppName::from_str("master").unwrap(),
+            &app_name,
             &AppStatusChangeId::new(),
             None,
-            &service_configs!("service-a", "service-b"),
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
+        apps.change_status(&app_name, &String::from("service-a"), ServiceStatus::Paused)
+            .await?;
+        apps.delete_app(&app_name, &AppStatusChangeId::new(), None)
+            .await?;
 
-        apps.create_or_update(
+        let names = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| event.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "deployment-started",
+                "deployment-finished",
+                "service-status-changed",
+                "deleted"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_record_owner_of_deployed_services() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            Some(String::from("john.doe")),
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("master").unwrap();
+        assert_eq!(services[0].owner(), Some(&String::from("john.doe")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_protect_services_with_basic_auth() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [basicAuth]
+            username = 'reviewer'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("master").unwrap();
+
+        let services = apps
+            .create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+                None,
+            )
+            .await?;
+        let credentials = services[0].credentials().cloned().unwrap();
+        assert_eq!(credentials.username(), "reviewer");
+
+        let deployed_apps = apps.get_apps().await?;
+        let service = &deployed_apps.get_vec("master").unwrap()[0];
+        assert_eq!(service.credentials(), None);
+        assert_eq!(service.config().basic_auth(), Some(&credentials.htpasswd()));
+
+        let services = apps
+            .create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a", "service-b"),
+                &[],
+                &[],
+                None,
+            )
+            .await?;
+        for service in services {
+            assert_eq!(service.credentials(), None);
+            assert_eq!(service.config().basic_auth(), Some(&credentials.htpasswd()));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_refuse_deployment_exceeding_host_capacity() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [capacity]
+            maxContainers = 2
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("other").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-c"),
+                &[],
+                &[],
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InsufficientCapacity { .. })
+        ));
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_link_services_to_tickets() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let mut service_configs = service_configs!("service-a", "service-b");
+        service_configs[0].set_tickets(vec![String::from("OPS-7"), String::from("PROJ-123")]);
+
+        apps.create_or_update(
+            &AppName::from_str("feat-PROJ-123").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs,
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        for service in deployed_apps.get_vec("feat-PROJ-123").unwrap() {
+            assert_eq!(
+                service.tickets(),
+                &[String::from("PROJ-123"), String::from("OPS-7")]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_redeploy_unchanged_companions() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+
+            [companions.db]
+            serviceName = 'db'
+            type = 'application'
+            image = 'private.example.com/library/db:latest'
+            env = [ 'SERVICES={{#each services}}{{name}},{{/each}}' ]
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("master").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            Some(String::from("john.doe")),
+        )
+        .await?;
+        let services = apps
+            .create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-b"),
+                &[],
+                &[],
+                Some(String::from("jane.doe")),
+            )
+            .await?;
+
+        assert!(services
+            .iter()
+            .any(|service| service.service_name() == "openid"));
+
+        let deployed_apps = apps.get_apps().await?;
+        let owner_of = |service_name: &str| {
+            deployed_apps
+                .get_vec("master")
+                .unwrap()
+                .iter()
+                .find(|service| service.service_name() == service_name)
+                .unwrap()
+                .owner()
+                .cloned()
+        };
+        assert_eq!(owner_of("openid"), Some(String::from("john.doe")));
+        assert_eq!(owner_of("db"), Some(String::from("jane.doe")));
+        assert_eq!(owner_of("service-b"), Some(String::from("jane.doe")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_redeploy_companions_according_to_deployment_strategy(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            deploymentStrategy = 'redeploy-always'
+
+            [companions.db]
+            serviceName = 'db'
+            type = 'application'
+            image = 'private.example.com/library/db:latest'
+            env = [ 'SERVICES={{#each services}}{{name}},{{/each}}' ]
+            deploymentStrategy = 'redeploy-never'
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("master").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            Some(String::from("john.doe")),
+        )
+        .await?;
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-b"),
+            &[],
+            &[],
+            Some(String::from("jane.doe")),
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let owner_of = |service_name: &str| {
+            deployed_apps
+                .get_vec("master")
+                .unwrap()
+                .iter()
+                .find(|service| service.service_name() == service_name)
+                .unwrap()
+                .owner()
+                .cloned()
+        };
+        assert_eq!(owner_of("openid"), Some(String::from("jane.doe")));
+        assert_eq!(owner_of("db"), Some(String::from("john.doe")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_provide_next_restart_of_scheduled_services() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [restarts.nightly]
+            schedule = '0 0 3 * * *'
+            services = [ 'service-a' ]
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("master").unwrap();
+        let service_a = services
+            .iter()
+            .find(|service| service.service_name() == "service-a")
+            .unwrap();
+        let service_b = services
+            .iter()
+            .find(|service| service.service_name() == "service-b")
+            .unwrap();
+
+        assert!(service_a.next_restart().is_some());
+        assert_eq!(service_b.next_restart(), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_replication_from_master() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        apps.create_or_update(
             &AppName::from_str("branch").unwrap(),
             &AppStatusChangeId::new(),
-            Some(AppName::from_str("master").unwrap()),
+            Some(AppName::from_str("master").unwrap()),
+            &service_configs!("service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+
+        let services = deployed_apps.get_vec("branch").unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "service-b", ContainerType::Instance);
+        assert_contains_service!(services, "service-a", ContainerType::Replica);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_diagnose_missing_base_app() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let report = apps.run_diagnostics().await;
+
+        let status_of = |name: &str| {
+            report
+                .checks()
+                .iter()
+                .find(|check| check.name() == name)
+                .map(|check| check.status().clone())
+        };
+        assert_eq!(status_of("configuration"), Some(CheckStatus::Passed));
+        assert_eq!(status_of("infrastructure"), Some(CheckStatus::Passed));
+        assert_eq!(status_of("base-app"), Some(CheckStatus::Warning));
+        assert!(apps.diagnostics().is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_start_stopped_services_of_desired_state() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+        let app_name = AppName::from_str("master").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.change_status(&app_name, &String::from("service-b"), ServiceStatus::Paused)
+            .await?;
+        // Pretend that the host has been restarted which stops all services
+        apps.infrastructure
+            .change_status(
+                &String::from("master"),
+                &String::from("service-a"),
+                ServiceStatus::Paused,
+            )
+            .await?;
+
+        let started_services = apps.reconcile_desired_state().await?;
+
+        assert_eq!(started_services.len(), 1);
+        assert_eq!(started_services[0].service_name(), "service-a");
+        let deployed_apps = apps.get_apps().await?;
+        let status_of = |service_name: &str| {
+            deployed_apps
+                .get_vec("master")
+                .unwrap()
+                .iter()
+                .find(|service| service.service_name() == service_name)
+                .unwrap()
+                .status()
+                .clone()
+        };
+        assert_eq!(status_of("service-a"), ServiceStatus::Running);
+        assert_eq!(status_of("service-b"), ServiceStatus::Paused);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_images_violating_the_image_policy() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [images.policy]
+            allowed = [ 'registry.example.com/.*' ]
+            forbiddenTags = [ 'latest' ]
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("master").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &[
+                    crate::sc!("backend", "registry.example.com/backend:1.0"),
+                    crate::sc!("miner", "ghcr.io/someone/miner:latest"),
+                ],
+                &[],
+                &[],
+                None,
+            )
+            .await;
+        match result {
+            Err(AppsServiceError::ImagePolicyViolation { violations }) => assert_eq!(
+                violations,
+                vec![
+                    String::from("miner: ghcr.io/someone/miner is not an allowed image"),
+                    String::from("miner: the tag latest of ghcr.io/someone/miner is forbidden"),
+                ]
+            ),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(apps.get_apps().await?.is_empty());
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &[crate::sc!("backend", "registry.example.com/backend:1.0")],
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_apply_image_policy_to_rewritten_images() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [[images.rewrites]]
+            from = 'docker.io/*'
+            to = 'mirror.example.com/*'
+
+            [images.policy]
+            allowed = [ 'mirror.example.com/.*' ]
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let services = apps
+            .create_or_update(
+                &AppName::from_str("master").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &[crate::sc!("nginx", "nginx:1.21")],
+                &[],
+                &[],
+                None,
+            )
+            .await?;
+
+        assert_eq!(
+            services[0].config().image().to_string(),
+            "mirror.example.com/library/nginx:1.21"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_deployment_during_freeze() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [freezes.release]
+            until = '2999-01-01T00:00:00Z'
+            reason = 'Release 2.0'
+            appSelector = 'release-.+'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("release-2.0").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsFrozen { reason, .. }) if reason == "Release 2.0"
+        ));
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        assert!(apps.freezes().lift("release"));
+        apps.create_or_update(
+            &AppName::from_str("release-2.0").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_replacement_and_deletion_during_recurring_freeze(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [freezes.demo]
+            recurrence = { schedule = '0 * * * * *', durationMinutes = 1 }
+            newAppsAllowed = true
+            reason = 'Customer demo'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("demo").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let result = apps
+            .create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsFrozen { reason, .. }) if reason == "Customer demo"
+        ));
+
+        let result = apps
+            .delete_app(&app_name, &AppStatusChangeId::new(), None)
+            .await;
+        assert!(matches!(result, Err(AppsServiceError::AppIsFrozen { .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_run_due_scheduled_operations() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("master").unwrap();
+        let now = Utc::now();
+
+        apps.schedule_deployment(
+            &app_name,
+            now,
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.schedule_deletion(&app_name, now + chrono::Duration::hours(1), None);
+
+        apps.run_scheduled_operations(&now).await;
+
+        assert!(apps.get_apps().await?.contains_key("master"));
+        assert_eq!(apps.scheduled_operations().operations().len(), 1);
+
+        apps.run_scheduled_operations(&(now + chrono::Duration::hours(1)))
+            .await;
+
+        assert!(!apps.get_apps().await?.contains_key("master"));
+        assert!(apps.scheduled_operations().operations().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_postpone_scheduled_deletion_during_recurring_freeze(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [freezes.demo]
+            recurrence = { schedule = '0 * * * * *', durationMinutes = 1 }
+            newAppsAllowed = true
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let app_name = AppName::from_str("demo").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let now = Utc::now();
+        apps.schedule_deletion(&app_name, now, None);
+        apps.run_scheduled_operations(&now).await;
+
+        assert!(apps.get_apps().await?.contains_key("demo"));
+        let operations = apps.scheduled_operations().operations();
+        assert_eq!(operations.len(), 1);
+        assert!(operations[0].run_at() > &now);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_clone_app() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let master = AppName::from_str("master").unwrap();
+        let branch = AppName::from_str("branch").unwrap();
+        let fork = AppName::from_str("branch-fork").unwrap();
+
+        apps.create_or_update(
+            &master,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.create_or_update(
+            &branch,
+            &AppStatusChangeId::new(),
+            Some(master.clone()),
+            &service_configs!("service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        apps.clone_app(&fork, &branch, &AppStatusChangeId::new(), None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch-fork").unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "service-b", ContainerType::Instance);
+        assert_contains_service!(services, "service-a", ContainerType::Replica);
+
+        let result = apps
+            .clone_app(&fork, &branch, &AppStatusChangeId::new(), None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppAlreadyExists { .. })
+        ));
+
+        let result = apps
+            .clone_app(
+                &AppName::from_str("other").unwrap(),
+                &AppName::from_str("unknown").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(AppsServiceError::AppNotFound { .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_clone_app_with_requested_companions() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+
+            [companions.db]
+            serviceName = 'db'
+            type = 'application'
+            image = 'private.example.com/library/db:latest'
+        "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        let branch = AppName::from_str("branch").unwrap();
+        let fork = AppName::from_str("branch-fork").unwrap();
+        let mock = serde_json::from_value::<Companion>(serde_json::json!({
+            "serviceName": "mock",
+            "type": "application",
+            "image": "wiremock/wiremock",
+            "env": { "APP": "{{application.name}}" }
+        }))
+        .unwrap();
+
+        apps.create_or_update(
+            &branch,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[mock],
+            &[String::from("db")],
+            None,
+        )
+        .await?;
+
+        apps.clone_app(&fork, &branch, &AppStatusChangeId::new(), None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch-fork").unwrap();
+        assert_eq!(services.len(), 3);
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+        assert_contains_service!(services, "openid", ContainerType::ApplicationCompanion);
+        assert_contains_service!(services, "mock", ContainerType::ApplicationCompanion);
+        let mock = services
+            .iter()
+            .find(|service| service.service_name() == "mock")
+            .unwrap();
+        assert_eq!(
+            mock.config()
+                .env()
+                .and_then(|env| env.variable("APP"))
+                .map(|variable| variable.value().unsecure()),
+            Some("branch-fork")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_clone_app_with_fresh_basic_auth_credentials() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [basicAuth]
+            username = 'reviewer'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let master = AppName::from_str("master").unwrap();
+        let fork = AppName::from_str("master-fork").unwrap();
+
+        let services = apps
+            .create_or_update(
+                &master,
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+                None,
+            )
+            .await?;
+        let master_credentials = services[0].credentials().cloned().unwrap();
+
+        let services = apps
+            .clone_app(&fork, &master, &AppStatusChangeId::new(), None)
+            .await?;
+        let fork_credentials = services[0].credentials().cloned().unwrap();
+
+        assert_ne!(fork_credentials.htpasswd(), master_credentials.htpasswd());
+        let deployed_apps = apps.get_apps().await?;
+        let service = &deployed_apps.get_vec("master-fork").unwrap()[0];
+        assert_eq!(
+            service.config().basic_auth(),
+            Some(&fork_credentials.htpasswd())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_clone_app_with_tickets_of_new_app() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let source = AppName::from_str("feat-PROJ-123").unwrap();
+        let fork = AppName::from_str("feat-PROJ-456").unwrap();
+
+        apps.create_or_update(
+            &source,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        apps.clone_app(&fork, &source, &AppStatusChangeId::new(), None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let service = &deployed_apps.get_vec("feat-PROJ-456").unwrap()[0];
+        assert_eq!(service.tickets(), &[String::from("PROJ-456")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_clone_app_with_host_based_routing() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+        let master = AppName::from_str("master").unwrap();
+        let fork = AppName::from_str("master-fork").unwrap();
+
+        let mut service_config = crate::sc!("service-a");
+        service_config.set_routing(
+            serde_json::from_value(serde_json::json!({
+                "host": "master.example.com",
+                "path": "/master/api/"
+            }))
+            .unwrap(),
+        );
+        apps.create_or_update(
+            &master,
+            &AppStatusChangeId::new(),
+            None,
+            &[service_config],
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        apps.clone_app(&fork, &master, &AppStatusChangeId::new(), None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let service = &deployed_apps.get_vec("master-fork").unwrap()[0];
+        assert_eq!(
+            service.config().traefik_rule(&String::from("master-fork")),
+            "Host(`master-fork.example.com`) && PathPrefix(`/master-fork/api/`)"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_import_exported_app() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let master = AppName::from_str("master").unwrap();
+        let branch = AppName::from_str("branch").unwrap();
+        let imported = AppName::from_str("branch-imported").unwrap();
+
+        apps.create_or_update(
+            &master,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.create_or_update(
+            &branch,
+            &AppStatusChangeId::new(),
+            Some(master.clone()),
+            &service_configs!("service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.change_status(&branch, &String::from("service-b"), ServiceStatus::Paused)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let export = AppExport::new(&branch, deployed_apps.get_vec("branch").unwrap());
+        let export =
+            serde_json::from_value::<AppExport>(serde_json::to_value(&export).unwrap()).unwrap();
+
+        apps.import_app(&imported, &AppStatusChangeId::new(), &export, None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch-imported").unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "service-b", ContainerType::Instance);
+        assert_contains_service!(services, "service-a", ContainerType::Replica);
+        assert_eq!(
+            services
+                .iter()
+                .find(|service| service.service_name() == "service-b")
+                .map(|service| service.status()),
+            Some(&ServiceStatus::Paused)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_import_exported_app_with_requested_companions() -> Result<(), AppsServiceError>
+    {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+
+            [companions.db]
+            serviceName = 'db'
+            type = 'application'
+            image = 'private.example.com/library/db:latest'
+        "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        let branch = AppName::from_str("branch").unwrap();
+        let imported = AppName::from_str("branch-imported").unwrap();
+        let mock = serde_json::from_value::<Companion>(serde_json::json!({
+            "serviceName": "mock",
+            "type": "application",
+            "image": "wiremock/wiremock"
+        }))
+        .unwrap();
+
+        apps.create_or_update(
+            &branch,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[mock],
+            &[String::from("db")],
+            None,
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let export = AppExport::new(&branch, deployed_apps.get_vec("branch").unwrap());
+        let export =
+            serde_json::from_value::<AppExport>(serde_json::to_value(&export).unwrap()).unwrap();
+        assert_eq!(export.user_defined_companions().len(), 1);
+        assert_eq!(export.skipped_companions(), &[String::from("db")]);
+
+        apps.import_app(&imported, &AppStatusChangeId::new(), &export, None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch-imported").unwrap();
+        assert_eq!(services.len(), 3);
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+        assert_contains_service!(services, "openid", ContainerType::ApplicationCompanion);
+        assert_contains_service!(services, "mock", ContainerType::ApplicationCompanion);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_mark_replicas_as_stale_and_refresh_them() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let master = AppName::from_str("master").unwrap();
+        let branch = AppName::from_str("branch").unwrap();
+
+        apps.create_or_update(
+            &master,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+        apps.create_or_update(
+            &branch,
+            &AppStatusChangeId::new(),
+            Some(master.clone()),
             &service_configs!("service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch").unwrap();
+        assert!(services.iter().all(|service| !service.is_stale()));
+
+        // The dummy infrastructure pretends that each deployment comes with a new image digest.
+        apps.create_or_update(
+            &master,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
         let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec("branch").unwrap();
+        let stale_services = services
+            .iter()
+            .filter(|service| service.is_stale())
+            .map(|service| service.service_name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(stale_services, vec!["service-a"]);
 
+        apps.refresh_stale_replicas(&branch, &AppStatusChangeId::new(), None)
+            .await?;
+
+        let deployed_apps = apps.get_apps().await?;
         let services = deployed_apps.get_vec("branch").unwrap();
         assert_eq!(services.len(), 2);
         assert_contains_service!(services, "service-b", ContainerType::Instance);
         assert_contains_service!(services, "service-a", ContainerType::Replica);
+        assert!(services.iter().all(|service| !service.is_stale()));
 
         Ok(())
     }
@@ -543,6 +3064,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -551,6 +3075,9 @@ mod tests {
             &AppStatusChangeId::new(),
             Some(AppName::from_str("master").unwrap()),
             &service_configs!("service-b"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -559,6 +3086,9 @@ mod tests {
             &AppStatusChangeId::new(),
             Some(AppName::from_str("master").unwrap()),
             &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -592,6 +3122,9 @@ mod tests {
             &AppStatusChangeId::new(),
             None,
             &service_configs!("mariadb"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -627,63 +3160,144 @@ mod tests {
         let apps = AppsService::new(config, infrastructure)?;
 
         apps.create_or_update(
-            &AppName::from_str("master-1.x").unwrap(),
+            &AppName::from_str("master-1.x").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("mariadb"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let configs = apps
+            .infrastructure
+            .get_configs_of_app(&String::from("master-1.x"))
+            .await?;
+        assert_eq!(configs.len(), 1);
+
+        let volumes = configs.get(0).unwrap().volumes();
+        assert_eq!(volumes, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_collect_log_chunk_from_infrastructure() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::from_str("master").unwrap();
+
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a", "service-b"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let log_chunk = apps
+            .get_logs(&app_name, &String::from("service-a"), &None, 100)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            log_chunk.since(),
+            &DateTime::parse_from_rfc3339("2019-07-18T07:25:00.000000000Z").unwrap()
+        );
+
+        assert_eq!(
+            log_chunk.until(),
+            &DateTime::parse_from_rfc3339("2019-07-18T07:35:00.000000000Z").unwrap()
+        );
+
+        assert_eq!(
+            log_chunk.log_lines(),
+            r#"Log msg 1 of service-a of app master
+Log msg 2 of service-a of app master
+Log msg 3 of service-a of app master
+"#
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_exec_command_in_service() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::from_str("master").unwrap();
+        let command = vec![String::from("cat"), String::from("/etc/hosts")];
+        assert!(apps
+            .exec(&app_name, &String::from("service-a"), &command)
+            .await?
+            .is_none());
+
+        apps.create_or_update(
+            &app_name,
             &AppStatusChangeId::new(),
             None,
-            &service_configs!("mariadb"),
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
-        let configs = apps
-            .infrastructure
-            .get_configs_of_app(&String::from("master-1.x"))
-            .await?;
-        assert_eq!(configs.len(), 1);
+        let mut output = apps
+            .exec(&app_name, &String::from("service-a"), &command)
+            .await?
+            .unwrap();
 
-        let volumes = configs.get(0).unwrap().volumes();
-        assert_eq!(volumes, None);
+        assert_eq!(
+            output.recv().await.unwrap().unwrap(),
+            ExecOutput::Stdout(String::from("cat /etc/hosts\n"))
+        );
+        assert!(output.recv().await.is_none());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_collect_log_chunk_from_infrastructure() -> Result<(), AppsServiceError> {
+    async fn should_collect_stats_from_infrastructure() -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
 
         let app_name = AppName::from_str("master").unwrap();
+        assert_eq!(apps.get_stats(&app_name).await?, None);
 
         apps.create_or_update(
             &app_name,
             &AppStatusChangeId::new(),
             None,
-            &service_configs!("service-a", "service-b"),
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
-        let log_chunk = apps
-            .get_logs(&app_name, &String::from("service-a"), &None, 100)
-            .await
-            .unwrap()
-            .unwrap();
-
-        assert_eq!(
-            log_chunk.since(),
-            &DateTime::parse_from_rfc3339("2019-07-18T07:25:00.000000000Z").unwrap()
-        );
-
-        assert_eq!(
-            log_chunk.until(),
-            &DateTime::parse_from_rfc3339("2019-07-18T07:35:00.000000000Z").unwrap()
-        );
+        let stats = apps.get_stats(&app_name).await?;
 
         assert_eq!(
-            log_chunk.log_lines(),
-            r#"Log msg 1 of service-a of app master
-Log msg 2 of service-a of app master
-Log msg 3 of service-a of app master
-"#
+            stats,
+            Some(vec![ServiceStats::new(
+                String::from("service-a"),
+                12.5,
+                64 * 1024 * 1024,
+                512 * 1024 * 1024,
+                1024,
+                2048,
+            )])
         );
 
         Ok(())
@@ -713,6 +3327,9 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
         let deployed_apps = apps.get_apps().await?;
@@ -726,6 +3343,161 @@ Log msg 3 of service-a of app master
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_skip_companions() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+
+            [companions.db]
+            serviceName = 'db-{{service.name}}'
+            type = 'service'
+            image = 'private.example.com/library/db:latest'
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::from_str("master").unwrap();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[String::from("db")],
+            None,
+        )
+        .await?;
+        let deployed_apps = apps.get_apps().await?;
+
+        let services = deployed_apps.get_vec("master").unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "openid", ContainerType::ApplicationCompanion);
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_skip_unknown_companions() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("master").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[String::from("openid"), String::from("kafka")],
+                None,
+            )
+            .await;
+
+        match result {
+            Err(AppsServiceError::UnknownCompanions { names }) => assert_eq!(names, "kafka"),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(apps.get_apps().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_plan_deployment_without_deploying() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            env = [ 'APP={{application.name}}' ]
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let configs = apps
+            .plan_deployment(
+                &AppName::from_str("master").unwrap(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+            )
+            .await?;
+
+        assert_eq!(configs.len(), 2);
+        let openid = configs
+            .iter()
+            .find(|config| config.service_name() == "openid")
+            .unwrap();
+        assert_eq!(
+            openid
+                .env()
+                .unwrap()
+                .variable("APP")
+                .unwrap()
+                .value()
+                .unsecure(),
+            "master"
+        );
+        assert!(apps.get_apps().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_deployment_with_cyclic_dependencies() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.service-b]
+            serviceName = 'service-b'
+            type = 'application'
+            image = 'private.example.com/library/service-b:latest'
+            dependsOn = [ 'service-a' ]
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let mut configs = service_configs!("service-a");
+        configs[0].set_depends_on(vec![String::from("service-b")]);
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("master").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &configs,
+                &[],
+                &[],
+                None,
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidServiceDependencies { .. })
+        ));
+        assert!(apps.get_apps().await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_filter_companions_if_services_to_deploy_contain_same_service_name(
     ) -> Result<(), AppsServiceError> {
@@ -747,8 +3519,16 @@ Log msg 3 of service-a of app master
 
         let app_name = AppName::from_str("master").unwrap();
         let configs = service_configs!("openid", "db");
-        apps.create_or_update(&app_name, &AppStatusChangeId::new(), None, &configs)
-            .await?;
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &configs,
+            &[],
+            &[],
+            None,
+        )
+        .await?;
         let deployed_apps = apps.get_apps().await?;
 
         let services = deployed_apps.get_vec("master").unwrap();
@@ -797,8 +3577,16 @@ Log msg 3 of service-a of app master
             volumes = ()
         )];
 
-        apps.create_or_update(&app_name, &AppStatusChangeId::new(), None, &configs)
-            .await?;
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &configs,
+            &[],
+            &[],
+            None,
+        )
+        .await?;
 
         let deployed_apps = apps.get_apps().await?;
 
@@ -856,6 +3644,9 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &vec![crate::sc!("service-a")],
+            &[],
+            &[],
+            None,
         )
         .await?;
         apps.create_or_update(
@@ -863,6 +3654,9 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &vec![crate::sc!("service-b")],
+            &[],
+            &[],
+            None,
         )
         .await?;
         apps.create_or_update(
@@ -870,6 +3664,9 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &vec![crate::sc!("service-c")],
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -903,10 +3700,13 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
         let deleted_services = apps
-            .delete_app(&app_name, &AppStatusChangeId::new())
+            .delete_app(&app_name, &AppStatusChangeId::new(), None)
             .await?;
 
         assert_eq!(
@@ -927,6 +3727,142 @@ Log msg 3 of service-a of app master
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_reload_config_from_file() -> Result<(), AppsServiceError> {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(&file, "[labels]\n'com.example.team' = 'a'\n").unwrap();
+        let config = Config::load(&file.to_string_lossy()).await.unwrap();
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        std::fs::write(&file, "[labels]\n'com.example.team' = 'b'\n").unwrap();
+        assert!(apps.reload_config().await.unwrap());
+
+        let mut service_config = crate::sc!("service-a");
+        apps.config().add_labels_to(&mut service_config);
+        assert_eq!(
+            service_config.labels().unwrap().get("com.example.team"),
+            Some(&String::from("b"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_apply_reloaded_freezes_and_notification_sinks() -> Result<(), AppsServiceError>
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(
+            &file,
+            "[freezes.release]\nuntil = '2999-01-01T00:00:00Z'\nreason = 'Release 2.0'\n",
+        )
+        .unwrap();
+        let config = Config::load(&file.to_string_lossy()).await.unwrap();
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        apps.freezes().declare(
+            String::from("incident"),
+            toml::de::from_str::<crate::config::FreezeWindow>(
+                "until = '2999-01-01T00:00:00Z'\nreason = 'Incident'\nappSelector = 'incident-.+'",
+            )
+            .unwrap(),
+        );
+
+        std::fs::write(
+            &file,
+            r#"
+            [freezes.release]
+            until = '2999-01-01T00:00:00Z'
+            reason = 'Release 2.0'
+            appSelector = 'release-.+'
+
+            [[webhooks]]
+            url = 'http://127.0.0.1:1/unreachable'
+            maxAttempts = 1
+            "#,
+        )
+        .unwrap();
+        assert!(apps.reload_config().await.unwrap());
+        assert!(apps.freezes().windows().contains_key("incident"));
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let mut dead_letters = Vec::new();
+        for _ in 0..100 {
+            dead_letters = apps.webhook_deliveries().dead_letters();
+            if !dead_letters.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(dead_letters.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_reload_config_without_file() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
+        assert!(!apps.reload_config().await.unwrap());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_record_changes_in_audit_log() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::from_str("master").unwrap();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            Some(String::from("alice")),
+        )
+        .await?;
+        apps.delete_app(
+            &app_name,
+            &AppStatusChangeId::new(),
+            Some(String::from("bob")),
+        )
+        .await?;
+        let _ = apps
+            .delete_app(
+                &app_name,
+                &AppStatusChangeId::new(),
+                Some(String::from("bob")),
+            )
+            .await;
+
+        let entries =
+            serde_json::to_value(apps.audit_log().entries(Some("master"), None, None)).unwrap();
+        assert_eq!(entries.as_array().unwrap().len(), 3);
+        assert_eq!(entries[0]["user"], "alice");
+        assert_eq!(entries[0]["action"], "deployment");
+        assert_eq!(entries[0]["services"], serde_json::json!(["service-a"]));
+        assert_eq!(entries[1]["user"], "bob");
+        assert_eq!(entries[1]["action"], "deletion");
+        assert_eq!(entries[1]["outcome"], "success");
+        assert_eq!(entries[2]["outcome"], "failure");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_delete_apps_from_parallel_threads_returning_the_same_result(
     ) -> Result<(), AppsServiceError> {
@@ -940,6 +3876,9 @@ Log msg 3 of service-a of app master
             &AppStatusChangeId::new(),
             None,
             &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
         )
         .await?;
 
@@ -949,7 +3888,7 @@ Log msg 3 of service-a of app master
                 .enable_time()
                 .build()
                 .unwrap();
-            rt.block_on(apps_clone.delete_app(&app_name, &AppStatusChangeId::new()))
+            rt.block_on(apps_clone.delete_app(&app_name, &AppStatusChangeId::new(), None))
         });
         let app_name = AppName::from_str("master").unwrap();
         let handle2 = std::thread::spawn(move || {
@@ -957,11 +3896,118 @@ Log msg 3 of service-a of app master
                 .enable_time()
                 .build()
                 .unwrap();
-            rt.block_on(apps.delete_app(&app_name, &AppStatusChangeId::new()))
+            rt.block_on(apps.delete_app(&app_name, &AppStatusChangeId::new(), None))
         });
 
         assert_eq!(handle1.join().unwrap()?, handle2.join().unwrap()?,);
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn should_drain_deployments_when_shutting_down() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::with_delay(std::time::Duration::from_millis(500)));
+        let apps = Arc::new(AppsService::new(Config::default(), infrastructure)?);
+
+        let deploying_apps = apps.clone();
+        let deployment = tokio::spawn(async move {
+            deploying_apps
+                .create_or_update(
+                    &AppName::from_str("master").unwrap(),
+                    &AppStatusChangeId::new(),
+                    None,
+                    &service_configs!("service-a"),
+                    &[],
+                    &[],
+                    None,
+                )
+                .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        apps.begin_shutdown();
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("other").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &service_configs!("service-a"),
+                &[],
+                &[],
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(AppsServiceError::ShuttingDown)));
+
+        assert!(apps.drain(std::time::Duration::from_secs(5)).await);
+        assert!(deployment.await.unwrap().is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_change_services_of_app_in_deployment() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+        let app_name = AppName::from_str("master").unwrap();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &service_configs!("service-a"),
+            &[],
+            &[],
+            None,
+        )
+        .await?;
+
+        let guard = apps.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
+        assert!(guard.is_first());
+
+        let result = apps
+            .change_status(&app_name, &String::from("service-a"), ServiceStatus::Paused)
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsInDeployment { .. })
+        ));
+        let result = apps
+            .restart_service(&app_name, &String::from("service-a"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsInDeployment { .. })
+        ));
+
+        guard.notify_with_result(&apps, Ok(Vec::new()));
+        let service = apps
+            .change_status(&app_name, &String::from("service-a"), ServiceStatus::Paused)
+            .await?;
+        assert_eq!(
+            service.map(|s| s.status().clone()),
+            Some(ServiceStatus::Paused)
+        );
+        assert!(apps.app_guards.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_result_of_app_guard_without_blocking_the_runtime(
+    ) -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+        let guard = Arc::new(AppGuard::new(
+            AppName::from_str("master").unwrap(),
+            AppGuardKind::Deletion,
+        ));
+
+        let waiting_guard = guard.clone();
+        let waiter = tokio::spawn(async move { waiting_guard.wait_for_result().await });
+        tokio::task::yield_now().await;
+
+        guard.notify_with_result(&apps, Ok(Vec::new()));
+
+        assert_eq!(waiter.await.unwrap()?, Vec::new());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,101 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::{Apps, AppsError};
+use crate::models::AppName;
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Restarts services periodically according to the restart schedules of the configuration.
+pub struct RestartScheduler {
+    last_check: DateTime<Utc>,
+}
+
+impl RestartScheduler {
+    pub fn new() -> Self {
+        RestartScheduler {
+            last_check: Utc::now(),
+        }
+    }
+
+    pub fn spawn(mut self, apps: Arc<Apps>) {
+        tokio::spawn(async move {
+            loop {
+                sleep(Duration::from_secs(30)).await;
+                if let Err(err) = self.restart_due_services(&apps).await {
+                    error!("Cannot restart scheduled services: {}", err);
+                }
+            }
+        });
+    }
+
+    async fn restart_due_services(&mut self, apps: &Arc<Apps>) -> Result<(), AppsError> {
+        let now = Utc::now();
+
+        let due_services = apps
+            .get_apps()
+            .await?
+            .iter_all()
+            .flat_map(|(app_name, services)| {
+                services
+                    .iter()
+                    .filter(|service| {
+                        apps.is_restart_due(
+                            app_name,
+                            service.service_name(),
+                            &self.last_check,
+                            &now,
+                        )
+                    })
+                    .map(move |service| (app_name.clone(), service.service_name().clone()))
+            })
+            .collect::<Vec<_>>();
+        self.last_check = now;
+
+        for (app_name, service_name) in due_services {
+            let app_name = match AppName::from_str(&app_name) {
+                Ok(app_name) => app_name,
+                Err(err) => {
+                    warn!("Cannot restart services of {}: {}", app_name, err);
+                    continue;
+                }
+            };
+
+            info!("Restarting {} of {} as scheduled.", service_name, app_name);
+            if let Err(err) = apps.restart_service(&app_name, &service_name).await {
+                error!(
+                    "Cannot restart {} of {} as scheduled: {}",
+                    service_name, app_name, err
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
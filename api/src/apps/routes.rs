@@ -24,50 +24,99 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::apps::HostMetaCache;
-use crate::apps::{Apps, AppsError};
+use crate::apps::compose::parse_compose_file;
+use crate::apps::{AppComparison, AppExport, AppsFilter, AppsOrder, AppsPage, HostMetaCache};
+use crate::apps::{Apps, AppsError, IdempotentOutcome};
+use crate::auth::{AuthenticationError, User};
+use crate::config::Companion;
 use crate::http_result::{HttpApiError, HttpResult};
+use crate::infrastructure::{ServiceDeploymentError, ServicesDeploymentError};
 use crate::models::request_info::RequestInfo;
-use crate::models::service::{Service, ServiceStatus};
-use crate::models::ServiceConfig;
+use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
 use crate::models::{AppName, AppNameError, LogChunk};
 use crate::models::{AppStatusChangeId, AppStatusChangeIdError};
-use chrono::DateTime;
+use crate::models::{Image, ServiceConfig, ServiceStats, ServiceStatusReport};
+use crate::services::idempotent_requests::IdempotentRequest;
+use crate::services::scheduled_operations::ScheduledOperation;
+use chrono::{DateTime, Utc};
 use http_api_problem::{HttpApiProblem, StatusCode};
-use multimap::MultiMap;
 use regex::Regex;
+use rocket::data::{Data, ToByteUnit};
 use rocket::http::{RawStr, Status};
 use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::stream::{Event, EventStream};
 use rocket::response::{Responder, Response};
-use rocket::serde::json::Json;
-use rocket::State;
+use rocket::serde::json::{Json, Value};
+use rocket::{Shutdown, State};
+use std::collections::BTreeMap;
 use std::future::Future;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::timeout;
 
 pub fn apps_routes() -> Vec<rocket::Route> {
     rocket::routes![
         apps,
+        app_events,
         delete_app,
         create_app,
+        create_app_from_compose_file,
         logs,
+        stats,
+        status,
+        compare,
+        export_app,
+        clone_app,
+        refresh_stale_replicas,
         change_status,
+        exec,
         status_change
     ]
 }
 
-#[get("/", format = "application/json")]
+#[get("/?<query..>", format = "application/json")]
 async fn apps(
     apps: &State<Arc<Apps>>,
     request_info: RequestInfo,
     host_meta_cache: &State<HostMetaCache>,
-) -> HttpResult<Json<MultiMap<String, Service>>> {
-    let services = apps.get_apps().await?;
-    Ok(Json(
-        host_meta_cache.update_meta_data(services, &request_info),
-    ))
+    query: AppsQuery,
+) -> HttpResult<AppsResponse> {
+    let filter = query.filter()?;
+    let order = query.order()?;
+
+    let services = apps.get_filtered_apps(&filter).await?;
+    let services = host_meta_cache.update_meta_data(services, &request_info);
+
+    Ok(AppsResponse {
+        page: AppsPage::new(services, order, query.offset.unwrap_or(0), query.limit),
+    })
+}
+
+/// Streams the lifecycle events of all apps as server-sent events until the client disconnects
+/// or PREvant shuts down.
+#[get("/events")]
+fn app_events(apps: &State<Arc<Apps>>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut events = apps.events().subscribe();
+    EventStream! {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = &mut shutdown => break,
+            };
+            match event {
+                Ok(event) => yield Event::json(&event).event(event.name()),
+                Err(RecvError::Closed) => break,
+                // The client missed some events and should reload the apps to catch up.
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Event stream client lagged behind by {} events", missed);
+                    yield Event::json(&missed).event("lagged");
+                }
+            }
+        }
+    }
 }
 
 #[get("/<app_name>/status-changes/<status_id>", format = "application/json")]
@@ -90,18 +139,40 @@ async fn status_change(
     }
 }
 
-#[delete("/<app_name>")]
+#[delete("/<app_name>?<delete_app_form..>")]
 pub async fn delete_app(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    delete_app_form: DeleteAppOptions,
+    options: RunOptions,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<DeleteAppResponse> {
+    let user = user?.name().cloned();
+
+    if let Some(run_at) = parse_run_at(&delete_app_form.run_at)? {
+        let operation = apps.schedule_deletion(&app_name?, run_at, user);
+        return Ok(DeleteAppResponse::Scheduled(ScheduledOperationResponse(
+            operation,
+        )));
+    }
+
+    Ok(DeleteAppResponse::Deletion(
+        delete_app_with_options(app_name, apps, options, user).await?,
+    ))
+}
+
+async fn delete_app_with_options(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
     options: RunOptions,
+    user: Option<String>,
 ) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
     let app_name = app_name?;
     let app_name_cloned = app_name.clone();
     let status_id = AppStatusChangeId::new();
 
     let apps = (**apps).clone();
-    let future = async move { apps.delete_app(&app_name, &status_id).await };
+    let future = async move { apps.delete_app(&app_name, &status_id, user).await };
 
     match spawn_with_options(options, future).await? {
         Poll::Pending => Ok(AsyncCompletion::Pending(app_name_cloned, status_id)),
@@ -113,8 +184,9 @@ pub async fn delete_app(
 pub async fn delete_app_sync(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
+    user: Option<String>,
 ) -> HttpResult<Json<Vec<Service>>> {
-    match delete_app(app_name, apps, RunOptions::Sync).await? {
+    match delete_app_with_options(app_name, apps, RunOptions::Sync, user).await? {
         AsyncCompletion::Pending(_, _) => {
             Err(HttpApiProblem::with_title(StatusCode::INTERNAL_SERVER_ERROR).into())
         }
@@ -125,29 +197,447 @@ pub async fn delete_app_sync(
 #[post(
     "/<app_name>?<create_app_form..>",
     format = "application/json",
-    data = "<service_configs>"
+    data = "<payload>"
 )]
 pub async fn create_app(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
     create_app_form: CreateAppOptions,
-    service_configs: Json<Vec<ServiceConfig>>,
+    payload: Json<Value>,
     options: RunOptions,
-) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
-    let status_id = AppStatusChangeId::new();
+    idempotency_key: Option<IdempotencyKey>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<CreateAppResponse> {
+    let owner = user?.name().cloned();
+    let app_name = app_name?;
+
+    if create_app_form.import() {
+        return import_app(
+            app_name,
+            apps,
+            create_app_form,
+            payload.into_inner(),
+            options,
+            idempotency_key,
+            owner,
+        )
+        .await;
+    }
+
+    let (service_configs, user_defined_companions) =
+        CreateAppPayload::from_value(payload.into_inner(), apps.config().strict_payloads())?
+            .into_parts();
+
+    create_app_from_configs(
+        app_name,
+        apps,
+        create_app_form,
+        service_configs,
+        user_defined_companions,
+        options,
+        idempotency_key,
+        owner,
+    )
+    .await
+}
+
+/// Deploys the services of a docker-compose file (see `compose::parse_compose_file` for the
+/// supported subset).
+#[post(
+    "/<app_name>?<create_app_form..>",
+    format = "application/x-yaml",
+    data = "<payload>"
+)]
+pub async fn create_app_from_compose_file(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Data<'_>,
+    options: RunOptions,
+    idempotency_key: Option<IdempotencyKey>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<CreateAppResponse> {
+    let owner = user?.name().cloned();
     let app_name = app_name?;
+    let payload = payload
+        .open(1.mebibytes())
+        .into_string()
+        .await
+        .map_err(|err| {
+            HttpApiError::from(
+                HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                    .detail(format!("Cannot read docker-compose file: {}", err)),
+            )
+        })?;
+    if !payload.is_complete() {
+        return Err(
+            HttpApiProblem::with_title_and_type(StatusCode::PAYLOAD_TOO_LARGE)
+                .detail("The docker-compose file must not exceed 1 MiB.")
+                .into(),
+        );
+    }
+    let service_configs = parse_compose_file(&payload)?;
+
+    create_app_from_configs(
+        app_name,
+        apps,
+        create_app_form,
+        service_configs,
+        Vec::new(),
+        options,
+        idempotency_key,
+        owner,
+    )
+    .await
+}
+
+async fn create_app_from_configs(
+    app_name: AppName,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    service_configs: Vec<ServiceConfig>,
+    user_defined_companions: Vec<Companion>,
+    options: RunOptions,
+    idempotency_key: Option<IdempotencyKey>,
+    owner: Option<String>,
+) -> HttpResult<CreateAppResponse> {
+    let status_id = AppStatusChangeId::new();
     let app_name_cloned = app_name.clone();
     let replicate_from = create_app_form.replicate_from().clone();
+    let skipped_companions = create_app_form.skipped_companions();
+
+    let idempotency_guard =
+        match replay_idempotent_request(apps, &idempotency_key, &app_name, &status_id) {
+            Ok(idempotency_guard) => idempotency_guard,
+            Err(response) => return response,
+        };
+
+    if create_app_form.dry_run() {
+        let (configs, trace) = apps
+            .plan_deployment_with_trace(
+                &app_name,
+                replicate_from,
+                &service_configs,
+                &user_defined_companions,
+                &skipped_companions,
+            )
+            .await?;
+        if create_app_form.explain() {
+            return Ok(CreateAppResponse::Explained(Json(serde_json::json!({
+                "services": configs,
+                "trace": trace,
+            }))));
+        }
+        return Ok(CreateAppResponse::DryRun(Json(configs)));
+    }
+
+    if let Some(run_at) = parse_run_at(&create_app_form.run_at)? {
+        let operation = apps
+            .schedule_deployment(
+                &app_name,
+                run_at,
+                replicate_from,
+                &service_configs,
+                &user_defined_companions,
+                &skipped_companions,
+                owner,
+            )
+            .await?;
+        idempotency_guard.complete(IdempotentOutcome::Scheduled(operation.clone()));
+        return Ok(CreateAppResponse::Scheduled(ScheduledOperationResponse(
+            operation,
+        )));
+    }
+
+    // The trace is resolved upfront because the deployment might continue in the background.
+    let trace = if create_app_form.explain() {
+        let (_configs, trace) = apps
+            .plan_deployment_with_trace(
+                &app_name,
+                replicate_from.clone(),
+                &service_configs,
+                &user_defined_companions,
+                &skipped_companions,
+            )
+            .await?;
+        Some(trace)
+    } else {
+        None
+    };
 
     let apps = (**apps).clone();
     let future = async move {
-        apps.create_or_update(
-            &app_name.clone(),
-            &status_id,
-            replicate_from,
-            &service_configs,
+        let result = apps
+            .create_or_update(
+                &app_name.clone(),
+                &status_id,
+                replicate_from,
+                &service_configs,
+                &user_defined_companions,
+                &skipped_companions,
+                owner,
+            )
+            .await;
+        if let Ok(services) = &result {
+            idempotency_guard.complete(IdempotentOutcome::Deployed(services.clone()));
+        }
+        result
+    };
+
+    match spawn_with_options(options, future).await? {
+        Poll::Pending => Ok(CreateAppResponse::Deployment(AsyncCompletion::Pending(
+            app_name_cloned,
+            status_id,
+        ))),
+        Poll::Ready(Ok(services)) => match trace {
+            Some(trace) => Ok(CreateAppResponse::Explained(Json(serde_json::json!({
+                "services": services,
+                "trace": trace,
+            })))),
+            None => Ok(CreateAppResponse::Deployment(AsyncCompletion::Ready(Json(
+                services,
+            )))),
+        },
+        Poll::Ready(Err(err)) => Err(err.into()),
+    }
+}
+
+async fn import_app(
+    app_name: AppName,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Value,
+    options: RunOptions,
+    idempotency_key: Option<IdempotencyKey>,
+    owner: Option<String>,
+) -> HttpResult<CreateAppResponse> {
+    if create_app_form.dry_run() || create_app_form.explain() || create_app_form.run_at.is_some() {
+        return Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+            .detail("An import cannot be combined with dryRun, explain, or runAt.")
+            .into());
+    }
+
+    let export = serde_json::from_value::<AppExport>(payload).map_err(|err| {
+        HttpApiError::from(
+            HttpApiProblem::with_title_and_type(StatusCode::UNPROCESSABLE_ENTITY)
+                .detail(format!("Invalid export: {}", err)),
         )
-        .await
+    })?;
+    if !export.is_supported_version() {
+        return Err(
+            HttpApiProblem::with_title_and_type(StatusCode::UNPROCESSABLE_ENTITY)
+                .detail("The version of the export is not supported.")
+                .into(),
+        );
+    }
+
+    let app_name_cloned = app_name.clone();
+    let status_id = AppStatusChangeId::new();
+
+    let idempotency_guard =
+        match replay_idempotent_request(apps, &idempotency_key, &app_name, &status_id) {
+            Ok(idempotency_guard) => idempotency_guard,
+            Err(response) => return response,
+        };
+
+    let apps = (**apps).clone();
+    let future = async move {
+        let result = apps.import_app(&app_name, &status_id, &export, owner).await;
+        if let Ok(services) = &result {
+            idempotency_guard.complete(IdempotentOutcome::Deployed(services.clone()));
+        }
+        result
+    };
+
+    match spawn_with_options(options, future).await? {
+        Poll::Pending => Ok(CreateAppResponse::Deployment(AsyncCompletion::Pending(
+            app_name_cloned,
+            status_id,
+        ))),
+        Poll::Ready(Ok(services)) => Ok(CreateAppResponse::Deployment(AsyncCompletion::Ready(
+            Json(services),
+        ))),
+        Poll::Ready(Err(err)) => Err(err.into()),
+    }
+}
+
+/// Answers a replay of a deployment request with the result of the original request. If the
+/// request has not been seen before and, thus, has to be processed, the returned guard holds its
+/// idempotency key until the outcome of the request is known.
+fn replay_idempotent_request(
+    apps: &State<Arc<Apps>>,
+    idempotency_key: &Option<IdempotencyKey>,
+    app_name: &AppName,
+    status_id: &AppStatusChangeId,
+) -> Result<IdempotencyGuard, HttpResult<CreateAppResponse>> {
+    let key = match idempotency_key {
+        Some(IdempotencyKey(key)) => key,
+        None => return Ok(IdempotencyGuard::without_key(apps)),
+    };
+
+    match apps.begin_idempotent_request(key, app_name, status_id) {
+        IdempotentRequest::New => Ok(IdempotencyGuard::new(apps, key)),
+        IdempotentRequest::InProgress(status_id) => Err(Ok(CreateAppResponse::Deployment(
+            AsyncCompletion::Pending(app_name.clone(), status_id),
+        ))),
+        IdempotentRequest::Completed(IdempotentOutcome::Deployed(services)) => Err(Ok(
+            CreateAppResponse::Deployment(AsyncCompletion::Ready(Json(services))),
+        )),
+        IdempotentRequest::Completed(IdempotentOutcome::Scheduled(operation)) => Err(Ok(
+            CreateAppResponse::Scheduled(ScheduledOperationResponse(operation)),
+        )),
+        IdempotentRequest::Conflict => Err(Err(HttpApiProblem::with_title_and_type(
+            StatusCode::UNPROCESSABLE_ENTITY,
+        )
+        .detail("The idempotency key has already been used for another app.")
+        .into())),
+    }
+}
+
+/// Holds the idempotency key of a request that is being processed. Unless the outcome of the
+/// request is recorded, the key is released when the guard is dropped, e.g. because the request
+/// failed, so that a replay processes the request again.
+struct IdempotencyGuard {
+    apps: Arc<Apps>,
+    key: Option<String>,
+}
+
+impl IdempotencyGuard {
+    fn new(apps: &Arc<Apps>, key: &str) -> Self {
+        IdempotencyGuard {
+            apps: apps.clone(),
+            key: Some(key.to_string()),
+        }
+    }
+
+    fn without_key(apps: &Arc<Apps>) -> Self {
+        IdempotencyGuard {
+            apps: apps.clone(),
+            key: None,
+        }
+    }
+
+    fn complete(mut self, outcome: IdempotentOutcome) {
+        if let Some(key) = self.key.take() {
+            self.apps.complete_idempotent_request(&key, outcome);
+        }
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.apps.forget_idempotent_request(&key);
+        }
+    }
+}
+
+/// Exports the app as a document that `POST /apps/{app}?import=true` recreates the app from. The
+/// document contains the values of the exported environment variables, thus, it requires the
+/// same authentication as a deployment.
+#[get("/<app_name>/export", format = "application/json")]
+async fn export_app(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<AppExport>> {
+    user?;
+    let app_name = app_name?;
+
+    let services = apps.get_apps().await?;
+    let app_services =
+        services
+            .get_vec(app_name.as_str())
+            .ok_or_else(|| AppsError::AppNotFound {
+                app_name: app_name.clone(),
+            })?;
+
+    Ok(Json(AppExport::new(&app_name, app_services)))
+}
+
+#[get("/<app_name>/compare/<other_app_name>", format = "application/json")]
+async fn compare(
+    app_name: Result<AppName, AppNameError>,
+    other_app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    request_info: RequestInfo,
+    host_meta_cache: &State<HostMetaCache>,
+) -> HttpResult<Json<AppComparison>> {
+    let app_name = app_name?;
+    let other_app_name = other_app_name?;
+
+    let services = host_meta_cache.update_meta_data(apps.get_apps().await?, &request_info);
+    let app_services =
+        services
+            .get_vec(app_name.as_str())
+            .ok_or_else(|| AppsError::AppNotFound {
+                app_name: app_name.clone(),
+            })?;
+    let other_app_services =
+        services
+            .get_vec(other_app_name.as_str())
+            .ok_or_else(|| AppsError::AppNotFound {
+                app_name: other_app_name.clone(),
+            })?;
+
+    Ok(Json(AppComparison::new(
+        app_name,
+        app_services,
+        other_app_name,
+        other_app_services,
+    )))
+}
+
+#[post("/<app_name>/clone?<from>")]
+async fn clone_app(
+    app_name: Result<AppName, AppNameError>,
+    from: Option<AppName>,
+    apps: &State<Arc<Apps>>,
+    options: RunOptions,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
+    let owner = user?.name().cloned();
+    let app_name = app_name?;
+    let source_app_name = match from {
+        Some(source_app_name) => source_app_name,
+        None => {
+            return Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail("The query parameter from must name the app to clone.")
+                .into())
+        }
+    };
+    let app_name_cloned = app_name.clone();
+    let status_id = AppStatusChangeId::new();
+
+    let apps = (**apps).clone();
+    let future = async move {
+        apps.clone_app(&app_name, &source_app_name, &status_id, owner)
+            .await
+    };
+
+    match spawn_with_options(options, future).await? {
+        Poll::Pending => Ok(AsyncCompletion::Pending(app_name_cloned, status_id)),
+        Poll::Ready(Ok(services)) => Ok(AsyncCompletion::Ready(Json(services))),
+        Poll::Ready(Err(err)) => Err(err.into()),
+    }
+}
+
+#[post("/<app_name>/replicas/refresh")]
+async fn refresh_stale_replicas(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    options: RunOptions,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
+    let owner = user?.name().cloned();
+    let app_name = app_name?;
+    let app_name_cloned = app_name.clone();
+    let status_id = AppStatusChangeId::new();
+
+    let apps = (**apps).clone();
+    let future = async move {
+        apps.refresh_stale_replicas(&app_name, &status_id, owner)
+            .await
     };
 
     match spawn_with_options(options, future).await? {
@@ -167,7 +657,9 @@ async fn change_status(
     service_name: String,
     apps: &State<Arc<Apps>>,
     status_data: Json<ServiceStatusData>,
+    user: Result<User, AuthenticationError>,
 ) -> HttpResult<ServiceStatusResponse> {
+    user?;
     let app_name = app_name?;
     let status = status_data.status.clone();
 
@@ -176,6 +668,60 @@ async fn change_status(
     Ok(ServiceStatusResponse { service })
 }
 
+/// Executes a command within the container of the service and streams the output of stdout and
+/// stderr as server-sent events until the command terminates.
+#[post(
+    "/<app_name>/services/<service_name>/exec",
+    format = "application/json",
+    data = "<payload>"
+)]
+async fn exec(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+    payload: Json<ExecPayload>,
+    user: Result<User, AuthenticationError>,
+    mut shutdown: Shutdown,
+) -> HttpResult<EventStream![]> {
+    let user = user?;
+    let app_name = app_name?;
+    if payload.command.is_empty() {
+        return Err(HttpApiProblem::with_title(StatusCode::BAD_REQUEST)
+            .detail("The command must not be empty.")
+            .into());
+    }
+
+    info!(
+        "User {} executes {:?} in service {} of app {}",
+        user.name().map_or("anonymous", String::as_str),
+        payload.command,
+        service_name,
+        app_name
+    );
+
+    let mut output = match apps
+        .exec(&app_name, &service_name, &payload.command)
+        .await?
+    {
+        Some(output) => output,
+        None => return Err(HttpApiProblem::with_title(StatusCode::NOT_FOUND).into()),
+    };
+
+    Ok(EventStream! {
+        loop {
+            let output = tokio::select! {
+                output = output.recv() => output,
+                _ = &mut shutdown => break,
+            };
+            match output {
+                Some(Ok(output)) => yield Event::data(output.text().to_string()).event(output.stream_name()),
+                Some(Err(err)) => yield Event::data(err.to_string()).event("error"),
+                None => break,
+            }
+        }
+    })
+}
+
 #[get(
     "/<app_name>/logs/<service_name>?<since>&<limit>",
     format = "text/plain"
@@ -216,6 +762,44 @@ async fn logs(
     })
 }
 
+#[get("/<app_name>/stats", format = "application/json")]
+async fn stats(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<ServiceStats>>> {
+    let app_name = app_name?;
+
+    match apps.get_stats(&app_name).await? {
+        Some(stats) => Ok(Json(stats)),
+        None => Err(AppsError::AppNotFound { app_name }.into()),
+    }
+}
+
+/// Summarizes the state of each service of the app, including restart counts and exit codes, in
+/// order to spot crash-looping containers.
+#[get("/<app_name>/status", format = "application/json")]
+async fn status(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<ServiceStatusReport>>> {
+    let app_name = app_name?;
+
+    let apps = apps.get_apps().await?;
+    match apps.get_vec(app_name.as_str()) {
+        Some(services) => {
+            let mut services = services.iter().collect::<Vec<_>>();
+            services.sort_by(|a, b| a.service_name().cmp(b.service_name()));
+            Ok(Json(
+                services
+                    .into_iter()
+                    .map(ServiceStatusReport::from)
+                    .collect(),
+            ))
+        }
+        None => Err(AppsError::AppNotFound { app_name }.into()),
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum RunOptions {
     Sync,
@@ -263,16 +847,292 @@ pub struct LogsResponse {
     limit: usize,
 }
 
+/// The query parameters of `GET /apps` that filter, sort, and paginate the apps.
+#[derive(FromForm)]
+pub struct AppsQuery {
+    name: Option<String>,
+    #[field(name = "type")]
+    container_type: Option<String>,
+    image: Option<String>,
+    owner: Option<String>,
+    sort: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+impl AppsQuery {
+    fn filter(&self) -> Result<AppsFilter, HttpApiError> {
+        let container_type = match &self.container_type {
+            Some(container_type) => {
+                Some(ContainerType::from_str(container_type).map_err(|err| {
+                    HttpApiError::from(
+                        HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                            .detail(err.to_string()),
+                    )
+                })?)
+            }
+            None => None,
+        };
+
+        AppsFilter::new(
+            self.name.as_deref(),
+            container_type,
+            self.image.clone(),
+            self.owner.clone(),
+        )
+        .map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail(format!("Invalid name pattern: {}", err))
+                .into()
+        })
+    }
+
+    fn order(&self) -> Result<AppsOrder, HttpApiError> {
+        match &self.sort {
+            Some(sort) => AppsOrder::from_str(sort).map_err(|err| {
+                HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                    .detail(err)
+                    .into()
+            }),
+            None => Ok(AppsOrder::default()),
+        }
+    }
+}
+
+/// The apps of `GET /apps` with the total number of matching apps as `X-Total-Count` header and,
+/// if there are more apps, a link to the next page.
+pub struct AppsResponse {
+    page: AppsPage,
+}
+
+impl<'r> Responder<'r, 'static> for AppsResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        let next_uri = self.page.next_offset().map(|next_offset| {
+            let uri = request.uri().to_string();
+            let (path, query) = match uri.split_once('?') {
+                Some((path, query)) => (path.to_string(), query.to_string()),
+                None => (uri.clone(), String::new()),
+            };
+            let mut parameters = query
+                .split('&')
+                .filter(|parameter| !parameter.is_empty() && !parameter.starts_with("offset="))
+                .map(String::from)
+                .collect::<Vec<_>>();
+            parameters.push(format!("offset={}", next_offset));
+            format!("{}?{}", path, parameters.join("&"))
+        });
+        let total = self.page.total();
+
+        let mut response = Response::build_from(Json(self.page).respond_to(request)?);
+        response.raw_header("X-Total-Count", total.to_string());
+        if let Some(next_uri) = next_uri {
+            response.raw_header("Link", format!("<{}>;rel=next", next_uri));
+        }
+        response.ok()
+    }
+}
+
 #[derive(FromForm)]
 pub struct CreateAppOptions {
     #[field(name = "replicateFrom")]
     replicate_from: Option<AppName>,
+    #[field(name = "dryRun")]
+    dry_run: Option<bool>,
+    explain: Option<bool>,
+    #[field(name = "skipCompanions")]
+    skip_companions: Option<String>,
+    import: Option<bool>,
+    /// The point in time (RFC 3339 timestamp) at which the deployment will be performed.
+    #[field(name = "runAt")]
+    run_at: Option<String>,
+}
+
+/// The query parameters of `DELETE /apps/{app}`.
+#[derive(FromForm)]
+pub struct DeleteAppOptions {
+    /// The point in time (RFC 3339 timestamp) at which the app will be deleted.
+    #[field(name = "runAt")]
+    run_at: Option<String>,
+}
+
+fn parse_run_at(run_at: &Option<String>) -> Result<Option<DateTime<Utc>>, HttpApiError> {
+    match run_at {
+        None => Ok(None),
+        Some(run_at) => match DateTime::parse_from_rfc3339(run_at) {
+            Ok(run_at) => Ok(Some(run_at.with_timezone(&Utc))),
+            Err(err) => Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail(format!("Invalid runAt {}: {}", run_at, err))
+                .into()),
+        },
+    }
 }
 
 impl CreateAppOptions {
     fn replicate_from(&self) -> &Option<AppName> {
         &self.replicate_from
     }
+
+    /// If `true`, the resolved service configurations will be returned without deploying them.
+    fn dry_run(&self) -> bool {
+        self.dry_run.unwrap_or(false)
+    }
+
+    /// If `true`, the response contains a trace of how each service configuration has been
+    /// produced.
+    fn explain(&self) -> bool {
+        self.explain.unwrap_or(false)
+    }
+
+    /// If `true`, the payload is the document of `GET /apps/{app}/export` instead of the service
+    /// configurations.
+    fn import(&self) -> bool {
+        self.import.unwrap_or(false)
+    }
+
+    /// The names of the configured companions that must not be deployed, given as comma separated
+    /// list, e.g. `skipCompanions=kafka,keycloak`.
+    fn skipped_companions(&self) -> Vec<String> {
+        match &self.skip_companions {
+            None => Vec::new(),
+            Some(names) => names
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// The payload of a deployment request: either the plain list of services or an object that
+/// additionally contains companions which are only deployed for this app and the tickets the app
+/// is linked to.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CreateAppPayload {
+    Services(Vec<ServiceConfig>),
+    ServicesWithCompanions {
+        services: Vec<ServiceConfig>,
+        #[serde(default)]
+        companions: BTreeMap<String, Companion>,
+        #[serde(default)]
+        tickets: Vec<String>,
+    },
+}
+
+impl CreateAppPayload {
+    /// Parses the payload. In strict mode, payloads with fields that are unknown to PREvant, e.g.
+    /// due to typos like `enviroment`, will be rejected.
+    pub(super) fn from_value(value: Value, strict: bool) -> Result<Self, HttpApiError> {
+        if strict {
+            let unknown_fields = CreateAppPayload::unknown_fields(&value);
+            if !unknown_fields.is_empty() {
+                return Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                    .detail(format!(
+                        "The payload contains unknown fields: {}",
+                        unknown_fields.join(", ")
+                    ))
+                    .into());
+            }
+        }
+
+        CreateAppPayload::validate_images(&value).map_err(AppsError::from)?;
+
+        serde_json::from_value(value).map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::UNPROCESSABLE_ENTITY)
+                .detail(format!("Invalid payload: {}", err))
+                .into()
+        })
+    }
+
+    /// Parses the image references of the services and companions upfront, so that invalid
+    /// references are reported with the reason instead of a generic deserialization error.
+    fn validate_images(value: &Value) -> Result<(), ServiceError> {
+        let (services, companions) = match value {
+            Value::Array(services) => (services.iter().collect::<Vec<_>>(), Vec::new()),
+            Value::Object(payload) => (
+                payload
+                    .get("services")
+                    .and_then(Value::as_array)
+                    .map(|services| services.iter().collect())
+                    .unwrap_or_default(),
+                payload
+                    .get("companions")
+                    .and_then(Value::as_object)
+                    .map(|companions| companions.values().collect())
+                    .unwrap_or_default(),
+            ),
+            _ => return Ok(()),
+        };
+
+        for config in services.into_iter().chain(companions) {
+            if let Some(image) = config.get("image").and_then(Value::as_str) {
+                Image::from_str(image)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unknown_fields(value: &Value) -> Vec<String> {
+        let mut unknown_fields = Vec::new();
+
+        let (services, prefix, companions) = match value {
+            Value::Array(_) => (Some(value), "", None),
+            Value::Object(payload) => {
+                unknown_fields.extend(
+                    payload
+                        .keys()
+                        .filter(|key| {
+                            *key != "services" && *key != "companions" && *key != "tickets"
+                        })
+                        .cloned(),
+                );
+                (
+                    payload.get("services"),
+                    "services",
+                    payload.get("companions"),
+                )
+            }
+            _ => return unknown_fields,
+        };
+
+        if let Some(Value::Array(services)) = services {
+            for (index, service) in services.iter().enumerate() {
+                let _: Result<ServiceConfig, _> =
+                    serde_ignored::deserialize(service.clone(), |path| {
+                        unknown_fields.push(format!("{}[{}].{}", prefix, index, path))
+                    });
+            }
+        }
+
+        if let Some(Value::Object(companions)) = companions {
+            for (name, companion) in companions {
+                let _: Result<Companion, _> =
+                    serde_ignored::deserialize(companion.clone(), |path| {
+                        unknown_fields.push(format!("companions.{}.{}", name, path))
+                    });
+            }
+        }
+
+        unknown_fields
+    }
+
+    pub(super) fn into_parts(self) -> (Vec<ServiceConfig>, Vec<Companion>) {
+        match self {
+            CreateAppPayload::Services(services) => (services, Vec::new()),
+            CreateAppPayload::ServicesWithCompanions {
+                mut services,
+                companions,
+                tickets,
+            } => {
+                for service in services.iter_mut() {
+                    service.set_tickets(tickets.clone());
+                }
+                (services, companions.into_iter().map(|(_, c)| c).collect())
+            }
+        }
+    }
 }
 
 impl<'r> Responder<'r, 'static> for LogsResponse {
@@ -314,6 +1174,12 @@ pub struct ServiceStatusData {
     status: ServiceStatus,
 }
 
+/// The command and its arguments that will be executed within the container of a service.
+#[derive(Deserialize)]
+pub struct ExecPayload {
+    command: Vec<String>,
+}
+
 pub struct ServiceStatusResponse {
     service: Option<Service>,
 }
@@ -341,6 +1207,51 @@ where
     }
 }
 
+/// Responds with `202 Accepted` and the operation that has been scheduled for later.
+pub struct ScheduledOperationResponse(ScheduledOperation);
+
+impl<'r> Responder<'r, 'static> for ScheduledOperationResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        let url = format!("/api/scheduled-operations/{}", self.0.id());
+        Response::build_from(Json(self.0).respond_to(request)?)
+            .status(Status::Accepted)
+            .raw_header("Location", url)
+            .ok()
+    }
+}
+
+pub enum CreateAppResponse {
+    Deployment(AsyncCompletion<Json<Vec<Service>>>),
+    DryRun(Json<Vec<ServiceConfig>>),
+    Explained(Json<Value>),
+    Scheduled(ScheduledOperationResponse),
+}
+
+impl<'r> Responder<'r, 'static> for CreateAppResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        match self {
+            CreateAppResponse::Deployment(completion) => completion.respond_to(request),
+            CreateAppResponse::DryRun(configs) => configs.respond_to(request),
+            CreateAppResponse::Explained(explanation) => explanation.respond_to(request),
+            CreateAppResponse::Scheduled(operation) => operation.respond_to(request),
+        }
+    }
+}
+
+pub enum DeleteAppResponse {
+    Deletion(AsyncCompletion<Json<Vec<Service>>>),
+    Scheduled(ScheduledOperationResponse),
+}
+
+impl<'r> Responder<'r, 'static> for DeleteAppResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        match self {
+            DeleteAppResponse::Deletion(completion) => completion.respond_to(request),
+            DeleteAppResponse::Scheduled(operation) => operation.respond_to(request),
+        }
+    }
+}
+
 impl<'r> Responder<'r, 'static> for ServiceStatusResponse {
     fn respond_to(self, _request: &'r Request) -> Result<Response<'static>, Status> {
         match self.service {
@@ -352,23 +1263,164 @@ impl<'r> Responder<'r, 'static> for ServiceStatusResponse {
 
 impl From<AppsError> for HttpApiError {
     fn from(error: AppsError) -> Self {
-        let status = match error {
-            AppsError::AppNotFound { .. } => StatusCode::NOT_FOUND,
-            AppsError::AppIsInDeployment { .. } => StatusCode::CONFLICT,
-            AppsError::AppIsInDeletion { .. } => StatusCode::CONFLICT,
-            AppsError::InfrastructureError { .. }
-            | AppsError::InvalidServerConfiguration { .. }
-            | AppsError::InvalidTemplateFormat { .. }
-            | AppsError::UnableToResolveImage { .. }
-            | AppsError::InvalidDeploymentHook => {
-                error!("Internal server error: {}", error);
-                StatusCode::INTERNAL_SERVER_ERROR
+        let (status, problem_type, title) = match &error {
+            AppsError::AppNotFound { .. } => {
+                (StatusCode::NOT_FOUND, "app-not-found", "App not found")
             }
+            AppsError::AppIsInDeployment { .. } => (
+                StatusCode::CONFLICT,
+                "app-in-deployment",
+                "App is in deployment",
+            ),
+            AppsError::AppAlreadyExists { .. } => (
+                StatusCode::CONFLICT,
+                "app-already-exists",
+                "App already exists",
+            ),
+            AppsError::AppIsInDeletion { .. } => (
+                StatusCode::CONFLICT,
+                "app-in-deletion",
+                "App is in deletion",
+            ),
+            AppsError::AppIsFrozen { .. } => (StatusCode::LOCKED, "app-frozen", "App is frozen"),
+            AppsError::InvalidServiceDependencies { .. } => (
+                StatusCode::BAD_REQUEST,
+                "invalid-service-dependencies",
+                "Invalid service dependencies",
+            ),
+            AppsError::AppExceedsImageSizeLimit { .. } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "image-size-limit-exceeded",
+                "Image size limit exceeded",
+            ),
+            AppsError::ImagePolicyViolation { .. } => (
+                StatusCode::FORBIDDEN,
+                "image-policy-violation",
+                "Image policy violation",
+            ),
+            AppsError::UnknownCompanions { .. } => (
+                StatusCode::BAD_REQUEST,
+                "unknown-companions",
+                "Unknown companions",
+            ),
+            AppsError::InvalidServiceModel { .. } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid-service-model",
+                "Invalid service",
+            ),
+            AppsError::ShuttingDown => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "shutting-down",
+                "Shutting down",
+            ),
+            AppsError::InfrastructureError { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "infrastructure-error",
+                "Infrastructure error",
+            ),
+            AppsError::TransientInfrastructureError { .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "transient-infrastructure-error",
+                "Temporary infrastructure error",
+            ),
+            AppsError::InvalidServerConfiguration { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "invalid-server-configuration",
+                "Invalid server configuration",
+            ),
+            AppsError::InvalidTemplateFormat { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "invalid-template",
+                "Invalid template",
+            ),
+            AppsError::UnableToResolveImage { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unresolvable-image",
+                "Unable to resolve image",
+            ),
+            AppsError::InvalidDeploymentHook => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "invalid-deployment-hook",
+                "Invalid deployment hook",
+            ),
+            AppsError::InsufficientCapacity { .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "insufficient-capacity",
+                "Insufficient capacity",
+            ),
+            AppsError::ExecNotSupported => (
+                StatusCode::NOT_IMPLEMENTED,
+                "exec-not-supported",
+                "Exec not supported",
+            ),
+            AppsError::BasicAuthNotSupported { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "basic-auth-not-supported",
+                "Basic auth not supported",
+            ),
         };
 
-        HttpApiProblem::with_title_and_type(status)
-            .detail(format!("{}", error))
-            .into()
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Internal server error: {}", error);
+        }
+
+        let mut problem = HttpApiProblem::new(status)
+            .type_url(format!("urn:prevant:{}", problem_type))
+            .title(title)
+            .detail(format!("{}", error));
+
+        match &error {
+            AppsError::InfrastructureError { error }
+            | AppsError::TransientInfrastructureError { error } => {
+                if let Some(err) = error.downcast_ref::<ServiceDeploymentError>() {
+                    problem = problem.value("serviceName", err.service_name());
+                }
+                if let Some(err) = error.downcast_ref::<ServicesDeploymentError>() {
+                    let failed_services = err
+                        .errors()
+                        .iter()
+                        .map(|err| {
+                            serde_json::json!({
+                                "serviceName": err.service_name(),
+                                "detail": err.message(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    problem = problem.value("failedServices", &failed_services);
+                }
+            }
+            AppsError::AppIsFrozen { until, .. } => {
+                problem = problem.value("frozenUntil", until);
+            }
+            AppsError::ImagePolicyViolation { violations }
+            | AppsError::InsufficientCapacity { violations } => {
+                problem = problem.value("violations", violations);
+            }
+            _ => {}
+        }
+
+        problem.into()
+    }
+}
+
+/// The value of the `Idempotency-Key` header of a deployment request. Replays of the same key
+/// are answered with the result of the original request instead of deploying the app again.
+pub struct IdempotencyKey(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        match request
+            .headers()
+            .get_one("Idempotency-Key")
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+        {
+            Some(key) => Outcome::Success(IdempotencyKey(key.to_string())),
+            None => Outcome::Forward(()),
+        }
     }
 }
 
@@ -512,4 +1564,425 @@ mod tests {
             assert_eq!(run_options, Some(RunOptions::Sync));
         }
     }
+
+    mod parse_idempotency_key_from_request {
+        use crate::apps::routes::*;
+        use rocket::http::Header;
+        use rocket::local::asynchronous::Client;
+
+        #[tokio::test]
+        async fn with_idempotency_key() {
+            let rocket = rocket::build();
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let post = client
+                .post("/")
+                .header(Header::new("Idempotency-Key", " ci-build-42 "));
+            let request = post.inner();
+
+            let key = IdempotencyKey::from_request(request).await.succeeded();
+
+            assert_eq!(
+                key.map(|IdempotencyKey(key)| key),
+                Some(String::from("ci-build-42"))
+            );
+        }
+
+        #[tokio::test]
+        async fn without_idempotency_key() {
+            let rocket = rocket::build();
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let post = client.post("/").header(Header::new("Idempotency-Key", ""));
+            let request = post.inner();
+
+            assert!(IdempotencyKey::from_request(request).await.is_forward());
+        }
+    }
+
+    mod respond_with_apps_page {
+        use crate::apps::routes::*;
+        use crate::models::service::ServiceBuilder;
+        use chrono::Utc;
+        use multimap::MultiMap;
+        use rocket::local::asynchronous::Client;
+
+        fn page(offset: usize, limit: Option<usize>) -> AppsPage {
+            let mut apps = MultiMap::new();
+            for app_name in &["master", "feature-a", "feature-b"] {
+                apps.insert(
+                    app_name.to_string(),
+                    ServiceBuilder::new()
+                        .id(format!("{}-db", app_name))
+                        .app_name(app_name.to_string())
+                        .config(crate::sc!("db", "mariadb:10.3"))
+                        .started_at(Utc::now())
+                        .build()
+                        .unwrap(),
+                );
+            }
+            AppsPage::new(apps, AppsOrder::Name, offset, limit)
+        }
+
+        #[tokio::test]
+        async fn with_link_to_next_page() {
+            let rocket = rocket::build();
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let get = client.get("/api/apps?sort=name&offset=0&limit=2");
+            let request = get.inner();
+
+            let response = AppsResponse {
+                page: page(0, Some(2)),
+            }
+            .respond_to(request)
+            .unwrap();
+
+            assert_eq!(response.headers().get_one("X-Total-Count"), Some("3"));
+            assert_eq!(
+                response.headers().get_one("Link"),
+                Some("</api/apps?sort=name&limit=2&offset=2>;rel=next")
+            );
+        }
+
+        #[tokio::test]
+        async fn without_link_on_last_page() {
+            let rocket = rocket::build();
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let get = client.get("/api/apps?offset=2&limit=2");
+            let request = get.inner();
+
+            let response = AppsResponse {
+                page: page(2, Some(2)),
+            }
+            .respond_to(request)
+            .unwrap();
+
+            assert_eq!(response.headers().get_one("X-Total-Count"), Some("3"));
+            assert_eq!(response.headers().get_one("Link"), None);
+        }
+    }
+
+    mod parse_create_app_payload {
+        use crate::apps::routes::*;
+
+        #[test]
+        fn with_list_of_services() {
+            let payload = serde_json::from_value::<CreateAppPayload>(serde_json::json!([{
+                "serviceName": "mariadb",
+                "image": "mariadb:10.3"
+            }]))
+            .unwrap();
+
+            let (services, companions) = payload.into_parts();
+
+            assert_eq!(services.len(), 1);
+            assert!(companions.is_empty());
+        }
+
+        #[test]
+        fn with_services_and_companions() {
+            let payload = serde_json::from_value::<CreateAppPayload>(serde_json::json!({
+                "services": [{
+                    "serviceName": "mariadb",
+                    "image": "mariadb:10.3"
+                }],
+                "companions": {
+                    "mock": {
+                        "serviceName": "{{service.name}}-mock",
+                        "type": "service",
+                        "image": "mockserver/mockserver:latest"
+                    }
+                }
+            }))
+            .unwrap();
+
+            let (services, companions) = payload.into_parts();
+
+            assert_eq!(services.len(), 1);
+            assert_eq!(companions.len(), 1);
+        }
+
+        #[test]
+        fn with_services_and_tickets() {
+            let payload = CreateAppPayload::from_value(
+                serde_json::json!({
+                    "services": [{
+                        "serviceName": "mariadb",
+                        "image": "mariadb:10.3"
+                    }],
+                    "tickets": [ "PROJ-123" ]
+                }),
+                true,
+            );
+
+            let (services, _) = payload.ok().unwrap().into_parts();
+
+            assert_eq!(services[0].tickets(), &[String::from("PROJ-123")]);
+        }
+
+        #[test]
+        fn with_unknown_fields_in_lenient_mode() {
+            let payload = CreateAppPayload::from_value(
+                serde_json::json!([{
+                    "serviceName": "mariadb",
+                    "image": "mariadb:10.3",
+                    "enviroment": [ "MYSQL_USER=admin" ]
+                }]),
+                false,
+            );
+
+            assert!(payload.is_ok());
+        }
+
+        #[test]
+        fn with_unknown_fields_in_strict_mode() {
+            let value = serde_json::json!({
+                "services": [{
+                    "serviceName": "mariadb",
+                    "image": "mariadb:10.3",
+                    "enviroment": [ "MYSQL_USER=admin" ]
+                }],
+                "companions": {
+                    "mock": {
+                        "serviceName": "{{service.name}}-mock",
+                        "type": "service",
+                        "image": "mockserver/mockserver:latest",
+                        "lables": {}
+                    }
+                },
+                "volumes": {}
+            });
+
+            assert_eq!(
+                CreateAppPayload::unknown_fields(&value),
+                vec![
+                    String::from("volumes"),
+                    String::from("services[0].enviroment"),
+                    String::from("companions.mock.lables"),
+                ]
+            );
+            assert!(CreateAppPayload::from_value(value, true).is_err());
+        }
+
+        #[test]
+        fn with_image_digest() {
+            let payload = CreateAppPayload::from_value(
+                serde_json::json!([{
+                    "serviceName": "app",
+                    "image": "ghcr.io/org/app@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+                }]),
+                true,
+            );
+
+            let (services, _) = payload.ok().unwrap().into_parts();
+            assert_eq!(
+                services[0].image().to_string(),
+                "ghcr.io/org/app@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+            );
+        }
+
+        #[test]
+        fn with_invalid_image() {
+            let value = serde_json::json!({
+                "services": [],
+                "companions": {
+                    "mock": {
+                        "serviceName": "{{service.name}}-mock",
+                        "type": "service",
+                        "image": "mockserver/mockserver@latest"
+                    }
+                }
+            });
+
+            assert!(matches!(
+                CreateAppPayload::validate_images(&value),
+                Err(ServiceError::InvalidImageString { .. })
+            ));
+        }
+
+        #[test]
+        fn without_unknown_fields_in_strict_mode() {
+            let payload = CreateAppPayload::from_value(
+                serde_json::json!([{
+                    "serviceName": "mariadb",
+                    "image": "mariadb:10.3",
+                    "env": [ "MYSQL_USER=admin" ]
+                }]),
+                true,
+            );
+
+            assert!(payload.is_ok());
+        }
+    }
+
+    mod map_apps_error {
+        use crate::apps::routes::*;
+        use crate::infrastructure::TransientInfrastructureError;
+        use assert_json_diff::assert_json_eq;
+        use std::str::FromStr;
+
+        #[test]
+        fn app_not_found_as_problem() {
+            let error = HttpApiError::from(AppsError::AppNotFound {
+                app_name: AppName::from_str("master").unwrap(),
+            });
+
+            assert_json_eq!(
+                serde_json::to_value(error.problem()).unwrap(),
+                serde_json::json!({
+                    "type": "urn:prevant:app-not-found",
+                    "status": 404,
+                    "title": "App not found",
+                    "detail": "Cannot find app master."
+                })
+            );
+        }
+
+        #[test]
+        fn app_is_frozen_as_problem() {
+            let error = HttpApiError::from(AppsError::AppIsFrozen {
+                app_name: String::from("master"),
+                until: chrono::DateTime::parse_from_rfc3339("2021-07-02T06:00:00Z")
+                    .unwrap()
+                    .with_timezone(&chrono::Utc),
+                reason: String::from("Release 2.0"),
+            });
+
+            assert_json_eq!(
+                serde_json::to_value(error.problem()).unwrap(),
+                serde_json::json!({
+                    "type": "urn:prevant:app-frozen",
+                    "status": 423,
+                    "title": "App is frozen",
+                    "detail": "The app master cannot be changed until 2021-07-02 06:00:00 UTC due to a deployment freeze: Release 2.0",
+                    "frozenUntil": "2021-07-02T06:00:00Z"
+                })
+            );
+        }
+
+        #[test]
+        fn infrastructure_error_with_failing_service_as_problem() {
+            let error = HttpApiError::from(AppsError::InfrastructureError {
+                error: Arc::new(failure::Error::from(ServiceDeploymentError::new(
+                    "db",
+                    &"port is already allocated",
+                ))),
+            });
+
+            assert_json_eq!(
+                serde_json::to_value(error.problem()).unwrap(),
+                serde_json::json!({
+                    "type": "urn:prevant:infrastructure-error",
+                    "status": 500,
+                    "title": "Infrastructure error",
+                    "detail": "Cannot interact with infrastructure: Cannot deploy service db: port is already allocated",
+                    "serviceName": "db"
+                })
+            );
+        }
+
+        #[test]
+        fn infrastructure_error_with_failing_services_as_problem() {
+            let error = HttpApiError::from(AppsError::from(
+                ServiceDeploymentError::collect::<()>(vec![
+                    Err(ServiceDeploymentError::new(
+                        "db",
+                        &"port is already allocated",
+                    )),
+                    Ok(()),
+                    Err(ServiceDeploymentError::new("cache", &"no such image")),
+                ])
+                .unwrap_err(),
+            ));
+
+            assert_json_eq!(
+                serde_json::to_value(error.problem()).unwrap(),
+                serde_json::json!({
+                    "type": "urn:prevant:infrastructure-error",
+                    "status": 500,
+                    "title": "Infrastructure error",
+                    "detail": "Cannot interact with infrastructure: Cannot deploy service db: port is already allocated; Cannot deploy service cache: no such image",
+                    "failedServices": [
+                        { "serviceName": "db", "detail": "port is already allocated" },
+                        { "serviceName": "cache", "detail": "no such image" }
+                    ]
+                })
+            );
+        }
+
+        #[test]
+        fn transient_infrastructure_error_as_problem() {
+            let error = HttpApiError::from(AppsError::from(failure::Error::from(
+                ServiceDeploymentError::caused_by(
+                    "db",
+                    &failure::Error::from(TransientInfrastructureError::new(
+                        "Pulling image",
+                        3,
+                        &"TLS handshake timeout",
+                    )),
+                ),
+            )));
+
+            assert_json_eq!(
+                serde_json::to_value(error.problem()).unwrap(),
+                serde_json::json!({
+                    "type": "urn:prevant:transient-infrastructure-error",
+                    "status": 503,
+                    "title": "Temporary infrastructure error",
+                    "detail": "Temporarily cannot interact with infrastructure: Cannot deploy service db: Pulling image failed after 3 attempts: TLS handshake timeout",
+                    "serviceName": "db"
+                })
+            );
+        }
+    }
+
+    mod replay_idempotent_request {
+        use crate::apps::routes::*;
+        use crate::config::Config;
+        use crate::infrastructure::Dummy;
+        use rocket::http::{ContentType, Header};
+        use rocket::local::asynchronous::Client;
+        use std::str::FromStr;
+
+        #[tokio::test]
+        async fn should_release_key_of_unfinished_request() {
+            let config = Config::default();
+            let apps = Arc::new(Apps::new(config, Box::new(Dummy::new())).unwrap());
+            let app_name = AppName::from_str("master").unwrap();
+
+            let guard = IdempotencyGuard::new(&apps, "ci-build-42");
+            drop(guard);
+
+            assert!(matches!(
+                apps.begin_idempotent_request("ci-build-42", &app_name, &AppStatusChangeId::new()),
+                IdempotentRequest::New
+            ));
+        }
+
+        #[tokio::test]
+        async fn should_schedule_deployment_only_once() {
+            let config = Config::default();
+            let apps = Arc::new(Apps::new(config, Box::new(Dummy::new())).unwrap());
+            let rocket = rocket::build()
+                .manage(apps.clone())
+                .mount("/api/apps", apps_routes());
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+
+            for _ in 0..2 {
+                client
+                    .post("/api/apps/master?runAt=2099-01-01T00:00:00Z")
+                    .header(ContentType::JSON)
+                    .header(Header::new("Idempotency-Key", "ci-build-42"))
+                    .body(
+                        serde_json::json!([{
+                            "serviceName": "db",
+                            "image": "mariadb"
+                        }])
+                        .to_string(),
+                    )
+                    .dispatch()
+                    .await;
+            }
+
+            assert_eq!(apps.scheduled_operations().operations().len(), 1);
+        }
+    }
 }
@@ -0,0 +1,154 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Records how each service configuration of a deployment has been produced, e.g. if it has been
+/// part of the payload, replicated, or created from a companion template.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct DeploymentTrace {
+    steps: BTreeMap<String, Vec<TraceStep>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "step", rename_all = "camelCase")]
+pub enum TraceStep {
+    /// The service has been part of the deployment payload.
+    Payload,
+    /// The service has been replicated from another app.
+    Replicated { from: String },
+    /// The service has been created from an application companion template.
+    #[serde(rename_all = "camelCase")]
+    ApplicationCompanion {
+        template: String,
+        user_defined: bool,
+    },
+    /// The service has been created from a service companion template for another service.
+    #[serde(rename_all = "camelCase")]
+    ServiceCompanion {
+        template: String,
+        for_service: String,
+        user_defined: bool,
+    },
+    /// The service overrides a companion and the companion template filled the gaps.
+    MergedWithCompanion { template: String },
+    /// Secrets of the server configuration have been mounted.
+    Secrets { paths: Vec<PathBuf> },
+    /// The image has been rewritten by a rule of the configuration, e.g. to use a registry mirror.
+    ImageRewritten { from: String },
+    /// The port has been resolved from the image.
+    ImagePort { port: u16 },
+    /// Templates of the configuration have been rendered.
+    Templated,
+    /// Globally configured labels have been added.
+    GlobalLabels,
+    /// The globally configured routing has been applied.
+    GlobalRouting,
+    /// The deployment hook modified or added the service.
+    DeploymentHook,
+}
+
+impl DeploymentTrace {
+    pub fn record(&mut self, service_name: &str, step: TraceStep) {
+        self.steps
+            .entry(service_name.to_string())
+            .or_insert_with(Vec::new)
+            .push(step);
+    }
+
+    pub fn record_all<I>(&mut self, service_name: &str, steps: I)
+    where
+        I: IntoIterator<Item = TraceStep>,
+    {
+        self.steps
+            .entry(service_name.to_string())
+            .or_insert_with(Vec::new)
+            .extend(steps);
+    }
+
+    pub fn steps_of(&self, service_name: &str) -> &[TraceStep] {
+        match self.steps.get(service_name) {
+            Some(steps) => steps,
+            None => &[],
+        }
+    }
+
+    /// Drops the steps of services that are not part of the final deployment, e.g. companions of
+    /// services that have been overridden by the payload.
+    pub fn retain_services<'a, I>(&mut self, service_names: I)
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let service_names = service_names.into_iter().collect::<Vec<_>>();
+        self.steps.retain(|name, _| service_names.contains(&name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_trace() {
+        let mut trace = DeploymentTrace::default();
+        trace.record("mariadb", TraceStep::Payload);
+        trace.record(
+            "mariadb-db",
+            TraceStep::ServiceCompanion {
+                template: String::from("{{service.name}}-db"),
+                for_service: String::from("mariadb"),
+                user_defined: false,
+            },
+        );
+
+        assert_eq!(
+            serde_json::to_value(&trace).unwrap(),
+            serde_json::json!({
+                "mariadb": [ { "step": "payload" } ],
+                "mariadb-db": [ {
+                    "step": "serviceCompanion",
+                    "template": "{{service.name}}-db",
+                    "forService": "mariadb",
+                    "userDefined": false
+                } ]
+            })
+        );
+    }
+
+    #[test]
+    fn should_retain_steps_of_deployed_services() {
+        let mut trace = DeploymentTrace::default();
+        trace.record("mariadb", TraceStep::Payload);
+        trace.record("openid", TraceStep::Templated);
+
+        trace.retain_services(&[String::from("mariadb")]);
+
+        assert_eq!(trace.steps_of("mariadb"), &[TraceStep::Payload]);
+        assert_eq!(trace.steps_of("openid"), &[]);
+    }
+}
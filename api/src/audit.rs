@@ -0,0 +1,68 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::http_result::{HttpApiError, HttpResult};
+use crate::services::audit_log::AuditEntry;
+use chrono::{DateTime, Utc};
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// Lists the recorded deployments and deletions, optionally restricted to an app and to a time
+/// range (RFC 3339 timestamps).
+#[get("/audit?<app>&<from>&<until>", format = "application/json")]
+pub async fn audit(
+    app: Option<String>,
+    from: Option<String>,
+    until: Option<String>,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<Vec<AuditEntry>>> {
+    user?;
+    let from = parse_timestamp(from)?;
+    let until = parse_timestamp(until)?;
+
+    Ok(Json(apps.audit_log().entries(
+        app.as_deref(),
+        from.as_ref(),
+        until.as_ref(),
+    )))
+}
+
+fn parse_timestamp(timestamp: Option<String>) -> Result<Option<DateTime<Utc>>, HttpApiError> {
+    match timestamp {
+        None => Ok(None),
+        Some(timestamp) => match DateTime::parse_from_rfc3339(&timestamp) {
+            Ok(timestamp) => Ok(Some(timestamp.with_timezone(&Utc))),
+            Err(err) => Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail(format!("Invalid timestamp {}: {}", timestamp, err))
+                .into()),
+        },
+    }
+}
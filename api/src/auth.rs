@@ -0,0 +1,517 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::config::AuthenticationConfig;
+use crate::http_result::HttpApiError;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use secstr::SecUtf8;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The user that performs a request against the API.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum User {
+    /// Authentication is disabled, i.e. there is no authentication section in the configuration.
+    Anonymous,
+    Authenticated {
+        name: String,
+    },
+}
+
+impl User {
+    pub fn name(&self) -> Option<&String> {
+        match self {
+            User::Anonymous => None,
+            User::Authenticated { name } => Some(name),
+        }
+    }
+}
+
+/// How long the user information of a token is reused before the provider is asked again.
+const USERINFO_TTL: Duration = Duration::from_secs(60);
+/// How long the discovered provider metadata is reused.
+const DISCOVERY_TTL: Duration = Duration::from_secs(60 * 60);
+
+lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(500))
+        .timeout(Duration::from_secs(5))
+        .user_agent(format!("PREvant/{}", crate_version!()))
+        .build()
+        .expect("The HTTP client for the identity provider cannot be initialized");
+    static ref USERINFOS: TtlCache<(Url, String), Value> = TtlCache::new(USERINFO_TTL);
+    static ref USERINFO_ENDPOINTS: TtlCache<Url, Url> = TtlCache::new(DISCOVERY_TTL);
+}
+
+impl AuthenticationConfig {
+    /// Resolves the user of the given bearer token by comparing it to the static tokens and, if
+    /// none of them matches, by requesting the user information from the OpenID Connect provider.
+    /// Users of the provider that are not allowed by the configuration are forbidden.
+    async fn authenticate(&self, token: &str) -> Result<User, AuthenticationError> {
+        let token = SecUtf8::from(token);
+        if let Some(static_token) = self.tokens().iter().find(|t| t.token() == &token) {
+            return Ok(User::Authenticated {
+                name: static_token.name().clone(),
+            });
+        }
+
+        let openid = match self.openid() {
+            Some(openid) => openid,
+            None => return Err(AuthenticationError::InvalidToken),
+        };
+
+        let userinfo_endpoint = match (openid.userinfo_endpoint(), openid.issuer()) {
+            (Some(endpoint), _) => endpoint.clone(),
+            (None, Some(issuer)) => discover_userinfo_endpoint(issuer).await?,
+            (None, None) => {
+                return Err(AuthenticationError::ProviderError {
+                    internal_message: String::from(
+                        "Neither issuer nor userinfo endpoint is configured",
+                    ),
+                })
+            }
+        };
+
+        let userinfo = fetch_userinfo(userinfo_endpoint, token.unsecure()).await?;
+
+        // GitHub provides the user name as login whereas OpenID Connect providers use the
+        // standard claims.
+        let name = ["preferred_username", "login", "email", "sub"]
+            .iter()
+            .find_map(|claim| userinfo.get(claim).and_then(|value| value.as_str()))
+            .ok_or(AuthenticationError::InvalidToken)?;
+
+        if let Some(audience) = openid.audience() {
+            // The provider accepted the token, thus its payload can be trusted.
+            if !token_audiences(token.unsecure()).contains(audience) {
+                return Err(AuthenticationError::Forbidden {
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        if !openid.allowed().permits(name, &userinfo) {
+            return Err(AuthenticationError::Forbidden {
+                name: name.to_string(),
+            });
+        }
+
+        Ok(User::Authenticated {
+            name: name.to_string(),
+        })
+    }
+}
+
+async fn fetch_userinfo(endpoint: Url, token: &str) -> Result<Value, AuthenticationError> {
+    let key = (endpoint, token.to_string());
+    if let Some(userinfo) = USERINFOS.get(&key) {
+        return Ok(userinfo);
+    }
+
+    let response = HTTP_CLIENT
+        .get(key.0.clone())
+        .bearer_auth(token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED
+        || response.status() == reqwest::StatusCode::FORBIDDEN
+    {
+        return Err(AuthenticationError::InvalidToken);
+    }
+
+    let userinfo = response.error_for_status()?.json::<Value>().await?;
+    USERINFOS.insert(key, userinfo.clone());
+
+    Ok(userinfo)
+}
+
+/// Reads the audiences from the payload of a JWT access token. Tokens that are not JWTs, e.g.
+/// GitHub tokens, do not have an audience.
+fn token_audiences(token: &str) -> Vec<String> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok())
+        .and_then(|payload| serde_json::from_slice::<Value>(&payload).ok());
+
+    match payload.as_ref().and_then(|payload| payload.get("aud")) {
+        Some(Value::String(audience)) => vec![audience.clone()],
+        Some(Value::Array(audiences)) => audiences
+            .iter()
+            .filter_map(|audience| audience.as_str())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+async fn discover_userinfo_endpoint(issuer: &Url) -> Result<Url, AuthenticationError> {
+    #[derive(Deserialize)]
+    struct ProviderMetadata {
+        userinfo_endpoint: Url,
+    }
+
+    if let Some(endpoint) = USERINFO_ENDPOINTS.get(issuer) {
+        return Ok(endpoint);
+    }
+
+    let discovery_url = Url::parse(&format!(
+        "{}/.well-known/openid-configuration",
+        issuer.as_str().trim_end_matches('/')
+    ))
+    .map_err(|err| AuthenticationError::ProviderError {
+        internal_message: err.to_string(),
+    })?;
+
+    debug!("Discover OpenID provider metadata at {}", discovery_url);
+
+    let metadata = HTTP_CLIENT
+        .get(discovery_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ProviderMetadata>()
+        .await?;
+
+    USERINFO_ENDPOINTS.insert(issuer.clone(), metadata.userinfo_endpoint.clone());
+
+    Ok(metadata.userinfo_endpoint)
+}
+
+/// Keeps the responses of the identity provider for a while so that not every request has to
+/// wait for the provider.
+struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < ttl);
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = AuthenticationError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        // The configuration is taken from the apps because it might have been reloaded since
+        // PREvant has been started.
+        let config = match request.rocket().state::<Arc<Apps>>() {
+            Some(apps) => apps.config(),
+            None => return Outcome::Success(User::Anonymous),
+        };
+        let authentication_config = match config.authentication_config() {
+            Some(authentication_config) => authentication_config,
+            None => return Outcome::Success(User::Anonymous),
+        };
+
+        let token = match request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+        {
+            Some(token) => token.trim(),
+            None => {
+                return Outcome::Failure((Status::Unauthorized, AuthenticationError::MissingToken))
+            }
+        };
+
+        match authentication_config.authenticate(token).await {
+            Ok(user) => Outcome::Success(user),
+            Err(err @ AuthenticationError::Forbidden { .. }) => {
+                info!("{}", err);
+                Outcome::Failure((Status::Forbidden, err))
+            }
+            Err(err) => {
+                debug!("Cannot authenticate user: {}", err);
+                Outcome::Failure((Status::Unauthorized, err))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum AuthenticationError {
+    #[fail(display = "The request does not provide a bearer token.")]
+    MissingToken,
+    #[fail(display = "The provided token is invalid.")]
+    InvalidToken,
+    #[fail(display = "The user {} is not allowed to use PREvant.", name)]
+    Forbidden { name: String },
+    #[fail(
+        display = "Cannot resolve user from identity provider: {}",
+        internal_message
+    )]
+    ProviderError { internal_message: String },
+}
+
+impl From<reqwest::Error> for AuthenticationError {
+    fn from(err: reqwest::Error) -> Self {
+        AuthenticationError::ProviderError {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+impl From<AuthenticationError> for HttpApiError {
+    fn from(err: AuthenticationError) -> Self {
+        let status = match err {
+            AuthenticationError::MissingToken | AuthenticationError::InvalidToken => {
+                StatusCode::UNAUTHORIZED
+            }
+            AuthenticationError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AuthenticationError::ProviderError { .. } => {
+                error!("Cannot authenticate user: {}", err);
+                StatusCode::BAD_GATEWAY
+            }
+        };
+
+        HttpApiProblem::with_title_and_type(status)
+            .detail(format!("{}", err))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use rocket::http::Header;
+    use rocket::local::asynchronous::Client;
+    use rocket::{Build, Rocket};
+
+    fn config_with_static_token() -> Config {
+        crate::config_from_str!(
+            r#"
+            [[authentication.tokens]]
+            name = "ci"
+            token = "s3cr3t"
+            "#
+        )
+    }
+
+    fn rocket(config: Config) -> Rocket<Build> {
+        let apps = Apps::new(config, Box::new(Dummy::new())).unwrap();
+        rocket::build().manage(Arc::new(apps))
+    }
+
+    #[tokio::test]
+    async fn should_be_anonymous_without_authentication_config() {
+        let rocket = rocket(Config::default());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client.get("/");
+        let request = get.inner();
+
+        let user = User::from_request(request).await.succeeded();
+
+        assert_eq!(user, Some(User::Anonymous));
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_with_static_token() {
+        let rocket = rocket(config_with_static_token());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer s3cr3t"));
+        let request = get.inner();
+
+        let user = User::from_request(request).await.succeeded();
+
+        assert_eq!(
+            user,
+            Some(User::Authenticated {
+                name: String::from("ci")
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_authenticate_without_token() {
+        let rocket = rocket(config_with_static_token());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client.get("/");
+        let request = get.inner();
+
+        let outcome = User::from_request(request).await;
+
+        assert!(outcome.is_failure());
+    }
+
+    #[tokio::test]
+    async fn should_not_authenticate_with_invalid_static_token() {
+        let rocket = rocket(config_with_static_token());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer guessed"));
+        let request = get.inner();
+
+        let outcome = User::from_request(request).await;
+
+        assert!(outcome.is_failure());
+    }
+
+    fn config_with_github_users() -> Config {
+        crate::config_from_str!(
+            r#"
+            [authentication.openid]
+            userinfoEndpoint = "https://api.github.com/user"
+
+            [authentication.openid.allowed]
+            users = [ "octocat" ]
+            "#
+        )
+    }
+
+    fn cache_userinfo(token: &str, userinfo: Value) {
+        USERINFOS.insert(
+            (
+                Url::parse("https://api.github.com/user").unwrap(),
+                String::from(token),
+            ),
+            userinfo,
+        );
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_allowed_openid_user() {
+        cache_userinfo("gho_octocat", serde_json::json!({ "login": "octocat" }));
+        let rocket = rocket(config_with_github_users());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer gho_octocat"));
+
+        let user = User::from_request(get.inner()).await.succeeded();
+
+        assert_eq!(
+            user,
+            Some(User::Authenticated {
+                name: String::from("octocat")
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_forbid_openid_user_that_is_not_allowed() {
+        cache_userinfo("gho_mallory", serde_json::json!({ "login": "mallory" }));
+        let rocket = rocket(config_with_github_users());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer gho_mallory"));
+
+        let outcome = User::from_request(get.inner()).await;
+
+        assert!(matches!(
+            outcome,
+            Outcome::Failure((Status::Forbidden, AuthenticationError::Forbidden { .. }))
+        ));
+    }
+
+    #[test]
+    fn should_read_audiences_of_jwt() {
+        let payload = base64::encode_config(
+            r#"{"aud":["prevant","account"],"sub":"alice"}"#,
+            base64::URL_SAFE_NO_PAD,
+        );
+
+        assert_eq!(
+            token_audiences(&format!("eyJhbGciOiJSUzI1NiJ9.{}.signature", payload)),
+            vec![String::from("prevant"), String::from("account")]
+        );
+        assert!(token_audiences("gho_octocat").is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_with_reloaded_static_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(
+            &file,
+            "[[authentication.tokens]]\nname = 'ci'\ntoken = 's3cr3t'\n",
+        )
+        .unwrap();
+        let config = Config::load(&file.to_string_lossy()).await.unwrap();
+        let apps = Arc::new(Apps::new(config, Box::new(Dummy::new())).unwrap());
+        let client = Client::tracked(rocket::build().manage(apps.clone()))
+            .await
+            .expect("valid rocket");
+
+        std::fs::write(
+            &file,
+            "[[authentication.tokens]]\nname = 'ci'\ntoken = 'r0t4t3d'\n",
+        )
+        .unwrap();
+        assert!(apps.reload_config().await.unwrap());
+
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer s3cr3t"));
+        assert!(User::from_request(get.inner()).await.is_failure());
+
+        let get = client
+            .get("/")
+            .header(Header::new("Authorization", "Bearer r0t4t3d"));
+        assert_eq!(
+            User::from_request(get.inner()).await.succeeded(),
+            Some(User::Authenticated {
+                name: String::from("ci")
+            })
+        );
+    }
+}
@@ -0,0 +1,76 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::infrastructure::Capabilities;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// Returns the features that the active infrastructure backend supports, e.g. whether files can be
+/// mounted into containers.
+#[get("/infrastructure/capabilities", format = "application/json")]
+pub fn capabilities(apps_service: &State<Arc<Apps>>) -> Json<Capabilities> {
+    Json(apps_service.infrastructure_capabilities())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use rocket::http::{Accept, Status};
+    use rocket::local::asynchronous::Client;
+
+    #[tokio::test]
+    async fn should_return_capabilities_of_infrastructure() {
+        let apps = Apps::new(Config::default(), Box::new(Dummy::new())).unwrap();
+        let rocket = rocket::build()
+            .manage(Arc::new(apps))
+            .mount("/api", routes![capabilities]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client
+            .get("/api/infrastructure/capabilities")
+            .header(Accept::JSON)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_json::<serde_json::Value>().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "volumes": true,
+                "exec": true,
+                "tcpRouting": false,
+                "replicas": true,
+                "stats": true,
+                "basicAuth": true
+            })
+        );
+    }
+}
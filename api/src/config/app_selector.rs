@@ -25,7 +25,7 @@
  */
 use regex::Regex;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub(super) struct AppSelector(Regex);
 
 impl AppSelector {
@@ -43,6 +43,15 @@ impl Default for AppSelector {
     }
 }
 
+impl serde::Serialize for AppSelector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for AppSelector {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -0,0 +1,214 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use secstr::SecUtf8;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use url::Url;
+
+#[derive(Clone, Deserialize)]
+pub struct AuthenticationConfig {
+    tokens: Option<Vec<StaticToken>>,
+    openid: Option<OpenIdConfig>,
+}
+
+/// A token that is known upfront, e.g. for CI pipelines that cannot perform an interactive login.
+#[derive(Clone, Deserialize)]
+pub struct StaticToken {
+    name: String,
+    token: SecUtf8,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenIdConfig {
+    issuer: Option<Url>,
+    userinfo_endpoint: Option<Url>,
+    audience: Option<String>,
+    allowed: AllowedUsers,
+}
+
+/// The users of the OpenID Connect provider that may use PREvant. Without such a list, any account
+/// of the provider, e.g. any GitHub user, would be able to change the apps. A user is allowed if
+/// the name is listed, if one of the user's groups is listed, or if the user has all the listed
+/// claims.
+#[derive(Clone, Deserialize)]
+pub struct AllowedUsers {
+    #[serde(default)]
+    users: Vec<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    claims: BTreeMap<String, String>,
+}
+
+impl AuthenticationConfig {
+    pub fn tokens(&self) -> &[StaticToken] {
+        match &self.tokens {
+            Some(tokens) => tokens,
+            None => &[],
+        }
+    }
+
+    pub fn openid(&self) -> Option<&OpenIdConfig> {
+        self.openid.as_ref()
+    }
+}
+
+impl StaticToken {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn token(&self) -> &SecUtf8 {
+        &self.token
+    }
+}
+
+impl OpenIdConfig {
+    pub fn issuer(&self) -> Option<&Url> {
+        self.issuer.as_ref()
+    }
+
+    /// The endpoint that resolves the user information of an access token. If it is not
+    /// configured, it will be discovered through the issuer. For providers that do not support
+    /// OpenID Connect discovery, such as GitHub, it has to be configured explicitly.
+    pub fn userinfo_endpoint(&self) -> Option<&Url> {
+        self.userinfo_endpoint.as_ref()
+    }
+
+    /// The audience that the access tokens have to be issued for, so that tokens that the provider
+    /// issued for other applications are refused.
+    pub fn audience(&self) -> Option<&String> {
+        self.audience.as_ref()
+    }
+
+    pub fn allowed(&self) -> &AllowedUsers {
+        &self.allowed
+    }
+}
+
+impl AllowedUsers {
+    /// Returns `true` if the user with the given name and user information may use PREvant.
+    pub fn permits(&self, name: &str, userinfo: &Value) -> bool {
+        if self.users.iter().any(|user| user == name) {
+            return true;
+        }
+
+        let groups = userinfo
+            .get("groups")
+            .and_then(|groups| groups.as_array())
+            .map_or_else(Vec::new, |groups| {
+                groups.iter().filter_map(|group| group.as_str()).collect()
+            });
+        if self
+            .groups
+            .iter()
+            .any(|group| groups.contains(&group.as_str()))
+        {
+            return true;
+        }
+
+        !self.claims.is_empty()
+            && self
+                .claims
+                .iter()
+                .all(|(claim, value)| userinfo.get(claim).and_then(|v| v.as_str()) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_static_tokens() {
+        let config = toml::de::from_str::<AuthenticationConfig>(
+            r#"
+            [[tokens]]
+            name = "ci"
+            token = "s3cr3t"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tokens().len(), 1);
+        assert_eq!(config.tokens()[0].name(), "ci");
+        assert_eq!(config.tokens()[0].token().unsecure(), "s3cr3t");
+        assert!(config.openid().is_none());
+    }
+
+    #[test]
+    fn should_parse_openid_config() {
+        let config = toml::de::from_str::<AuthenticationConfig>(
+            r#"
+            [openid]
+            userinfoEndpoint = "https://api.github.com/user"
+
+            [openid.allowed]
+            users = [ "octocat" ]
+            "#,
+        )
+        .unwrap();
+
+        let openid = config.openid().unwrap();
+        assert_eq!(openid.issuer(), None);
+        assert_eq!(
+            openid.userinfo_endpoint(),
+            Some(&Url::parse("https://api.github.com/user").unwrap())
+        );
+        assert_eq!(openid.audience(), None);
+    }
+
+    #[test]
+    fn should_not_parse_openid_config_without_allowed_users() {
+        let config = toml::de::from_str::<AuthenticationConfig>(
+            r#"
+            [openid]
+            userinfoEndpoint = "https://api.github.com/user"
+            "#,
+        );
+
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn should_permit_allowed_users_only() {
+        let allowed = toml::de::from_str::<AllowedUsers>(
+            r#"
+            users = [ "octocat" ]
+            groups = [ "platform" ]
+            claims = { hd = "example.com" }
+            "#,
+        )
+        .unwrap();
+
+        assert!(allowed.permits("octocat", &serde_json::json!({})));
+        assert!(allowed.permits("alice", &serde_json::json!({ "groups": [ "platform" ] })));
+        assert!(allowed.permits("bob", &serde_json::json!({ "hd": "example.com" })));
+        assert!(!allowed.permits("mallory", &serde_json::json!({ "groups": [ "guests" ] })));
+        assert!(!allowed.permits("mallory", &serde_json::json!({ "hd": "evil.com" })));
+    }
+}
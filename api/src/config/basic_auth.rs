@@ -0,0 +1,83 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::AppSelector;
+use serde::Deserialize;
+
+/// Protects the routes of the services with basic auth so that review apps, e.g. apps containing
+/// customer data, are not publicly reachable. The credentials are generated on the first
+/// deployment of an app.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicAuthConfig {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(default = "BasicAuthConfig::default_username")]
+    username: String,
+}
+
+impl BasicAuthConfig {
+    fn default_username() -> String {
+        String::from("prevant")
+    }
+
+    pub fn applies_to(&self, app_name: &str) -> bool {
+        self.app_selector.matches(app_name)
+    }
+
+    /// The user name of the generated credentials.
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_apply_to_all_apps_by_default() {
+        let config = toml::de::from_str::<BasicAuthConfig>("").unwrap();
+
+        assert!(config.applies_to("master"));
+        assert_eq!(config.username(), "prevant");
+    }
+
+    #[test]
+    fn should_apply_to_selected_apps() {
+        let config = toml::de::from_str::<BasicAuthConfig>(
+            r#"
+            appSelector = 'customer-.*'
+            username = 'reviewer'
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.applies_to("customer-acme"));
+        assert!(!config.applies_to("master"));
+        assert_eq!(config.username(), "reviewer");
+    }
+}
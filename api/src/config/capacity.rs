@@ -0,0 +1,154 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::ContainerConfig;
+use crate::models::HostCapacity;
+use serde::Deserialize;
+
+/// Thresholds for the resources of the host that must be left after a deployment so that one more
+/// review app does not take down the host for everyone.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityConfig {
+    max_containers: Option<usize>,
+    #[serde(
+        default,
+        deserialize_with = "ContainerConfig::parse_from_memory_string"
+    )]
+    min_free_memory: Option<u64>,
+    #[serde(
+        default,
+        deserialize_with = "ContainerConfig::parse_from_memory_string"
+    )]
+    min_free_disk: Option<u64>,
+}
+
+impl CapacityConfig {
+    /// Returns `true` if at least one threshold is configured.
+    pub fn is_limited(&self) -> bool {
+        self.max_containers.is_some()
+            || self.min_free_memory.is_some()
+            || self.min_free_disk.is_some()
+    }
+
+    /// Describes the thresholds that the host violates if the given number of containers will be
+    /// added. Resources that the infrastructure cannot measure are not checked.
+    pub fn violations(&self, capacity: &HostCapacity, additional_containers: usize) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if let Some(max_containers) = self.max_containers {
+            let containers = capacity.containers() + additional_containers;
+            if containers > max_containers {
+                violations.push(format!(
+                    "{} containers exceed the maximum of {} containers",
+                    containers, max_containers
+                ));
+            }
+        }
+
+        if let (Some(min_free_memory), Some(free_memory)) =
+            (self.min_free_memory, capacity.free_memory())
+        {
+            if free_memory < min_free_memory {
+                violations.push(format!(
+                    "{} bytes of free memory are less than the minimum of {} bytes",
+                    free_memory, min_free_memory
+                ));
+            }
+        }
+
+        if let (Some(min_free_disk), Some(free_disk)) = (self.min_free_disk, capacity.free_disk()) {
+            if free_disk < min_free_disk {
+                violations.push(format!(
+                    "{} bytes of free disk space are less than the minimum of {} bytes",
+                    free_disk, min_free_disk
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(config: &str) -> CapacityConfig {
+        toml::de::from_str::<CapacityConfig>(config).unwrap()
+    }
+
+    #[test]
+    fn should_not_be_limited_by_default() {
+        let config = parse("");
+
+        assert!(!config.is_limited());
+        assert!(config
+            .violations(&HostCapacity::new(1000, Some(0), Some(0)), 10)
+            .is_empty());
+    }
+
+    #[test]
+    fn should_refuse_too_many_containers() {
+        let config = parse("maxContainers = 10");
+
+        assert!(config
+            .violations(&HostCapacity::new(8, None, None), 2)
+            .is_empty());
+        assert_eq!(
+            config.violations(&HostCapacity::new(8, None, None), 3),
+            vec![String::from(
+                "11 containers exceed the maximum of 10 containers"
+            )]
+        );
+    }
+
+    #[test]
+    fn should_refuse_low_free_memory_and_disk() {
+        let config = parse(
+            r#"
+            minFreeMemory = '1g'
+            minFreeDisk = '10g'
+            "#,
+        );
+
+        let violations = config.violations(
+            &HostCapacity::new(0, Some(512 * 1024 * 1024), Some(5 * 1024 * 1024 * 1024)),
+            1,
+        );
+
+        assert_eq!(violations.len(), 2);
+    }
+
+    #[test]
+    fn should_skip_unmeasured_resources() {
+        let config = parse("minFreeMemory = '1g'");
+
+        assert!(config
+            .violations(&HostCapacity::new(0, None, None), 1)
+            .is_empty());
+    }
+}
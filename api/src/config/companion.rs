@@ -25,14 +25,14 @@
  */
 use crate::config::AppSelector;
 use crate::models::service::ContainerType;
-use crate::models::{Environment, Image, Router, ServiceConfig};
+use crate::models::{DeploymentStrategy, Environment, Image, Port, Router, ServiceConfig};
 use serde_value::Value;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub(super) struct Companion {
+pub struct Companion {
     service_name: String,
     #[serde(rename = "type")]
     companion_type: CompanionType,
@@ -44,10 +44,16 @@ pub(super) struct Companion {
     app_selector: AppSelector,
     router: Option<Router>,
     middlewares: Option<BTreeMap<String, Value>>,
+    depends_on: Option<Vec<String>>,
+    ports: Option<Vec<Port>>,
+    command: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    #[serde(default)]
+    deployment_strategy: DeploymentStrategy,
 }
 
-#[derive(Clone, Deserialize, Debug, PartialEq)]
-pub(super) enum CompanionType {
+#[derive(Clone, Deserialize, Debug, PartialEq, Serialize)]
+pub enum CompanionType {
     #[serde(rename = "application")]
     Application,
     #[serde(rename = "service")]
@@ -90,6 +96,18 @@ impl From<Companion> for ServiceConfig {
             config.set_middlewares(middlewares.clone());
         }
 
+        if let Some(depends_on) = &companion.depends_on {
+            config.set_depends_on(depends_on.clone());
+        }
+
+        if let Some(ports) = &companion.ports {
+            config.set_ports(ports.clone());
+        }
+
+        config.set_command(companion.command.clone());
+        config.set_entrypoint(companion.entrypoint.clone());
+
+        config.set_deployment_strategy(companion.deployment_strategy.clone());
         config.set_container_type(companion.companion_type.into());
 
         config
@@ -133,4 +151,63 @@ mod tests {
             Image::from_str("private.example.com/library/openid:latest").unwrap()
         );
     }
+
+    #[test]
+    fn should_parse_companion_with_dependencies() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            dependsOn = [ 'db' ]
+        "#
+        );
+
+        let config = ServiceConfig::from(companion);
+
+        assert_eq!(config.depends_on(), &[String::from("db")]);
+    }
+
+    #[test]
+    fn should_parse_companion_with_deployment_strategy() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'db'
+            type = 'application'
+            image = 'private.example.com/library/postgres:latest'
+            deploymentStrategy = 'redeploy-never'
+        "#
+        );
+
+        let config = ServiceConfig::from(companion);
+
+        assert_eq!(
+            config.deployment_strategy(),
+            &DeploymentStrategy::RedeployNever
+        );
+    }
+
+    #[test]
+    fn should_parse_companion_with_command_and_entrypoint() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'migrations'
+            type = 'application'
+            image = 'private.example.com/library/flyway:latest'
+            entrypoint = [ 'flyway' ]
+            command = [ '-url=jdbc:postgresql://db/{{application.name}}', 'migrate' ]
+        "#
+        );
+
+        let config = ServiceConfig::from(companion);
+
+        assert_eq!(config.entrypoint(), Some(&vec![String::from("flyway")]));
+        assert_eq!(
+            config.command(),
+            Some(&vec![
+                String::from("-url=jdbc:postgresql://db/{{application.name}}"),
+                String::from("migrate")
+            ])
+        );
+    }
 }
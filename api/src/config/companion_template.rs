@@ -0,0 +1,301 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{Companion, ConfigError};
+use serde::de::Error as SerdeError;
+use serde::{Deserialize, Deserializer};
+use serde_value::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+lazy_static! {
+    /// The remote templates by their checksum. Reloading the configuration does not download the
+    /// templates again unless their checksum has been changed.
+    static ref REMOTE_TEMPLATES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// A companion of the configuration is either defined inline or loaded from a template, e.g.
+/// because the definition is too long to be maintained in the configuration file.
+#[derive(Clone)]
+pub(super) enum CompanionSource {
+    Inline(Companion),
+    Template(CompanionTemplate),
+}
+
+/// References a TOML file that contains the companion definition, either a file path, which is
+/// relative to the configuration file, or an HTTPS URL whose content must match the SHA-256
+/// checksum.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct CompanionTemplate {
+    file: Option<PathBuf>,
+    url: Option<Url>,
+    sha256: Option<String>,
+}
+
+impl CompanionSource {
+    /// The companion or `None` if the template has not been loaded yet.
+    pub(super) fn companion(&self) -> Option<&Companion> {
+        match self {
+            CompanionSource::Inline(companion) => Some(companion),
+            CompanionSource::Template(_) => None,
+        }
+    }
+
+    /// Replaces the template with the companion it defines.
+    pub(super) async fn load(
+        self,
+        name: &str,
+        config_dir: &Path,
+    ) -> Result<CompanionSource, ConfigError> {
+        match self {
+            CompanionSource::Inline(companion) => Ok(CompanionSource::Inline(companion)),
+            CompanionSource::Template(template) => template
+                .load(config_dir)
+                .await
+                .map(CompanionSource::Inline)
+                .map_err(|reason| ConfigError::InvalidCompanionTemplate {
+                    name: name.to_string(),
+                    reason,
+                }),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CompanionSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let is_template = match &value {
+            Value::Map(map) => ["file", "url"]
+                .iter()
+                .any(|key| map.contains_key(&Value::String(key.to_string()))),
+            _ => false,
+        };
+
+        if is_template {
+            value
+                .deserialize_into::<CompanionTemplate>()
+                .map(CompanionSource::Template)
+                .map_err(SerdeError::custom)
+        } else {
+            value
+                .deserialize_into::<Companion>()
+                .map(CompanionSource::Inline)
+                .map_err(SerdeError::custom)
+        }
+    }
+}
+
+impl CompanionTemplate {
+    async fn load(&self, config_dir: &Path) -> Result<Companion, String> {
+        let content = match (&self.file, &self.url) {
+            (Some(file), None) => {
+                let path = config_dir.join(file);
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+                if let Some(sha256) = &self.sha256 {
+                    verify_checksum(&content, sha256)?;
+                }
+                content
+            }
+            (None, Some(url)) => {
+                let sha256 = self
+                    .sha256
+                    .as_ref()
+                    .ok_or_else(|| String::from("Remote templates require a sha256 checksum."))?;
+                fetch(url, sha256).await?
+            }
+            _ => return Err(String::from("Either file or url must be given.")),
+        };
+
+        toml::from_str::<Companion>(&content).map_err(|err| err.to_string())
+    }
+}
+
+async fn fetch(url: &Url, sha256: &str) -> Result<String, String> {
+    if url.scheme() != "https" {
+        return Err(format!("{} is not an HTTPS URL.", url));
+    }
+
+    let cache_key = sha256.to_lowercase();
+    if let Some(content) = REMOTE_TEMPLATES.lock().unwrap().get(&cache_key) {
+        return Ok(content.clone());
+    }
+
+    debug!("Download companion template from {}", url);
+    let content = reqwest::get(url.clone())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Cannot download {}: {}", url, err))?
+        .text()
+        .await
+        .map_err(|err| format!("Cannot download {}: {}", url, err))?;
+    verify_checksum(&content, sha256)?;
+
+    REMOTE_TEMPLATES
+        .lock()
+        .unwrap()
+        .insert(cache_key, content.clone());
+    Ok(content)
+}
+
+fn verify_checksum(content: &str, sha256: &str) -> Result<(), String> {
+    let checksum = openssl::sha::sha256(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    if checksum.eq_ignore_ascii_case(sha256) {
+        Ok(())
+    } else {
+        Err(format!(
+            "The checksum {} does not match the expected checksum {}.",
+            checksum, sha256
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_from_str(source: &str) -> CompanionSource {
+        toml::de::from_str::<CompanionSource>(source).unwrap()
+    }
+
+    #[test]
+    fn should_parse_inline_companion() {
+        let source = source_from_str(
+            r#"
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            "#,
+        );
+
+        assert!(source.companion().is_some());
+    }
+
+    #[test]
+    fn should_parse_template_reference() {
+        let source = source_from_str(
+            r#"
+            url = 'https://config.example.com/companions/openid.toml'
+            sha256 = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'
+            "#,
+        );
+
+        assert!(source.companion().is_none());
+    }
+
+    #[test]
+    fn should_not_parse_invalid_inline_companion() {
+        let result = toml::de::from_str::<CompanionSource>(
+            r#"
+            serviceName = 'openid'
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_load_template_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("openid.toml"),
+            "serviceName = 'openid'\ntype = 'application'\nimage = 'private.example.com/library/openid:latest'\n",
+        )
+        .unwrap();
+
+        let source = source_from_str("file = 'openid.toml'")
+            .load("openid", dir.path())
+            .await
+            .unwrap();
+
+        assert!(source.companion().is_some());
+    }
+
+    #[tokio::test]
+    async fn should_not_load_template_with_wrong_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("openid.toml"),
+            "serviceName = 'openid'\ntype = 'application'\nimage = 'private.example.com/library/openid:latest'\n",
+        )
+        .unwrap();
+
+        let result = source_from_str(
+            r#"
+            file = 'openid.toml'
+            sha256 = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'
+            "#,
+        )
+        .load("openid", dir.path())
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidCompanionTemplate { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_not_load_template_from_plain_http() {
+        let result = source_from_str(
+            r#"
+            url = 'http://config.example.com/companions/openid.toml'
+            sha256 = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'
+            "#,
+        )
+        .load("openid", Path::new("."))
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidCompanionTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn should_verify_checksum() {
+        assert!(verify_checksum(
+            "",
+            "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"
+        )
+        .is_ok());
+        assert!(verify_checksum(
+            "x",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        )
+        .is_err());
+    }
+}
@@ -23,8 +23,13 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
-use crate::config::{Companion, CompanionType, ContainerConfig, Runtime, Secret};
-use crate::models::ServiceConfig;
+use crate::config::{
+    AuthenticationConfig, BasicAuthConfig, CapacityConfig, Companion, CompanionSource,
+    CompanionType, ContainerConfig, FreezeWindow, ImagesConfig, IngressConfig, NotificationSink,
+    NotificationsConfig, RestartSchedule, Runtime, Secret, WebhookConfig,
+};
+use crate::models::{Routing, ServiceConfig};
+use regex::Regex;
 use secstr::SecUtf8;
 use serde::Deserialize;
 use std::collections::BTreeMap;
@@ -32,9 +37,11 @@ use std::convert::From;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::Error as IOError;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use toml::de::Error as TomlError;
 use toml::from_str;
+use url::Url;
 
 #[derive(Clone, Deserialize)]
 pub struct JiraConfig {
@@ -43,6 +50,36 @@ pub struct JiraConfig {
     password: SecUtf8,
 }
 
+#[derive(Clone, Default, Deserialize)]
+pub struct TicketsConfig {
+    #[serde(default, with = "serde_regex")]
+    pattern: Option<Regex>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiConfig {
+    #[serde(default)]
+    strict_payloads: bool,
+    batch_parallelism: Option<usize>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct StateConfig {
+    file: Option<PathBuf>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct AuditConfig {
+    file: Option<PathBuf>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownConfig {
+    drain_timeout_secs: Option<u64>,
+}
+
 #[derive(Clone, Deserialize)]
 struct Service {
     secrets: Option<Vec<Secret>>,
@@ -53,22 +90,59 @@ pub struct Config {
     runtime: Option<Runtime>,
     containers: Option<ContainerConfig>,
     jira: Option<JiraConfig>,
-    companions: Option<BTreeMap<String, Companion>>,
+    tickets: Option<TicketsConfig>,
+    companions: Option<BTreeMap<String, CompanionSource>>,
     services: Option<BTreeMap<String, Service>>,
     hooks: Option<BTreeMap<String, PathBuf>>,
+    authentication: Option<AuthenticationConfig>,
+    #[serde(rename = "basicAuth")]
+    basic_auth: Option<BasicAuthConfig>,
+    webhooks: Option<Vec<WebhookConfig>>,
+    restarts: Option<BTreeMap<String, RestartSchedule>>,
+    api: Option<ApiConfig>,
+    labels: Option<BTreeMap<String, String>>,
+    images: Option<ImagesConfig>,
+    capacity: Option<CapacityConfig>,
+    freezes: Option<BTreeMap<String, FreezeWindow>>,
+    state: Option<StateConfig>,
+    routing: Option<Routing>,
+    ingress: Option<IngressConfig>,
+    audit: Option<AuditConfig>,
+    notifications: Option<NotificationsConfig>,
+    shutdown: Option<ShutdownConfig>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
 }
 
 impl Config {
-    pub fn load(path: &str) -> Result<Config, ConfigError> {
+    /// Loads the configuration file and the companion templates it references.
+    pub async fn load(path: &str) -> Result<Config, ConfigError> {
         let mut f = File::open(path)?;
 
         let mut contents = String::new();
         f.read_to_string(&mut contents)?;
 
-        let config = from_str::<Config>(contents.as_str())?;
+        let mut config = from_str::<Config>(contents.as_str())?;
+        config.file = Some(PathBuf::from(path));
+
+        if let Some(sources) = config.companions.take() {
+            let config_dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+            let mut companions = BTreeMap::new();
+            for (name, source) in sources {
+                let companion = source.load(&name, config_dir).await?;
+                companions.insert(name, companion);
+            }
+            config.companions = Some(companions);
+        }
+
         Ok(config)
     }
 
+    /// The file from which the configuration has been loaded.
+    pub fn file(&self) -> Option<&PathBuf> {
+        self.file.as_ref()
+    }
+
     pub fn runtime_config(&self) -> Runtime {
         match &self.runtime {
             Some(runtime) => runtime.clone(),
@@ -83,6 +157,27 @@ impl Config {
         }
     }
 
+    pub fn images_config(&self) -> ImagesConfig {
+        match &self.images {
+            Some(images) => images.clone(),
+            None => ImagesConfig::default(),
+        }
+    }
+
+    pub fn capacity_config(&self) -> CapacityConfig {
+        match &self.capacity {
+            Some(capacity) => capacity.clone(),
+            None => CapacityConfig::default(),
+        }
+    }
+
+    pub fn ingress_config(&self) -> IngressConfig {
+        match &self.ingress {
+            Some(ingress) => ingress.clone(),
+            None => IngressConfig::default(),
+        }
+    }
+
     pub fn jira_config(&self) -> Option<JiraConfig> {
         match &self.jira {
             None => None,
@@ -90,19 +185,70 @@ impl Config {
         }
     }
 
-    pub fn service_companion_configs(&self, app_name: &str) -> Vec<ServiceConfig> {
-        self.companion_configs(app_name, |companion| {
+    /// Returns the basic auth configuration if the routes of the given app have to be protected.
+    pub fn basic_auth_for(&self, app_name: &str) -> Option<&BasicAuthConfig> {
+        self.basic_auth
+            .as_ref()
+            .filter(|basic_auth| basic_auth.applies_to(app_name))
+    }
+
+    /// Extracts the keys of the tickets that are referenced by the app name, e.g. `PROJ-123` of
+    /// `feat-PROJ-123`, with the configured pattern or with a pattern that matches Jira keys.
+    pub fn tickets_of(&self, app_name: &str) -> Vec<String> {
+        lazy_static! {
+            static ref JIRA_KEY: Regex = Regex::new("[A-Z][A-Z0-9_]+-[0-9]+").unwrap();
+        }
+
+        let pattern = self
+            .tickets
+            .as_ref()
+            .and_then(|tickets| tickets.pattern.as_ref())
+            .unwrap_or(&JIRA_KEY);
+
+        let mut tickets = Vec::new();
+        for ticket in pattern.find_iter(app_name).map(|m| m.as_str().to_string()) {
+            if !tickets.contains(&ticket) {
+                tickets.push(ticket);
+            }
+        }
+        tickets
+    }
+
+    /// Returns `true` if a companion with the given name, i.e. the key of the companion's table in
+    /// the configuration, exists.
+    pub fn has_companion(&self, name: &str) -> bool {
+        match &self.companions {
+            None => false,
+            Some(companions_map) => companions_map.contains_key(name),
+        }
+    }
+
+    pub fn service_companion_configs(
+        &self,
+        app_name: &str,
+        skipped: &[String],
+    ) -> Vec<ServiceConfig> {
+        self.companion_configs(app_name, skipped, |companion| {
             companion.companion_type() == &CompanionType::Service
         })
     }
 
-    pub fn application_companion_configs(&self, app_name: &str) -> Vec<ServiceConfig> {
-        self.companion_configs(app_name, |companion| {
+    pub fn application_companion_configs(
+        &self,
+        app_name: &str,
+        skipped: &[String],
+    ) -> Vec<ServiceConfig> {
+        self.companion_configs(app_name, skipped, |companion| {
             companion.companion_type() == &CompanionType::Application
         })
     }
 
-    fn companion_configs<P>(&self, app_name: &str, predicate: P) -> Vec<ServiceConfig>
+    fn companion_configs<P>(
+        &self,
+        app_name: &str,
+        skipped: &[String],
+        predicate: P,
+    ) -> Vec<ServiceConfig>
     where
         P: Fn(&Companion) -> bool,
     {
@@ -110,9 +256,11 @@ impl Config {
             None => vec![],
             Some(companions_map) => companions_map
                 .iter()
-                .filter(|(_, companion)| companion.matches_app_name(app_name))
-                .filter(|(_, companion)| predicate(*companion))
-                .map(|(_, companion)| companion.clone().into())
+                .filter(|(name, _)| !skipped.contains(name))
+                .filter_map(|(_, source)| source.companion())
+                .filter(|companion| companion.matches_app_name(app_name))
+                .filter(|companion| predicate(*companion))
+                .map(|companion| companion.clone().into())
                 .collect(),
         }
     }
@@ -125,12 +273,124 @@ impl Config {
         }
     }
 
+    /// Adds the globally configured labels to the service configuration. Labels of the service
+    /// configuration take precedence.
+    pub fn add_labels_to(&self, service_config: &mut ServiceConfig) {
+        if let Some(global_labels) = &self.labels {
+            let mut labels = global_labels.clone();
+            if let Some(service_labels) = service_config.labels() {
+                labels.extend(service_labels.clone());
+            }
+            service_config.set_labels(Some(labels));
+        }
+    }
+
+    /// Applies the globally configured routing to the service configuration. The routing options
+    /// of the service configuration take precedence.
+    pub fn add_routing_to(&self, service_config: &mut ServiceConfig) {
+        if let Some(global_routing) = &self.routing {
+            let routing = match service_config.routing() {
+                Some(routing) => routing.or(global_routing),
+                None => global_routing.clone(),
+            };
+            service_config.set_routing(routing);
+        }
+    }
+
     pub fn hook(&self, hook_name: &str) -> Option<&PathBuf> {
         self.hooks
             .as_ref()
             .map(|hooks| hooks.get(hook_name))
             .flatten()
     }
+
+    /// Returns the authentication configuration. If there is none, the API does not require any
+    /// authentication.
+    pub fn authentication_config(&self) -> Option<&AuthenticationConfig> {
+        self.authentication.as_ref()
+    }
+
+    /// If `true`, deployment payloads with unknown fields will be rejected instead of ignoring
+    /// these fields.
+    pub fn strict_payloads(&self) -> bool {
+        self.api.as_ref().map_or(false, |api| api.strict_payloads)
+    }
+
+    /// The maximum number of apps of a batch deployment that are deployed at the same time.
+    pub fn batch_parallelism(&self) -> usize {
+        self.api
+            .as_ref()
+            .and_then(|api| api.batch_parallelism)
+            .unwrap_or(4)
+            .max(1)
+    }
+
+    /// Returns the sinks that will be notified about deployment events, including the plain
+    /// webhooks.
+    pub fn notification_sinks(&self) -> Vec<NotificationSink> {
+        let webhooks = self
+            .webhooks
+            .iter()
+            .flatten()
+            .cloned()
+            .map(NotificationSink::Webhook);
+        let sinks = self
+            .notifications
+            .iter()
+            .flat_map(|notifications| notifications.sinks().iter().cloned());
+        webhooks.chain(sinks).collect()
+    }
+
+    pub fn notifications_base_url(&self) -> Option<&Url> {
+        self.notifications
+            .as_ref()
+            .and_then(|notifications| notifications.base_url())
+    }
+
+    /// Returns the file in which the desired state of the apps is recorded, so that it can be
+    /// restored after a restart of the host.
+    pub fn state_file(&self) -> Option<&PathBuf> {
+        self.state.as_ref().and_then(|state| state.file.as_ref())
+    }
+
+    /// Returns the file next to the state file in which the scheduled operations are recorded,
+    /// so that they survive a restart of PREvant.
+    pub fn scheduled_operations_file(&self) -> Option<PathBuf> {
+        self.state_file()
+            .map(|file| file.with_file_name("scheduled-operations.json"))
+    }
+
+    /// The JSON lines file to which the audit log is appended.
+    pub fn audit_file(&self) -> Option<&PathBuf> {
+        self.audit.as_ref().and_then(|audit| audit.file.as_ref())
+    }
+
+    /// The maximum duration to wait for running deployments and deletions when PREvant shuts down.
+    pub fn shutdown_drain_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.shutdown
+                .as_ref()
+                .and_then(|shutdown| shutdown.drain_timeout_secs)
+                .unwrap_or(120),
+        )
+    }
+
+    /// Returns the freeze windows of the configuration by their names.
+    pub fn freeze_windows(&self) -> BTreeMap<String, FreezeWindow> {
+        self.freezes.clone().unwrap_or_default()
+    }
+
+    /// Returns the restart schedules that apply to the service of the given app.
+    pub fn restart_schedules<'a>(
+        &'a self,
+        app_name: &'a str,
+        service_name: &'a str,
+    ) -> impl Iterator<Item = &'a RestartSchedule> + 'a {
+        self.restarts
+            .iter()
+            .flat_map(|restarts| restarts.values())
+            .filter(move |schedule| schedule.applies_to(app_name, service_name))
+    }
 }
 
 impl JiraConfig {
@@ -167,6 +427,8 @@ pub enum ConfigError {
     CannotOpenConfigFile { error: IOError },
     #[fail(display = "Invalid config file format. {}", error)]
     ConfigFormatError { error: TomlError },
+    #[fail(display = "Cannot load template of companion {}: {}", name, reason)]
+    InvalidCompanionTemplate { name: String, reason: String },
 }
 
 impl From<IOError> for ConfigError {
@@ -225,7 +487,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.application_companion_configs("master");
+        let companion_configs = config.application_companion_configs("master", &[]);
 
         assert_eq!(companion_configs.len(), 1);
         companion_configs.iter().for_each(|config| {
@@ -260,7 +522,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.service_companion_configs("master");
+        let companion_configs = config.service_companion_configs("master", &[]);
 
         assert_eq!(companion_configs.len(), 1);
         companion_configs.iter().for_each(|config| {
@@ -290,7 +552,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.application_companion_configs("master");
+        let companion_configs = config.application_companion_configs("master", &[]);
 
         assert_eq!(companion_configs.len(), 1);
         companion_configs.iter().for_each(|config| {
@@ -312,7 +574,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.application_companion_configs("master");
+        let companion_configs = config.application_companion_configs("master", &[]);
 
         assert_eq!(companion_configs.len(), 1);
         companion_configs.iter().for_each(|config| {
@@ -336,7 +598,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.application_companion_configs("master");
+        let companion_configs = config.application_companion_configs("master", &[]);
 
         assert_eq!(companion_configs.len(), 1);
         companion_configs.iter().for_each(|config| {
@@ -366,7 +628,7 @@ mod tests {
             "#
         );
 
-        let companion_configs = config.application_companion_configs("random-name");
+        let companion_configs = config.application_companion_configs("random-name", &[]);
 
         assert_eq!(companion_configs.len(), 0);
     }
@@ -482,6 +744,161 @@ mod tests {
         let config = from_str::<Config>(config_str).unwrap();
 
         let runtime = config.runtime_config();
-        assert_eq!(runtime, Runtime::Docker);
+        assert_eq!(runtime, Runtime::default());
+    }
+
+    #[test]
+    fn should_return_restart_schedules_of_service() {
+        let config = config_from_str!(
+            r#"
+            [restarts.legacy]
+            schedule = '0 0 3 * * *'
+            appSelector = 'legacy-.+'
+
+            [restarts.backend]
+            schedule = '0 0 4 * * *'
+            services = [ 'backend' ]
+            "#
+        );
+
+        assert_eq!(config.restart_schedules("legacy-1", "frontend").count(), 1);
+        assert_eq!(config.restart_schedules("legacy-1", "backend").count(), 2);
+        assert_eq!(config.restart_schedules("master", "frontend").count(), 0);
+    }
+
+    #[test]
+    fn should_add_global_routing() {
+        let config = config_from_str!(
+            r#"
+            [routing]
+            host = '{service}.{app}.example.com'
+            path = '/'
+            "#
+        );
+
+        let mut service_config = crate::sc!("backend", "backend:latest");
+        config.add_routing_to(&mut service_config);
+
+        assert_eq!(
+            service_config.traefik_rule(&String::from("master")),
+            "Host(`backend.master.example.com`) && PathPrefix(`/`)"
+        );
+    }
+
+    #[test]
+    fn should_parse_freeze_windows() {
+        let config = config_from_str!(
+            r#"
+            [freezes.release]
+            from = '2021-07-01T18:00:00Z'
+            until = '2021-07-02T06:00:00Z'
+            reason = 'Release 2.0'
+            "#
+        );
+
+        let windows = config.freeze_windows();
+        assert_eq!(windows.len(), 1);
+        assert_eq!(
+            windows["release"].reason(),
+            Some(&String::from("Release 2.0"))
+        );
+    }
+
+    #[test]
+    fn should_parse_strict_payloads() {
+        let config = config_from_str!(
+            r#"
+            [api]
+            strictPayloads = true
+            "#
+        );
+
+        assert!(config.strict_payloads());
+        assert!(!Config::default().strict_payloads());
+    }
+
+    #[test]
+    fn should_parse_batch_parallelism() {
+        let config = config_from_str!(
+            r#"
+            [api]
+            batchParallelism = 2
+            "#
+        );
+
+        assert_eq!(config.batch_parallelism(), 2);
+        assert_eq!(Config::default().batch_parallelism(), 4);
+    }
+
+    #[test]
+    fn should_add_global_labels() {
+        let config = config_from_str!(
+            r#"
+            [labels]
+            'com.example.cost-center' = '4711'
+            'com.example.team' = 'platform'
+            "#
+        );
+
+        let mut labels = BTreeMap::new();
+        labels.insert(String::from("com.example.team"), String::from("frontend"));
+        let mut service_config = crate::sc!("frontend", "nginx:latest");
+        service_config.set_labels(Some(labels));
+
+        config.add_labels_to(&mut service_config);
+
+        let labels = service_config.labels().unwrap();
+        assert_eq!(
+            labels.get("com.example.cost-center"),
+            Some(&String::from("4711"))
+        );
+        assert_eq!(
+            labels.get("com.example.team"),
+            Some(&String::from("frontend"))
+        );
+    }
+
+    #[test]
+    fn should_protect_selected_apps_with_basic_auth() {
+        let config = config_from_str!(
+            r#"
+            [basicAuth]
+            appSelector = 'customer-.*'
+            "#
+        );
+
+        assert!(config.basic_auth_for("customer-acme").is_some());
+        assert!(config.basic_auth_for("master").is_none());
+    }
+
+    #[test]
+    fn should_extract_tickets_from_app_name() {
+        let config = Config::default();
+
+        assert_eq!(
+            config.tickets_of("feat-PROJ-123"),
+            vec![String::from("PROJ-123")]
+        );
+        assert_eq!(
+            config.tickets_of("PROJ-1-and-OPS-22"),
+            vec![String::from("PROJ-1"), String::from("OPS-22")]
+        );
+        assert!(config.tickets_of("master").is_empty());
+    }
+
+    #[test]
+    fn should_extract_tickets_with_configured_pattern() {
+        let config = config_from_str!(
+            r#"
+            [tickets]
+            pattern = '#[0-9]+'
+            "#
+        );
+
+        assert_eq!(
+            config.tickets_of("fix-#42-login"),
+            vec![String::from("#42")]
+        );
+        assert!(config.tickets_of("feat-PROJ-123").is_empty());
     }
 }
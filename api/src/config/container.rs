@@ -32,7 +32,7 @@ pub struct ContainerConfig {
 }
 
 impl ContainerConfig {
-    fn parse_from_memory_string<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+    pub(super) fn parse_from_memory_string<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -0,0 +1,310 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::AppSelector;
+use chrono::{DateTime, Duration, Utc};
+use cron::Schedule;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Defines a period, e.g. during a release, in which apps must not be deployed, deleted, or
+/// changed otherwise. With a recurrence, the window defines recurring periods instead, e.g. the
+/// business hours in which demo environments are shown to customers.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", try_from = "FreezeWindowDefinition")]
+pub struct FreezeWindow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recurrence: Option<Recurrence>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    app_selector: AppSelector,
+    new_apps_allowed: bool,
+}
+
+/// The definition of a freeze window as it is written by the users, which is validated before it
+/// becomes a `FreezeWindow`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FreezeWindowDefinition {
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    recurrence: Option<Recurrence>,
+    reason: Option<String>,
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(default)]
+    new_apps_allowed: bool,
+}
+
+impl TryFrom<FreezeWindowDefinition> for FreezeWindow {
+    type Error = FreezeWindowError;
+
+    fn try_from(definition: FreezeWindowDefinition) -> Result<Self, Self::Error> {
+        if definition.until.is_none() && definition.recurrence.is_none() {
+            return Err(FreezeWindowError::MissingEnd);
+        }
+
+        Ok(FreezeWindow {
+            from: definition.from,
+            until: definition.until,
+            recurrence: definition.recurrence,
+            reason: definition.reason,
+            app_selector: definition.app_selector,
+            new_apps_allowed: definition.new_apps_allowed,
+        })
+    }
+}
+
+impl FreezeWindow {
+    /// Returns the end of the period in which the given app is frozen at the given point in time
+    /// or `None` if the window is not in effect for the app. A window without start is in effect
+    /// until its end and a recurring window is only in effect during its periods.
+    pub fn active_until(&self, app_name: &str, now: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.app_selector.matches(app_name) {
+            return None;
+        }
+
+        let has_started = match &self.from {
+            Some(from) => from <= now,
+            None => true,
+        };
+        let has_ended = match &self.until {
+            Some(until) => until <= now,
+            None => false,
+        };
+        if !has_started || has_ended {
+            return None;
+        }
+
+        match &self.recurrence {
+            None => self.until,
+            Some(recurrence) => recurrence.active_until(now).map(|end| match self.until {
+                Some(until) => end.min(until),
+                None => end,
+            }),
+        }
+    }
+
+    pub fn reason(&self) -> Option<&String> {
+        self.reason.as_ref()
+    }
+
+    /// Returns `true` if apps that do not run yet can be deployed while the window is in effect,
+    /// i.e. the window only protects running apps from being replaced or deleted.
+    pub fn allows_new_apps(&self) -> bool {
+        self.new_apps_allowed
+    }
+}
+
+/// Recurring periods that start according to a cron expression (including seconds, in UTC) and
+/// last for the given number of minutes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Recurrence {
+    schedule: CronSchedule,
+    duration_minutes: u32,
+}
+
+impl Recurrence {
+    fn active_until(&self, now: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let duration = Duration::minutes(i64::from(self.duration_minutes));
+        self.schedule
+            .schedule
+            .after(&(*now - duration))
+            .next()
+            .filter(|start| start <= now)
+            .map(|start| start + duration)
+    }
+}
+
+/// A parsed cron schedule that keeps its expression, so that the freeze windows can be listed as
+/// they have been declared.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+struct CronSchedule {
+    schedule: Schedule,
+    expression: String,
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        match Schedule::from_str(&expression) {
+            Ok(schedule) => Ok(CronSchedule {
+                schedule,
+                expression,
+            }),
+            Err(err) => Err(format!("Invalid schedule {}: {}", expression, err)),
+        }
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+impl fmt::Debug for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.expression)
+    }
+}
+
+#[derive(Debug, Fail)]
+enum FreezeWindowError {
+    #[fail(display = "A freeze window requires an end (until) or a recurrence.")]
+    MissingEnd,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    macro_rules! freeze_window_from_str {
+        ( $config_str:expr ) => {
+            toml::de::from_str::<FreezeWindow>($config_str).unwrap()
+        };
+    }
+
+    #[test]
+    fn should_be_active_within_window() {
+        let window = freeze_window_from_str!(
+            r#"
+            from = '2021-07-01T18:00:00Z'
+            until = '2021-07-02T06:00:00Z'
+            reason = 'Release 2.0'
+        "#
+        );
+
+        assert_eq!(
+            window.active_until("master", &Utc.ymd(2021, 7, 1).and_hms(17, 59, 59)),
+            None
+        );
+        assert_eq!(
+            window.active_until("master", &Utc.ymd(2021, 7, 1).and_hms(18, 0, 0)),
+            Some(Utc.ymd(2021, 7, 2).and_hms(6, 0, 0))
+        );
+        assert_eq!(
+            window.active_until("master", &Utc.ymd(2021, 7, 2).and_hms(6, 0, 0)),
+            None
+        );
+        assert_eq!(window.reason(), Some(&String::from("Release 2.0")));
+        assert!(!window.allows_new_apps());
+    }
+
+    #[test]
+    fn should_be_active_for_matching_apps_only() {
+        let window = freeze_window_from_str!(
+            r#"
+            until = '2021-07-02T06:00:00Z'
+            appSelector = 'release-.+'
+        "#
+        );
+
+        let now = Utc.ymd(2021, 7, 1).and_hms(12, 0, 0);
+        assert!(window.active_until("release-2.0", &now).is_some());
+        assert_eq!(window.active_until("master", &now), None);
+    }
+
+    #[test]
+    fn should_be_active_during_recurring_periods() {
+        let window = freeze_window_from_str!(
+            r#"
+            until = '2021-07-02T12:00:00Z'
+            reason = 'Customer demos'
+            newAppsAllowed = true
+            recurrence = { schedule = '0 0 9 * * Mon-Fri', durationMinutes = 480 }
+        "#
+        );
+
+        // 2021-07-01 is a Thursday
+        assert_eq!(
+            window.active_until("demo", &Utc.ymd(2021, 7, 1).and_hms(8, 59, 59)),
+            None
+        );
+        assert_eq!(
+            window.active_until("demo", &Utc.ymd(2021, 7, 1).and_hms(9, 0, 0)),
+            Some(Utc.ymd(2021, 7, 1).and_hms(17, 0, 0))
+        );
+        assert_eq!(
+            window.active_until("demo", &Utc.ymd(2021, 7, 1).and_hms(17, 0, 0)),
+            None
+        );
+        assert_eq!(
+            window.active_until("demo", &Utc.ymd(2021, 7, 2).and_hms(10, 0, 0)),
+            Some(Utc.ymd(2021, 7, 2).and_hms(12, 0, 0))
+        );
+        assert!(window.allows_new_apps());
+    }
+
+    #[test]
+    fn should_not_parse_window_without_end() {
+        let window = toml::de::from_str::<FreezeWindow>("reason = 'Release 2.0'");
+
+        assert!(window.is_err());
+    }
+
+    #[test]
+    fn should_not_parse_invalid_recurrence() {
+        let window = toml::de::from_str::<FreezeWindow>(
+            r#"
+            recurrence = { schedule = 'business hours', durationMinutes = 480 }
+        "#,
+        );
+
+        assert!(window.is_err());
+    }
+
+    #[test]
+    fn should_serialize_recurring_window() {
+        let window = freeze_window_from_str!(
+            r#"
+            newAppsAllowed = true
+            recurrence = { schedule = '0 0 9 * * Mon-Fri', durationMinutes = 480 }
+        "#
+        );
+
+        assert_eq!(
+            serde_json::to_value(&window).unwrap(),
+            serde_json::json!({
+                "recurrence": {
+                    "schedule": "0 0 9 * * Mon-Fri",
+                    "durationMinutes": 480
+                },
+                "appSelector": ".+",
+                "newAppsAllowed": true
+            })
+        );
+    }
+}
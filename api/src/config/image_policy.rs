@@ -0,0 +1,176 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::Image;
+use regex::Regex;
+use serde::de::Error as SerdeError;
+use serde::{Deserialize, Deserializer};
+
+/// Restricts the images that users may deploy, e.g. to prevent arbitrary public images from being
+/// launched on a shared host. Images are checked as requested, i.e. before they are rewritten.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagePolicy {
+    #[serde(default)]
+    allowed: Vec<ImagePattern>,
+    #[serde(default)]
+    denied: Vec<ImagePattern>,
+    #[serde(default)]
+    forbidden_tags: Vec<String>,
+}
+
+/// A regular expression that has to match the whole name of an image including its registry,
+/// e.g. `docker.io/library/.*` for the official images of Docker Hub.
+#[derive(Clone, Debug)]
+struct ImagePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl ImagePattern {
+    fn matches(&self, image_name: &str) -> bool {
+        self.regex.is_match(image_name)
+    }
+}
+
+impl PartialEq for ImagePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl<'de> Deserialize<'de> for ImagePattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pattern = String::deserialize(deserializer)?;
+        let regex = Regex::new(&format!("^(?:{})$", pattern)).map_err(SerdeError::custom)?;
+        Ok(ImagePattern { pattern, regex })
+    }
+}
+
+impl ImagePolicy {
+    /// Returns the reasons why the image violates the policy. An empty list means that the image
+    /// may be deployed.
+    pub fn violations(&self, image: &Image) -> Vec<String> {
+        let image_name = match (image.registry(), image.name()) {
+            (Some(registry), Some(name)) => format!("{}/{}", registry, name),
+            _ if self.allowed.is_empty() => return Vec::new(),
+            _ => {
+                return vec![format!(
+                    "{} is referenced by its id and cannot be checked against the allowed images",
+                    image
+                )]
+            }
+        };
+
+        let mut violations = Vec::new();
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|p| p.matches(&image_name)) {
+            violations.push(format!("{} is not an allowed image", image_name));
+        }
+        if let Some(pattern) = self.denied.iter().find(|p| p.matches(&image_name)) {
+            violations.push(format!("{} is denied by {:?}", image_name, pattern.pattern));
+        }
+        if let Some(tag) = image.tag() {
+            if self.forbidden_tags.contains(&tag) {
+                violations.push(format!("the tag {} of {} is forbidden", tag, image_name));
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn policy() -> ImagePolicy {
+        toml::de::from_str::<ImagePolicy>(
+            r#"
+            allowed = [ 'registry.example.com/.*', 'docker.io/library/.*' ]
+            denied = [ 'docker.io/library/ubuntu' ]
+            forbiddenTags = [ 'latest' ]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_allow_everything_by_default() {
+        let policy = ImagePolicy::default();
+
+        assert!(policy
+            .violations(&Image::from_str("nginx").unwrap())
+            .is_empty());
+        assert!(policy
+            .violations(&Image::from_str("sha256:9895c9b90b58").unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn should_allow_matching_images() {
+        assert!(policy()
+            .violations(&Image::from_str("registry.example.com/team/backend:1.0").unwrap())
+            .is_empty());
+        assert!(policy()
+            .violations(&Image::from_str("mariadb:10.3").unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn should_report_violations() {
+        assert_eq!(
+            policy().violations(&Image::from_str("ghcr.io/someone/miner:1.0").unwrap()),
+            vec![String::from(
+                "ghcr.io/someone/miner is not an allowed image"
+            )]
+        );
+        assert_eq!(
+            policy().violations(&Image::from_str("ubuntu").unwrap()),
+            vec![
+                String::from("docker.io/library/ubuntu is denied by \"docker.io/library/ubuntu\""),
+                String::from("the tag latest of docker.io/library/ubuntu is forbidden")
+            ]
+        );
+    }
+
+    #[test]
+    fn should_not_allow_images_referenced_by_id() {
+        assert_eq!(
+            policy()
+                .violations(&Image::from_str("sha256:9895c9b90b58").unwrap())
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn should_not_parse_invalid_pattern() {
+        assert!(toml::de::from_str::<ImagePolicy>("allowed = [ '(' ]").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{ContainerConfig, ImagePolicy};
+use crate::models::Image;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Limits the total size of the images of an app, protecting small review hosts from huge images,
+/// rewrites image references, e.g. to pull them from a registry mirror, and restricts the images
+/// that may be deployed.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagesConfig {
+    #[serde(
+        default,
+        deserialize_with = "ContainerConfig::parse_from_memory_string"
+    )]
+    max_app_size: Option<u64>,
+    #[serde(default)]
+    size_limit_action: SizeLimitAction,
+    #[serde(default)]
+    rewrites: Vec<ImageRewrite>,
+    #[serde(default)]
+    policy: ImagePolicy,
+}
+
+/// Replaces the prefix `from` of fully qualified image references, such as
+/// `docker.io/library/nginx:latest`, with `to`. A trailing `*` of `from` matches the remainder of
+/// the reference which will be inserted at the `*` of `to`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ImageRewrite {
+    from: String,
+    to: String,
+}
+
+impl ImageRewrite {
+    fn rewrite(&self, image: &str) -> Option<String> {
+        match self.from.strip_suffix('*') {
+            Some(prefix) => image
+                .strip_prefix(prefix)
+                .map(|remainder| self.to.replacen('*', remainder, 1)),
+            None if self.from == image => Some(self.to.clone()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SizeLimitAction {
+    Warn,
+    Refuse,
+}
+
+impl Default for SizeLimitAction {
+    fn default() -> Self {
+        SizeLimitAction::Warn
+    }
+}
+
+impl ImagesConfig {
+    /// The maximum total size (in bytes) of the images of an app. If there is none, PREvant does
+    /// not resolve the image sizes at all.
+    pub fn max_app_size(&self) -> Option<u64> {
+        self.max_app_size
+    }
+
+    pub fn size_limit_action(&self) -> &SizeLimitAction {
+        &self.size_limit_action
+    }
+
+    pub fn policy(&self) -> &ImagePolicy {
+        &self.policy
+    }
+
+    /// Applies the first matching rewrite rule to the image. Returns `None` if no rule matches the
+    /// image or if the image is referenced by its id.
+    pub fn rewrite(&self, image: &Image) -> Option<Image> {
+        if let Image::Digest { .. } = image {
+            return None;
+        }
+
+        let image_string = image.to_string();
+        let rewritten = self
+            .rewrites
+            .iter()
+            .find_map(|rule| rule.rewrite(&image_string))?;
+        match Image::from_str(&rewritten) {
+            Ok(rewritten) => Some(rewritten),
+            Err(err) => {
+                warn!("Cannot rewrite image {}: {}", image_string, err);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_images_config_with_defaults() {
+        let config = toml::de::from_str::<ImagesConfig>(
+            r#"
+            maxAppSize = '10g'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.max_app_size(), Some(10 * 1024 * 1024 * 1024));
+        assert_eq!(config.size_limit_action(), &SizeLimitAction::Warn);
+    }
+
+    #[test]
+    fn should_parse_images_config_refusing_oversized_apps() {
+        let config = toml::de::from_str::<ImagesConfig>(
+            r#"
+            maxAppSize = '512m'
+            sizeLimitAction = 'refuse'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.max_app_size(), Some(512 * 1024 * 1024));
+        assert_eq!(config.size_limit_action(), &SizeLimitAction::Refuse);
+    }
+
+    #[test]
+    fn should_rewrite_images() {
+        let config = toml::de::from_str::<ImagesConfig>(
+            r#"
+            [[rewrites]]
+            from = 'docker.io/acme/*'
+            to = 'proxy.example.com/acme/*'
+
+            [[rewrites]]
+            from = 'docker.io/*'
+            to = 'mirror.example.com/*'
+            "#,
+        )
+        .unwrap();
+
+        let rewrite = |image: &str| {
+            config
+                .rewrite(&Image::from_str(image).unwrap())
+                .map(|image| image.to_string())
+        };
+        assert_eq!(
+            rewrite("nginx:1.21"),
+            Some(String::from("mirror.example.com/library/nginx:1.21"))
+        );
+        assert_eq!(
+            rewrite("acme/shop"),
+            Some(String::from("proxy.example.com/acme/shop:latest"))
+        );
+        assert_eq!(rewrite("ghcr.io/acme/shop:1.0"), None);
+    }
+}
@@ -0,0 +1,78 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use serde::Deserialize;
+
+/// Selects the reverse proxy that routes the requests to the services.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressConfig {
+    #[serde(default)]
+    provider: IngressProviderKind,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IngressProviderKind {
+    Traefik,
+    Nginx,
+}
+
+impl Default for IngressProviderKind {
+    fn default() -> Self {
+        IngressProviderKind::Traefik
+    }
+}
+
+impl IngressConfig {
+    pub fn provider(&self) -> IngressProviderKind {
+        self.provider
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_ingress_config() {
+        let config = toml::de::from_str::<IngressConfig>(
+            r#"
+            provider = 'nginx'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.provider(), IngressProviderKind::Nginx);
+    }
+
+    #[test]
+    fn should_default_to_traefik() {
+        let config = toml::de::from_str::<IngressConfig>("").unwrap();
+
+        assert_eq!(config.provider(), IngressProviderKind::Traefik);
+    }
+}
@@ -24,15 +24,37 @@
  * =========================LICENSE_END==================================
  */
 pub(self) use app_selector::AppSelector;
-pub(self) use companion::{Companion, CompanionType};
+pub use authentication::AuthenticationConfig;
+pub use basic_auth::BasicAuthConfig;
+pub use capacity::CapacityConfig;
+pub use companion::{Companion, CompanionType};
+pub(self) use companion_template::CompanionSource;
 pub use config::{Config, ConfigError};
 pub use container::ContainerConfig;
-pub use runtime::Runtime;
+pub use freeze::FreezeWindow;
+pub use image_policy::ImagePolicy;
+pub use images::{ImagesConfig, SizeLimitAction};
+pub use ingress::{IngressConfig, IngressProviderKind};
+pub use notification::{EmailConfig, NotificationSink, NotificationsConfig};
+pub use restart::RestartSchedule;
+pub use runtime::{DockerHost, DockerRetryConfig, DockerRuntimeConfig, Runtime};
 pub(self) use secret::Secret;
+pub use webhook::WebhookConfig;
 
 mod app_selector;
+mod authentication;
+mod basic_auth;
+mod capacity;
 mod companion;
+mod companion_template;
 mod config;
 mod container;
+mod freeze;
+mod image_policy;
+mod images;
+mod ingress;
+mod notification;
+mod restart;
 mod runtime;
 mod secret;
+mod webhook;
@@ -0,0 +1,177 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::WebhookConfig;
+use secstr::SecUtf8;
+use url::Url;
+
+/// Configures the sinks that will be notified about deployment events.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationsConfig {
+    base_url: Option<Url>,
+    #[serde(default)]
+    sinks: Vec<NotificationSink>,
+}
+
+impl NotificationsConfig {
+    /// The URL under which PREvant is reachable, which is required to include the URLs of the
+    /// services in the notifications.
+    pub fn base_url(&self) -> Option<&Url> {
+        self.base_url.as_ref()
+    }
+
+    pub fn sinks(&self) -> &[NotificationSink] {
+        &self.sinks
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NotificationSink {
+    /// Posts the deployment event as JSON payload.
+    Webhook(WebhookConfig),
+    /// Posts a message to a [Slack incoming webhook](https://api.slack.com/messaging/webhooks).
+    Slack(WebhookConfig),
+    /// Sends a message through an SMTP server.
+    Email(EmailConfig),
+}
+
+impl NotificationSink {
+    /// The URL that identifies the sink. For emails, this is a `mailto` URL of the recipients.
+    pub fn url(&self) -> Url {
+        match self {
+            NotificationSink::Webhook(webhook) | NotificationSink::Slack(webhook) => {
+                webhook.url().clone()
+            }
+            NotificationSink::Email(email) => Url::parse(&format!("mailto:{}", email.to.join(",")))
+                .expect("mailto URL should be valid"),
+        }
+    }
+
+    /// The URL that identifies the sink without its path and credentials, e.g. in logs or dead
+    /// letters, because the path of Slack webhooks contains their token.
+    pub fn redacted_url(&self) -> String {
+        match self {
+            NotificationSink::Webhook(webhook) | NotificationSink::Slack(webhook) => {
+                webhook.url().origin().ascii_serialization()
+            }
+            NotificationSink::Email(_) => self.url().to_string(),
+        }
+    }
+
+    /// The number of delivery attempts until a delivery is moved into the dead letters.
+    pub fn max_attempts(&self) -> u32 {
+        match self {
+            NotificationSink::Webhook(webhook) | NotificationSink::Slack(webhook) => {
+                webhook.max_attempts()
+            }
+            NotificationSink::Email(email) => email.max_attempts.unwrap_or(5).max(1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailConfig {
+    smtp_host: String,
+    smtp_port: Option<u16>,
+    username: Option<String>,
+    password: Option<SecUtf8>,
+    from: String,
+    to: Vec<String>,
+    max_attempts: Option<u32>,
+}
+
+impl EmailConfig {
+    pub fn smtp_host(&self) -> &String {
+        &self.smtp_host
+    }
+
+    /// The port of the SMTP server, which has to support STARTTLS. Default is 587.
+    pub fn smtp_port(&self) -> u16 {
+        self.smtp_port.unwrap_or(587)
+    }
+
+    /// The credentials for authenticating at the SMTP server, if any.
+    pub fn credentials(&self) -> Option<(&String, &SecUtf8)> {
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Some((username, password)),
+            _ => None,
+        }
+    }
+
+    pub fn from(&self) -> &String {
+        &self.from
+    }
+
+    pub fn to(&self) -> &[String] {
+        &self.to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_notification_sinks() {
+        let config = toml::de::from_str::<NotificationsConfig>(
+            r#"
+            baseUrl = 'https://prevant.example.com'
+
+            [[sinks]]
+            type = 'slack'
+            url = 'https://hooks.slack.com/services/T000/B000/XXXX'
+
+            [[sinks]]
+            type = 'email'
+            smtpHost = 'smtp.example.com'
+            from = 'prevant@example.com'
+            to = [ 'team-a@example.com', 'team-b@example.com' ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.base_url(),
+            Some(&Url::parse("https://prevant.example.com").unwrap())
+        );
+        assert!(matches!(config.sinks()[0], NotificationSink::Slack(_)));
+        assert_eq!(config.sinks()[0].redacted_url(), "https://hooks.slack.com");
+        assert_eq!(
+            config.sinks()[1].url(),
+            Url::parse("mailto:team-a@example.com,team-b@example.com").unwrap()
+        );
+        match &config.sinks()[1] {
+            NotificationSink::Email(email) => {
+                assert_eq!(email.smtp_port(), 587);
+                assert_eq!(email.credentials(), None);
+            }
+            _ => panic!("Should be an email sink"),
+        }
+    }
+}
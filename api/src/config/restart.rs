@@ -0,0 +1,145 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::AppSelector;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Defines a cron schedule for restarting services automatically, e.g. for legacy applications
+/// that leak memory and must be restarted every night.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartSchedule {
+    #[serde(deserialize_with = "RestartSchedule::parse_schedule")]
+    schedule: Schedule,
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    services: Option<Vec<String>>,
+}
+
+impl RestartSchedule {
+    fn parse_schedule<'de, D>(deserializer: D) -> Result<Schedule, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let expression = String::deserialize(deserializer)?;
+        Schedule::from_str(&expression).map_err(serde::de::Error::custom)
+    }
+
+    /// Returns `true` if the schedule applies to the service of the given app. If the schedule
+    /// does not restrict the services, all services of matching apps will be restarted.
+    pub fn applies_to(&self, app_name: &str, service_name: &str) -> bool {
+        if !self.app_selector.matches(app_name) {
+            return false;
+        }
+
+        match &self.services {
+            None => true,
+            Some(services) => services.iter().any(|s| s == service_name),
+        }
+    }
+
+    /// Returns the first restart time after the given timestamp.
+    pub fn next_restart_after(&self, timestamp: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.after(timestamp).next()
+    }
+
+    /// Returns `true` if a restart was scheduled within the interval `(since, until]`.
+    pub fn is_due(&self, since: &DateTime<Utc>, until: &DateTime<Utc>) -> bool {
+        match self.next_restart_after(since) {
+            Some(next_restart) => &next_restart <= until,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    macro_rules! restart_schedule_from_str {
+        ( $config_str:expr ) => {
+            toml::de::from_str::<RestartSchedule>($config_str).unwrap()
+        };
+    }
+
+    #[test]
+    fn should_apply_to_all_services_of_matching_apps() {
+        let schedule = restart_schedule_from_str!(
+            r#"
+            schedule = '0 0 3 * * *'
+            appSelector = 'legacy-.+'
+        "#
+        );
+
+        assert!(schedule.applies_to("legacy-1", "backend"));
+        assert!(!schedule.applies_to("master", "backend"));
+    }
+
+    #[test]
+    fn should_apply_to_listed_services() {
+        let schedule = restart_schedule_from_str!(
+            r#"
+            schedule = '0 0 3 * * *'
+            services = [ 'backend' ]
+        "#
+        );
+
+        assert!(schedule.applies_to("master", "backend"));
+        assert!(!schedule.applies_to("master", "frontend"));
+    }
+
+    #[test]
+    fn should_compute_next_restart() {
+        let schedule = restart_schedule_from_str!(
+            r#"
+            schedule = '0 0 3 * * *'
+        "#
+        );
+
+        let since = Utc.ymd(2021, 6, 1).and_hms(12, 0, 0);
+
+        assert_eq!(
+            schedule.next_restart_after(&since),
+            Some(Utc.ymd(2021, 6, 2).and_hms(3, 0, 0))
+        );
+        assert!(!schedule.is_due(&since, &Utc.ymd(2021, 6, 2).and_hms(2, 59, 59)));
+        assert!(schedule.is_due(&since, &Utc.ymd(2021, 6, 2).and_hms(3, 0, 0)));
+    }
+
+    #[test]
+    fn should_not_parse_invalid_schedule() {
+        let schedule = toml::de::from_str::<RestartSchedule>(
+            r#"
+            schedule = 'every night'
+        "#,
+        );
+
+        assert!(schedule.is_err());
+    }
+}
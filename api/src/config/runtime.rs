@@ -24,19 +24,271 @@
  * =========================LICENSE_END==================================
  */
 use secstr::SecUtf8;
+use std::convert::TryFrom;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 use url::Url;
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 pub enum Runtime {
-    Docker,
+    Docker(DockerRuntimeConfig),
     Kubernetes(KubernetesRuntimeConfig),
 }
 
 impl Default for Runtime {
     fn default() -> Self {
-        Runtime::Docker
+        Runtime::Docker(DockerRuntimeConfig::default())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerRuntimeConfig {
+    #[serde(default)]
+    internal_networks: bool,
+    host: Option<DockerHost>,
+    cert_path: Option<PathBuf>,
+    #[serde(default)]
+    tls_verify: bool,
+    #[serde(default)]
+    cleanup: DockerCleanupConfig,
+    #[serde(default)]
+    retry: DockerRetryConfig,
+    parallelism: Option<usize>,
+    #[serde(default)]
+    hosts: Vec<DockerHostConfig>,
+}
+
+/// A Docker host that PREvant places apps on if the apps are distributed across several hosts.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerHostConfig {
+    name: String,
+    host: DockerHost,
+    cert_path: Option<PathBuf>,
+    tls_verify: Option<bool>,
+}
+
+impl DockerHostConfig {
+    /// The name that identifies the host in the labels of the containers. Thus, the name must not
+    /// change as long as there are apps on the host.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The address of the host, e.g. `tcp://docker-1.example.com:2376`.
+    pub fn host(&self) -> &DockerHost {
+        &self.host
+    }
+
+    /// The directory of the TLS client certificates for this host. By default, the `certPath` of
+    /// the runtime applies.
+    pub fn cert_path(&self) -> Option<&PathBuf> {
+        self.cert_path.as_ref()
+    }
+
+    /// Overrides `tlsVerify` of the runtime for this host.
+    pub fn tls_verify(&self) -> Option<bool> {
+        self.tls_verify
+    }
+}
+
+/// The address of a Docker daemon, either a Unix socket, e.g. `unix:///var/run/docker.sock`, or a
+/// TCP address, e.g. `tcp://docker.example.com:2376`. The address is validated when the
+/// configuration is loaded, so that PREvant does not start with a host it cannot connect to.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum DockerHost {
+    Unix(PathBuf),
+    Tcp { host: String, port: u16 },
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum DockerHostError {
+    #[fail(display = "Invalid Docker host {}: {}", host, err)]
+    InvalidHost { host: String, err: String },
+}
+
+impl Default for DockerHost {
+    fn default() -> Self {
+        DockerHost::Unix(PathBuf::from("/var/run/docker.sock"))
+    }
+}
+
+impl FromStr for DockerHost {
+    type Err = DockerHostError;
+
+    fn from_str(host: &str) -> Result<Self, Self::Err> {
+        let invalid = |err: &str| DockerHostError::InvalidHost {
+            host: host.to_string(),
+            err: err.to_string(),
+        };
+
+        let url = Url::parse(host).map_err(|err| invalid(&err.to_string()))?;
+        match url.scheme() {
+            "unix" if !url.path().is_empty() && url.path() != "/" => {
+                Ok(DockerHost::Unix(PathBuf::from(url.path())))
+            }
+            "unix" => Err(invalid("the path of the socket is missing")),
+            "tcp" => match url.host_str() {
+                Some(_) if !url.path().is_empty() && url.path() != "/" => {
+                    Err(invalid("the address must not contain a path"))
+                }
+                Some(tcp_host) => Ok(DockerHost::Tcp {
+                    host: tcp_host.to_string(),
+                    port: url.port().unwrap_or(2375),
+                }),
+                None => Err(invalid("the host name is missing")),
+            },
+            scheme => Err(invalid(&format!(
+                "the scheme {} is not supported, use unix or tcp",
+                scheme
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for DockerHost {
+    type Error = DockerHostError;
+
+    fn try_from(host: String) -> Result<Self, Self::Error> {
+        DockerHost::from_str(&host)
+    }
+}
+
+impl fmt::Display for DockerHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DockerHost::Unix(path) => write!(f, "unix://{}", path.display()),
+            DockerHost::Tcp { host, port } => write!(f, "tcp://{}:{}", host, port),
+        }
+    }
+}
+
+/// Controls which resources of an app are removed from the Docker host after the app has been
+/// deleted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct DockerCleanupConfig {
+    #[serde(default)]
+    images: bool,
+    #[serde(default)]
+    volumes: bool,
+}
+
+impl DockerCleanupConfig {
+    /// If `true`, the images of the app are removed unless other containers still use them.
+    pub fn images(&self) -> bool {
+        self.images
+    }
+
+    /// If `true`, the anonymous volumes of the app's containers are removed.
+    pub fn volumes(&self) -> bool {
+        self.volumes
+    }
+}
+
+/// Controls how often Docker operations, such as pulling images, creating containers, and
+/// attaching them to networks, are retried if they fail due to transient causes.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerRetryConfig {
+    #[serde(default = "DockerRetryConfig::default_max_attempts")]
+    max_attempts: u32,
+    #[serde(default = "DockerRetryConfig::default_initial_backoff_millis")]
+    initial_backoff_millis: u64,
+}
+
+impl DockerRetryConfig {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_initial_backoff_millis() -> u64 {
+        1000
+    }
+
+    /// The number of attempts including the first one, i.e. `1` disables retries.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.max(1)
+    }
+
+    /// The duration to wait after the given failed attempt. The backoff doubles with each attempt
+    /// but does not exceed one minute.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let max_backoff = Duration::from_secs(60);
+        Duration::from_millis(self.initial_backoff_millis)
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .map_or(max_backoff, |backoff| backoff.min(max_backoff))
+    }
+}
+
+impl Default for DockerRetryConfig {
+    fn default() -> Self {
+        DockerRetryConfig {
+            max_attempts: Self::default_max_attempts(),
+            initial_backoff_millis: Self::default_initial_backoff_millis(),
+        }
+    }
+}
+
+impl DockerRuntimeConfig {
+    /// The Docker host to connect to, e.g. `tcp://docker.example.com:2376`. By default, PREvant
+    /// connects to the host of `DOCKER_HOST` or to the local Unix socket.
+    pub fn host(&self) -> Option<&DockerHost> {
+        self.host.as_ref()
+    }
+
+    /// The directory that contains the TLS client certificate (`cert.pem`), its key (`key.pem`),
+    /// and the certificate authority (`ca.pem`) for connecting to a remote Docker host.
+    pub fn cert_path(&self) -> Option<&PathBuf> {
+        self.cert_path.as_ref()
+    }
+
+    /// If `true`, the certificate of the remote Docker host is verified against `ca.pem`.
+    pub fn tls_verify(&self) -> bool {
+        self.tls_verify
+    }
+
+    /// If `true`, the networks of the apps are internal-only, i.e. the containers of an app
+    /// cannot access the internet.
+    pub fn internal_networks(&self) -> bool {
+        self.internal_networks
+    }
+
+    pub fn cleanup(&self) -> &DockerCleanupConfig {
+        &self.cleanup
+    }
+
+    pub fn retry(&self) -> &DockerRetryConfig {
+        &self.retry
+    }
+
+    /// The maximum number of containers that are started concurrently. Services that depend on
+    /// each other are always started one after another. By default, all independent services are
+    /// started at once.
+    pub fn parallelism(&self) -> Option<usize> {
+        self.parallelism
+    }
+
+    /// The Docker hosts that the apps are distributed across. If there are none, all apps run on
+    /// `host`.
+    pub fn hosts(&self) -> &Vec<DockerHostConfig> {
+        &self.hosts
+    }
+
+    /// Derives the configuration for connecting to one of the `hosts`. The TLS settings of the
+    /// host take precedence over those of the runtime, the remaining settings apply to all hosts.
+    pub fn for_host(&self, host: &DockerHostConfig) -> DockerRuntimeConfig {
+        DockerRuntimeConfig {
+            host: Some(host.host.clone()),
+            cert_path: host.cert_path.clone().or_else(|| self.cert_path.clone()),
+            tls_verify: host.tls_verify.unwrap_or(self.tls_verify),
+            hosts: Vec::new(),
+            ..self.clone()
+        }
     }
 }
 
@@ -83,7 +335,188 @@ mod tests {
 
         let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
 
-        assert_eq!(runtime, Runtime::Docker);
+        assert_eq!(runtime, Runtime::Docker(DockerRuntimeConfig::default()));
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_internal_networks() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        internalNetworks = true
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => assert!(docker.internal_networks()),
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_cleanup() {
+        let runtime_toml = r#"
+        type = 'Docker'
+
+        [cleanup]
+        images = true
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => {
+                assert!(docker.cleanup().images());
+                assert!(!docker.cleanup().volumes());
+            }
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_retry() {
+        let runtime_toml = r#"
+        type = 'Docker'
+
+        [retry]
+        maxAttempts = 5
+        initialBackoffMillis = 500
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => {
+                assert_eq!(docker.retry().max_attempts(), 5);
+                assert_eq!(docker.retry().backoff(1), Duration::from_millis(500));
+                assert_eq!(docker.retry().backoff(3), Duration::from_secs(2));
+                assert_eq!(docker.retry().backoff(20), Duration::from_secs(60));
+            }
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_parallelism() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        parallelism = 4
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => assert_eq!(docker.parallelism(), Some(4)),
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_remote_host() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        host = 'tcp://docker.example.com:2376'
+        certPath = '/etc/prevant/docker'
+        tlsVerify = true
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => {
+                assert_eq!(
+                    docker.host(),
+                    Some(&DockerHost::Tcp {
+                        host: String::from("docker.example.com"),
+                        port: 2376
+                    })
+                );
+                assert_eq!(
+                    docker.cert_path(),
+                    Some(&PathBuf::from("/etc/prevant/docker"))
+                );
+                assert!(docker.tls_verify());
+            }
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_as_docker_runtime_with_multiple_hosts() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        certPath = '/etc/prevant/docker'
+
+        [[hosts]]
+        name = 'docker-1'
+        host = 'tcp://docker-1.example.com:2376'
+
+        [[hosts]]
+        name = 'docker-2'
+        host = 'tcp://docker-2.example.com:2376'
+        certPath = '/etc/prevant/docker-2'
+        tlsVerify = true
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        match runtime {
+            Runtime::Docker(docker) => {
+                assert_eq!(docker.hosts().len(), 2);
+                assert_eq!(docker.hosts()[1].name(), "docker-2");
+
+                let host_config = docker.for_host(&docker.hosts()[1]);
+                assert_eq!(
+                    host_config.host(),
+                    Some(&DockerHost::Tcp {
+                        host: String::from("docker-2.example.com"),
+                        port: 2376
+                    })
+                );
+                assert_eq!(
+                    host_config.cert_path(),
+                    Some(&PathBuf::from("/etc/prevant/docker-2"))
+                );
+                assert!(host_config.tls_verify());
+                assert!(host_config.hosts().is_empty());
+
+                let host_config = docker.for_host(&docker.hosts()[0]);
+                assert_eq!(
+                    host_config.cert_path(),
+                    Some(&PathBuf::from("/etc/prevant/docker"))
+                );
+                assert!(!host_config.tls_verify());
+            }
+            _ => panic!("Should be a docker config"),
+        }
+    }
+
+    #[test]
+    fn should_parse_docker_hosts() {
+        assert_eq!(
+            DockerHost::from_str("unix:///var/run/docker.sock"),
+            Ok(DockerHost::default())
+        );
+        assert_eq!(
+            DockerHost::from_str("tcp://10.0.0.1"),
+            Ok(DockerHost::Tcp {
+                host: String::from("10.0.0.1"),
+                port: 2375
+            })
+        );
+        assert!(DockerHost::from_str("docker.example.com:2376").is_err());
+        assert!(DockerHost::from_str("http://docker.example.com:2376").is_err());
+        assert!(DockerHost::from_str("unix://").is_err());
+    }
+
+    #[test]
+    fn should_not_parse_as_docker_runtime_with_invalid_host() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        host = 'docker.example.com:2376'
+        "#;
+
+        assert!(toml::de::from_str::<Runtime>(runtime_toml).is_err());
     }
 
     #[test]
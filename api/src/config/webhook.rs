@@ -0,0 +1,79 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use url::Url;
+
+/// An HTTP endpoint that will be notified about deployment events.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    url: Url,
+    max_attempts: Option<u32>,
+}
+
+impl WebhookConfig {
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The number of delivery attempts until a delivery is moved into the dead letters.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(5).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_webhook_with_defaults() {
+        let webhook = toml::de::from_str::<WebhookConfig>(
+            r#"
+            url = "https://chat.example.com/hooks/prevant"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            webhook.url(),
+            &Url::parse("https://chat.example.com/hooks/prevant").unwrap()
+        );
+        assert_eq!(webhook.max_attempts(), 5);
+    }
+
+    #[test]
+    fn should_parse_webhook_with_max_attempts() {
+        let webhook = toml::de::from_str::<WebhookConfig>(
+            r#"
+            url = "https://chat.example.com/hooks/prevant"
+            maxAttempts = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(webhook.max_attempts(), 10);
+    }
+}
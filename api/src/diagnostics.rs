@@ -0,0 +1,52 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::http_result::HttpResult;
+use crate::models::DiagnosticsReport;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// Returns the results of the diagnostic pass that ran while PREvant started, including the
+/// repairs that have been suggested or applied.
+#[get("/system/diagnostics", format = "application/json")]
+pub async fn diagnostics(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<DiagnosticsReport>> {
+    user?;
+    match apps.diagnostics() {
+        Some(report) => Ok(Json(report)),
+        None => Err(
+            HttpApiProblem::with_title_and_type(StatusCode::SERVICE_UNAVAILABLE)
+                .detail("The diagnostic pass has not been finished yet.")
+                .into(),
+        ),
+    }
+}
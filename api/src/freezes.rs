@@ -0,0 +1,85 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::config::FreezeWindow;
+use crate::http_result::HttpResult;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Lists the freeze windows by their names, including the windows that have been configured.
+#[get("/admin/freezes", format = "application/json")]
+pub async fn freezes(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<BTreeMap<String, FreezeWindow>>> {
+    user?;
+    Ok(Json(apps.freezes().windows()))
+}
+
+/// Declares a freeze window or replaces the window with the same name.
+#[put(
+    "/admin/freezes/<name>",
+    format = "application/json",
+    data = "<window>"
+)]
+pub async fn declare_freeze(
+    name: String,
+    window: Json<FreezeWindow>,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<FreezeWindow>> {
+    user?;
+    let window = window.into_inner();
+    info!("Declaring freeze window {}: {:?}", name, window);
+
+    apps.freezes().declare(name, window.clone());
+    Ok(Json(window))
+}
+
+/// Lifts the freeze window so that apps can be changed again.
+#[delete("/admin/freezes/<name>")]
+pub async fn lift_freeze(
+    name: String,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Status> {
+    user?;
+
+    if apps.freezes().lift(&name) {
+        info!("Lifted freeze window {}", name);
+        Ok(Status::NoContent)
+    } else {
+        Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!("There is no freeze window {}", name))
+            .into())
+    }
+}
@@ -36,6 +36,28 @@ pub type HttpResult<T> = Result<T, HttpApiError>;
 #[derive(Debug)]
 pub struct HttpApiError(HttpApiProblem);
 
+impl HttpApiError {
+    /// The human-readable explanation of the problem.
+    pub fn detail(&self) -> Option<&String> {
+        self.0.detail.as_ref()
+    }
+
+    /// Prepends the given context to the explanation of the problem, e.g. the part of the request
+    /// that caused the problem.
+    pub fn with_context(mut self, context: &str) -> Self {
+        self.0.detail = Some(match self.0.detail.take() {
+            Some(detail) => format!("{}: {}", context, detail),
+            None => context.to_string(),
+        });
+        self
+    }
+
+    #[cfg(test)]
+    pub fn problem(&self) -> &HttpApiProblem {
+        &self.0
+    }
+}
+
 impl From<HttpApiProblem> for HttpApiError {
     fn from(problem: HttpApiProblem) -> Self {
         Self(problem)
@@ -24,35 +24,58 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::config::ContainerConfig;
+use super::docker_client::{DockerClient, Stats};
+use crate::config::{ContainerConfig, DockerHost, DockerRetryConfig, DockerRuntimeConfig};
 use crate::infrastructure::{
-    Infrastructure, APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL,
-    SERVICE_NAME_LABEL, STATUS_ID,
+    depends_on_from_label_value, depends_on_to_label_value, skipped_companions_from_label_value,
+    skipped_companions_to_label_value, tickets_from_label_value, tickets_to_label_value,
+    Capabilities, Infrastructure, IngressProvider, ServiceDeploymentError,
+    TransientInfrastructureError, APP_NAME_LABEL, BASIC_AUTH_LABEL, COMPANION_DEFINITION_LABEL,
+    CONTAINER_TYPE_LABEL, DEPENDS_ON_LABEL, DOCKER_HOST_LABEL, FINGERPRINT_LABEL, IMAGE_LABEL,
+    OWNER_LABEL, PORTS_LABEL, REPLICATED_ENV_LABEL, REPLICATED_FROM_LABEL,
+    REPLICATED_IMAGE_DIGEST_LABEL, REVERSE_PROXY_LABEL, ROUTING_LABEL, SERVICE_NAME_LABEL,
+    SERVICE_READINESS_TIMEOUT, SKIPPED_COMPANIONS_LABEL, STATUS_ID, TICKETS_LABEL,
+    USER_LABELS_LABEL,
 };
 use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
-use crate::models::{Environment, Image, ServiceBuilder, ServiceBuilderError, ServiceConfig};
+use crate::models::{
+    deployment_waves, is_dependency, DiagnosticCheck, Environment, ExecOutput, HostCapacity, Image,
+    Orphan, OrphanReason, Port, Routing, ServiceBuilder, ServiceBuilderError, ServiceConfig,
+    ServiceStats,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset};
 use failure::{format_err, Error};
 use futures::future::join_all;
-use futures::{StreamExt, TryStreamExt};
+use futures::StreamExt;
 use multimap::MultiMap;
 use regex::Regex;
 use shiplift::container::{ContainerCreateInfo, ContainerDetails, ContainerInfo};
 use shiplift::errors::Error as ShipLiftError;
 use shiplift::tty::TtyChunk;
 use shiplift::{
-    ContainerConnectionOptions, ContainerFilter, ContainerListOptions, ContainerOptions, Docker,
-    LogsOptions, NetworkCreateOptions, PullOptions,
+    ContainerConnectionOptions, ContainerFilter, ContainerListOptions, ContainerOptions,
+    ExecContainerOptions, LogsOptions, RmContainerOptions,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::{From, TryFrom};
+use std::future::Future;
 use std::net::{AddrParseError, IpAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{channel, Receiver};
 
 static CONTAINER_PORT_LABEL: &str = "traefik.port";
 
-pub struct DockerInfrastructure {}
+pub struct DockerInfrastructure {
+    config: DockerRuntimeConfig,
+    ingress: Box<dyn IngressProvider>,
+    host_name: Option<String>,
+    host: DockerHost,
+    docker: DockerClient,
+}
 
 #[derive(Debug, Fail, PartialEq)]
 pub enum DockerInfrastructureError {
@@ -76,11 +99,90 @@ pub enum DockerInfrastructureError {
     UnknownServiceType { unknown_label: String },
     #[fail(display = "Unexpected container address: {}", internal_message)]
     InvalidContainerAddress { internal_message: String },
+    #[fail(display = "The service {} did not become ready in time.", service_name)]
+    ServiceNotReady { service_name: String },
+    #[fail(
+        display = "Cannot create a client for the Docker host {}: {}",
+        host, err
+    )]
+    CannotCreateClient { host: String, err: String },
+    #[fail(display = "There are no Docker hosts to distribute the apps across.")]
+    MissingDockerHosts,
 }
 
 impl DockerInfrastructure {
-    pub fn new() -> DockerInfrastructure {
-        DockerInfrastructure {}
+    /// Creates the client for the configured Docker host. Settings missing in the config are read
+    /// from the environment variables known from the Docker CLI, i.e. `DOCKER_HOST`,
+    /// `DOCKER_CERT_PATH`, and `DOCKER_TLS_VERIFY`.
+    pub fn new(
+        config: DockerRuntimeConfig,
+        ingress: Box<dyn IngressProvider>,
+    ) -> Result<DockerInfrastructure, DockerInfrastructureError> {
+        let host = match (config.host(), std::env::var("DOCKER_HOST")) {
+            (Some(host), _) => host.clone(),
+            (None, Ok(host)) if !host.is_empty() => DockerHost::from_str(&host).map_err(|err| {
+                DockerInfrastructureError::CannotCreateClient {
+                    host: host.clone(),
+                    err: err.to_string(),
+                }
+            })?,
+            (None, _) => DockerHost::default(),
+        };
+        let cert_path = config.cert_path().cloned().or_else(|| {
+            std::env::var_os("DOCKER_CERT_PATH")
+                .filter(|cert_path| !cert_path.is_empty())
+                .map(PathBuf::from)
+        });
+        let tls_verify = config.tls_verify()
+            || std::env::var_os("DOCKER_TLS_VERIFY").map_or(false, |verify| !verify.is_empty());
+
+        let docker = DockerClient::new(&host, cert_path.as_deref(), tls_verify).map_err(|err| {
+            DockerInfrastructureError::CannotCreateClient {
+                host: host.to_string(),
+                err: err.to_string(),
+            }
+        })?;
+
+        Ok(DockerInfrastructure {
+            config,
+            ingress,
+            host_name: None,
+            host,
+            docker,
+        })
+    }
+
+    /// Labels the containers with the name of the Docker host so that the placement of the apps
+    /// can be resolved if PREvant distributes the apps across several hosts.
+    pub(super) fn with_host_name(mut self, host_name: String) -> Self {
+        self.host_name = Some(host_name);
+        self
+    }
+
+    fn docker(&self) -> DockerClient {
+        self.docker.clone()
+    }
+
+    /// Checks whether the app has been placed on this host, i.e. whether there are containers of
+    /// the app, including those of operations in progress. Containers without the label of the
+    /// Docker host, e.g. those deployed before the apps have been distributed across several
+    /// hosts, belong to the host that runs them.
+    pub(super) async fn hosts_app(&self, app_name: &String) -> Result<bool, Error> {
+        Ok(self
+            .get_containers(vec![label_filter(APP_NAME_LABEL, Some(app_name))])
+            .await?
+            .iter()
+            .any(|container| {
+                container
+                    .labels
+                    .get(DOCKER_HOST_LABEL)
+                    .map_or(true, |host_name| Some(host_name) == self.host_name.as_ref())
+            }))
+    }
+
+    /// The number of containers of all apps on this host.
+    pub(super) async fn load(&self) -> Result<usize, Error> {
+        Ok(self.get_app_containers(None, None).await?.len())
     }
 
     async fn find_status_change_container(
@@ -113,39 +215,41 @@ impl DockerInfrastructure {
 
         let image = "docker.io/library/busybox:stable";
 
-        pull(&image).await?;
+        let docker = &self.docker();
+        with_retry(self.config.retry(), "Pulling busybox", move || {
+            pull(docker, image)
+        })
+        .await?;
 
         let mut labels: HashMap<&str, &str> = HashMap::new();
         labels.insert(APP_NAME_LABEL, app_name);
         labels.insert(STATUS_ID, &status_id);
+        if let Some(host_name) = &self.host_name {
+            labels.insert(DOCKER_HOST_LABEL, host_name);
+        }
 
         let mut options = ContainerOptions::builder(image);
         options.labels(&labels);
 
-        let docker = Docker::new();
-        let containers = docker.containers();
-
         trace!(
             "Create deployment task container {} for {}",
             status_id,
             app_name
         );
-        let container_info = containers.create(&options.build()).await;
+        let container_info = docker.create_container(&options.build()).await;
         let ci = container_info?;
 
-        let container = containers.get(&ci.id);
-        Ok(container.inspect().await?)
+        Ok(docker.inspect_container(&ci.id).await?)
     }
 
-    async fn create_or_get_network_id(&self, app_name: &String) -> Result<String, ShipLiftError> {
+    async fn create_or_get_network_id(&self, app_name: &String) -> Result<String, Error> {
         trace!("Resolve network id for {}", app_name);
 
-        let network_name = format!("{}-net", app_name);
+        let network_name = network_name(app_name);
 
-        let docker = Docker::new();
+        let docker = self.docker();
         let network_id = docker
-            .networks()
-            .list(&Default::default())
+            .list_networks()
             .await?
             .iter()
             .find(|n| n.name == network_name)
@@ -157,62 +261,100 @@ impl DockerInfrastructure {
 
         debug!("Creating network for app {}.", app_name);
 
-        let network_create_info = docker
-            .networks()
-            .create(&NetworkCreateOptions::builder(network_name.as_ref()).build())
+        let network_id = docker
+            .create_network(&network_name, self.config.internal_networks())
             .await?;
 
         debug!(
             "Created network for app {} with id {}",
-            app_name, network_create_info.id
+            app_name, network_id
         );
 
-        Ok(network_create_info.id)
+        Ok(network_id)
     }
 
-    async fn connect_traefik(&self, network_id: &String) -> Result<(), ShipLiftError> {
-        let docker = Docker::new();
+    /// Returns the IDs of the containers that must be connected to every app network: the reverse
+    /// proxy, which routes the requests to the services, and PREvant itself (if it runs within a
+    /// container), which has to reach the services for resolving their web host meta data.
+    async fn infrastructure_container_ids(&self) -> Result<Vec<String>, ShipLiftError> {
+        let own_container_id = std::env::var("HOSTNAME").ok();
 
-        let containers = docker
-            .containers()
-            .list(&ContainerListOptions::builder().build())
-            .await?;
-        let traefik_container_id = containers
+        let mut ids = self
+            .reverse_proxy_containers()
+            .await?
             .into_iter()
-            .find(|c| c.image.contains("traefik"))
-            .map(|c| c.id);
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            warn!(
+                "There is no running {} container with the label {}, thus the services of the new app network are not reachable.",
+                self.ingress.name(),
+                REVERSE_PROXY_LABEL
+            );
+        }
 
-        if let Some(id) = traefik_container_id {
+        if let Some(own_container_id) = own_container_id.filter(|id| !id.is_empty()) {
+            let docker = self.docker();
+            let own_container = docker
+                .list_containers(&ContainerListOptions::builder().build())
+                .await?
+                .into_iter()
+                .find(|c| c.id.starts_with(&own_container_id));
+            if let Some(own_container) = own_container {
+                ids.push(own_container.id);
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Returns the running containers of the reverse proxy. Administrators mark them explicitly
+    /// with a label because services of the apps might be based on the same image as the reverse
+    /// proxy and must not be connected to the networks of other apps.
+    async fn reverse_proxy_containers(&self) -> Result<Vec<ContainerInfo>, ShipLiftError> {
+        let docker = self.docker();
+        docker
+            .list_containers(
+                &ContainerListOptions::builder()
+                    .filter(vec![label_filter(REVERSE_PROXY_LABEL, None)])
+                    .build(),
+            )
+            .await
+    }
+
+    async fn connect_infrastructure_containers(
+        &self,
+        network_id: &String,
+    ) -> Result<(), ShipLiftError> {
+        let docker = self.docker();
+
+        for id in self.infrastructure_container_ids().await? {
             if let Err(e) = docker
-                .networks()
-                .get(network_id)
-                .connect(&ContainerConnectionOptions::builder(&id).build())
+                .connect_network(
+                    network_id,
+                    &ContainerConnectionOptions::builder(&id).build(),
+                )
                 .await
             {
-                debug!("Cannot traefik: {}", e);
+                debug!("Cannot connect {} to network: {}", id, e);
             }
         }
 
         Ok(())
     }
 
-    async fn disconnect_traefik(&self, network_id: &String) -> Result<(), ShipLiftError> {
-        let docker = Docker::new();
-
-        let containers = docker
-            .containers()
-            .list(&ContainerListOptions::builder().build())
-            .await?;
-        let traefik_container_id = containers
-            .into_iter()
-            .find(|c| c.image.contains("traefik"))
-            .map(|c| c.id);
+    async fn disconnect_infrastructure_containers(
+        &self,
+        network_id: &String,
+    ) -> Result<(), ShipLiftError> {
+        let docker = self.docker();
 
-        if let Some(id) = traefik_container_id {
+        for id in self.infrastructure_container_ids().await? {
             docker
-                .networks()
-                .get(network_id)
-                .disconnect(&ContainerConnectionOptions::builder(&id).build())
+                .disconnect_network(
+                    network_id,
+                    &ContainerConnectionOptions::builder(&id).build(),
+                )
                 .await?;
         }
 
@@ -220,18 +362,17 @@ impl DockerInfrastructure {
     }
 
     async fn delete_network(&self, app_name: &String) -> Result<(), ShipLiftError> {
-        let network_name = format!("{}-net", app_name);
+        let network_name = network_name(app_name);
 
-        let docker = Docker::new();
+        let docker = self.docker();
         for n in docker
-            .networks()
-            .list(&Default::default())
+            .list_networks()
             .await?
             .iter()
             .filter(|n| n.name == network_name)
         {
-            self.disconnect_traefik(&n.id).await?;
-            docker.networks().get(&n.id).delete().await?;
+            self.disconnect_infrastructure_containers(&n.id).await?;
+            docker.delete_network(&n.id).await?;
         }
 
         Ok(())
@@ -245,24 +386,85 @@ impl DockerInfrastructure {
     ) -> Result<Vec<Service>, Error> {
         let network_id = self.create_or_get_network_id(app_name).await?;
 
-        self.connect_traefik(&network_id).await?;
-
-        let futures = configs
-            .iter()
-            .map(|service_config| {
-                self.start_container(app_name, &network_id, &service_config, container_config)
-            })
-            .collect::<Vec<_>>();
+        self.connect_infrastructure_containers(&network_id).await?;
 
+        let network_id = &network_id;
         let mut services: Vec<Service> = Vec::new();
-        for service in join_all(futures).await {
-            services.push(service?);
+        for wave in deployment_waves(configs)? {
+            let parallelism = self.config.parallelism().unwrap_or(wave.len()).max(1);
+            let results = futures::stream::iter(wave.iter().map(|service_config| async move {
+                self.start_container(app_name, network_id, service_config, container_config)
+                    .await
+                    .map_err(|err| {
+                        ServiceDeploymentError::caused_by(service_config.service_name(), &err)
+                    })
+            }))
+            .buffered(parallelism)
+            .collect::<Vec<_>>()
+            .await;
+            let started_services = ServiceDeploymentError::collect(results)?;
+
+            let dependencies = started_services
+                .iter()
+                .filter(|service| is_dependency(configs, service.service_name()))
+                .collect::<Vec<_>>();
+            let futures = dependencies
+                .iter()
+                .map(|service| self.wait_until_ready(service))
+                .collect::<Vec<_>>();
+            ServiceDeploymentError::collect(dependencies.iter().zip(join_all(futures).await).map(
+                |(service, readiness)| {
+                    readiness
+                        .map_err(|err| ServiceDeploymentError::new(service.service_name(), &err))
+                },
+            ))?;
+
+            services.extend(started_services);
         }
 
         Ok(services)
     }
 
+    /// Waits until the container of the service is running and, if the service exposes a port,
+    /// until the port accepts connections.
+    async fn wait_until_ready(&self, service: &Service) -> Result<(), Error> {
+        let docker = self.docker();
+        let deadline = Instant::now() + SERVICE_READINESS_TIMEOUT;
+
+        loop {
+            let container_details = docker.inspect_container(service.id()).await?;
+
+            if container_details.state.running && !container_details.state.restarting {
+                match service.endpoint_addr() {
+                    None => return Ok(()),
+                    Some(addr) => {
+                        let connection =
+                            tokio::time::timeout(Duration::from_secs(1), TcpStream::connect(addr))
+                                .await;
+                        if let Ok(Ok(_)) = connection {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(DockerInfrastructureError::ServiceNotReady {
+                    service_name: service.service_name().clone(),
+                }
+                .into());
+            }
+
+            trace!(
+                "Waiting for service {} to become ready",
+                service.service_name()
+            );
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
     async fn stop_services_impl(&self, app_name: &String) -> Result<Vec<Service>, Error> {
+        let docker = self.docker();
         let container_details = match self
             .get_container_details(Some(app_name), None)
             .await?
@@ -275,15 +477,16 @@ impl DockerInfrastructure {
         let futures = container_details
             .iter()
             .filter(|details| details.state.running)
-            .map(|details| stop(details.clone()));
+            .map(|details| stop(&docker, details.clone()));
         for container in join_all(futures).await {
             trace!("Stopped container {:?}", container?);
         }
 
         let mut services = Vec::with_capacity(container_details.len());
+        let remove_volumes = self.config.cleanup().volumes();
         let futures = container_details
             .iter()
-            .map(|details| delete(details.clone()));
+            .map(|details| remove(&docker, details.clone(), remove_volumes));
         for container in join_all(futures).await {
             let container = container?;
             trace!("Deleted container {:?}", container);
@@ -293,9 +496,44 @@ impl DockerInfrastructure {
 
         self.delete_network(app_name).await?;
 
+        if self.config.cleanup().images() {
+            let images = container_details
+                .iter()
+                .map(|details| details.image.clone())
+                .collect::<HashSet<String>>();
+            self.delete_unused_images(images).await?;
+        }
+
         Ok(services)
     }
 
+    /// Deletes the images that are not used by any remaining container of PREvant. Images that
+    /// are still in use by other containers on the Docker host cannot be deleted and will be
+    /// skipped.
+    async fn delete_unused_images(&self, images: HashSet<String>) -> Result<(), ShipLiftError> {
+        let used_images = self
+            .get_app_containers(None, None)
+            .await?
+            .into_iter()
+            .map(|container| container.image_id)
+            .collect::<HashSet<String>>();
+
+        let docker = self.docker();
+        for image in images.difference(&used_images) {
+            info!("Clean up unused image {}", image);
+            match docker.delete_image(image).await {
+                Ok(output) => {
+                    for o in output {
+                        debug!("{:?}", o);
+                    }
+                }
+                Err(err) => debug!("Could not clean up image {}: {:?}", image, err),
+            }
+        }
+
+        Ok(())
+    }
+
     async fn start_container(
         &self,
         app_name: &String,
@@ -303,9 +541,7 @@ impl DockerInfrastructure {
         service_config: &ServiceConfig,
         container_config: &ContainerConfig,
     ) -> Result<Service, Error> {
-        let docker = Docker::new();
-        let containers = docker.containers();
-        let images = docker.images();
+        let docker = self.docker();
 
         if let Image::Named { .. } = service_config.image() {
             self.pull_image(app_name, &service_config).await?;
@@ -316,8 +552,7 @@ impl DockerInfrastructure {
             .get_app_container(app_name, service_config.service_name())
             .await?
         {
-            let container = containers.get(&container_info.id);
-            let container_details = container.inspect().await?;
+            let container_details = docker.inspect_container(&container_info.id).await?;
 
             info!(
                 "Removing container {:?} of review app {:?}",
@@ -325,11 +560,11 @@ impl DockerInfrastructure {
             );
 
             if container_details.state.running {
-                container
-                    .stop(Some(core::time::Duration::from_secs(10)))
+                docker
+                    .stop_container(&container_info.id, Some(Duration::from_secs(10)))
                     .await?;
             }
-            container.delete().await?;
+            docker.delete_container(&container_info.id).await?;
 
             image_to_delete = Some(container_details.image);
         }
@@ -342,40 +577,57 @@ impl DockerInfrastructure {
             service_config.container_type(),
         );
 
-        let options = DockerInfrastructure::create_container_options(
-            app_name,
-            &service_config,
-            container_config,
-        );
+        let options =
+            self.create_container_options(app_name, network_id, &service_config, container_config);
 
-        let container_info = containers.create(&options).await?;
+        let docker_ref = &docker;
+        let options_ref = &options;
+        let container_info = with_retry(self.config.retry(), "Creating container", move || {
+            docker_ref.create_container(options_ref)
+        })
+        .await?;
         debug!("Created container: {:?}", container_info);
 
         self.copy_volume_data(&container_info, service_config)
             .await?;
 
-        containers.get(&container_info.id).start().await?;
-        debug!("Started container: {:?}", container_info);
-
+        // The container has been created within the app network, so that it is never attached to
+        // another network and the containers of different apps cannot reach each other. Since the
+        // container options cannot declare network aliases, the endpoint is replaced by one that
+        // makes the service reachable by its name.
         docker
-            .networks()
-            .get(network_id)
-            .connect(
-                &ContainerConnectionOptions::builder(&container_info.id)
-                    .aliases(vec![service_config.service_name().as_str()])
-                    .build(),
+            .disconnect_network(
+                network_id,
+                &ContainerConnectionOptions::builder(&container_info.id).build(),
             )
             .await?;
+        let connection_options = ContainerConnectionOptions::builder(&container_info.id)
+            .aliases(vec![service_config.service_name().as_str()])
+            .build();
+        let connection_options_ref = &connection_options;
+        with_retry(
+            self.config.retry(),
+            "Connecting container to network",
+            move || async move {
+                docker_ref
+                    .connect_network(network_id, connection_options_ref)
+                    .await
+            },
+        )
+        .await?;
         debug!(
             "Connected container {:?} to {:?}",
             container_info.id, network_id
         );
 
-        let container_details = containers.get(&container_info.id).inspect().await?;
+        docker.start_container(&container_info.id).await?;
+        debug!("Started container: {:?}", container_info);
+
+        let container_details = docker.inspect_container(&container_info.id).await?;
 
         if let Some(image) = image_to_delete {
             info!("Clean up image {:?} of app {:?}", image, app_name);
-            match images.get(&image).delete().await {
+            match docker.delete_image(&image).await {
                 Ok(output) => {
                     for o in output {
                         debug!("{:?}", o);
@@ -389,7 +641,9 @@ impl DockerInfrastructure {
     }
 
     fn create_container_options(
+        &self,
         app_name: &String,
+        network_id: &String,
         service_config: &ServiceConfig,
         container_config: &ContainerConfig,
     ) -> ContainerOptions {
@@ -403,20 +657,39 @@ impl DockerInfrastructure {
             options.env(variables.iter().map(|s| s.as_str()).collect::<Vec<&str>>());
         }
 
+        if let Some(command) = service_config.command() {
+            options.cmd(command.iter().map(|s| s.as_str()).collect::<Vec<&str>>());
+        }
+
+        if let Some(entrypoint) = service_config.entrypoint() {
+            options.entrypoint(entrypoint.iter().map(|s| s.as_str()).collect::<Vec<&str>>());
+        }
+
         let mut labels: HashMap<&str, &str> = HashMap::new();
 
-        let traefik_frontend = format!(
-            "PathPrefixStrip: /{app_name}/{service_name}/; PathPrefix:/{app_name}/{service_name}/;",
-            app_name = app_name,
-            service_name = service_config.service_name()
-        );
-        labels.insert("traefik.frontend.rule", &traefik_frontend);
+        let route_labels = self.ingress.route_labels(app_name, service_config);
+        for (k, v) in &route_labels {
+            labels.insert(k, v);
+        }
+        let routing = service_config
+            .routing()
+            .map(|routing| serde_json::json!(routing).to_string());
+        if let Some(routing) = &routing {
+            labels.insert(ROUTING_LABEL, routing);
+        }
 
+        let user_labels = service_config
+            .labels()
+            .filter(|labels| !labels.is_empty())
+            .map(|labels| serde_json::json!(labels).to_string());
         if let Some(config_labels) = service_config.labels() {
             for (k, v) in config_labels {
                 labels.insert(k, v);
             }
         }
+        if let Some(user_labels) = &user_labels {
+            labels.insert(USER_LABELS_LABEL, user_labels);
+        }
 
         labels.insert(APP_NAME_LABEL, app_name);
         labels.insert(SERVICE_NAME_LABEL, &service_config.service_name());
@@ -435,8 +708,63 @@ impl DockerInfrastructure {
             labels.insert(REPLICATED_ENV_LABEL, &replicated_env);
         }
 
+        if let Some(owner) = service_config.owner() {
+            labels.insert(OWNER_LABEL, owner);
+        }
+
+        let tickets = tickets_to_label_value(service_config);
+        if let Some(tickets) = &tickets {
+            labels.insert(TICKETS_LABEL, tickets);
+        }
+
+        let skipped_companions = skipped_companions_to_label_value(service_config);
+        if let Some(skipped_companions) = &skipped_companions {
+            labels.insert(SKIPPED_COMPANIONS_LABEL, skipped_companions);
+        }
+
+        if let Some(definition) = service_config.companion_definition() {
+            labels.insert(COMPANION_DEFINITION_LABEL, definition);
+        }
+
+        if let Some(basic_auth) = service_config.basic_auth() {
+            labels.insert(BASIC_AUTH_LABEL, basic_auth);
+        }
+
+        if let Some(replicated_from) = service_config.replicated_from() {
+            labels.insert(REPLICATED_FROM_LABEL, replicated_from);
+        }
+        if let Some(image_digest) = service_config.replicated_image_digest() {
+            labels.insert(REPLICATED_IMAGE_DIGEST_LABEL, image_digest);
+        }
+
+        let ports = if service_config.ports().is_empty() {
+            None
+        } else {
+            Some((
+                service_config.port().to_string(),
+                serde_json::json!(service_config.ports()).to_string(),
+            ))
+        };
+        if let Some((http_port, ports)) = &ports {
+            labels.insert(CONTAINER_PORT_LABEL, http_port);
+            labels.insert(PORTS_LABEL, ports);
+        }
+
+        let fingerprint = service_config.fingerprint();
+        labels.insert(FINGERPRINT_LABEL, &fingerprint);
+
+        let depends_on = depends_on_to_label_value(service_config);
+        if let Some(depends_on) = &depends_on {
+            labels.insert(DEPENDS_ON_LABEL, depends_on);
+        }
+
+        if let Some(host_name) = &self.host_name {
+            labels.insert(DOCKER_HOST_LABEL, host_name);
+        }
+
         options.labels(&labels);
         options.restart_policy("always", 5);
+        options.network_mode(network_id);
 
         if let Some(memory_limit) = container_config.memory_limit() {
             options.memory(memory_limit.clone());
@@ -461,24 +789,18 @@ impl DockerInfrastructure {
             service_config.service_name()
         );
 
-        let docker = Docker::new();
-        let containers = docker.containers();
+        let docker = self.docker();
 
         for (path, data) in volumes.into_iter() {
-            containers
-                .get(&container_info.id)
-                .copy_file_into(path, &data.as_bytes())
+            docker
+                .copy_file_into(&container_info.id, &path, data.as_bytes())
                 .await?;
         }
 
         Ok(())
     }
 
-    async fn pull_image(
-        &self,
-        app_name: &String,
-        config: &ServiceConfig,
-    ) -> Result<(), ShipLiftError> {
+    async fn pull_image(&self, app_name: &String, config: &ServiceConfig) -> Result<(), Error> {
         let image = config.image().to_string();
 
         info!(
@@ -488,7 +810,12 @@ impl DockerInfrastructure {
             app_name
         );
 
-        let pull_results = pull(&image).await?;
+        let docker = &self.docker();
+        let image_ref = &image;
+        let pull_results = with_retry(self.config.retry(), "Pulling image", move || {
+            pull(docker, image_ref)
+        })
+        .await?;
 
         for pull_result in pull_results {
             debug!("{:?}", pull_result);
@@ -501,15 +828,14 @@ impl DockerInfrastructure {
         &self,
         filters: Vec<ContainerFilter>,
     ) -> Result<Vec<ContainerInfo>, ShipLiftError> {
-        let docker = Docker::new();
-        let containers = docker.containers();
+        let docker = self.docker();
 
         let list_options = ContainerListOptions::builder()
             .all()
             .filter(filters)
             .build();
 
-        containers.list(&list_options).await
+        docker.list_containers(&list_options).await
     }
 
     async fn get_app_containers(
@@ -555,9 +881,10 @@ impl DockerInfrastructure {
 
         let container_list = self.get_app_containers(app_name, service_name).await?;
 
+        let docker = self.docker();
         let mut container_details = MultiMap::new();
         for container in container_list.into_iter() {
-            if let Some(details) = not_found_to_none(inspect(container).await)? {
+            if let Some(details) = not_found_to_none(inspect(&docker, container).await)? {
                 let app_name = details
                     .config
                     .labels
@@ -572,10 +899,110 @@ impl DockerInfrastructure {
 
         Ok(container_details)
     }
+
+    /// Inspects all containers that carry the app name label of PREvant and returns those that
+    /// do not belong to a properly deployed app.
+    async fn find_orphans(&self) -> Result<Vec<(Orphan, ContainerDetails)>, ShipLiftError> {
+        let docker = self.docker();
+        let mut container_details = Vec::new();
+        for container in self
+            .get_containers(vec![label_filter(APP_NAME_LABEL, None)])
+            .await?
+        {
+            if let Some(details) = not_found_to_none(inspect(&docker, container).await)? {
+                container_details.push(details);
+            }
+        }
+        Ok(orphans_of(container_details))
+    }
+}
+
+/// Classifies the containers as orphans: containers of interrupted operations, containers without
+/// valid service labels, containers that have never been started, older containers of services
+/// with a newer container, and companions of apps without any instance or replica.
+fn orphans_of(container_details: Vec<ContainerDetails>) -> Vec<(Orphan, ContainerDetails)> {
+    let orphan = |details: &ContainerDetails, reason: OrphanReason| {
+        let labels = details.config.labels.as_ref();
+        let label = |name: &str| labels.map(|labels| labels.get(name)).flatten().cloned();
+        Orphan::new(
+            details.id.clone(),
+            label(APP_NAME_LABEL).unwrap_or_default(),
+            label(SERVICE_NAME_LABEL),
+            reason,
+        )
+    };
+
+    let mut orphans = Vec::new();
+    let mut apps = MultiMap::new();
+    for details in container_details {
+        let reason = if details
+            .config
+            .labels
+            .as_ref()
+            .map_or(false, |labels| labels.contains_key(STATUS_ID))
+        {
+            Some(OrphanReason::InterruptedOperation)
+        } else if Service::try_from(&details).is_err() {
+            Some(OrphanReason::InvalidLabels)
+        } else if details.state.status == "created" {
+            Some(OrphanReason::NeverStarted)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => orphans.push((orphan(&details, reason), details)),
+            None => {
+                let service = Service::try_from(&details).unwrap();
+                apps.insert(service.app_name().clone(), (service, details));
+            }
+        }
+    }
+
+    for (_, mut services) in apps.into_iter() {
+        // The newest container of a service is the deployed one, older ones remained from
+        // redeployments.
+        services.sort_by(|(_, a), (_, b)| b.created.cmp(&a.created));
+        let mut service_names = HashSet::new();
+        let (services, duplicates): (Vec<_>, Vec<_>) = services
+            .into_iter()
+            .partition(|(service, _)| service_names.insert(service.service_name().clone()));
+        for (_, details) in duplicates {
+            orphans.push((orphan(&details, OrphanReason::Duplicate), details));
+        }
+
+        let has_services = services.iter().any(|(service, _)| {
+            matches!(
+                service.container_type(),
+                ContainerType::Instance | ContainerType::Replica
+            )
+        });
+        if !has_services {
+            for (_, details) in services {
+                orphans.push((
+                    orphan(&details, OrphanReason::CompanionWithoutServices),
+                    details,
+                ));
+            }
+        }
+    }
+
+    orphans
 }
 
 #[async_trait]
 impl Infrastructure for DockerInfrastructure {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            volumes: true,
+            exec: true,
+            tcp_routing: false,
+            replicas: true,
+            stats: true,
+            basic_auth: self.ingress.supports_basic_auth(),
+        }
+    }
+
     async fn get_services(&self) -> Result<MultiMap<String, Service>, Error> {
         let mut apps = MultiMap::new();
         let container_details = self.get_container_details(None, None).await?;
@@ -612,7 +1039,7 @@ impl Infrastructure for DockerInfrastructure {
             .deploy_services_impl(app_name, configs, container_config)
             .await;
 
-        delete(deployment_container).await?;
+        delete(&self.docker(), deployment_container).await?;
 
         result
     }
@@ -656,7 +1083,7 @@ impl Infrastructure for DockerInfrastructure {
 
         let result = self.stop_services_impl(app_name).await;
 
-        delete(deployment_container).await?;
+        delete(&self.docker(), deployment_container).await?;
 
         result
     }
@@ -671,7 +1098,7 @@ impl Infrastructure for DockerInfrastructure {
         match self.get_app_container(app_name, service_name).await? {
             None => Ok(None),
             Some(container) => {
-                let docker = Docker::new();
+                let docker = self.docker();
 
                 trace!(
                     "Acquiring logs of container {} since {:?}",
@@ -693,20 +1120,14 @@ impl Infrastructure for DockerInfrastructure {
                         .build(),
                 };
 
-                let logs = docker
-                    .containers()
-                    .get(&container.id)
-                    .logs(&log_options)
-                    .collect::<Vec<Result<TtyChunk, ShipLiftError>>>()
-                    .await;
+                let logs = docker.container_logs(&container.id, &log_options).await?;
 
                 let logs = logs.into_iter()
                     .enumerate()
                     // Unfortunately, docker API does not support head (cf. https://github.com/moby/moby/issues/13096)
                     // Until then we have to skip these log messages which is super slow…
                     .filter(move |(index, _)| index < &limit)
-                    .filter_map(|(_, chunk)| chunk.ok())
-                    .map(|chunk| {
+                    .map(|(_, chunk)| {
                         let line = String::from_utf8_lossy(&chunk.to_vec()).to_string();
 
                         let mut iter = line.splitn(2, ' ').into_iter();
@@ -732,6 +1153,276 @@ impl Infrastructure for DockerInfrastructure {
         }
     }
 
+    async fn exec(
+        &self,
+        app_name: &String,
+        service_name: &String,
+        command: &[String],
+    ) -> Result<Option<Receiver<Result<ExecOutput, Error>>>, Error> {
+        let container = match self.get_app_container(app_name, service_name).await? {
+            None => return Ok(None),
+            Some(container) => container,
+        };
+
+        debug!("Executing {:?} in container {}", command, container.id);
+
+        let command = command.to_vec();
+        let (sender, receiver) = channel(16);
+        let docker = self.docker();
+        tokio::spawn(async move {
+            let options = ExecContainerOptions::builder()
+                .cmd(command.iter().map(String::as_str).collect())
+                .attach_stdout(true)
+                .attach_stderr(true)
+                .build();
+
+            let mut chunks = docker.exec(&container.id, &options);
+            while let Some(chunk) = chunks.next().await {
+                let output = match chunk {
+                    Ok(TtyChunk::StdOut(bytes)) => Ok(ExecOutput::Stdout(
+                        String::from_utf8_lossy(&bytes).to_string(),
+                    )),
+                    Ok(TtyChunk::StdErr(bytes)) => Ok(ExecOutput::Stderr(
+                        String::from_utf8_lossy(&bytes).to_string(),
+                    )),
+                    Ok(TtyChunk::StdIn(_)) => continue,
+                    Err(err) => Err(Error::from(err)),
+                };
+
+                // The receiver is gone if the client disconnected, so there is no one left who
+                // is interested in the remaining output.
+                if sender.send(output).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Some(receiver))
+    }
+
+    async fn get_stats(&self, app_name: &String) -> Result<Vec<ServiceStats>, failure::Error> {
+        let containers = self
+            .get_app_containers(Some(app_name), None)
+            .await?
+            .into_iter()
+            .filter(|container| container.state == "running")
+            .collect::<Vec<_>>();
+
+        let docker = self.docker();
+        let futures = containers
+            .iter()
+            .map(|container| container_stats(&docker, container))
+            .collect::<Vec<_>>();
+
+        let mut stats = Vec::with_capacity(containers.len());
+        for (container, container_stats) in containers.iter().zip(join_all(futures).await) {
+            let service_name = match container.labels.get(SERVICE_NAME_LABEL) {
+                Some(service_name) => service_name.clone(),
+                None => continue,
+            };
+
+            if let Some(container_stats) = container_stats? {
+                stats.push(container_stats.into_service_stats(service_name));
+            }
+        }
+
+        Ok(stats)
+    }
+
+    async fn diagnose(&self) -> Result<Vec<DiagnosticCheck>, failure::Error> {
+        let docker = self.docker();
+        let mut checks = Vec::new();
+
+        let has_proxy = !self.reverse_proxy_containers().await?.is_empty();
+        checks.push(if has_proxy {
+            DiagnosticCheck::passed("proxy", format!("{} is running.", self.ingress.name()))
+        } else {
+            DiagnosticCheck::failed(
+                "proxy",
+                format!(
+                    "There is no running {} container with the label {}, thus the services are not reachable.",
+                    self.ingress.name(),
+                    REVERSE_PROXY_LABEL
+                ),
+            )
+            .with_suggested_repair(format!(
+                "Start {} with the Docker provider and the label {} on the Docker host.",
+                self.ingress.name(),
+                REVERSE_PROXY_LABEL
+            ))
+        });
+
+        // PREvant has just been started, thus, there cannot be any operation in progress and the
+        // remaining status change containers would block further deployments of their apps.
+        let status_change_containers = self.get_status_change_containers(None, None).await?;
+        checks.push(if status_change_containers.is_empty() {
+            DiagnosticCheck::passed(
+                "status-change-containers",
+                String::from("There are no leftovers of interrupted operations."),
+            )
+        } else {
+            let mut apps = Vec::new();
+            for container in status_change_containers {
+                if let Some(app_name) = container.labels.get(APP_NAME_LABEL) {
+                    apps.push(app_name.clone());
+                }
+                if let Some(details) = not_found_to_none(inspect(&docker, container).await)? {
+                    delete(&docker, details).await?;
+                }
+            }
+            DiagnosticCheck::warning(
+                "status-change-containers",
+                format!("Operations of the apps {:?} have been interrupted.", apps),
+            )
+            .with_applied_repair(String::from(
+                "Deleted the status change containers of the interrupted operations.",
+            ))
+        });
+
+        // Containers that have never been started remained from interrupted deployments and, as
+        // with the status change containers, there cannot be a deployment in progress yet.
+        let (never_started, orphans): (Vec<_>, Vec<_>) = self
+            .find_orphans()
+            .await?
+            .into_iter()
+            .partition(|(orphan, _)| orphan.reason() == &OrphanReason::NeverStarted);
+        for (_, details) in &never_started {
+            not_found_to_none(
+                remove(&docker, details.clone(), self.config.cleanup().volumes()).await,
+            )?;
+        }
+        checks.push(if !orphans.is_empty() {
+            DiagnosticCheck::warning(
+                "orphaned-containers",
+                format!(
+                    "The containers of {:?} do not belong to properly deployed apps.",
+                    orphans
+                        .iter()
+                        .map(|(orphan, _)| orphan.to_string())
+                        .collect::<Vec<_>>()
+                ),
+            )
+            .with_suggested_repair(String::from(
+                "Review the orphans with `GET /api/admin/orphans` and remove them with `DELETE /api/admin/orphans`.",
+            ))
+        } else if !never_started.is_empty() {
+            DiagnosticCheck::warning(
+                "orphaned-containers",
+                format!(
+                    "The containers of {:?} have never been started.",
+                    never_started
+                        .iter()
+                        .map(|(orphan, _)| orphan.to_string())
+                        .collect::<Vec<_>>()
+                ),
+            )
+            .with_applied_repair(String::from(
+                "Deleted the containers that have never been started.",
+            ))
+        } else {
+            DiagnosticCheck::passed(
+                "orphaned-containers",
+                String::from("All containers belong to properly deployed apps."),
+            )
+        });
+
+        let app_names = self
+            .get_app_containers(None, None)
+            .await?
+            .into_iter()
+            .filter_map(|c| c.labels.get(APP_NAME_LABEL).cloned())
+            .collect::<HashSet<_>>();
+        let orphaned_networks = docker
+            .list_networks()
+            .await?
+            .into_iter()
+            .filter(|n| {
+                n.name
+                    .strip_suffix("-net")
+                    .map_or(false, |app_name| !app_names.contains(app_name))
+            })
+            .map(|n| n.name)
+            .collect::<Vec<_>>();
+        checks.push(if orphaned_networks.is_empty() {
+            DiagnosticCheck::passed(
+                "orphaned-networks",
+                String::from("All app networks belong to existing apps."),
+            )
+        } else {
+            DiagnosticCheck::warning(
+                "orphaned-networks",
+                format!(
+                    "The networks {:?} do not belong to any app.",
+                    orphaned_networks
+                ),
+            )
+            .with_suggested_repair(format!(
+                "Remove the networks with `docker network rm {}` if they have been created by PREvant.",
+                orphaned_networks.join(" ")
+            ))
+        });
+
+        Ok(checks)
+    }
+
+    async fn get_host_capacity(&self, _app_name: &String) -> Result<Option<HostCapacity>, Error> {
+        let containers = self.get_app_containers(None, None).await?.len();
+
+        // The memory and the disk can only be measured if PREvant runs on the Docker host.
+        let is_local_host = matches!(self.host, DockerHost::Unix(_));
+        let (free_memory, free_disk) = if is_local_host {
+            (free_memory(), free_disk_space("/"))
+        } else {
+            (None, None)
+        };
+
+        Ok(Some(HostCapacity::new(containers, free_memory, free_disk)))
+    }
+
+    async fn get_orphans(&self) -> Result<Vec<Orphan>, failure::Error> {
+        Ok(self
+            .find_orphans()
+            .await?
+            .into_iter()
+            .map(|(orphan, _)| orphan)
+            .collect())
+    }
+
+    async fn remove_orphans(&self, orphans: &[Orphan]) -> Result<Vec<Orphan>, failure::Error> {
+        let docker = self.docker();
+        let mut removed = Vec::new();
+        for (orphan, details) in self.find_orphans().await? {
+            if !orphans.contains(&orphan) {
+                continue;
+            }
+
+            if details.state.running {
+                not_found_to_none(stop(&docker, details.clone()).await)?;
+            }
+            if not_found_to_none(remove(&docker, details, self.config.cleanup().volumes()).await)?
+                .is_some()
+            {
+                removed.push(orphan);
+            }
+        }
+
+        // Without any remaining container, the network of the app is orphaned as well.
+        let app_names = removed
+            .iter()
+            .map(|orphan| orphan.app_name().clone())
+            .collect::<HashSet<_>>();
+        for app_name in app_names {
+            let remaining_containers = self
+                .get_containers(vec![label_filter(APP_NAME_LABEL, Some(&app_name))])
+                .await?;
+            if remaining_containers.is_empty() {
+                self.delete_network(&app_name).await?;
+            }
+        }
+
+        Ok(removed)
+    }
+
     async fn change_status(
         &self,
         app_name: &String,
@@ -740,11 +1431,8 @@ impl Infrastructure for DockerInfrastructure {
     ) -> Result<Option<Service>, failure::Error> {
         match self.get_app_container(app_name, service_name).await? {
             Some(container) => {
-                let docker = Docker::new();
-                let containers = docker.containers();
-                let c = containers.get(&container.id);
-
-                let details = c.inspect().await?;
+                let docker = self.docker();
+                let details = docker.inspect_container(&container.id).await?;
 
                 macro_rules! run_future_and_map_err {
                     ( $future:expr, $log_format:expr ) => {
@@ -769,12 +1457,18 @@ impl Infrastructure for DockerInfrastructure {
                 match status {
                     ServiceStatus::Running => {
                         if !details.state.running {
-                            run_future_and_map_err!(c.start(), "Could not start container: {}");
+                            run_future_and_map_err!(
+                                docker.start_container(&container.id),
+                                "Could not start container: {}"
+                            );
                         }
                     }
                     ServiceStatus::Paused => {
                         if details.state.running {
-                            run_future_and_map_err!(c.stop(None), "Could not pause container: {}");
+                            run_future_and_map_err!(
+                                docker.stop_container(&container.id, None),
+                                "Could not pause container: {}"
+                            );
                         }
                     }
                 }
@@ -795,6 +1489,73 @@ fn label_filter(label_name: &str, label_value: Option<&String>) -> ContainerFilt
     }
 }
 
+/// A sample of the Docker stats API. Docker reports the CPU usage as cumulative counters, hence
+/// the CPU percentage is computed from the difference to the previous sample that Docker includes
+/// in the response.
+struct ContainerStats {
+    stats: Stats,
+}
+
+impl ContainerStats {
+    fn into_service_stats(self, service_name: String) -> ServiceStats {
+        let cpu_usage = &self.stats.cpu_stats.cpu_usage;
+        let previous_cpu_usage = &self.stats.precpu_stats.cpu_usage;
+
+        let cpu_percentage = cpu_percentage(
+            cpu_usage
+                .total_usage
+                .saturating_sub(previous_cpu_usage.total_usage),
+            self.stats
+                .cpu_stats
+                .system_cpu_usage
+                .saturating_sub(self.stats.precpu_stats.system_cpu_usage),
+            self.stats
+                .cpu_stats
+                .online_cpus
+                .map_or(cpu_usage.percpu_usage.len(), |online_cpus| {
+                    online_cpus as usize
+                }),
+        );
+
+        let (network_rx_bytes, network_tx_bytes) =
+            self.stats
+                .networks
+                .values()
+                .fold((0, 0), |(rx_bytes, tx_bytes), network| {
+                    (rx_bytes + network.rx_bytes, tx_bytes + network.tx_bytes)
+                });
+
+        ServiceStats::new(
+            service_name,
+            cpu_percentage,
+            self.stats.memory_stats.usage,
+            self.stats.memory_stats.limit,
+            network_rx_bytes,
+            network_tx_bytes,
+        )
+    }
+}
+
+async fn container_stats(
+    docker: &DockerClient,
+    container: &ContainerInfo,
+) -> Result<Option<ContainerStats>, Error> {
+    trace!("Acquiring stats of container {}", container.id);
+
+    let stats = not_found_to_none(docker.container_stats(&container.id).await)?;
+    Ok(stats.map(|stats| ContainerStats { stats }))
+}
+
+/// Computes the CPU usage the same way `docker stats` does: the container's share of the
+/// consumed system CPU time, scaled by the number of CPUs.
+fn cpu_percentage(cpu_delta: u64, system_delta: u64, online_cpus: usize) -> f64 {
+    if cpu_delta == 0 || system_delta == 0 {
+        return 0.0;
+    }
+
+    (cpu_delta as f64 / system_delta as f64) * online_cpus.max(1) as f64 * 100.0
+}
+
 /// Helper function to map ShipLift 404 errors to None
 fn not_found_to_none<T>(result: Result<T, ShipLiftError>) -> Result<Option<T>, ShipLiftError> {
     match result {
@@ -805,36 +1566,141 @@ fn not_found_to_none<T>(result: Result<T, ShipLiftError>) -> Result<Option<T>, S
 }
 
 /// Helper function to pull images
-async fn pull(image: &str) -> Result<Vec<serde_json::Value>, ShipLiftError> {
-    let pull_options = PullOptions::builder().image(image).build();
+async fn pull(docker: &DockerClient, image: &str) -> Result<Vec<serde_json::Value>, ShipLiftError> {
+    docker.pull_image(image).await
+}
 
-    let docker = Docker::new();
-    let images = docker.images();
+/// Runs the Docker operation and retries it with an exponential backoff as long as it fails due to a
+/// transient cause, such as a timeout of the registry or an unavailable Docker daemon. If all
+/// attempts fail, the error is reported as `TransientInfrastructureError`.
+async fn with_retry<T, F, Fut>(
+    policy: &DockerRetryConfig,
+    operation: &str,
+    f: F,
+) -> Result<T, Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, ShipLiftError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match f().await {
+            Ok(result) => return Ok(result),
+            Err(err) => err,
+        };
 
-    images.pull(&pull_options).try_collect().await
+        if !is_transient(&err) {
+            return Err(err.into());
+        }
+        if attempt >= policy.max_attempts() {
+            return Err(TransientInfrastructureError::new(operation, attempt, &err).into());
+        }
+
+        let backoff = policy.backoff(attempt);
+        warn!(
+            "{} failed (attempt {}), retrying in {:?}: {}",
+            operation, attempt, backoff, err
+        );
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+/// Decides whether a failed Docker operation might succeed later. Connection problems and errors of
+/// the Docker daemon are transient unless the daemon reports that the image cannot be accessed at
+/// all, e.g. because it does not exist or the credentials are missing.
+fn is_transient(err: &ShipLiftError) -> bool {
+    match err {
+        ShipLiftError::Hyper(_) | ShipLiftError::IO(_) => true,
+        ShipLiftError::Fault { code, message } => {
+            let message = message.to_lowercase();
+            code.is_server_error()
+                && !["not found", "manifest unknown", "unauthorized", "denied"]
+                    .iter()
+                    .any(|fatal| message.contains(fatal))
+        }
+        _ => false,
+    }
+}
+
+/// Reads the memory that is available for new containers without swapping from `/proc/meminfo`.
+fn free_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_available_memory(&meminfo)
+}
+
+fn parse_available_memory(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kilobytes| kilobytes.trim().parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+/// Resolves the disk space of the file system containing the path that is available to
+/// unprivileged users, such as the containers.
+// The field types of statvfs differ between the platforms, hence the casts.
+#[allow(clippy::unnecessary_cast)]
+fn free_disk_space(path: &str) -> Option<u64> {
+    let path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        warn!(
+            "Cannot resolve the free disk space: {}",
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+fn network_name(app_name: &str) -> String {
+    format!("{}-net", app_name)
 }
 
 /// Helper function to stop containers with the aid of futures::future::join_all
-async fn stop(details: ContainerDetails) -> Result<ContainerDetails, ShipLiftError> {
-    let docker = Docker::new();
-    let containers = docker.containers();
-    containers.get(&details.id).stop(None).await?;
+async fn stop(
+    docker: &DockerClient,
+    details: ContainerDetails,
+) -> Result<ContainerDetails, ShipLiftError> {
+    docker.stop_container(&details.id, None).await?;
     Ok(details)
 }
 
 /// Helper function to delete containers with the aid of futures::future::join_all
-async fn delete(details: ContainerDetails) -> Result<ContainerDetails, ShipLiftError> {
-    let docker = Docker::new();
-    let containers = docker.containers();
-    containers.get(&details.id).delete().await?;
+async fn delete(
+    docker: &DockerClient,
+    details: ContainerDetails,
+) -> Result<ContainerDetails, ShipLiftError> {
+    docker.delete_container(&details.id).await?;
+    Ok(details)
+}
+
+/// Helper function to delete containers, optionally with their anonymous volumes, with the aid of
+/// futures::future::join_all
+async fn remove(
+    docker: &DockerClient,
+    details: ContainerDetails,
+    remove_volumes: bool,
+) -> Result<ContainerDetails, ShipLiftError> {
+    docker
+        .remove_container(
+            &details.id,
+            &RmContainerOptions::builder()
+                .volumes(remove_volumes)
+                .build(),
+        )
+        .await?;
     Ok(details)
 }
 
 /// Helper function to inspect containers with the aid of futures::future::join_all
-async fn inspect(container: ContainerInfo) -> Result<ContainerDetails, ShipLiftError> {
-    let docker = Docker::new();
-    let containers = docker.containers();
-    containers.get(&container.id).inspect().await
+async fn inspect(
+    docker: &DockerClient,
+    container: ContainerInfo,
+) -> Result<ContainerDetails, ShipLiftError> {
+    docker.inspect_container(&container.id).await
 }
 
 fn find_port(
@@ -895,15 +1761,32 @@ impl TryFrom<&ContainerDetails> for Service {
             ServiceStatus::Paused
         };
 
+        // Docker keeps the exit code of the last run, thus, the exit code is meaningful as soon as
+        // the container has exited once, even if it is running again due to its restart policy.
+        let exit_code = if container_details.restart_count > 0 || !container_details.state.running {
+            Some(container_details.state.exit_code)
+        } else {
+            None
+        };
+
         let mut builder = ServiceBuilder::new()
             .id(container_details.id.clone())
             .app_name(app_name.clone())
             .config(ServiceConfig::try_from(container_details)?)
             .service_status(status)
-            .started_at(started_at);
-
-        if !container_details.network_settings.ip_address.is_empty() {
-            let addr = IpAddr::from_str(&container_details.network_settings.ip_address)?;
+            .started_at(started_at)
+            .restart_count(container_details.restart_count)
+            .exit_code(exit_code);
+
+        let ip_address = container_details
+            .network_settings
+            .networks
+            .get(&network_name(app_name))
+            .map(|network| &network.ip_address)
+            .filter(|ip_address| !ip_address.is_empty())
+            .unwrap_or(&container_details.network_settings.ip_address);
+        if !ip_address.is_empty() {
+            let addr = IpAddr::from_str(ip_address)?;
             let port = find_port(container_details, labels)?;
             builder = builder.endpoint(addr, port);
         }
@@ -965,6 +1848,84 @@ impl TryFrom<&ContainerDetails> for ServiceConfig {
             config.set_env(Some(env));
         }
 
+        if let Some(owner) = labels.map(|labels| labels.get(OWNER_LABEL)).flatten() {
+            config.set_owner(Some(owner.clone()));
+        }
+
+        if let Some(tickets) = labels.map(|labels| labels.get(TICKETS_LABEL)).flatten() {
+            config.set_tickets(tickets_from_label_value(tickets));
+        }
+
+        if let Some(skipped_companions) = labels
+            .map(|labels| labels.get(SKIPPED_COMPANIONS_LABEL))
+            .flatten()
+        {
+            config.set_skipped_companions(skipped_companions_from_label_value(skipped_companions));
+        }
+
+        config.set_companion_definition(
+            labels
+                .map(|labels| labels.get(COMPANION_DEFINITION_LABEL))
+                .flatten()
+                .cloned(),
+        );
+
+        config.set_basic_auth(
+            labels
+                .map(|labels| labels.get(BASIC_AUTH_LABEL))
+                .flatten()
+                .cloned(),
+        );
+
+        if let Some(ports) = labels.map(|labels| labels.get(PORTS_LABEL)).flatten() {
+            let ports = serde_json::from_str::<Vec<Port>>(ports).map_err(|err| {
+                DockerInfrastructureError::UnexpectedError {
+                    internal_message: err.to_string(),
+                }
+            })?;
+            config.set_ports(ports);
+        }
+
+        if let Some(routing) = labels.map(|labels| labels.get(ROUTING_LABEL)).flatten() {
+            let routing = serde_json::from_str::<Routing>(routing).map_err(|err| {
+                DockerInfrastructureError::UnexpectedError {
+                    internal_message: err.to_string(),
+                }
+            })?;
+            config.set_routing(routing);
+        }
+
+        config.set_deployed_fingerprint(
+            labels
+                .map(|labels| labels.get(FINGERPRINT_LABEL))
+                .flatten()
+                .cloned(),
+        );
+
+        if let Some(depends_on) = labels.map(|labels| labels.get(DEPENDS_ON_LABEL)).flatten() {
+            config.set_depends_on(depends_on_from_label_value(depends_on));
+        }
+
+        config.set_deployed_image_digest(Some(container_details.image.clone()));
+        config.set_replicated_from(
+            labels
+                .map(|labels| labels.get(REPLICATED_FROM_LABEL))
+                .flatten()
+                .cloned(),
+            labels
+                .map(|labels| labels.get(REPLICATED_IMAGE_DIGEST_LABEL))
+                .flatten()
+                .cloned(),
+        );
+
+        if let Some(user_labels) = labels.map(|labels| labels.get(USER_LABELS_LABEL)).flatten() {
+            let user_labels = serde_json::from_str::<BTreeMap<String, String>>(user_labels)
+                .map_err(|err| DockerInfrastructureError::UnexpectedError {
+                    internal_message: err.to_string(),
+                })?;
+            config.set_labels(Some(user_labels));
+        }
+
         Ok(config)
     }
 }
@@ -1021,10 +1982,19 @@ impl From<ServiceBuilderError> for DockerInfrastructureError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::infrastructure::TraefikIngressProvider;
     use crate::models::{Environment, EnvironmentVariable};
     use crate::sc;
     use secstr::SecUtf8;
 
+    fn infrastructure() -> DockerInfrastructure {
+        DockerInfrastructure::new(
+            DockerRuntimeConfig::default(),
+            Box::new(TraefikIngressProvider),
+        )
+        .unwrap()
+    }
+
     macro_rules! container_details {
         ($id:expr, $app_name:expr, $service_name:expr, $image:expr, $container_type:expr, $($l_key:expr => $l_value:expr),* ) => {{
             let mut labels = std::collections::HashMap::new();
@@ -1119,12 +2089,148 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn should_find_orphans() {
+        let instance = container_details!(
+            "instance".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            Some(String::from("instance")),
+        );
+        let mut duplicate = container_details!(
+            "duplicate".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            Some(String::from("instance")),
+        );
+        duplicate.created = instance.created - chrono::Duration::minutes(5);
+        let mut never_started = container_details!(
+            "never-started".to_string(),
+            Some(String::from("master")),
+            Some(String::from("db")),
+            Some(String::from("mariadb")),
+            Some(String::from("instance")),
+        );
+        never_started.state.status = String::from("created");
+        let companion = container_details!(
+            "companion".to_string(),
+            Some(String::from("feature-a")),
+            Some(String::from("openid")),
+            Some(String::from("openid")),
+            Some(String::from("app-companion")),
+        );
+        let invalid = container_details!(
+            "invalid".to_string(),
+            Some(String::from("feature-b")),
+            None,
+            Some(String::from("nginx")),
+            None,
+        );
+
+        let mut orphans = orphans_of(vec![instance, duplicate, never_started, companion, invalid])
+            .into_iter()
+            .map(|(orphan, _)| orphan)
+            .collect::<Vec<_>>();
+        orphans.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+
+        assert_eq!(
+            orphans,
+            vec![
+                Orphan::new(
+                    String::from("companion"),
+                    String::from("feature-a"),
+                    Some(String::from("openid")),
+                    OrphanReason::CompanionWithoutServices
+                ),
+                Orphan::new(
+                    String::from("invalid"),
+                    String::from("feature-b"),
+                    None,
+                    OrphanReason::InvalidLabels
+                ),
+                Orphan::new(
+                    String::from("never-started"),
+                    String::from("master"),
+                    Some(String::from("db")),
+                    OrphanReason::NeverStarted
+                ),
+                Orphan::new(
+                    String::from("duplicate"),
+                    String::from("master"),
+                    Some(String::from("nginx")),
+                    OrphanReason::Duplicate
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_consider_daemon_errors_as_transient() {
+        assert!(is_transient(&ShipLiftError::Fault {
+            code: hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            message: String::from(
+                "Get https://registry-1.docker.io/v2/: net/http: TLS handshake timeout"
+            ),
+        }));
+        assert!(!is_transient(&ShipLiftError::Fault {
+            code: hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            message: String::from("manifest for nginx:unknown not found: manifest unknown"),
+        }));
+        assert!(!is_transient(&ShipLiftError::Fault {
+            code: hyper::StatusCode::CONFLICT,
+            message: String::from("Conflict"),
+        }));
+    }
+
+    #[tokio::test]
+    async fn should_retry_transient_errors() {
+        let policy = toml::de::from_str::<DockerRetryConfig>("initialBackoffMillis = 1").unwrap();
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let attempts_ref = &attempts;
+
+        let result = with_retry(&policy, "Testing", move || async move {
+            if attempts_ref.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 {
+                Err(ShipLiftError::Fault {
+                    code: hyper::StatusCode::SERVICE_UNAVAILABLE,
+                    message: String::from("Service Unavailable"),
+                })
+            } else {
+                Ok(42)
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_give_up_after_max_attempts() {
+        let policy = toml::de::from_str::<DockerRetryConfig>("initialBackoffMillis = 1").unwrap();
+
+        let result: Result<(), Error> = with_retry(&policy, "Testing", || async {
+            Err(ShipLiftError::Fault {
+                code: hyper::StatusCode::SERVICE_UNAVAILABLE,
+                message: String::from("Service Unavailable"),
+            })
+        })
+        .await;
+
+        assert!(result
+            .unwrap_err()
+            .downcast_ref::<TransientInfrastructureError>()
+            .is_some());
+    }
+
     #[test]
     fn should_create_container_options() {
         let config = sc!("db", "mariadb:10.3.17");
 
-        let options = DockerInfrastructure::create_container_options(
+        let options = infrastructure().create_container_options(
             &String::from("master"),
+            &String::from("master-net"),
             &config,
             &ContainerConfig::default(),
         );
@@ -1135,6 +2241,7 @@ mod tests {
             serde_json::json!({
               "name": null,
               "params": {
+                "HostConfig.NetworkMode": "master-net",
                 "HostConfig.RestartPolicy.Name": "always",
                 "Image": "docker.io/library/mariadb:10.3.17",
                 "Labels": {
@@ -1142,6 +2249,7 @@ mod tests {
                   "com.aixigo.preview.servant.container-type": "instance",
                   "com.aixigo.preview.servant.service-name": "db",
                   "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                  "com.aixigo.preview.servant.config-fingerprint": config.fingerprint(),
                   "traefik.frontend.rule": "PathPrefixStrip: /master/db/; PathPrefix:/master/db/;"
                 }
               }
@@ -1157,8 +2265,9 @@ mod tests {
             SecUtf8::from("example"),
         )])));
 
-        let options = DockerInfrastructure::create_container_options(
+        let options = infrastructure().create_container_options(
             &String::from("master"),
+            &String::from("master-net"),
             &config,
             &ContainerConfig::default(),
         );
@@ -1169,6 +2278,7 @@ mod tests {
             serde_json::json!({
               "name": null,
               "params": {
+                "HostConfig.NetworkMode": "master-net",
                 "HostConfig.RestartPolicy.Name": "always",
                 "Image": "docker.io/library/mariadb:10.3.17",
                 "Labels": {
@@ -1176,6 +2286,7 @@ mod tests {
                   "com.aixigo.preview.servant.container-type": "instance",
                   "com.aixigo.preview.servant.service-name": "db",
                   "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                  "com.aixigo.preview.servant.config-fingerprint": config.fingerprint(),
                   "traefik.frontend.rule": "PathPrefixStrip: /master/db/; PathPrefix:/master/db/;"
                 },
                 "Env": [
@@ -1196,8 +2307,9 @@ mod tests {
             ),
         ])));
 
-        let options = DockerInfrastructure::create_container_options(
+        let options = infrastructure().create_container_options(
             &String::from("master"),
+            &String::from("master-net"),
             &config,
             &ContainerConfig::default(),
         );
@@ -1208,6 +2320,7 @@ mod tests {
             serde_json::json!({
                 "name": null,
                 "params": {
+                  "HostConfig.NetworkMode": "master-net",
                   "HostConfig.RestartPolicy.Name": "always",
                   "Image": "docker.io/library/mariadb:10.3.17",
                   "Labels": {
@@ -1215,6 +2328,7 @@ mod tests {
                     "com.aixigo.preview.servant.container-type": "instance",
                     "com.aixigo.preview.servant.service-name": "db",
                     "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                    "com.aixigo.preview.servant.config-fingerprint": config.fingerprint(),
                     "com.aixigo.preview.servant.replicated-env": serde_json::json!({
                       "MYSQL_ROOT_PASSWORD": {
                         "value": "example",
@@ -1232,6 +2346,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_container_options_with_ports() {
+        let mut config = sc!("backend", "backend:latest");
+        config.set_ports(vec![
+            Port::new(String::from("http"), 8080),
+            Port::new(String::from("debug"), 5005),
+        ]);
+
+        let options = infrastructure().create_container_options(
+            &String::from("master"),
+            &String::from("master-net"),
+            &config,
+            &ContainerConfig::default(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "Labels": {
+                  "com.aixigo.preview.servant.ports": serde_json::json!([
+                    { "name": "http", "port": 8080 },
+                    { "name": "debug", "port": 5005 }
+                  ]).to_string(),
+                  "traefik.port": "8080"
+                }
+              }
+            })
+        );
+    }
+
     #[test]
     fn should_create_service_config_from_container_details() {
         let details = container_details!(
@@ -1253,6 +2399,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_service_with_restart_count_and_exit_code() {
+        let mut details = container_details!(
+            "some-random-id".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            None,
+        );
+        details.restart_count = 3;
+        details.state.running = true;
+        details.state.exit_code = 137;
+
+        let service = Service::try_from(&details).unwrap();
+
+        assert_eq!(service.restart_count(), Some(3));
+        assert_eq!(service.exit_code(), Some(137));
+    }
+
     #[test]
     fn should_not_create_service_config_from_container_details_with_invalid_image_information() {
         let details = container_details!(
@@ -1268,7 +2433,9 @@ mod tests {
             error,
             DockerInfrastructureError::UnexpectedImageFormat {
                 img: String::from("\n"),
-                err: String::from("Invalid image: \n")
+                err: String::from(
+                    "Invalid image \"\\n\": the repository must consist of lowercase alphanumeric components separated by slashes"
+                )
             }
         );
     }
@@ -1315,4 +2482,169 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn should_create_container_options_with_user_defined_labels() {
+        let mut labels = BTreeMap::new();
+        labels.insert(String::from("com.example.team"), String::from("platform"));
+        labels.insert(String::from(APP_NAME_LABEL), String::from("other-app"));
+        let mut config = sc!("backend", "backend:latest");
+        config.set_labels(Some(labels));
+
+        let options = infrastructure().create_container_options(
+            &String::from("master"),
+            &String::from("master-net"),
+            &config,
+            &ContainerConfig::default(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "Labels": {
+                  "com.example.team": "platform",
+                  "com.aixigo.preview.servant.app-name": "master"
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_service_config_from_container_details_with_user_defined_labels() {
+        let details = container_details!(
+            "some-random-id".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            None,
+            String::from(USER_LABELS_LABEL) => serde_json::json!({ "com.example.team": "platform" }).to_string()
+        );
+
+        let service = Service::try_from(&details).unwrap();
+
+        assert_eq!(
+            service.config().labels().unwrap().get("com.example.team"),
+            Some(&String::from("platform"))
+        );
+    }
+
+    #[test]
+    fn should_create_service_config_from_container_details_with_replication_source() {
+        let details = container_details!(
+            "some-random-id".to_string(),
+            Some(String::from("feature-xyz")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            Some(String::from("replica")),
+            String::from(REPLICATED_FROM_LABEL) => String::from("master"),
+            String::from(REPLICATED_IMAGE_DIGEST_LABEL) => String::from("sha256:abcd")
+        );
+
+        let service = Service::try_from(&details).unwrap();
+
+        assert_eq!(
+            service.config().replicated_from(),
+            Some(&String::from("master"))
+        );
+        assert_eq!(
+            service.config().replicated_image_digest(),
+            Some(&String::from("sha256:abcd"))
+        );
+        assert_eq!(
+            service.config().deployed_image_digest(),
+            Some(&String::from(
+                "sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+            ))
+        );
+    }
+
+    #[test]
+    fn should_not_create_infrastructure_without_client_certificates() {
+        let config = toml::de::from_str::<DockerRuntimeConfig>(
+            r#"
+            host = 'tcp://docker.example.com:2376'
+            certPath = '/etc/prevant/docker'
+            tlsVerify = true
+            "#,
+        )
+        .unwrap();
+
+        let result = DockerInfrastructure::new(config, Box::new(TraefikIngressProvider));
+
+        assert!(matches!(
+            result,
+            Err(DockerInfrastructureError::CannotCreateClient { host, .. })
+                if host == "tcp://docker.example.com:2376"
+        ));
+    }
+
+    #[test]
+    fn should_parse_available_memory() {
+        let meminfo = r#"MemTotal:       16314812 kB
+MemFree:         1022896 kB
+MemAvailable:    8388608 kB
+Buffers:          330012 kB"#;
+
+        assert_eq!(
+            parse_available_memory(meminfo),
+            Some(8 * 1024 * 1024 * 1024)
+        );
+        assert_eq!(parse_available_memory("MemTotal: 16314812 kB"), None);
+    }
+
+    #[test]
+    fn should_compute_cpu_percentage_with_online_cpus() {
+        let stats = serde_json::from_value::<Stats>(serde_json::json!({
+            "cpu_stats": {
+                "cpu_usage": { "total_usage": 150 },
+                "system_cpu_usage": 1400,
+                "online_cpus": 4
+            },
+            "precpu_stats": {
+                "cpu_usage": { "total_usage": 100 },
+                "system_cpu_usage": 1000
+            },
+            "memory_stats": { "usage": 1024, "limit": 4096 }
+        }))
+        .unwrap();
+
+        let service_stats = ContainerStats { stats }.into_service_stats(String::from("db"));
+
+        assert_eq!(
+            service_stats,
+            ServiceStats::new(String::from("db"), 50.0, 1024, 4096, 0, 0)
+        );
+    }
+
+    #[test]
+    fn should_compute_cpu_percentage_from_per_cpu_usage_without_online_cpus() {
+        let stats = serde_json::from_value::<Stats>(serde_json::json!({
+            "cpu_stats": {
+                "cpu_usage": { "total_usage": 150, "percpu_usage": [75, 75] },
+                "system_cpu_usage": 1400
+            },
+            "precpu_stats": {
+                "cpu_usage": { "total_usage": 100, "percpu_usage": [50, 50] },
+                "system_cpu_usage": 1000
+            }
+        }))
+        .unwrap();
+
+        let service_stats = ContainerStats { stats }.into_service_stats(String::from("db"));
+
+        assert_eq!(
+            service_stats,
+            ServiceStats::new(String::from("db"), 25.0, 0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn should_compute_cpu_percentage_from_deltas() {
+        assert_eq!(cpu_percentage(50, 400, 4), 50.0);
+        assert_eq!(cpu_percentage(0, 400, 4), 0.0);
+        assert_eq!(cpu_percentage(50, 0, 4), 0.0);
+    }
 }
@@ -0,0 +1,668 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::DockerHost;
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, Response};
+use hyper_openssl::HttpsConnector;
+use hyperlocal::{UnixClientExt, UnixConnector};
+use openssl::error::ErrorStack;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use serde::de::DeserializeOwned;
+use shiplift::container::{ContainerCreateInfo, ContainerDetails, ContainerInfo};
+use shiplift::errors::Error as ShipLiftError;
+use shiplift::tty::TtyChunk;
+use shiplift::{
+    ContainerConnectionOptions, ContainerListOptions, ContainerOptions, ExecContainerOptions,
+    LogsOptions, PullOptions, RmContainerOptions,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A client of the [Docker Engine API](https://docs.docker.com/engine/api/v1.41/). In contrast
+/// to shiplift, which resolves the TLS client certificates from the environment variables of the
+/// Docker CLI, the connection is derived from the configuration only. The client reuses the
+/// option builders and the response types of shiplift.
+#[derive(Clone)]
+pub struct DockerClient {
+    transport: Transport,
+}
+
+#[derive(Clone)]
+enum Transport {
+    Unix {
+        client: Client<UnixConnector>,
+        socket_path: PathBuf,
+    },
+    Tcp {
+        client: Client<HttpConnector>,
+        base_url: String,
+    },
+    EncryptedTcp {
+        client: Client<HttpsConnector<HttpConnector>>,
+        base_url: String,
+    },
+}
+
+/// The network as listed by `GET /networks`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NetworkInfo {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+}
+
+/// A sample of `GET /containers/{id}/stats`, including the previous sample of the CPU usage.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Stats {
+    pub cpu_stats: CpuStats,
+    pub precpu_stats: CpuStats,
+    #[serde(default)]
+    pub memory_stats: MemoryStats,
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkStats>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CpuStats {
+    #[serde(default)]
+    pub cpu_usage: CpuUsage,
+    #[serde(default)]
+    pub system_cpu_usage: u64,
+    /// The number of CPUs available to the container. On hosts with cgroup v2, this is the only
+    /// source of the number of CPUs because Docker does not report `percpu_usage` there.
+    pub online_cpus: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CpuUsage {
+    #[serde(default)]
+    pub total_usage: u64,
+    #[serde(default)]
+    pub percpu_usage: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MemoryStats {
+    #[serde(default)]
+    pub usage: u64,
+    #[serde(default)]
+    pub limit: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NetworkStats {
+    #[serde(default)]
+    pub rx_bytes: u64,
+    #[serde(default)]
+    pub tx_bytes: u64,
+}
+
+#[derive(Debug, Fail)]
+pub enum DockerClientError {
+    #[fail(
+        display = "The certificate of the Docker host cannot be verified without a certificate path containing ca.pem."
+    )]
+    MissingCertificateAuthority,
+    #[fail(display = "Cannot set up TLS: {}", internal_message)]
+    TlsError { internal_message: String },
+}
+
+impl From<ErrorStack> for DockerClientError {
+    fn from(err: ErrorStack) -> Self {
+        DockerClientError::TlsError {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IdResponse {
+    #[serde(rename = "Id")]
+    id: String,
+}
+
+impl DockerClient {
+    /// Creates a client for the host. If there is a `cert_path`, the TCP connections are secured
+    /// with TLS, authenticated with the client certificate `cert.pem` and its key `key.pem` if
+    /// present, and, if `tls_verify` is `true`, the certificate of the host is verified against
+    /// `ca.pem`. Verifying the host without a `cert_path` is refused instead of silently falling
+    /// back to plain HTTP.
+    pub fn new(
+        host: &DockerHost,
+        cert_path: Option<&Path>,
+        tls_verify: bool,
+    ) -> Result<DockerClient, DockerClientError> {
+        let transport = match (host, cert_path) {
+            (DockerHost::Unix(socket_path), _) => Transport::Unix {
+                client: Client::unix(),
+                socket_path: socket_path.clone(),
+            },
+            (DockerHost::Tcp { .. }, None) if tls_verify => {
+                return Err(DockerClientError::MissingCertificateAuthority)
+            }
+            (DockerHost::Tcp { host, port }, None) => Transport::Tcp {
+                client: Client::builder().build(HttpConnector::new()),
+                base_url: format!("http://{}:{}", host, port),
+            },
+            (DockerHost::Tcp { host, port }, Some(cert_path)) => {
+                let mut ssl = SslConnector::builder(SslMethod::tls())?;
+                // Without a client certificate, the host only authenticates itself, e.g. if the
+                // access to the Docker API is restricted otherwise.
+                let client_certificate = cert_path.join("cert.pem");
+                if client_certificate.exists() {
+                    ssl.set_certificate_file(client_certificate, SslFiletype::PEM)?;
+                    ssl.set_private_key_file(cert_path.join("key.pem"), SslFiletype::PEM)?;
+                }
+                if tls_verify {
+                    ssl.set_ca_file(cert_path.join("ca.pem"))?;
+                } else {
+                    ssl.set_verify(SslVerifyMode::NONE);
+                }
+
+                let mut http = HttpConnector::new();
+                http.enforce_http(false);
+                Transport::EncryptedTcp {
+                    client: Client::builder().build(HttpsConnector::with_connector(http, ssl)?),
+                    base_url: format!("https://{}:{}", host, port),
+                }
+            }
+        };
+
+        Ok(DockerClient { transport })
+    }
+
+    pub async fn list_containers(
+        &self,
+        options: &ContainerListOptions,
+    ) -> Result<Vec<ContainerInfo>, ShipLiftError> {
+        self.get_json(&with_query("/containers/json", options.serialize()))
+            .await
+    }
+
+    pub async fn inspect_container(&self, id: &str) -> Result<ContainerDetails, ShipLiftError> {
+        self.get_json(&format!("/containers/{}/json", id)).await
+    }
+
+    pub async fn create_container(
+        &self,
+        options: &ContainerOptions,
+    ) -> Result<ContainerCreateInfo, ShipLiftError> {
+        let query = options.name.as_ref().map(|name| {
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("name", name)
+                .finish()
+        });
+        self.post_json(
+            &with_query("/containers/create", query),
+            Some(options.serialize()?),
+        )
+        .await
+    }
+
+    pub async fn start_container(&self, id: &str) -> Result<(), ShipLiftError> {
+        self.post(&format!("/containers/{}/start", id), None).await
+    }
+
+    pub async fn stop_container(
+        &self,
+        id: &str,
+        wait: Option<Duration>,
+    ) -> Result<(), ShipLiftError> {
+        let query = wait.map(|wait| format!("t={}", wait.as_secs()));
+        self.post(
+            &with_query(&format!("/containers/{}/stop", id), query),
+            None,
+        )
+        .await
+    }
+
+    pub async fn delete_container(&self, id: &str) -> Result<(), ShipLiftError> {
+        self.request(Method::DELETE, &format!("/containers/{}", id), None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_container(
+        &self,
+        id: &str,
+        options: &RmContainerOptions,
+    ) -> Result<(), ShipLiftError> {
+        self.request(
+            Method::DELETE,
+            &with_query(&format!("/containers/{}", id), options.serialize()),
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the log lines of a container that has been created without a TTY.
+    pub async fn container_logs(
+        &self,
+        id: &str,
+        options: &LogsOptions,
+    ) -> Result<Vec<TtyChunk>, ShipLiftError> {
+        let response = self
+            .request(
+                Method::GET,
+                &with_query(&format!("/containers/{}/logs", id), options.serialize()),
+                None,
+            )
+            .await?;
+        let mut buffer = hyper::body::to_bytes(response.into_body()).await?.to_vec();
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = next_tty_chunk(&mut buffer) {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    /// Executes the command in the container and streams its output while the command is running.
+    pub fn exec(
+        &self,
+        id: &str,
+        options: &ExecContainerOptions,
+    ) -> BoxStream<'static, Result<TtyChunk, ShipLiftError>> {
+        let client = self.clone();
+        let endpoint = format!("/containers/{}/exec", id);
+        let payload = options.serialize();
+        futures::stream::once(async move {
+            let exec: IdResponse = client.post_json(&endpoint, Some(payload?)).await?;
+            let response = client
+                .request(
+                    Method::POST,
+                    &format!("/exec/{}/start", exec.id),
+                    Some(json(String::from(r#"{"Detach":false,"Tty":false}"#))),
+                )
+                .await?;
+            Ok::<_, ShipLiftError>(tty_chunks(response.into_body()))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    pub async fn container_stats(&self, id: &str) -> Result<Stats, ShipLiftError> {
+        self.get_json(&format!("/containers/{}/stats?stream=false", id))
+            .await
+    }
+
+    /// Copies the file into the container, e.g. before the container will be started.
+    pub async fn copy_file_into(
+        &self,
+        id: &str,
+        path: &Path,
+        bytes: &[u8],
+    ) -> Result<(), ShipLiftError> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o0644);
+
+        let mut archive = tar::Builder::new(Vec::new());
+        archive.append_data(&mut header, path.iter().skip(1).collect::<PathBuf>(), bytes)?;
+        let archive = archive.into_inner()?;
+
+        self.request(
+            Method::PUT,
+            &format!("/containers/{}/archive?path=/", id),
+            Some(("application/x-tar", Body::from(archive))),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Pulls the image and returns the progress messages of the Docker daemon.
+    pub async fn pull_image(&self, image: &str) -> Result<Vec<serde_json::Value>, ShipLiftError> {
+        let options = PullOptions::builder().image(image).build();
+        let response = self
+            .request(
+                Method::POST,
+                &with_query("/images/create", options.serialize()),
+                None,
+            )
+            .await?;
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        serde_json::Deserializer::from_slice(&body)
+            .into_iter::<serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ShipLiftError::from)
+    }
+
+    pub async fn delete_image(&self, image: &str) -> Result<Vec<serde_json::Value>, ShipLiftError> {
+        let response = self
+            .request(Method::DELETE, &format!("/images/{}", image), None)
+            .await?;
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    pub async fn list_networks(&self) -> Result<Vec<NetworkInfo>, ShipLiftError> {
+        self.get_json("/networks").await
+    }
+
+    /// Creates a network and returns its id. The containers of an internal network cannot access
+    /// the internet.
+    pub async fn create_network(
+        &self,
+        name: &str,
+        internal: bool,
+    ) -> Result<String, ShipLiftError> {
+        let payload = serde_json::json!({
+            "Name": name,
+            "Internal": internal,
+            "CheckDuplicate": true,
+        });
+        let network: IdResponse = self
+            .post_json("/networks/create", Some(payload.to_string()))
+            .await?;
+        Ok(network.id)
+    }
+
+    pub async fn connect_network(
+        &self,
+        network_id: &str,
+        options: &ContainerConnectionOptions,
+    ) -> Result<(), ShipLiftError> {
+        self.post(
+            &format!("/networks/{}/connect", network_id),
+            Some(options.serialize()?),
+        )
+        .await
+    }
+
+    pub async fn disconnect_network(
+        &self,
+        network_id: &str,
+        options: &ContainerConnectionOptions,
+    ) -> Result<(), ShipLiftError> {
+        self.post(
+            &format!("/networks/{}/disconnect", network_id),
+            Some(options.serialize()?),
+        )
+        .await
+    }
+
+    pub async fn delete_network(&self, network_id: &str) -> Result<(), ShipLiftError> {
+        self.request(Method::DELETE, &format!("/networks/{}", network_id), None)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, ShipLiftError> {
+        let response = self.request(Method::GET, endpoint, None).await?;
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    async fn post_json<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        payload: Option<String>,
+    ) -> Result<T, ShipLiftError> {
+        let response = self
+            .request(Method::POST, endpoint, payload.map(json))
+            .await?;
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    async fn post(&self, endpoint: &str, payload: Option<String>) -> Result<(), ShipLiftError> {
+        self.request(Method::POST, endpoint, payload.map(json))
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the request and maps the responses with an error status to `ShipLiftError::Fault`,
+    /// e.g. `304` if a container is already in the desired state.
+    async fn request(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<(&'static str, Body)>,
+    ) -> Result<Response<Body>, ShipLiftError> {
+        let mut request = Request::builder().method(method);
+        let body = match body {
+            Some((content_type, body)) => {
+                request = request.header(CONTENT_TYPE, content_type);
+                body
+            }
+            None => Body::empty(),
+        };
+
+        let response = match &self.transport {
+            Transport::Unix {
+                client,
+                socket_path,
+            } => {
+                let uri: hyper::Uri = hyperlocal::Uri::new(socket_path, endpoint).into();
+                client.request(request.uri(uri).body(body)?).await?
+            }
+            Transport::Tcp { client, base_url } => {
+                let uri = format!("{}{}", base_url, endpoint);
+                client.request(request.uri(uri).body(body)?).await?
+            }
+            Transport::EncryptedTcp { client, base_url } => {
+                let uri = format!("{}{}", base_url, endpoint);
+                client.request(request.uri(uri).body(body)?).await?
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        let message = serde_json::from_slice::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|error| error["message"].as_str().map(String::from))
+            .unwrap_or_else(|| {
+                status
+                    .canonical_reason()
+                    .unwrap_or("unknown error code")
+                    .to_string()
+            });
+        Err(ShipLiftError::Fault {
+            code: status,
+            message,
+        })
+    }
+}
+
+fn json(payload: String) -> (&'static str, Body) {
+    ("application/json", Body::from(payload))
+}
+
+fn with_query(endpoint: &str, query: Option<String>) -> String {
+    match query {
+        Some(query) if !query.is_empty() => {
+            let separator = if endpoint.contains('?') { '&' } else { '?' };
+            format!("{}{}{}", endpoint, separator, query)
+        }
+        _ => endpoint.to_string(),
+    }
+}
+
+/// Decodes the output streams that Docker multiplexes into the response body if the container
+/// runs without a TTY.
+fn tty_chunks(body: Body) -> impl Stream<Item = Result<TtyChunk, ShipLiftError>> + Send {
+    futures::stream::unfold((body, Vec::new()), |(mut body, mut buffer)| async move {
+        loop {
+            if let Some(chunk) = next_tty_chunk(&mut buffer) {
+                return Some((Ok(chunk), (body, buffer)));
+            }
+
+            match body.data().await {
+                Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                Some(Err(err)) => return Some((Err(ShipLiftError::from(err)), (body, buffer))),
+                None => return None,
+            }
+        }
+    })
+}
+
+/// Takes the next complete frame out of the buffer. Each frame starts with a header of eight
+/// bytes: the stream type, three bytes of padding, and the size of the payload (big endian).
+fn next_tty_chunk(buffer: &mut Vec<u8>) -> Option<TtyChunk> {
+    if buffer.len() < 8 {
+        return None;
+    }
+
+    let size = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if buffer.len() < 8 + size {
+        return None;
+    }
+
+    let payload = buffer[8..8 + size].to_vec();
+    let chunk = match buffer[0] {
+        0 => TtyChunk::StdIn(payload),
+        2 => TtyChunk::StdErr(payload),
+        _ => TtyChunk::StdOut(payload),
+    };
+    buffer.drain(..8 + size);
+    Some(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(stream: u8, payload: &str) -> Vec<u8> {
+        let mut frame = vec![stream, 0, 0, 0];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(payload.as_bytes());
+        frame
+    }
+
+    #[test]
+    fn should_demultiplex_tty_chunks() {
+        let mut buffer = frame(1, "Hello");
+        buffer.extend(frame(2, "World"));
+        buffer.extend(&frame(1, "incomplete")[..6]);
+
+        let stdout = next_tty_chunk(&mut buffer);
+        let stderr = next_tty_chunk(&mut buffer);
+
+        assert!(matches!(stdout, Some(TtyChunk::StdOut(bytes)) if bytes == b"Hello"));
+        assert!(matches!(stderr, Some(TtyChunk::StdErr(bytes)) if bytes == b"World"));
+        assert!(next_tty_chunk(&mut buffer).is_none());
+        assert_eq!(buffer.len(), 6);
+    }
+
+    #[test]
+    fn should_append_query() {
+        assert_eq!(
+            with_query("/containers/json", Some(String::from("all=true"))),
+            "/containers/json?all=true"
+        );
+        assert_eq!(
+            with_query("/containers/abc/stop", None),
+            "/containers/abc/stop"
+        );
+    }
+
+    #[test]
+    fn should_not_verify_host_without_cert_path() {
+        let client = DockerClient::new(
+            &DockerHost::Tcp {
+                host: String::from("docker.example.com"),
+                port: 2376,
+            },
+            None,
+            true,
+        );
+
+        assert!(matches!(
+            client,
+            Err(DockerClientError::MissingCertificateAuthority)
+        ));
+    }
+
+    #[test]
+    fn should_create_tls_client_with_certificate_authority_only() {
+        use openssl::asn1::Asn1Time;
+        use openssl::hash::MessageDigest;
+        use openssl::pkey::PKey;
+        use openssl::rsa::Rsa;
+        use openssl::x509::{X509NameBuilder, X509};
+
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "docker.example.com")
+            .unwrap();
+        let name = name.build();
+        let mut ca = X509::builder().unwrap();
+        ca.set_subject_name(&name).unwrap();
+        ca.set_issuer_name(&name).unwrap();
+        ca.set_pubkey(&key).unwrap();
+        ca.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        ca.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        ca.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let cert_path = tempfile::tempdir().unwrap();
+        std::fs::write(
+            cert_path.path().join("ca.pem"),
+            ca.build().to_pem().unwrap(),
+        )
+        .unwrap();
+
+        let client = DockerClient::new(
+            &DockerHost::Tcp {
+                host: String::from("docker.example.com"),
+                port: 2376,
+            },
+            Some(cert_path.path()),
+            true,
+        );
+
+        assert!(client.is_ok());
+    }
+
+    #[test]
+    fn should_not_create_tls_client_without_certificates() {
+        let cert_path = tempfile::tempdir().unwrap();
+
+        let client = DockerClient::new(
+            &DockerHost::Tcp {
+                host: String::from("docker.example.com"),
+                port: 2376,
+            },
+            Some(cert_path.path()),
+            true,
+        );
+
+        assert!(client.is_err());
+    }
+}
@@ -25,20 +25,23 @@
  */
 
 use crate::config::ContainerConfig;
-use crate::infrastructure::Infrastructure;
+use crate::infrastructure::{Capabilities, Infrastructure};
 use crate::models::service::{Service, ServiceStatus};
-use crate::models::{ServiceBuilder, ServiceConfig};
+use crate::models::{ExecOutput, HostCapacity, ServiceBuilder, ServiceConfig, ServiceStats};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset, Utc};
 use multimap::MultiMap;
 use std::collections::HashSet;
 use std::sync::Mutex;
 use std::time::Duration;
+use tokio::sync::mpsc::{channel, Receiver};
+use uuid::Uuid;
 
 #[cfg(test)]
 pub struct DummyInfrastructure {
     delay: Option<Duration>,
     services: Mutex<MultiMap<String, ServiceConfig>>,
+    paused_services: Mutex<HashSet<(String, String)>>,
 }
 
 #[cfg(test)]
@@ -47,6 +50,7 @@ impl DummyInfrastructure {
         DummyInfrastructure {
             delay: None,
             services: Mutex::new(MultiMap::new()),
+            paused_services: Mutex::new(HashSet::new()),
         }
     }
 
@@ -54,6 +58,7 @@ impl DummyInfrastructure {
         DummyInfrastructure {
             delay: Some(delay),
             services: Mutex::new(MultiMap::new()),
+            paused_services: Mutex::new(HashSet::new()),
         }
     }
 }
@@ -70,17 +75,35 @@ impl DummyInfrastructure {
 #[cfg(test)]
 #[async_trait]
 impl Infrastructure for DummyInfrastructure {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            volumes: true,
+            exec: true,
+            tcp_routing: false,
+            replicas: true,
+            stats: true,
+            basic_auth: true,
+        }
+    }
+
     async fn get_services(&self) -> Result<MultiMap<String, Service>, failure::Error> {
         let mut s = MultiMap::new();
 
         let services = self.services.lock().unwrap();
+        let paused_services = self.paused_services.lock().unwrap();
         for (app, configs) in services.iter_all() {
             for config in configs {
+                let status =
+                    if paused_services.contains(&(app.clone(), config.service_name().clone())) {
+                        ServiceStatus::Paused
+                    } else {
+                        ServiceStatus::Running
+                    };
                 let service = ServiceBuilder::new()
                     .id(format!("{}-{}", app.clone(), config.service_name()))
                     .app_name(app.clone())
                     .config(config.clone())
-                    .service_status(ServiceStatus::Running)
+                    .service_status(status)
                     .started_at(
                         DateTime::parse_from_rfc3339("2019-07-18T07:30:00.000000000Z")
                             .unwrap()
@@ -96,6 +119,20 @@ impl Infrastructure for DummyInfrastructure {
         Ok(s)
     }
 
+    async fn get_host_capacity(
+        &self,
+        _app_name: &String,
+    ) -> Result<Option<HostCapacity>, failure::Error> {
+        let containers = self
+            .services
+            .lock()
+            .unwrap()
+            .iter_all()
+            .map(|(_, configs)| configs.len())
+            .sum();
+        Ok(Some(HostCapacity::new(containers, None, None)))
+    }
+
     async fn deploy_services(
         &self,
         _status_id: &String,
@@ -118,7 +155,11 @@ impl Infrastructure for DummyInfrastructure {
 
         for config in configs {
             info!("started {} for {}.", config.service_name(), app_name);
-            services.insert(app_name.clone(), config.clone());
+            let mut config = config.clone();
+            config.set_deployed_fingerprint(Some(config.fingerprint()));
+            // Every deployment pretends to pull a new image.
+            config.set_deployed_image_digest(Some(format!("sha256:{}", Uuid::new_v4())));
+            services.insert(app_name.clone(), config);
         }
         Ok(vec![])
     }
@@ -175,12 +216,93 @@ impl Infrastructure for DummyInfrastructure {
         ]))
     }
 
+    async fn exec(
+        &self,
+        app_name: &String,
+        service_name: &String,
+        command: &[String],
+    ) -> Result<Option<Receiver<Result<ExecOutput, failure::Error>>>, failure::Error> {
+        let exists = self
+            .services
+            .lock()
+            .unwrap()
+            .get_vec(app_name)
+            .map_or(false, |configs| {
+                configs
+                    .iter()
+                    .any(|config| config.service_name() == service_name)
+            });
+        if !exists {
+            return Ok(None);
+        }
+
+        let (sender, receiver) = channel(1);
+        sender
+            .send(Ok(ExecOutput::Stdout(format!("{}\n", command.join(" ")))))
+            .await
+            .unwrap();
+        Ok(Some(receiver))
+    }
+
+    async fn get_stats(&self, app_name: &String) -> Result<Vec<ServiceStats>, failure::Error> {
+        let services = self.services.lock().unwrap();
+        Ok(services
+            .get_vec(app_name)
+            .map(|configs| {
+                configs
+                    .iter()
+                    .map(|config| {
+                        ServiceStats::new(
+                            config.service_name().clone(),
+                            12.5,
+                            64 * 1024 * 1024,
+                            512 * 1024 * 1024,
+                            1024,
+                            2048,
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     async fn change_status(
         &self,
-        _app_name: &String,
-        _service_name: &String,
-        _status: ServiceStatus,
+        app_name: &String,
+        service_name: &String,
+        status: ServiceStatus,
     ) -> Result<Option<Service>, failure::Error> {
-        Ok(None)
+        let exists = self
+            .services
+            .lock()
+            .unwrap()
+            .get_vec(app_name)
+            .map_or(false, |configs| {
+                configs
+                    .iter()
+                    .any(|config| config.service_name() == service_name)
+            });
+        if !exists {
+            return Ok(None);
+        }
+
+        let key = (app_name.clone(), service_name.clone());
+        let mut paused_services = self.paused_services.lock().unwrap();
+        match status {
+            ServiceStatus::Running => paused_services.remove(&key),
+            ServiceStatus::Paused => paused_services.insert(key),
+        };
+        drop(paused_services);
+
+        Ok(self
+            .get_services()
+            .await?
+            .get_vec(app_name)
+            .and_then(|services| {
+                services
+                    .iter()
+                    .find(|service| service.service_name() == service_name)
+                    .cloned()
+            }))
     }
 }
@@ -26,14 +26,21 @@
 
 use crate::config::ContainerConfig;
 use crate::models::service::{Service, ServiceStatus};
-use crate::models::{ContainerType, ServiceConfig};
+use crate::models::{
+    ContainerType, DiagnosticCheck, ExecOutput, HostCapacity, Orphan, ServiceConfig, ServiceStats,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset};
-use failure::Error;
+use failure::{format_err, Error};
 use multimap::MultiMap;
+use tokio::sync::mpsc::Receiver;
 
 #[async_trait]
 pub trait Infrastructure: Send + Sync {
+    /// Describes the features that this infrastructure supports so that clients are able to hide
+    /// actions that cannot be performed.
+    fn capabilities(&self) -> Capabilities;
+
     /// Returns a `MultiMap` of `app-name` and the running services for this app.
     async fn get_services(&self) -> Result<MultiMap<String, Service>, Error>;
 
@@ -76,6 +83,57 @@ pub trait Infrastructure: Send + Sync {
         limit: usize,
     ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, Error>;
 
+    /// Executes the command within the container of the service and sends the output of stdout
+    /// and stderr through the returned channel as soon as the command writes it. The channel will
+    /// be closed when the command terminates. Returns `None` if there is no such service.
+    ///
+    /// Infrastructures that do not announce `Capabilities::exec` cannot execute commands.
+    async fn exec(
+        &self,
+        _app_name: &String,
+        _service_name: &String,
+        _command: &[String],
+    ) -> Result<Option<Receiver<Result<ExecOutput, Error>>>, Error> {
+        Err(format_err!(
+            "The infrastructure cannot execute commands within containers."
+        ))
+    }
+
+    /// Returns the current resource usage (CPU, memory, and network) of the running services of
+    /// the given `app_name`.
+    ///
+    /// Infrastructures that cannot gather these statistics return an empty list and do not
+    /// announce `Capabilities::stats`.
+    async fn get_stats(&self, _app_name: &String) -> Result<Vec<ServiceStats>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Checks infrastructure specific requirements, e.g. the presence of the reverse proxy, and
+    /// may repair problems that can be resolved safely, such as removing orphaned resources.
+    async fn diagnose(&self) -> Result<Vec<DiagnosticCheck>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Returns the resources that carry the labels of PREvant but do not belong to a properly
+    /// deployed app, e.g. leftovers of crashes during deployments. Infrastructures that cannot
+    /// detect such resources return an empty list.
+    async fn get_orphans(&self) -> Result<Vec<Orphan>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Removes the resources of the given orphans that are still orphaned and returns the removed
+    /// ones.
+    async fn remove_orphans(&self, _orphans: &[Orphan]) -> Result<Vec<Orphan>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Measures the resources of the host that runs the app or, if the app is new, of the host
+    /// that the app will be placed on, so that deployments can be refused before they exhaust the
+    /// host. Infrastructures that cannot measure the host return `None`.
+    async fn get_host_capacity(&self, _app_name: &String) -> Result<Option<HostCapacity>, Error> {
+        Ok(None)
+    }
+
     /// Changes the status of a service, for example, the service might me stopped or started.
     async fn change_status(
         &self,
@@ -83,6 +141,172 @@ pub trait Infrastructure: Send + Sync {
         service_name: &String,
         status: ServiceStatus,
     ) -> Result<Option<Service>, Error>;
+
+    /// Restarts a service by stopping and starting it again. Returns `None` if there is no such
+    /// service.
+    async fn restart_service(
+        &self,
+        app_name: &String,
+        service_name: &String,
+    ) -> Result<Option<Service>, Error> {
+        if self
+            .change_status(app_name, service_name, ServiceStatus::Paused)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        self.change_status(app_name, service_name, ServiceStatus::Running)
+            .await
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Files can be mounted into the containers (see `ServiceConfig::volumes`).
+    pub(super) volumes: bool,
+    /// Commands can be executed within running containers.
+    pub(super) exec: bool,
+    /// Services can be exposed through plain TCP routes in addition to HTTP routes.
+    pub(super) tcp_routing: bool,
+    /// Services can be replicated from other applications.
+    pub(super) replicas: bool,
+    /// The resource usage of services can be gathered (see `Infrastructure::get_stats`).
+    pub(super) stats: bool,
+    /// The routes of the services can be protected with basic auth (see
+    /// `ServiceConfig::basic_auth`).
+    pub(super) basic_auth: bool,
+}
+
+impl Capabilities {
+    pub fn exec(&self) -> bool {
+        self.exec
+    }
+
+    pub fn basic_auth(&self) -> bool {
+        self.basic_auth
+    }
+}
+
+/// An infrastructure error that can be attributed to a specific service, e.g. because its container
+/// could not be started.
+#[derive(Debug, Fail)]
+#[fail(display = "Cannot deploy service {}: {}", service_name, message)]
+pub struct ServiceDeploymentError {
+    service_name: String,
+    message: String,
+    transient: bool,
+}
+
+impl ServiceDeploymentError {
+    pub fn new<E: std::fmt::Display>(service_name: &str, error: &E) -> Self {
+        ServiceDeploymentError {
+            service_name: service_name.to_string(),
+            message: error.to_string(),
+            transient: false,
+        }
+    }
+
+    /// Creates the error for the given cause and keeps track of whether the cause is transient.
+    pub fn caused_by(service_name: &str, error: &Error) -> Self {
+        ServiceDeploymentError {
+            transient: error
+                .downcast_ref::<TransientInfrastructureError>()
+                .is_some(),
+            ..ServiceDeploymentError::new(service_name, error)
+        }
+    }
+
+    pub fn service_name(&self) -> &String {
+        &self.service_name
+    }
+
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+
+    /// Returns the values of all results or, if services could not be deployed, the error of the
+    /// single failed service or a [`ServicesDeploymentError`] with the errors of all of them.
+    pub fn collect<T>(
+        results: impl IntoIterator<Item = Result<T, ServiceDeploymentError>>,
+    ) -> Result<Vec<T>, Error> {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        match errors.len() {
+            0 => Ok(values),
+            1 => Err(errors.remove(0).into()),
+            _ => Err(ServicesDeploymentError { errors }.into()),
+        }
+    }
+
+    /// If `true`, the service could not be deployed due to a transient cause, e.g. a registry
+    /// timeout, and a later attempt might succeed.
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+}
+
+/// Will be returned if multiple services of a deployment could not be deployed, so that users can
+/// fix all of them at once instead of one after another.
+#[derive(Debug)]
+pub struct ServicesDeploymentError {
+    errors: Vec<ServiceDeploymentError>,
+}
+
+impl ServicesDeploymentError {
+    pub fn errors(&self) -> &[ServiceDeploymentError] {
+        &self.errors
+    }
+
+    /// If `true`, all services could not be deployed due to transient causes.
+    pub fn is_transient(&self) -> bool {
+        self.errors.iter().all(|err| err.is_transient())
+    }
+}
+
+impl std::fmt::Display for ServicesDeploymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors = self
+            .errors
+            .iter()
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl failure::Fail for ServicesDeploymentError {}
+
+/// Will be returned if an operation of the infrastructure failed due to a transient cause, e.g. a
+/// timeout of the registry, and all retries failed as well.
+#[derive(Debug, Fail)]
+#[fail(
+    display = "{} failed after {} attempts: {}",
+    operation, attempts, message
+)]
+pub struct TransientInfrastructureError {
+    operation: String,
+    attempts: u32,
+    message: String,
+}
+
+impl TransientInfrastructureError {
+    pub fn new<E: std::fmt::Display>(operation: &str, attempts: u32, error: &E) -> Self {
+        TransientInfrastructureError {
+            operation: operation.to_string(),
+            attempts,
+            message: error.to_string(),
+        }
+    }
 }
 
 impl dyn Infrastructure {
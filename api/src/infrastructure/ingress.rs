@@ -0,0 +1,338 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use super::kubernetes::{
+    ingress_payload, ingress_route_payload, middleware_payload, IngressRoute, Middleware,
+};
+use crate::models::ServiceConfig;
+use async_trait::async_trait;
+use k8s_openapi::api::networking::v1beta1::Ingress as V1beta1Ingress;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1beta1::CustomResourceDefinition;
+use kube::api::{Api, DeleteParams, Patch, PatchParams, PostParams};
+use kube::{Client, Error as KubeError};
+use std::collections::BTreeMap;
+
+/// The labels that describe the route of a service on Docker for nginx, e.g. to be consumed by a
+/// [docker-gen](https://github.com/nginx-proxy/docker-gen) template.
+static NGINX_HOST_LABEL: &str = "com.aixigo.preview.servant.nginx.host";
+static NGINX_PATH_LABEL: &str = "com.aixigo.preview.servant.nginx.path";
+static NGINX_STRIP_PATH_LABEL: &str = "com.aixigo.preview.servant.nginx.strip-path";
+
+/// An ingress provider generates the labels or the Kubernetes resources that a reverse proxy
+/// requires to route the requests to a service. Thus, the infrastructure implementations do not
+/// depend on a specific proxy.
+#[async_trait]
+pub trait IngressProvider: Send + Sync {
+    /// The name of the reverse proxy, e.g. for the diagnostic messages.
+    fn name(&self) -> &'static str;
+
+    /// The labels that make the service reachable through the reverse proxy.
+    fn route_labels(
+        &self,
+        app_name: &str,
+        service_config: &ServiceConfig,
+    ) -> BTreeMap<String, String>;
+
+    /// Returns `true` if the route labels protect the service with the htpasswd entry of
+    /// `ServiceConfig::basic_auth`.
+    fn supports_basic_auth(&self) -> bool {
+        false
+    }
+
+    /// Registers the custom resource definitions that the Kubernetes routes of this provider
+    /// require.
+    async fn create_kubernetes_crds(&self, _client: Client) -> Result<(), KubeError> {
+        Ok(())
+    }
+
+    /// Creates the Kubernetes resources, e.g. an `Ingress`, that make the service reachable
+    /// through the reverse proxy.
+    async fn create_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError>;
+
+    /// Deletes the Kubernetes resources created by
+    /// [`create_kubernetes_routes`](IngressProvider::create_kubernetes_routes).
+    async fn delete_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError>;
+}
+
+/// Routes the requests through Traefik which reads the frontend rules from the container labels
+/// on Docker (Traefik 1) and the `IngressRoute` resources on Kubernetes (Traefik 2).
+pub struct TraefikIngressProvider;
+
+#[async_trait]
+impl IngressProvider for TraefikIngressProvider {
+    fn name(&self) -> &'static str {
+        "traefik"
+    }
+
+    /// Additional middlewares of the routing options are not supported by Traefik 1, instead, the
+    /// Traefik labels, e.g. `traefik.frontend.auth.basic.users`, can be set directly on the
+    /// service.
+    fn route_labels(
+        &self,
+        app_name: &str,
+        service_config: &ServiceConfig,
+    ) -> BTreeMap<String, String> {
+        let mut rule = String::new();
+        if let Some(host) = service_config.routing_host(app_name) {
+            rule.push_str(&format!("Host:{};", host));
+        }
+
+        let path = service_config.routing_path(app_name);
+        if service_config.strips_routing_path(app_name) {
+            rule.push_str(&format!(
+                "PathPrefixStrip: {path}; PathPrefix:{path};",
+                path = path
+            ));
+        } else {
+            rule.push_str(&format!("PathPrefix:{};", path));
+        }
+
+        let mut labels = BTreeMap::new();
+        labels.insert(String::from("traefik.frontend.rule"), rule);
+        if let Some(basic_auth) = service_config.basic_auth() {
+            labels.insert(
+                String::from("traefik.frontend.auth.basic.users"),
+                basic_auth.clone(),
+            );
+        }
+        labels
+    }
+
+    fn supports_basic_auth(&self) -> bool {
+        true
+    }
+
+    async fn create_kubernetes_crds(&self, client: Client) -> Result<(), KubeError> {
+        let pp = PatchParams::default();
+        let api: Api<CustomResourceDefinition> = Api::all(client);
+        api.patch(
+            "ingressroutes.traefik.containo.us",
+            &pp,
+            &Patch::Merge(IngressRoute::crd()),
+        )
+        .await?;
+        api.patch(
+            "middlewares.traefik.containo.us",
+            &pp,
+            &Patch::Merge(Middleware::crd()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn create_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError> {
+        Api::namespaced(client.clone(), &app_name)
+            .create(
+                &PostParams::default(),
+                &ingress_route_payload(app_name, service_config),
+            )
+            .await?;
+
+        if !service_config.traefik_middlewares(app_name).is_empty() {
+            Api::namespaced(client, &app_name)
+                .create(
+                    &PostParams::default(),
+                    &middleware_payload(app_name, service_config),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError> {
+        Api::<IngressRoute>::namespaced(client.clone(), &app_name)
+            .delete(
+                &format!(
+                    "{}-{}-ingress-route",
+                    app_name,
+                    service_config.service_name()
+                ),
+                &DeleteParams::default(),
+            )
+            .await?;
+
+        if !service_config.traefik_middlewares(app_name).is_empty() {
+            Api::<Middleware>::namespaced(client, &app_name)
+                .delete(
+                    &format!("{}-{}-middleware", app_name, service_config.service_name()),
+                    &DeleteParams::default(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Routes the requests through [ingress-nginx](https://kubernetes.github.io/ingress-nginx/) on
+/// Kubernetes. On Docker, nginx does not read any container labels by itself, thus, the route is
+/// described by labels that a template of the nginx configuration can make use of.
+pub struct NginxIngressProvider;
+
+#[async_trait]
+impl IngressProvider for NginxIngressProvider {
+    fn name(&self) -> &'static str {
+        "nginx"
+    }
+
+    fn route_labels(
+        &self,
+        app_name: &str,
+        service_config: &ServiceConfig,
+    ) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::new();
+        if let Some(host) = service_config.routing_host(app_name) {
+            labels.insert(String::from(NGINX_HOST_LABEL), host);
+        }
+        labels.insert(
+            String::from(NGINX_PATH_LABEL),
+            service_config.routing_path(app_name),
+        );
+        labels.insert(
+            String::from(NGINX_STRIP_PATH_LABEL),
+            service_config.strips_routing_path(app_name).to_string(),
+        );
+        labels
+    }
+
+    async fn create_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError> {
+        Api::<V1beta1Ingress>::namespaced(client, &app_name)
+            .create(
+                &PostParams::default(),
+                &ingress_payload(app_name, service_config),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_kubernetes_routes(
+        &self,
+        client: Client,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubeError> {
+        Api::<V1beta1Ingress>::namespaced(client, &app_name)
+            .delete(
+                &format!("{}-{}-ingress", app_name, service_config.service_name()),
+                &DeleteParams::default(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc;
+
+    #[test]
+    fn should_create_traefik_frontend_rule() {
+        let labels = TraefikIngressProvider.route_labels("master", &sc!("db", "mariadb:10.3.17"));
+
+        assert_eq!(
+            labels.get("traefik.frontend.rule"),
+            Some(&String::from(
+                "PathPrefixStrip: /master/db/; PathPrefix:/master/db/;"
+            ))
+        );
+    }
+
+    #[test]
+    fn should_create_traefik_frontend_rule_for_host_based_routing() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_routing(
+            serde_json::from_value(serde_json::json!({
+                "host": "{service}.{app}.example.com",
+                "path": "/"
+            }))
+            .unwrap(),
+        );
+
+        let labels = TraefikIngressProvider.route_labels("master", &config);
+
+        assert_eq!(
+            labels.get("traefik.frontend.rule"),
+            Some(&String::from("Host:db.master.example.com;PathPrefix:/;"))
+        );
+    }
+
+    #[test]
+    fn should_describe_nginx_route_with_labels() {
+        let labels = NginxIngressProvider.route_labels("master", &sc!("db", "mariadb:10.3.17"));
+
+        assert_eq!(
+            labels.get(NGINX_PATH_LABEL),
+            Some(&String::from("/master/db/"))
+        );
+        assert_eq!(
+            labels.get(NGINX_STRIP_PATH_LABEL),
+            Some(&String::from("true"))
+        );
+        assert_eq!(labels.get(NGINX_HOST_LABEL), None);
+    }
+
+    #[test]
+    fn should_protect_traefik_frontend_with_basic_auth() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_basic_auth(Some(String::from(
+            "prevant:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+        )));
+
+        let labels = TraefikIngressProvider.route_labels("master", &config);
+
+        assert_eq!(
+            labels.get("traefik.frontend.auth.basic.users"),
+            Some(&String::from("prevant:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g="))
+        );
+    }
+}
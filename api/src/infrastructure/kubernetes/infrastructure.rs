@@ -24,16 +24,25 @@
  * =========================LICENSE_END==================================
  */
 use super::super::{
-    APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL, SERVICE_NAME_LABEL,
+    depends_on_from_label_value, skipped_companions_from_label_value, tickets_from_label_value,
+    APP_NAME_LABEL, COMPANION_DEFINITION_LABEL, CONTAINER_TYPE_LABEL, DEPENDS_ON_LABEL,
+    FINGERPRINT_LABEL, IMAGE_LABEL, OWNER_LABEL, PORTS_LABEL, REPLICATED_ENV_LABEL,
+    REPLICATED_FROM_LABEL, REPLICATED_IMAGE_DIGEST_LABEL, ROUTING_LABEL, SERVICE_NAME_LABEL,
+    SERVICE_READINESS_TIMEOUT, SKIPPED_COMPANIONS_LABEL, TICKETS_LABEL, USER_LABELS_LABEL,
 };
 use super::payloads::{
-    deployment_payload, deployment_replicas_payload, ingress_route_payload, middleware_payload,
-    namespace_payload, secrets_payload, service_payload, IngressRoute, Middleware,
+    deployment_payload, deployment_replicas_payload, namespace_payload, secrets_payload,
+    service_payload,
 };
 use crate::config::ContainerConfig;
-use crate::infrastructure::Infrastructure;
+use crate::infrastructure::{
+    Capabilities, Infrastructure, IngressProvider, ServiceDeploymentError,
+};
 use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
-use crate::models::{Environment, Image, ServiceBuilder, ServiceBuilderError, ServiceConfig};
+use crate::models::{
+    deployment_waves, is_dependency, Environment, Image, Port, Routing, ServiceBuilder,
+    ServiceBuilderError, ServiceConfig,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset, Utc};
 use failure::Error;
@@ -42,7 +51,6 @@ use k8s_openapi::api::{
     apps::v1::Deployment as V1Deployment, core::v1::Namespace as V1Namespace,
     core::v1::Pod as V1Pod, core::v1::Secret as V1Secret, core::v1::Service as V1Service,
 };
-use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1beta1::CustomResourceDefinition;
 use kube::{
     api::{Api, DeleteParams, ListParams, LogParams, Patch, PatchParams, PostParams},
     client::Client,
@@ -58,12 +66,14 @@ use std::convert::{From, TryFrom};
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use url::Url;
 
 pub struct KubernetesInfrastructure {
     cluster_endpoint: Url,
     cluster_ca: Option<Vec<X509>>,
     cluster_token: Option<SecUtf8>,
+    ingress: Box<dyn IngressProvider>,
 }
 
 #[derive(Debug, Fail, PartialEq)]
@@ -92,6 +102,8 @@ pub enum KubernetesInfrastructureError {
     MissingImageLabel { deployment_name: String },
     #[fail(display = "Could not convert certificate: {}", internal_message)]
     CertificateError { internal_message: String },
+    #[fail(display = "The service {} did not become ready in time.", service_name)]
+    ServiceNotReady { service_name: String },
 }
 
 impl KubernetesInfrastructure {
@@ -99,11 +111,13 @@ impl KubernetesInfrastructure {
         cluster_endpoint: Url,
         cluster_ca: Option<Vec<X509>>,
         cluster_token: Option<SecUtf8>,
+        ingress: Box<dyn IngressProvider>,
     ) -> Self {
         KubernetesInfrastructure {
             cluster_endpoint,
             cluster_ca,
             cluster_token,
+            ingress,
         }
     }
 
@@ -281,18 +295,8 @@ impl KubernetesInfrastructure {
             )
             .await?;
 
-        Api::namespaced(self.client()?, &app_name)
-            .create(
-                &PostParams::default(),
-                &ingress_route_payload(app_name, service_config),
-            )
-            .await?;
-
-        Api::namespaced(self.client()?, &app_name)
-            .create(
-                &PostParams::default(),
-                &middleware_payload(app_name, service_config),
-            )
+        self.ingress
+            .create_kubernetes_routes(self.client()?, app_name, service_config)
             .await?;
 
         Ok(())
@@ -302,20 +306,7 @@ impl KubernetesInfrastructure {
         &self,
         _app_name: &String,
     ) -> Result<(), KubernetesInfrastructureError> {
-        let pp = PatchParams::default();
-        let api: Api<CustomResourceDefinition> = Api::all(self.client()?);
-        api.patch(
-            "ingressroutes.traefik.containo.us",
-            &pp,
-            &Patch::Merge(IngressRoute::crd()),
-        )
-        .await?;
-        api.patch(
-            "middlewares.traefik.containo.us",
-            &pp,
-            &Patch::Merge(Middleware::crd()),
-        )
-        .await?;
+        self.ingress.create_kubernetes_crds(self.client()?).await?;
         Ok(())
     }
 
@@ -394,6 +385,42 @@ impl KubernetesInfrastructure {
         }
     }
 
+    /// Waits until the deployment of the service provides at least one ready replica.
+    async fn wait_until_ready(
+        &self,
+        app_name: &String,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let deployment_name = format!("{}-{}-deployment", app_name, service_config.service_name());
+        let deadline = Instant::now() + SERVICE_READINESS_TIMEOUT;
+
+        loop {
+            let deployment = Api::<V1Deployment>::namespaced(self.client()?, &app_name)
+                .get(&deployment_name)
+                .await?;
+
+            let ready_replicas = deployment
+                .status
+                .and_then(|status| status.ready_replicas)
+                .unwrap_or(0);
+            if ready_replicas > 0 {
+                return Ok(());
+            }
+
+            if Instant::now() >= deadline {
+                return Err(KubernetesInfrastructureError::ServiceNotReady {
+                    service_name: service_config.service_name().clone(),
+                });
+            }
+
+            trace!(
+                "Waiting for service {} to become ready",
+                service_config.service_name()
+            );
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    }
+
     async fn deploy_secret(
         &self,
         app_name: &String,
@@ -451,17 +478,8 @@ impl KubernetesInfrastructure {
         Api::<V1Service>::namespaced(self.client()?, &service.app_name())
             .delete(service.service_name(), &DeleteParams::default())
             .await?;
-        Api::<IngressRoute>::namespaced(self.client()?, &service.app_name())
-            .delete(
-                &format!("{}-{}-ingress-route", app_name, service.service_name()),
-                &DeleteParams::default(),
-            )
-            .await?;
-        Api::<Middleware>::namespaced(self.client()?, &service.app_name())
-            .delete(
-                &format!("{}-{}-middleware", app_name, service.service_name()),
-                &DeleteParams::default(),
-            )
+        self.ingress
+            .delete_kubernetes_routes(self.client()?, app_name, service.config())
             .await?;
 
         Ok(service)
@@ -470,6 +488,17 @@ impl KubernetesInfrastructure {
 
 #[async_trait]
 impl Infrastructure for KubernetesInfrastructure {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            volumes: true,
+            exec: false,
+            tcp_routing: false,
+            replicas: true,
+            stats: false,
+            basic_auth: false,
+        }
+    }
+
     async fn get_services(&self) -> Result<MultiMap<String, Service>, Error> {
         let mut p = ListParams::default();
         p.label_selector = Some(format!("{},{}", APP_NAME_LABEL, SERVICE_NAME_LABEL));
@@ -505,14 +534,34 @@ impl Infrastructure for KubernetesInfrastructure {
         self.create_crds_if_necessary(app_name).await?;
         self.create_namespace_if_necessary(app_name).await?;
 
-        let futures = configs
-            .iter()
-            .map(|config| self.deploy_service(app_name, config, container_config))
-            .collect::<Vec<_>>();
-
-        for deploy_result in join_all(futures).await {
-            trace!("deployed {:?}", deploy_result);
-            deploy_result?;
+        for wave in deployment_waves(configs)? {
+            let futures = wave
+                .iter()
+                .map(|config| self.deploy_service(app_name, config, container_config))
+                .collect::<Vec<_>>();
+
+            ServiceDeploymentError::collect(wave.iter().zip(join_all(futures).await).map(
+                |(config, deploy_result)| {
+                    trace!("deployed {:?}", deploy_result);
+                    deploy_result
+                        .map_err(|err| ServiceDeploymentError::new(config.service_name(), &err))
+                },
+            ))?;
+
+            let dependencies = wave
+                .iter()
+                .filter(|config| is_dependency(configs, config.service_name()))
+                .collect::<Vec<_>>();
+            let futures = dependencies
+                .iter()
+                .map(|config| self.wait_until_ready(app_name, config))
+                .collect::<Vec<_>>();
+            ServiceDeploymentError::collect(dependencies.iter().zip(join_all(futures).await).map(
+                |(config, readiness)| {
+                    readiness
+                        .map_err(|err| ServiceDeploymentError::new(config.service_name(), &err))
+                },
+            ))?;
         }
 
         Ok(self.get_services_of_app(app_name).await?)
@@ -729,6 +778,52 @@ impl TryFrom<&V1Deployment> for ServiceConfig {
                 config.set_container_type(lb.parse::<ContainerType>()?);
             }
 
+            config.set_owner(annotations.get(OWNER_LABEL).cloned());
+            if let Some(tickets) = annotations.get(TICKETS_LABEL) {
+                config.set_tickets(tickets_from_label_value(tickets));
+            }
+            if let Some(skipped_companions) = annotations.get(SKIPPED_COMPANIONS_LABEL) {
+                config.set_skipped_companions(skipped_companions_from_label_value(
+                    skipped_companions,
+                ));
+            }
+            config.set_companion_definition(annotations.get(COMPANION_DEFINITION_LABEL).cloned());
+            config.set_deployed_fingerprint(annotations.get(FINGERPRINT_LABEL).cloned());
+            config.set_replicated_from(
+                annotations.get(REPLICATED_FROM_LABEL).cloned(),
+                annotations.get(REPLICATED_IMAGE_DIGEST_LABEL).cloned(),
+            );
+
+            if let Some(ports) = annotations.get(PORTS_LABEL) {
+                let ports = serde_json::from_str::<Vec<Port>>(ports).map_err(|err| {
+                    KubernetesInfrastructureError::UnexpectedError {
+                        internal_message: err.to_string(),
+                    }
+                })?;
+                config.set_ports(ports);
+            }
+
+            if let Some(routing) = annotations.get(ROUTING_LABEL) {
+                let routing = serde_json::from_str::<Routing>(routing).map_err(|err| {
+                    KubernetesInfrastructureError::UnexpectedError {
+                        internal_message: err.to_string(),
+                    }
+                })?;
+                config.set_routing(routing);
+            }
+
+            if let Some(depends_on) = annotations.get(DEPENDS_ON_LABEL) {
+                config.set_depends_on(depends_on_from_label_value(depends_on));
+            }
+
+            if let Some(user_labels) = annotations.get(USER_LABELS_LABEL) {
+                let user_labels = serde_json::from_str::<BTreeMap<String, String>>(user_labels)
+                    .map_err(|err| KubernetesInfrastructureError::UnexpectedError {
+                        internal_message: err.to_string(),
+                    })?;
+                config.set_labels(Some(user_labels));
+            }
+
             Ok(config)
         } else {
             Err(KubernetesInfrastructureError::UnexpectedError {
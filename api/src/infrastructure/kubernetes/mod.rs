@@ -24,6 +24,9 @@
  * =========================LICENSE_END==================================
  */
 pub use infrastructure::{KubernetesInfrastructure, KubernetesInfrastructureError};
+pub(super) use payloads::{
+    ingress_payload, ingress_route_payload, middleware_payload, IngressRoute, Middleware,
+};
 
 mod infrastructure;
 mod payloads;
@@ -24,16 +24,21 @@
  * =========================LICENSE_END==================================
  */
 use super::super::{
-    APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL, SERVICE_NAME_LABEL,
+    depends_on_to_label_value, skipped_companions_to_label_value, tickets_to_label_value,
+    APP_NAME_LABEL, COMPANION_DEFINITION_LABEL, CONTAINER_TYPE_LABEL, DEPENDS_ON_LABEL,
+    FINGERPRINT_LABEL, IMAGE_LABEL, OWNER_LABEL, PORTS_LABEL, REPLICATED_ENV_LABEL,
+    REPLICATED_FROM_LABEL, REPLICATED_IMAGE_DIGEST_LABEL, ROUTING_LABEL, SERVICE_NAME_LABEL,
+    SKIPPED_COMPANIONS_LABEL, TICKETS_LABEL, USER_LABELS_LABEL,
 };
 use crate::config::ContainerConfig;
 use crate::models::service::Service;
-use crate::models::ServiceConfig;
+use crate::models::{Port, ServiceConfig, HTTP_PORT_NAME};
 use base64::encode;
 use chrono::Utc;
 use k8s_openapi::api::{
     apps::v1::Deployment as V1Deployment, core::v1::Namespace as V1Namespace,
     core::v1::Secret as V1Secret, core::v1::Service as V1Service,
+    networking::v1beta1::Ingress as V1beta1Ingress,
 };
 use kube_derive::CustomResource;
 use multimap::MultiMap;
@@ -123,7 +128,7 @@ pub fn deployment_payload(
             .collect()
     });
 
-    let annotations = if let Some(replicated_env) = service_config
+    let mut annotations = if let Some(replicated_env) = service_config
         .env()
         .map(super::super::replicated_environment_variable_to_json)
         .flatten()
@@ -138,6 +143,67 @@ pub fn deployment_payload(
         })
     };
 
+    // The owner is stored as annotation because user names might contain characters, such as @,
+    // that are not allowed in label values.
+    if let Some(owner) = service_config.owner() {
+        annotations[OWNER_LABEL] = serde_json::json!(owner);
+    }
+
+    if let Some(tickets) = tickets_to_label_value(service_config) {
+        annotations[TICKETS_LABEL] = serde_json::json!(tickets);
+    }
+
+    if let Some(skipped_companions) = skipped_companions_to_label_value(service_config) {
+        annotations[SKIPPED_COMPANIONS_LABEL] = serde_json::json!(skipped_companions);
+    }
+
+    if let Some(definition) = service_config.companion_definition() {
+        annotations[COMPANION_DEFINITION_LABEL] = serde_json::json!(definition);
+    }
+
+    annotations[FINGERPRINT_LABEL] = serde_json::json!(service_config.fingerprint());
+
+    if let Some(replicated_from) = service_config.replicated_from() {
+        annotations[REPLICATED_FROM_LABEL] = serde_json::json!(replicated_from);
+    }
+    if let Some(image_digest) = service_config.replicated_image_digest() {
+        annotations[REPLICATED_IMAGE_DIGEST_LABEL] = serde_json::json!(image_digest);
+    }
+
+    if !service_config.ports().is_empty() {
+        annotations[PORTS_LABEL] =
+            Value::String(serde_json::json!(service_config.ports()).to_string());
+    }
+
+    if let Some(routing) = service_config.routing() {
+        annotations[ROUTING_LABEL] = Value::String(serde_json::json!(routing).to_string());
+    }
+
+    let mut container_ports = vec![serde_json::json!({
+      "containerPort": service_config.port()
+    })];
+    container_ports.extend(additional_ports(service_config).map(|port| {
+        serde_json::json!({
+          "name": port.name(),
+          "containerPort": port.port()
+        })
+    }));
+
+    if let Some(depends_on) = depends_on_to_label_value(service_config) {
+        annotations[DEPENDS_ON_LABEL] = serde_json::json!(depends_on);
+    }
+
+    // User-defined labels are applied as pod annotations because Kubernetes restricts the values
+    // of labels, e.g. Traefik rules of companions would not be valid label values.
+    let mut pod_annotations = Map::new();
+    if let Some(labels) = service_config.labels().filter(|labels| !labels.is_empty()) {
+        annotations[USER_LABELS_LABEL] = Value::String(serde_json::json!(labels).to_string());
+        for (key, value) in labels {
+            pod_annotations.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+    pod_annotations.insert(String::from("date"), Value::String(Utc::now().to_rfc3339()));
+
     let mounts = if let Some(volumes) = service_config.volumes() {
         let parent_paths = volumes
             .iter()
@@ -223,9 +289,7 @@ pub fn deployment_payload(
               SERVICE_NAME_LABEL: service_config.service_name(),
               CONTAINER_TYPE_LABEL: service_config.container_type().to_string()
             },
-            "annotations": {
-              "date": Utc::now().to_rfc3339()
-            }
+            "annotations": Value::Object(pod_annotations)
           },
           "spec": {
             "containers": [
@@ -235,12 +299,10 @@ pub fn deployment_payload(
                 "imagePullPolicy": "Always",
                 "env": Value::Array(env),
                 "volumeMounts": Value::Array(mounts),
-                "ports": [
-                  {
-                    "containerPort": service_config.port()
-                  }
-                ],
-                "resources": resources
+                "ports": Value::Array(container_ports),
+                "resources": resources,
+                "command": service_config.entrypoint(),
+                "args": service_config.command()
               }
             ],
             "volumes": volumes
@@ -316,6 +378,19 @@ pub fn secrets_payload(
 
 /// Creates a JSON payload suitable for [Kubernetes' Services](https://kubernetes.io/docs/concepts/services-networking/service/)
 pub fn service_payload(app_name: &String, service_config: &ServiceConfig) -> V1Service {
+    let mut ports = vec![serde_json::json!({
+      "name": service_config.service_name(),
+      "targetPort": service_config.port(),
+      "port": service_config.port()
+    })];
+    ports.extend(additional_ports(service_config).map(|port| {
+        serde_json::json!({
+          "name": port.name(),
+          "targetPort": port.port(),
+          "port": port.port()
+        })
+    }));
+
     serde_json::from_value(serde_json::json!({
       "apiVersion": "v1",
       "kind": "Service",
@@ -327,13 +402,7 @@ pub fn service_payload(app_name: &String, service_config: &ServiceConfig) -> V1S
         CONTAINER_TYPE_LABEL: service_config.container_type().to_string()
       },
       "spec": {
-        "ports": [
-          {
-            "name": service_config.service_name(),
-            "targetPort": service_config.port(),
-            "port": service_config.port()
-          }
-        ],
+        "ports": Value::Array(ports),
         "selector": {
           APP_NAME_LABEL: app_name,
           SERVICE_NAME_LABEL: service_config.service_name(),
@@ -344,11 +413,29 @@ pub fn service_payload(app_name: &String, service_config: &ServiceConfig) -> V1S
     .expect("Cannot convert value to core/v1/Service")
 }
 
+/// The ports, apart from the HTTP port, that are reachable within the namespace of the app only.
+fn additional_ports<'a>(service_config: &'a ServiceConfig) -> impl Iterator<Item = &'a Port> + 'a {
+    service_config
+        .ports()
+        .iter()
+        .filter(|port| port.name() != HTTP_PORT_NAME)
+}
+
 /// Creates a payload that ensures that Traefik find the correct route in Kubernetes
 ///
 /// See [Traefik Routers](https://docs.traefik.io/v2.0/user-guides/crd-acme/#traefik-routers)
 /// for more information.
 pub fn ingress_route_payload(app_name: &String, service_config: &ServiceConfig) -> IngressRoute {
+    let middlewares = if service_config.traefik_middlewares(app_name).is_empty() {
+        serde_json::json!([])
+    } else {
+        serde_json::json!([
+          {
+            "name": format!("{}-{}-middleware", app_name, service_config.service_name())
+          }
+        ])
+    };
+
     serde_json::from_value(serde_json::json!({
       "apiVersion": "traefik.containo.us/v1alpha1",
       "kind": "IngressRoute",
@@ -373,11 +460,7 @@ pub fn ingress_route_payload(app_name: &String, service_config: &ServiceConfig)
                 "port": service_config.port()
               }
             ],
-            "middlewares": [
-              {
-                "name": format!("{}-{}-middleware", app_name, service_config.service_name())
-              }
-            ]
+            "middlewares": middlewares
           }
         ]
       }
@@ -402,6 +485,71 @@ pub fn middleware_payload(app_name: &String, service_config: &ServiceConfig) ->
     .expect("Cannot convert value to traefik.containo.us/v1alpha1/MiddleWare")
 }
 
+/// Creates an ingress that routes the requests through
+/// [ingress-nginx](https://kubernetes.github.io/ingress-nginx/). If the path prefix has to be
+/// stripped, the path is matched as regular expression and nginx rewrites the target to the
+/// captured remainder.
+pub fn ingress_payload(app_name: &String, service_config: &ServiceConfig) -> V1beta1Ingress {
+    let mut annotations = BTreeMap::new();
+    annotations.insert(
+        String::from("kubernetes.io/ingress.class"),
+        String::from("nginx"),
+    );
+
+    let mut path = service_config.routing_path(app_name);
+    if service_config.strips_routing_path(app_name) {
+        annotations.insert(
+            String::from("nginx.ingress.kubernetes.io/use-regex"),
+            String::from("true"),
+        );
+        annotations.insert(
+            String::from("nginx.ingress.kubernetes.io/rewrite-target"),
+            String::from("/$1"),
+        );
+        annotations.insert(
+            String::from("nginx.ingress.kubernetes.io/x-forwarded-prefix"),
+            path.trim_end_matches('/').to_string(),
+        );
+        path = format!("{}(.*)", path);
+    }
+
+    let mut rule = serde_json::json!({
+      "http": {
+        "paths": [
+          {
+            "path": path,
+            "backend": {
+              "serviceName": service_config.service_name(),
+              "servicePort": service_config.port()
+            }
+          }
+        ]
+      }
+    });
+    if let Some(host) = service_config.routing_host(app_name) {
+        rule["host"] = Value::String(host);
+    }
+
+    serde_json::from_value(serde_json::json!({
+      "apiVersion": "networking.k8s.io/v1beta1",
+      "kind": "Ingress",
+      "metadata": {
+        "name": format!("{}-{}-ingress", app_name, service_config.service_name()),
+        "namespace": app_name,
+        "annotations": annotations,
+        "labels": {
+          APP_NAME_LABEL: app_name,
+          SERVICE_NAME_LABEL: service_config.service_name(),
+          CONTAINER_TYPE_LABEL: service_config.container_type().to_string()
+        }
+      },
+      "spec": {
+        "rules": [ rule ]
+      }
+    }))
+    .expect("Cannot convert value to networking.k8s.io/v1beta1/Ingress")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -621,6 +769,154 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_deployment_with_user_defined_labels() {
+        let mut labels = BTreeMap::new();
+        labels.insert(String::from("com.example.team"), String::from("platform"));
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_labels(Some(labels));
+
+        let payload = deployment_payload("master", &config, &ContainerConfig::default());
+
+        assert_json_diff::assert_json_include!(
+            actual: serde_json::to_value(payload).unwrap(),
+            expected: serde_json::json!({
+              "metadata": {
+                "annotations": {
+                  "com.aixigo.preview.servant.labels": "{\"com.example.team\":\"platform\"}"
+                }
+              },
+              "spec": {
+                "template": {
+                  "metadata": {
+                    "annotations": {
+                      "com.example.team": "platform"
+                    }
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_service_with_additional_ports() {
+        let mut config = sc!("backend", "backend:latest");
+        config.set_ports(vec![
+            Port::new(String::from("http"), 8080),
+            Port::new(String::from("debug"), 5005),
+        ]);
+
+        let payload = service_payload(&String::from("master"), &config);
+
+        assert_json_diff::assert_json_include!(
+            actual: serde_json::to_value(payload).unwrap(),
+            expected: serde_json::json!({
+              "spec": {
+                "ports": [
+                  {
+                    "name": "backend",
+                    "port": 8080,
+                    "targetPort": 8080
+                  },
+                  {
+                    "name": "debug",
+                    "port": 5005,
+                    "targetPort": 5005
+                  }
+                ]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_nginx_ingress() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        let port = 1234;
+        config.set_port(port);
+
+        let payload = ingress_payload(&String::from("master"), &config);
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "apiVersion": "networking.k8s.io/v1beta1",
+              "kind": "Ingress",
+              "metadata": {
+                "name": "master-db-ingress",
+                "namespace": "master",
+                "annotations": {
+                  "kubernetes.io/ingress.class": "nginx",
+                  "nginx.ingress.kubernetes.io/use-regex": "true",
+                  "nginx.ingress.kubernetes.io/rewrite-target": "/$1",
+                  "nginx.ingress.kubernetes.io/x-forwarded-prefix": "/master/db"
+                }
+              },
+              "spec": {
+                "rules": [
+                  {
+                    "http": {
+                      "paths": [
+                        {
+                          "path": "/master/db/(.*)",
+                          "backend": {
+                            "serviceName": "db",
+                            "servicePort": port
+                          }
+                        }
+                      ]
+                    }
+                  }
+                ]
+              },
+            }),
+        );
+    }
+
+    #[test]
+    fn should_create_nginx_ingress_for_host_based_routing() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_routing(
+            serde_json::from_value(serde_json::json!({
+                "host": "{service}.{app}.example.com",
+                "path": "/"
+            }))
+            .unwrap(),
+        );
+
+        let payload = ingress_payload(&String::from("master"), &config);
+
+        assert_eq!(
+            payload.metadata.annotations.as_ref().and_then(
+                |annotations| annotations.get("nginx.ingress.kubernetes.io/rewrite-target")
+            ),
+            None
+        );
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "rules": [
+                  {
+                    "host": "db.master.example.com",
+                    "http": {
+                      "paths": [
+                        {
+                          "path": "/",
+                          "backend": {
+                            "serviceName": "db",
+                          }
+                        }
+                      ]
+                    }
+                  }
+                ]
+              },
+            }),
+        );
+    }
+
     #[test]
     fn should_create_ingress_route() {
         let mut config = sc!("db", "mariadb:10.3.17");
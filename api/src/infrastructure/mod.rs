@@ -24,19 +24,28 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::models::Environment;
+use crate::models::{Environment, ServiceConfig};
 pub use docker::DockerInfrastructure as Docker;
 #[cfg(test)]
 pub use dummy_infrastructure::DummyInfrastructure as Dummy;
-pub use infrastructure::Infrastructure;
+pub use infrastructure::{
+    Capabilities, Infrastructure, ServiceDeploymentError, ServicesDeploymentError,
+    TransientInfrastructureError,
+};
+pub use ingress::{IngressProvider, NginxIngressProvider, TraefikIngressProvider};
 pub use kubernetes::KubernetesInfrastructure as Kubernetes;
+pub use multi_docker::MultiDockerInfrastructure as MultiDocker;
 use serde_json::{map::Map, Value};
+use std::time::Duration;
 
 mod docker;
+mod docker_client;
 #[cfg(test)]
 mod dummy_infrastructure;
 mod infrastructure;
+mod ingress;
 mod kubernetes;
+mod multi_docker;
 
 static APP_NAME_LABEL: &str = "com.aixigo.preview.servant.app-name";
 static SERVICE_NAME_LABEL: &str = "com.aixigo.preview.servant.service-name";
@@ -44,6 +53,77 @@ static CONTAINER_TYPE_LABEL: &str = "com.aixigo.preview.servant.container-type";
 static REPLICATED_ENV_LABEL: &str = "com.aixigo.preview.servant.replicated-env";
 static IMAGE_LABEL: &str = "com.aixigo.preview.servant.image";
 static STATUS_ID: &str = "com.aixigo.preview.servant.status-id";
+static OWNER_LABEL: &str = "com.aixigo.preview.servant.owner";
+static TICKETS_LABEL: &str = "com.aixigo.preview.servant.tickets";
+static BASIC_AUTH_LABEL: &str = "com.aixigo.preview.servant.basic-auth";
+static DEPENDS_ON_LABEL: &str = "com.aixigo.preview.servant.depends-on";
+static FINGERPRINT_LABEL: &str = "com.aixigo.preview.servant.config-fingerprint";
+static PORTS_LABEL: &str = "com.aixigo.preview.servant.ports";
+static ROUTING_LABEL: &str = "com.aixigo.preview.servant.routing";
+static USER_LABELS_LABEL: &str = "com.aixigo.preview.servant.labels";
+static REPLICATED_FROM_LABEL: &str = "com.aixigo.preview.servant.replicated-from";
+static REPLICATED_IMAGE_DIGEST_LABEL: &str = "com.aixigo.preview.servant.replicated-image-digest";
+static DOCKER_HOST_LABEL: &str = "com.aixigo.preview.servant.docker-host";
+static REVERSE_PROXY_LABEL: &str = "com.aixigo.preview.servant.reverse-proxy";
+static COMPANION_DEFINITION_LABEL: &str = "com.aixigo.preview.servant.companion-definition";
+static SKIPPED_COMPANIONS_LABEL: &str = "com.aixigo.preview.servant.skipped-companions";
+
+/// The maximum duration to wait for a service, that other services depend on, to become ready.
+static SERVICE_READINESS_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Joins the names of the services which the given service depends on so that they can be
+/// stored in a single label or annotation value.
+fn depends_on_to_label_value(service_config: &ServiceConfig) -> Option<String> {
+    if service_config.depends_on().is_empty() {
+        None
+    } else {
+        Some(service_config.depends_on().join(","))
+    }
+}
+
+fn depends_on_from_label_value(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|service_name| !service_name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Joins the keys of the tickets which the given service is linked to so that they can be stored
+/// in a single label or annotation value.
+fn tickets_to_label_value(service_config: &ServiceConfig) -> Option<String> {
+    if service_config.tickets().is_empty() {
+        None
+    } else {
+        Some(service_config.tickets().join(","))
+    }
+}
+
+fn tickets_from_label_value(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|ticket| !ticket.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Joins the names of the companions that the deployment request of the given service skipped so
+/// that they can be stored in a single label or annotation value.
+fn skipped_companions_to_label_value(service_config: &ServiceConfig) -> Option<String> {
+    if service_config.skipped_companions().is_empty() {
+        None
+    } else {
+        Some(service_config.skipped_companions().join(","))
+    }
+}
+
+fn skipped_companions_from_label_value(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|service_name| !service_name.is_empty())
+        .map(String::from)
+        .collect()
+}
 
 /// This function converts the environment variables and adds all variables, that
 /// must be replicated, into a JSON object. This function should be used by implementations
@@ -0,0 +1,294 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{ContainerConfig, DockerRuntimeConfig};
+use crate::infrastructure::docker::DockerInfrastructureError;
+use crate::infrastructure::{Capabilities, Docker, Infrastructure, IngressProvider};
+use crate::models::service::{Service, ServiceStatus};
+use crate::models::{
+    DiagnosticCheck, ExecOutput, HostCapacity, Orphan, ServiceConfig, ServiceStats,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use failure::{format_err, Error};
+use multimap::MultiMap;
+use tokio::sync::mpsc::Receiver;
+
+/// Distributes the apps across several Docker hosts. A new app is placed on the host with the
+/// fewest containers and its containers are labeled with the name of the host so that all further
+/// operations of the app, e.g. updates and deletions, target the same host.
+pub struct MultiDockerInfrastructure {
+    hosts: Vec<(String, Docker)>,
+    capabilities: Capabilities,
+}
+
+impl MultiDockerInfrastructure {
+    /// Creates an infrastructure for each of the `hosts` of the config, which must not be empty.
+    /// Each host requires its own ingress provider because each host runs its own reverse proxy.
+    pub fn new<F>(
+        config: DockerRuntimeConfig,
+        ingress: F,
+    ) -> Result<MultiDockerInfrastructure, DockerInfrastructureError>
+    where
+        F: Fn() -> Box<dyn IngressProvider>,
+    {
+        let hosts = config
+            .hosts()
+            .iter()
+            .map(|host| {
+                Ok((
+                    host.name().clone(),
+                    Docker::new(config.for_host(host), ingress())?
+                        .with_host_name(host.name().clone()),
+                ))
+            })
+            .collect::<Result<Vec<_>, DockerInfrastructureError>>()?;
+
+        // All hosts are driven by the same configuration, thus, they provide the same capabilities.
+        let capabilities = match hosts.first() {
+            Some((_, host)) => host.capabilities(),
+            None => return Err(DockerInfrastructureError::MissingDockerHosts),
+        };
+
+        Ok(MultiDockerInfrastructure {
+            hosts,
+            capabilities,
+        })
+    }
+
+    /// Resolves the host that the app has been placed on.
+    async fn host_of(&self, app_name: &String) -> Result<Option<&Docker>, Error> {
+        for (_, host) in &self.hosts {
+            if host.hosts_app(app_name).await? {
+                return Ok(Some(host));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Resolves the host that the app has been placed on or, if the app is new, the host with the
+    /// fewest containers.
+    async fn place(&self, app_name: &String) -> Result<&Docker, Error> {
+        let mut placements = Vec::with_capacity(self.hosts.len());
+        for (_, host) in &self.hosts {
+            placements.push((host.hosts_app(app_name).await?, host.load().await?));
+        }
+
+        match placement(&placements) {
+            Some(index) => {
+                let (host_name, host) = &self.hosts[index];
+                debug!("Place app {} on Docker host {}", app_name, host_name);
+                Ok(host)
+            }
+            None => Err(format_err!(
+                "There is no Docker host for placing the app {}.",
+                app_name
+            )),
+        }
+    }
+}
+
+/// Chooses the index of the host for an app, given whether each host already runs the app and the
+/// number of containers on each host: a host that runs the app takes precedence, otherwise, the
+/// first of the hosts with the fewest containers is chosen.
+fn placement(hosts: &[(bool, usize)]) -> Option<usize> {
+    hosts
+        .iter()
+        .position(|(hosts_app, _)| *hosts_app)
+        .or_else(|| {
+            hosts
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, (_, load))| *load)
+                .map(|(index, _)| index)
+        })
+}
+
+#[async_trait]
+impl Infrastructure for MultiDockerInfrastructure {
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities.clone()
+    }
+
+    async fn get_services(&self) -> Result<MultiMap<String, Service>, Error> {
+        let mut apps = MultiMap::new();
+        for (_, host) in &self.hosts {
+            for (app_name, services) in host.get_services().await? {
+                apps.insert_many(app_name, services);
+            }
+        }
+        Ok(apps)
+    }
+
+    async fn deploy_services(
+        &self,
+        status_id: &String,
+        app_name: &String,
+        configs: &Vec<ServiceConfig>,
+        container_config: &ContainerConfig,
+    ) -> Result<Vec<Service>, Error> {
+        self.place(app_name)
+            .await?
+            .deploy_services(status_id, app_name, configs, container_config)
+            .await
+    }
+
+    async fn get_status_change(&self, status_id: &String) -> Result<Option<Vec<Service>>, Error> {
+        for (_, host) in &self.hosts {
+            if let Some(services) = host.get_status_change(status_id).await? {
+                return Ok(Some(services));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn stop_services(
+        &self,
+        status_id: &String,
+        app_name: &String,
+    ) -> Result<Vec<Service>, Error> {
+        match self.host_of(app_name).await? {
+            Some(host) => host.stop_services(status_id, app_name).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn get_logs(
+        &self,
+        app_name: &String,
+        service_name: &String,
+        from: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+    ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, Error> {
+        match self.host_of(app_name).await? {
+            Some(host) => host.get_logs(app_name, service_name, from, limit).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn exec(
+        &self,
+        app_name: &String,
+        service_name: &String,
+        command: &[String],
+    ) -> Result<Option<Receiver<Result<ExecOutput, Error>>>, Error> {
+        match self.host_of(app_name).await? {
+            Some(host) => host.exec(app_name, service_name, command).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn get_stats(&self, app_name: &String) -> Result<Vec<ServiceStats>, Error> {
+        match self.host_of(app_name).await? {
+            Some(host) => host.get_stats(app_name).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn diagnose(&self) -> Result<Vec<DiagnosticCheck>, Error> {
+        let mut checks = Vec::new();
+        for (host_name, host) in &self.hosts {
+            checks.extend(
+                host.diagnose()
+                    .await?
+                    .into_iter()
+                    .map(|check| check.on_host(host_name)),
+            );
+        }
+        Ok(checks)
+    }
+
+    async fn get_orphans(&self) -> Result<Vec<Orphan>, Error> {
+        let mut orphans = Vec::new();
+        for (_, host) in &self.hosts {
+            orphans.extend(host.get_orphans().await?);
+        }
+        Ok(orphans)
+    }
+
+    async fn remove_orphans(&self, orphans: &[Orphan]) -> Result<Vec<Orphan>, Error> {
+        let mut removed = Vec::new();
+        for (_, host) in &self.hosts {
+            removed.extend(host.remove_orphans(orphans).await?);
+        }
+        Ok(removed)
+    }
+
+    /// Returns the capacity of the host that runs the app or, if the app is new, of the host that
+    /// the app will be placed on.
+    async fn get_host_capacity(&self, app_name: &String) -> Result<Option<HostCapacity>, Error> {
+        let host = match self.host_of(app_name).await? {
+            Some(host) => host,
+            None => self.place(app_name).await?,
+        };
+        host.get_host_capacity(app_name).await
+    }
+
+    async fn change_status(
+        &self,
+        app_name: &String,
+        service_name: &String,
+        status: ServiceStatus,
+    ) -> Result<Option<Service>, Error> {
+        match self.host_of(app_name).await? {
+            Some(host) => host.change_status(app_name, service_name, status).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::TraefikIngressProvider;
+
+    #[test]
+    fn should_place_app_on_host_that_runs_the_app() {
+        assert_eq!(placement(&[(false, 2), (true, 10), (false, 0)]), Some(1));
+    }
+
+    #[test]
+    fn should_place_new_app_on_least_loaded_host() {
+        assert_eq!(placement(&[(false, 7), (false, 3), (false, 3)]), Some(1));
+    }
+
+    #[test]
+    fn should_not_place_app_without_hosts() {
+        assert_eq!(placement(&[]), None);
+    }
+
+    #[test]
+    fn should_not_create_infrastructure_without_hosts() {
+        let result = MultiDockerInfrastructure::new(DockerRuntimeConfig::default(), || {
+            Box::new(TraefikIngressProvider)
+        });
+
+        assert!(matches!(
+            result,
+            Err(DockerInfrastructureError::MissingDockerHosts)
+        ));
+    }
+}
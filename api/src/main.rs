@@ -37,9 +37,14 @@ extern crate serde_derive;
 
 use crate::apps::host_meta_crawling;
 use crate::apps::Apps;
-use crate::config::{Config, Runtime};
-use crate::infrastructure::{Docker, Infrastructure, Kubernetes};
+use crate::apps::{OperationScheduler, RestartScheduler};
+use crate::config::{Config, IngressProviderKind, Runtime};
+use crate::infrastructure::{
+    Docker, Infrastructure, IngressProvider, Kubernetes, MultiDocker, NginxIngressProvider,
+    TraefikIngressProvider,
+};
 use crate::models::request_info::RequestInfo;
+use crate::models::CheckStatus;
 use clap::{App, Arg};
 use env_logger::Env;
 use openssl::x509::X509;
@@ -52,13 +57,22 @@ use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
 use url::Url;
 
 mod apps;
+mod audit;
+mod auth;
+mod capabilities;
 mod config;
+mod diagnostics;
+mod freezes;
 mod http_result;
 mod infrastructure;
 mod models;
+mod orphans;
+mod reload;
+mod scheduled_operations;
 mod services;
 mod tickets;
 mod webhooks;
@@ -94,9 +108,32 @@ fn openapi(request_info: RequestInfo) -> Option<String> {
     Some(to_string(&v).unwrap())
 }
 
+fn ingress_provider(kind: IngressProviderKind) -> Box<dyn IngressProvider> {
+    match kind {
+        IngressProviderKind::Traefik => Box::new(TraefikIngressProvider),
+        IngressProviderKind::Nginx => Box::new(NginxIngressProvider),
+    }
+}
+
 fn create_infrastructure(config: &Config) -> Result<Box<dyn Infrastructure>, StartUpError> {
+    let ingress = config.ingress_config().provider();
     match config.runtime_config() {
-        Runtime::Docker => Ok(Box::new(Docker::new())),
+        Runtime::Docker(docker_config) if !docker_config.hosts().is_empty() => {
+            let infrastructure = MultiDocker::new(docker_config, || ingress_provider(ingress))
+                .map_err(|err| StartUpError::CannotCreateInfrastructure {
+                    err: err.to_string(),
+                })?;
+            Ok(Box::new(infrastructure))
+        }
+        Runtime::Docker(docker_config) => {
+            let infrastructure =
+                Docker::new(docker_config, ingress_provider(ingress)).map_err(|err| {
+                    StartUpError::CannotCreateInfrastructure {
+                        err: err.to_string(),
+                    }
+                })?;
+            Ok(Box::new(infrastructure))
+        }
         Runtime::Kubernetes(kubernetes_config) => {
             let cluster_endpoint = match kubernetes_config.endpoint() {
                 Some(endpoint) => endpoint.clone(),
@@ -138,6 +175,7 @@ fn create_infrastructure(config: &Config) -> Result<Box<dyn Infrastructure>, Sta
                 cluster_endpoint,
                 cluster_ca,
                 cluster_token,
+                ingress_provider(ingress),
             )))
         }
     }
@@ -211,7 +249,8 @@ async fn main() -> Result<(), StartUpError> {
 
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    let config = match Config::load(argument_matches.value_of("config").unwrap_or("config.toml")) {
+    let config_path = argument_matches.value_of("config").unwrap_or("config.toml");
+    let config = match Config::load(config_path).await {
         Ok(config) => config,
         Err(e) => {
             error!("Cannot load config: {}", e);
@@ -231,23 +270,100 @@ async fn main() -> Result<(), StartUpError> {
     let (host_meta_cache, host_meta_crawler) = host_meta_crawling();
     let apps = Arc::new(apps);
     host_meta_crawler.spawn(apps.clone());
+    RestartScheduler::new().spawn(apps.clone());
+    OperationScheduler::new().spawn(apps.clone());
+
+    for check in apps.run_diagnostics().await.checks() {
+        match check.status() {
+            CheckStatus::Passed => debug!("Diagnostics {}: {}", check.name(), check.message()),
+            CheckStatus::Warning => warn!("Diagnostics {}: {}", check.name(), check.message()),
+            CheckStatus::Failed => error!("Diagnostics {}: {}", check.name(), check.message()),
+        }
+    }
+
+    let reconciling_apps = apps.clone();
+    tokio::spawn(async move {
+        if let Err(err) = reconciling_apps.reconcile_desired_state().await {
+            error!("Cannot restore the desired state of the apps: {}", err);
+        }
+    });
+
+    let draining_apps = apps.clone();
 
-    rocket::build()
-        .manage(config)
+    let rocket = rocket::build()
         .manage(apps)
         .manage(host_meta_cache)
         .mount("/", routes![index])
         .mount("/openapi.yaml", routes![openapi])
         .mount("/", routes![files])
         .mount("/api/apps", crate::apps::apps_routes())
+        .mount("/api", crate::apps::apps_batch_routes())
         .mount("/api", routes![tickets::tickets])
-        .mount("/api", routes![webhooks::webhooks])
-        .launch()
+        .mount("/api", routes![capabilities::capabilities])
+        .mount("/api", routes![diagnostics::diagnostics])
+        .mount("/api", routes![audit::audit])
+        .mount("/api", routes![reload::reload_config])
+        .mount("/api", routes![orphans::orphans, orphans::remove_orphans])
+        .mount(
+            "/api",
+            routes![
+                freezes::freezes,
+                freezes::declare_freeze,
+                freezes::lift_freeze
+            ],
+        )
+        .mount(
+            "/api",
+            routes![
+                scheduled_operations::scheduled_operations,
+                scheduled_operations::cancel_scheduled_operation
+            ],
+        )
+        .mount(
+            "/api",
+            routes![
+                webhooks::webhooks,
+                webhooks::dead_letters,
+                webhooks::redeliver_dead_letter
+            ],
+        )
+        .ignite()
         .await?;
 
+    let shutdown = rocket.shutdown();
+    tokio::spawn(async move {
+        wait_for_termination().await;
+
+        info!("Shutting down, waiting for running deployments to finish.");
+        draining_apps.begin_shutdown();
+        let drain_timeout = draining_apps.config().shutdown_drain_timeout();
+        if !draining_apps.drain(drain_timeout).await {
+            warn!(
+                "Running deployments did not finish within {:?}, shutting down anyway.",
+                drain_timeout
+            );
+        }
+        shutdown.notify();
+    });
+
+    rocket.launch().await?;
+
     Ok(())
 }
 
+/// Resolves when the process receives `SIGTERM`, e.g. when the container is stopped.
+async fn wait_for_termination() {
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(err) => {
+            error!("Cannot listen for SIGTERM: {}", err);
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
 #[derive(Debug, Fail)]
 enum StartUpError {
     #[fail(display = "Cannot read certificate authority from {}: {}", path, err)]
@@ -256,6 +372,8 @@ enum StartUpError {
     CannotReadToken { path: String, err: String },
     #[fail(display = "Cannot start HTTP server: {}", err)]
     CannotStartWebServer { err: String },
+    #[fail(display = "Cannot create the infrastructure: {}", err)]
+    CannotCreateInfrastructure { err: String },
 }
 
 impl std::convert::From<rocket::Error> for StartUpError {
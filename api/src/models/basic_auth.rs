@@ -0,0 +1,104 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use secstr::SecUtf8;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use uuid::Uuid;
+
+/// The credentials that protect the routes of an app. The password is only known when the
+/// credentials are generated; afterwards, only the htpasswd entry is stored with the services.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicAuthCredentials {
+    username: String,
+    password: SecUtf8,
+}
+
+impl BasicAuthCredentials {
+    #[cfg(test)]
+    pub fn new(username: String, password: SecUtf8) -> Self {
+        BasicAuthCredentials { username, password }
+    }
+
+    /// Generates credentials with a random password for the given user.
+    pub fn generate(username: &str) -> Self {
+        BasicAuthCredentials {
+            username: username.to_string(),
+            password: SecUtf8::from(Uuid::new_v4().to_simple().to_string()),
+        }
+    }
+
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+
+    /// The htpasswd entry of the credentials with the SHA-1 hash of the password which is
+    /// supported by Traefik.
+    pub fn htpasswd(&self) -> String {
+        let hash = openssl::sha::sha1(self.password.unsecure().as_bytes());
+        format!(
+            "{}:{{SHA}}{}",
+            self.username,
+            openssl::base64::encode_block(&hash)
+        )
+    }
+}
+
+impl Serialize for BasicAuthCredentials {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("BasicAuthCredentials", 2)?;
+        s.serialize_field("username", &self.username)?;
+        s.serialize_field("password", self.password.unsecure())?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_create_htpasswd_entry() {
+        let credentials =
+            BasicAuthCredentials::new(String::from("prevant"), SecUtf8::from("password"));
+
+        assert_eq!(
+            credentials.htpasswd(),
+            "prevant:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g="
+        );
+    }
+
+    #[test]
+    fn should_generate_different_passwords() {
+        let first = BasicAuthCredentials::generate("prevant");
+        let second = BasicAuthCredentials::generate("prevant");
+
+        assert_eq!(first.username(), "prevant");
+        assert_ne!(first.htpasswd(), second.htpasswd());
+    }
+}
@@ -0,0 +1,135 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use chrono::{DateTime, Utc};
+
+/// The results of the diagnostic pass that checks whether PREvant is able to operate properly,
+/// e.g. after the startup.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    checked_at: DateTime<Utc>,
+    checks: Vec<DiagnosticCheck>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    name: String,
+    status: CheckStatus,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repair: Option<Repair>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+/// Describes how a problem can be resolved and whether PREvant has resolved it on its own.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repair {
+    description: String,
+    applied: bool,
+}
+
+impl DiagnosticsReport {
+    pub fn new(checks: Vec<DiagnosticCheck>) -> Self {
+        DiagnosticsReport {
+            checked_at: Utc::now(),
+            checks,
+        }
+    }
+
+    pub fn checks(&self) -> &Vec<DiagnosticCheck> {
+        &self.checks
+    }
+}
+
+impl DiagnosticCheck {
+    pub fn passed(name: &str, message: String) -> Self {
+        Self::new(name, CheckStatus::Passed, message)
+    }
+
+    pub fn warning(name: &str, message: String) -> Self {
+        Self::new(name, CheckStatus::Warning, message)
+    }
+
+    pub fn failed(name: &str, message: String) -> Self {
+        Self::new(name, CheckStatus::Failed, message)
+    }
+
+    fn new(name: &str, status: CheckStatus, message: String) -> Self {
+        DiagnosticCheck {
+            name: name.to_string(),
+            status,
+            message,
+            repair: None,
+        }
+    }
+
+    /// Suggests a repair that administrators have to apply.
+    pub fn with_suggested_repair(mut self, description: String) -> Self {
+        self.repair = Some(Repair {
+            description,
+            applied: false,
+        });
+        self
+    }
+
+    /// Documents a repair that has already been applied by PREvant.
+    pub fn with_applied_repair(mut self, description: String) -> Self {
+        self.repair = Some(Repair {
+            description,
+            applied: true,
+        });
+        self
+    }
+
+    /// Qualifies the name of the check with the host that has been checked, e.g. if PREvant
+    /// distributes the apps across several Docker hosts.
+    pub fn on_host(mut self, host_name: &str) -> Self {
+        self.name = format!("{}/{}", host_name, self.name);
+        self
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn status(&self) -> &CheckStatus {
+        &self.status
+    }
+
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
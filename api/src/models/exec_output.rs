@@ -0,0 +1,49 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// A chunk of the output that a command, executed within the container of a service, has written
+/// to stdout or stderr.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecOutput {
+    Stdout(String),
+    Stderr(String),
+}
+
+impl ExecOutput {
+    /// The name of the stream, i.e. `stdout` or `stderr`.
+    pub fn stream_name(&self) -> &'static str {
+        match self {
+            ExecOutput::Stdout(_) => "stdout",
+            ExecOutput::Stderr(_) => "stderr",
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            ExecOutput::Stdout(text) | ExecOutput::Stderr(text) => text,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// The resources of the host that runs the services. Resources that the infrastructure cannot
+/// measure, e.g. the free memory of a remote Docker host, are `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostCapacity {
+    containers: usize,
+    free_memory: Option<u64>,
+    free_disk: Option<u64>,
+}
+
+impl HostCapacity {
+    pub fn new(containers: usize, free_memory: Option<u64>, free_disk: Option<u64>) -> Self {
+        HostCapacity {
+            containers,
+            free_memory,
+            free_disk,
+        }
+    }
+
+    /// The number of containers of PREvant's apps.
+    pub fn containers(&self) -> usize {
+        self.containers
+    }
+
+    /// The free memory in bytes.
+    pub fn free_memory(&self) -> Option<u64> {
+        self.free_memory
+    }
+
+    /// The free disk space in bytes.
+    pub fn free_disk(&self) -> Option<u64> {
+        self.free_disk
+    }
+}
@@ -32,107 +32,161 @@ use std::str::FromStr;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Image {
+    /// An image referenced by its name, e.g. `ghcr.io/org/app:1.0` or `ghcr.io/org/app@sha256:…`.
+    /// The registry is `None` for images of the Docker Hub and single component repositories of
+    /// the Docker Hub are stored with the `library/` prefix.
     Named {
-        image_repository: String,
         registry: Option<String>,
-        image_user: Option<String>,
-        image_tag: Option<String>,
-    },
-    Digest {
-        hash: String,
+        repository: String,
+        tag: Option<String>,
+        digest: Option<String>,
     },
+    /// An image referenced by its id
+    Digest { hash: String },
 }
 
 impl Image {
+    /// Returns the tag of the image. Named images without tag and digest default to `latest`.
     pub fn tag(&self) -> Option<String> {
         match &self {
             Image::Digest { .. } => None,
-            Image::Named {
-                image_repository: _,
-                registry: _,
-                image_user: _,
-                image_tag,
-            } => match &image_tag {
-                None => Some(String::from("latest")),
-                Some(tag) => Some(tag.clone()),
+            Image::Named { tag, digest, .. } => match (tag, digest) {
+                (Some(tag), _) => Some(tag.clone()),
+                (None, None) => Some(String::from("latest")),
+                (None, Some(_)) => None,
             },
         }
     }
 
-    pub fn name(&self) -> Option<String> {
+    pub fn digest(&self) -> Option<String> {
         match &self {
             Image::Digest { .. } => None,
-            Image::Named {
-                image_repository,
-                registry: _,
-                image_user,
-                image_tag: _,
-            } => {
-                let user = match &image_user {
-                    None => String::from("library"),
-                    Some(user) => user.clone(),
-                };
+            Image::Named { digest, .. } => digest.clone(),
+        }
+    }
 
-                Some(format!("{}/{}", user, image_repository))
-            }
+    /// Returns the reference that identifies the manifest of the image in the registry: the digest
+    /// if the image is pinned to a digest, otherwise the tag.
+    pub fn reference(&self) -> Option<String> {
+        self.digest().or_else(|| self.tag())
+    }
+
+    pub fn name(&self) -> Option<String> {
+        match &self {
+            Image::Digest { .. } => None,
+            Image::Named { repository, .. } => Some(repository.clone()),
         }
     }
 
     pub fn registry(&self) -> Option<String> {
         match &self {
             Image::Digest { .. } => None,
-            Image::Named {
-                image_repository: _,
-                registry,
-                image_user: _,
-                image_tag: _,
-            } => Some(
+            Image::Named { registry, .. } => Some(
                 registry
                     .clone()
-                    .unwrap_or_else(|| String::from("docker.io")),
+                    .unwrap_or_else(|| String::from(DOCKER_HUB_REGISTRY)),
             ),
         }
     }
 }
 
+const DOCKER_HUB_REGISTRY: &str = "docker.io";
+
+lazy_static! {
+    static ref ID_REGEX: Regex = Regex::new(r"^(sha256:)?[a-fA-F0-9]+$").unwrap();
+    static ref REGISTRY_REGEX: Regex =
+        Regex::new(r"^[a-zA-Z0-9]([a-zA-Z0-9-]*[a-zA-Z0-9])?(\.[a-zA-Z0-9]([a-zA-Z0-9-]*[a-zA-Z0-9])?)*(:[0-9]+)?$")
+            .unwrap();
+    static ref PATH_COMPONENT_REGEX: Regex =
+        Regex::new(r"^[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*$").unwrap();
+    static ref TAG_REGEX: Regex = Regex::new(r"^[\w][\w.-]{0,127}$").unwrap();
+    static ref DIGEST_REGEX: Regex =
+        Regex::new(r"^[a-z0-9]+([+._-][a-z0-9]+)*:[a-fA-F0-9]{32,}$").unwrap();
+}
+
 /// Parse a docker image string and returns an image
 impl FromStr for Image {
     type Err = ServiceError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut regex = Regex::new(r"^(sha256:)?(?P<id>[a-fA-F0-9]+)$").unwrap();
-        if let Some(_captures) = regex.captures(s) {
+        if ID_REGEX.is_match(s) {
             return Ok(Image::Digest {
                 hash: s.to_string(),
             });
         }
 
-        regex = Regex::new(
-            r"^(((?P<registry>.+)/)?(?P<user>[\w-]+)/)?(?P<repo>[\w-]+)(:(?P<tag>[\w\.-]+))?$",
-        )
-        .unwrap();
-        let captures = match regex.captures(s) {
-            Some(captures) => captures,
-            None => {
-                return Err(ServiceError::InvalidImageString {
-                    invalid_string: s.to_string(),
-                });
+        let invalid = |reason: &str| ServiceError::InvalidImageString {
+            invalid_string: s.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let (name_and_tag, digest) = match s.split_once('@') {
+            Some((name_and_tag, digest)) => {
+                if !DIGEST_REGEX.is_match(digest) {
+                    return Err(invalid("the digest must be of the form algorithm:hex"));
+                }
+                (name_and_tag, Some(digest.to_string()))
             }
+            None => (s, None),
         };
 
-        let repo = captures
-            .name("repo")
-            .map(|m| String::from(m.as_str()))
-            .unwrap();
-        let registry = captures.name("registry").map(|m| String::from(m.as_str()));
-        let user = captures.name("user").map(|m| String::from(m.as_str()));
-        let tag = captures.name("tag").map(|m| String::from(m.as_str()));
+        // A colon after the last slash separates the tag, other colons belong to the port of the
+        // registry.
+        let last_slash = name_and_tag.rfind('/').map_or(0, |index| index + 1);
+        let (name, tag) = match name_and_tag[last_slash..].find(':') {
+            Some(index) => {
+                let (name, tag) = name_and_tag.split_at(last_slash + index);
+                let tag = &tag[1..];
+                if !TAG_REGEX.is_match(tag) {
+                    return Err(invalid(
+                        "the tag must consist of at most 128 word characters, dots, and dashes",
+                    ));
+                }
+                (name, Some(tag.to_string()))
+            }
+            None => (name_and_tag, None),
+        };
+
+        let mut components = name.split('/').collect::<Vec<_>>();
+        let registry = match components.first().copied() {
+            Some(first)
+                if components.len() > 1
+                    && (first.contains('.') || first.contains(':') || first == "localhost") =>
+            {
+                if !REGISTRY_REGEX.is_match(first) {
+                    return Err(invalid(
+                        "the registry must be a host name with optional port",
+                    ));
+                }
+                let registry = components.remove(0);
+                if registry == DOCKER_HUB_REGISTRY {
+                    None
+                } else {
+                    Some(registry.to_string())
+                }
+            }
+            _ => None,
+        };
+
+        if components
+            .iter()
+            .any(|component| !PATH_COMPONENT_REGEX.is_match(component))
+        {
+            return Err(invalid(
+                "the repository must consist of lowercase alphanumeric components separated by slashes",
+            ));
+        }
+
+        let mut repository = components.join("/");
+        if registry.is_none() && components.len() == 1 {
+            repository = format!("library/{}", repository);
+        }
 
         Ok(Image::Named {
-            image_repository: repo,
             registry,
-            image_user: user,
-            image_tag: tag,
+            repository,
+            tag,
+            digest,
         })
     }
 }
@@ -142,27 +196,24 @@ impl Display for Image {
         match &self {
             Image::Digest { hash } => write!(f, "{}", hash),
             Image::Named {
-                image_repository,
                 registry,
-                image_user,
-                image_tag,
+                repository,
+                tag,
+                digest,
             } => {
-                let registry = match &registry {
-                    None => String::from("docker.io"),
-                    Some(registry) => registry.clone(),
-                };
-
-                let user = match &image_user {
-                    None => String::from("library"),
-                    Some(user) => user.clone(),
-                };
-
-                let tag = match &image_tag {
-                    None => "latest".to_owned(),
-                    Some(tag) => tag.clone(),
-                };
-
-                write!(f, "{}/{}/{}:{}", registry, user, image_repository, tag)
+                let registry = registry.as_deref().unwrap_or(DOCKER_HUB_REGISTRY);
+                write!(f, "{}/{}", registry, repository)?;
+
+                match (tag, digest) {
+                    (Some(tag), _) => write!(f, ":{}", tag)?,
+                    (None, None) => write!(f, ":latest")?,
+                    (None, Some(_)) => {}
+                }
+
+                match digest {
+                    Some(digest) => write!(f, "@{}", digest),
+                    None => Ok(()),
+                }
             }
         }
     }
@@ -292,4 +343,75 @@ mod tests {
         assert_eq!(&image.to_string(), "localhost:5000/library/nginx:latest");
         assert_eq!(&image.registry().unwrap(), "localhost:5000");
     }
+
+    #[test]
+    fn should_parse_image_with_digest() {
+        let image = Image::from_str(
+            "ghcr.io/org/app@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913",
+        )
+        .unwrap();
+
+        assert_eq!(&image.registry().unwrap(), "ghcr.io");
+        assert_eq!(&image.name().unwrap(), "org/app");
+        assert_eq!(image.tag(), None);
+        assert_eq!(
+            &image.reference().unwrap(),
+            "sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+        );
+        assert_eq!(
+            &image.to_string(),
+            "ghcr.io/org/app@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+        );
+    }
+
+    #[test]
+    fn should_parse_image_with_tag_and_digest() {
+        let image = Image::from_str(
+            "ghcr.io/org/app:1.0@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913",
+        )
+        .unwrap();
+
+        assert_eq!(&image.tag().unwrap(), "1.0");
+        assert_eq!(
+            &image.to_string(),
+            "ghcr.io/org/app:1.0@sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913"
+        );
+    }
+
+    #[test]
+    fn should_parse_image_with_nested_repository() {
+        let image = Image::from_str("registry.gitlab.com/group/subgroup/app:main").unwrap();
+
+        assert_eq!(&image.registry().unwrap(), "registry.gitlab.com");
+        assert_eq!(&image.name().unwrap(), "group/subgroup/app");
+        assert_eq!(&image.tag().unwrap(), "main");
+    }
+
+    #[test]
+    fn should_parse_image_of_docker_hub_with_explicit_registry() {
+        let image = Image::from_str("docker.io/nginx").unwrap();
+
+        assert_eq!(image, Image::from_str("nginx:latest").unwrap());
+    }
+
+    #[test]
+    fn should_not_parse_image_with_invalid_parts() {
+        for invalid in &[
+            "",
+            "\n",
+            "Nginx",
+            "nginx:",
+            "nginx:-latest",
+            "ghcr.io/org/app@sha256:",
+            "ghcr.io/org/app@9895c9b9",
+            "ghcr.io//app",
+            "ghcr.io/org/app:1.0:2.0",
+        ] {
+            assert!(
+                Image::from_str(invalid).is_err(),
+                "{:?} should be invalid",
+                invalid
+            );
+        }
+    }
 }
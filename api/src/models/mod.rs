@@ -26,21 +26,38 @@
 
 pub use app_name::{AppName, AppNameError};
 pub use app_status_change_id::{AppStatusChangeId, AppStatusChangeIdError};
+pub use basic_auth::BasicAuthCredentials;
+pub use diagnostics::{CheckStatus, DiagnosticCheck, DiagnosticsReport};
+pub use exec_output::ExecOutput;
+pub use host_capacity::HostCapacity;
 pub use image::Image;
 pub use logs_chunks::LogChunk;
+pub use orphan::{Orphan, OrphanReason};
 pub use request_info::RequestInfo;
 pub use service::{ContainerType, ServiceBuilder, ServiceBuilderError};
-pub use service_config::{Environment, EnvironmentVariable, Router, ServiceConfig};
+pub use service_config::{
+    deployment_waves, is_dependency, DependencyCycleError, DeploymentStrategy, Environment,
+    EnvironmentVariable, Port, Router, Routing, ServiceConfig, HTTP_PORT_NAME,
+};
+pub use service_stats::ServiceStats;
+pub use service_status_report::ServiceStatusReport;
 pub use web_host_meta::WebHostMeta;
 
 mod app_name;
 mod app_status_change_id;
+mod basic_auth;
+mod diagnostics;
+mod exec_output;
+mod host_capacity;
 mod image;
 mod logs_chunks;
+mod orphan;
 pub mod request_info;
 #[cfg_attr(test, macro_use)]
 pub mod service;
 mod service_config;
+mod service_stats;
+mod service_status_report;
 pub mod ticket_info;
 pub mod web_hook_info;
 pub mod web_host_meta;
@@ -0,0 +1,85 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// A resource that carries the labels of PREvant but does not belong to a properly deployed app,
+/// e.g. a container that remained after PREvant crashed during a deployment.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Orphan {
+    id: String,
+    app_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_name: Option<String>,
+    reason: OrphanReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrphanReason {
+    /// The container coordinated a deployment or deletion that has been interrupted.
+    InterruptedOperation,
+    /// The labels of the container do not describe a service.
+    InvalidLabels,
+    /// The container has been created but never been started.
+    NeverStarted,
+    /// A newer container of the same service exists.
+    Duplicate,
+    /// The container is a companion of an app without any other service.
+    CompanionWithoutServices,
+}
+
+impl Orphan {
+    pub fn new(
+        id: String,
+        app_name: String,
+        service_name: Option<String>,
+        reason: OrphanReason,
+    ) -> Self {
+        Orphan {
+            id,
+            app_name,
+            service_name,
+            reason,
+        }
+    }
+
+    pub fn app_name(&self) -> &String {
+        &self.app_name
+    }
+
+    pub fn reason(&self) -> &OrphanReason {
+        &self.reason
+    }
+}
+
+impl std::fmt::Display for Orphan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.service_name {
+            Some(service_name) => write!(f, "{}/{}", self.app_name, service_name),
+            None => write!(f, "{}/{}", self.app_name, self.id),
+        }
+    }
+}
@@ -24,12 +24,12 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::models::{web_host_meta::WebHostMeta, Image, ServiceConfig};
+use crate::models::{web_host_meta::WebHostMeta, BasicAuthCredentials, Image, ServiceConfig};
 use chrono::{DateTime, Utc};
 use serde::ser::{Serialize, Serializer};
 use serde::Deserialize;
 use std::fmt::Display;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 use url::Url;
 
@@ -43,6 +43,9 @@ pub struct Service {
     web_host_meta: Option<WebHostMeta>,
     state: State,
     config: ServiceConfig,
+    next_restart: Option<DateTime<Utc>>,
+    stale: bool,
+    credentials: Option<BasicAuthCredentials>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -67,6 +70,10 @@ pub struct State {
     status: ServiceStatus,
     #[serde(skip)]
     started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -81,10 +88,14 @@ impl Service {
         &self.app_name
     }
 
-    fn service_url(&self) -> Option<Url> {
-        self.base_url.clone().map(|url| {
-            url.join(&format!("/{}/{}/", &self.app_name, self.service_name()))
-                .unwrap()
+    pub fn service_url(&self) -> Option<Url> {
+        self.base_url.clone().map(|mut url| {
+            if let Some(host) = self.config.routing_host(&self.app_name) {
+                if url.set_host(Some(&host)).is_err() {
+                    warn!("Cannot use {} as host of the service URL", host);
+                }
+            }
+            url.join(&self.config.routing_path(&self.app_name)).unwrap()
         })
     }
 
@@ -111,6 +122,16 @@ impl Service {
         }
     }
 
+    pub fn endpoint_addr(&self) -> Option<SocketAddr> {
+        match &self.endpoint {
+            None => None,
+            Some(endpoint) => Some(SocketAddr::new(
+                endpoint.internal_addr,
+                endpoint.exposed_port,
+            )),
+        }
+    }
+
     pub fn endpoint_url(&self) -> Option<Url> {
         match &self.endpoint {
             None => None,
@@ -126,9 +147,50 @@ impl Service {
         &self.state.status
     }
 
+    /// How often the infrastructure restarted the service, if the infrastructure tracks restarts.
+    pub fn restart_count(&self) -> Option<u64> {
+        self.state.restart_count
+    }
+
+    /// The exit code of the last run of the service, if it has exited at least once.
+    pub fn exit_code(&self) -> Option<u64> {
+        self.state.exit_code
+    }
+
     pub fn image(&self) -> &Image {
         self.config.image()
     }
+
+    pub fn web_host_meta(&self) -> Option<&WebHostMeta> {
+        self.web_host_meta.as_ref()
+    }
+
+    /// The name of the user who deployed the service, if authentication has been enabled.
+    pub fn owner(&self) -> Option<&String> {
+        self.config.owner()
+    }
+
+    /// The keys of the tickets the service is linked to.
+    pub fn tickets(&self) -> &[String] {
+        self.config.tickets()
+    }
+
+    /// The next time the service will be restarted according to the configured restart schedules.
+    pub fn next_restart(&self) -> Option<&DateTime<Utc>> {
+        self.next_restart.as_ref()
+    }
+
+    /// A replica is stale if the image of its source service has changed since it has been
+    /// replicated.
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// The generated basic auth credentials of the service which are only known right after the
+    /// first deployment of the app.
+    pub fn credentials(&self) -> Option<&BasicAuthCredentials> {
+        self.credentials.as_ref()
+    }
 }
 
 impl Serialize for Service {
@@ -149,6 +211,16 @@ impl Serialize for Service {
             #[serde(skip_serializing_if = "Option::is_none")]
             open_api_url: Option<Url>,
             state: &'a State,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            owner: Option<&'a String>,
+            #[serde(skip_serializing_if = "<[String]>::is_empty")]
+            tickets: &'a [String],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            next_restart: Option<&'a DateTime<Utc>>,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            stale: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            basic_auth: Option<&'a BasicAuthCredentials>,
         }
 
         #[derive(Serialize)]
@@ -182,6 +254,11 @@ impl Serialize for Service {
             version,
             open_api_url,
             state: &self.state,
+            owner: self.owner(),
+            tickets: self.tickets(),
+            next_restart: self.next_restart(),
+            stale: self.stale,
+            basic_auth: self.credentials(),
         };
 
         s.serialize(serializer)
@@ -195,9 +272,14 @@ pub struct ServiceBuilder {
     config: Option<ServiceConfig>,
     status: Option<ServiceStatus>,
     started_at: Option<DateTime<Utc>>,
+    restart_count: Option<u64>,
+    exit_code: Option<u64>,
     base_url: Option<Url>,
     web_host_meta: Option<WebHostMeta>,
     endpoint: Option<ServiceEndpoint>,
+    next_restart: Option<DateTime<Utc>>,
+    stale: bool,
+    credentials: Option<BasicAuthCredentials>,
 }
 
 impl ServiceBuilder {
@@ -207,10 +289,15 @@ impl ServiceBuilder {
             app_name: None,
             status: None,
             started_at: None,
+            restart_count: None,
+            exit_code: None,
             base_url: None,
             web_host_meta: None,
             endpoint: None,
             config: None,
+            next_restart: None,
+            stale: false,
+            credentials: None,
         }
     }
 
@@ -232,7 +319,12 @@ impl ServiceBuilder {
             state: State {
                 started_at,
                 status: self.status.unwrap_or(ServiceStatus::Running),
+                restart_count: self.restart_count,
+                exit_code: self.exit_code,
             },
+            next_restart: self.next_restart,
+            stale: self.stale,
+            credentials: self.credentials,
         })
     }
 
@@ -264,6 +356,16 @@ impl ServiceBuilder {
         self
     }
 
+    pub fn restart_count(mut self, restart_count: u64) -> Self {
+        self.restart_count = Some(restart_count);
+        self
+    }
+
+    pub fn exit_code(mut self, exit_code: Option<u64>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
     pub fn base_url(mut self, base_url: Url) -> Self {
         self.base_url = Some(base_url);
         self
@@ -279,6 +381,21 @@ impl ServiceBuilder {
         self
     }
 
+    pub fn next_restart(mut self, next_restart: Option<DateTime<Utc>>) -> Self {
+        self.next_restart = next_restart;
+        self
+    }
+
+    pub fn stale(mut self, stale: bool) -> Self {
+        self.stale = stale;
+        self
+    }
+
+    pub fn credentials(mut self, credentials: Option<BasicAuthCredentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
     pub fn endpoint(mut self, addr: IpAddr, port: u16) -> Self {
         self.endpoint = Some(ServiceEndpoint {
             internal_addr: addr,
@@ -306,9 +423,14 @@ impl From<Service> for ServiceBuilder {
             config: Some(service.config),
             status: Some(service.state.status),
             started_at: Some(service.state.started_at),
+            restart_count: service.state.restart_count,
+            exit_code: service.state.exit_code,
             base_url: service.base_url,
             web_host_meta: service.web_host_meta,
             endpoint: service.endpoint,
+            next_restart: service.next_restart,
+            stale: service.stale,
+            credentials: service.credentials,
         }
     }
 }
@@ -367,8 +489,11 @@ pub enum ServiceError {
         invalid_name
     )]
     InvalidServiceName { invalid_name: String },
-    #[fail(display = "Invalid image: {}", invalid_string)]
-    InvalidImageString { invalid_string: String },
+    #[fail(display = "Invalid image {:?}: {}", invalid_string, reason)]
+    InvalidImageString {
+        invalid_string: String,
+        reason: String,
+    },
 }
 
 #[cfg(test)]
@@ -376,6 +501,33 @@ mod tests {
     use super::*;
     use crate::sc;
 
+    #[test]
+    fn should_deserialize_serialized_service_with_client_model() {
+        let mut config = sc!("nginx", "nginx");
+        config.set_tickets(vec![String::from("PROJ-123")]);
+        let service = ServiceBuilder::new()
+            .id("some-random-id".to_string())
+            .app_name("master".to_string())
+            .config(config)
+            .started_at(Utc::now())
+            .restart_count(2)
+            .exit_code(Some(137))
+            .next_restart(Some(Utc::now()))
+            .stale(true)
+            .credentials(Some(BasicAuthCredentials::new(
+                String::from("prevant"),
+                secstr::SecUtf8::from("s3cr3t"),
+            )))
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(&service).unwrap();
+
+        let client_service =
+            serde_json::from_value::<prevant_client::Service>(json.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&client_service).unwrap(), json);
+    }
+
     #[test]
     fn should_build_service() {
         let started_at = Utc::now();
@@ -0,0 +1,169 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use super::ServiceConfig;
+use std::collections::HashSet;
+
+/// Groups the service configurations into waves that have to be started one after another. The
+/// services of a wave only depend on services of previous waves and, thus, the services of a wave
+/// can be started concurrently.
+///
+/// Dependencies to services that are not part of `configs` will be ignored because these services
+/// are expected to be running already.
+pub fn deployment_waves(
+    configs: &[ServiceConfig],
+) -> Result<Vec<Vec<&ServiceConfig>>, DependencyCycleError> {
+    let service_names = configs
+        .iter()
+        .map(|config| config.service_name().as_str())
+        .collect::<HashSet<_>>();
+
+    let mut started = HashSet::new();
+    let mut pending = configs.iter().collect::<Vec<_>>();
+    let mut waves = Vec::new();
+
+    while !pending.is_empty() {
+        let (wave, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|config| {
+            config.depends_on().iter().all(|dependency| {
+                !service_names.contains(dependency.as_str()) || started.contains(dependency)
+            })
+        });
+
+        if wave.is_empty() {
+            let mut services = rest
+                .iter()
+                .map(|config| config.service_name().clone())
+                .collect::<Vec<_>>();
+            services.sort();
+            return Err(DependencyCycleError { services });
+        }
+
+        started.extend(wave.iter().map(|config| config.service_name().clone()));
+        waves.push(wave);
+        pending = rest;
+    }
+
+    Ok(waves)
+}
+
+/// Returns `true` if any of the `configs` depends on the service with the given name.
+pub fn is_dependency(configs: &[ServiceConfig], service_name: &str) -> bool {
+    configs.iter().any(|config| {
+        config
+            .depends_on()
+            .iter()
+            .any(|dependency| dependency == service_name)
+    })
+}
+
+#[derive(Clone, Debug, Fail, PartialEq)]
+#[fail(
+    display = "The services {:?} have cyclic dependencies on each other.",
+    services
+)]
+pub struct DependencyCycleError {
+    services: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc;
+
+    fn sc_depending_on(service_name: &str, dependencies: &[&str]) -> ServiceConfig {
+        let mut config = sc!(service_name);
+        config.set_depends_on(dependencies.iter().map(|d| d.to_string()).collect());
+        config
+    }
+
+    fn names<'a>(waves: &[Vec<&'a ServiceConfig>]) -> Vec<Vec<&'a str>> {
+        waves
+            .iter()
+            .map(|wave| wave.iter().map(|c| c.service_name().as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn should_start_independent_services_in_one_wave() {
+        let configs = vec![sc!("service-a"), sc!("service-b")];
+
+        let waves = deployment_waves(&configs).unwrap();
+
+        assert_eq!(names(&waves), vec![vec!["service-a", "service-b"]]);
+    }
+
+    #[test]
+    fn should_start_dependencies_first() {
+        let configs = vec![
+            sc_depending_on("backend", &["db", "kafka"]),
+            sc_depending_on("frontend", &["backend"]),
+            sc!("db"),
+            sc!("kafka"),
+        ];
+
+        let waves = deployment_waves(&configs).unwrap();
+
+        assert_eq!(
+            names(&waves),
+            vec![vec!["db", "kafka"], vec!["backend"], vec!["frontend"]]
+        );
+    }
+
+    #[test]
+    fn should_ignore_dependencies_outside_of_deployment() {
+        let configs = vec![sc_depending_on("backend", &["db"])];
+
+        let waves = deployment_waves(&configs).unwrap();
+
+        assert_eq!(names(&waves), vec![vec!["backend"]]);
+    }
+
+    #[test]
+    fn should_detect_cyclic_dependencies() {
+        let configs = vec![
+            sc_depending_on("service-a", &["service-b"]),
+            sc_depending_on("service-b", &["service-a"]),
+            sc!("db"),
+        ];
+
+        let err = deployment_waves(&configs).unwrap_err();
+
+        assert_eq!(
+            err,
+            DependencyCycleError {
+                services: vec![String::from("service-a"), String::from("service-b")]
+            }
+        );
+    }
+
+    #[test]
+    fn should_identify_dependencies() {
+        let configs = vec![sc_depending_on("backend", &["db"]), sc!("db")];
+
+        assert!(is_dependency(&configs, "db"));
+        assert!(!is_dependency(&configs, "backend"));
+    }
+}
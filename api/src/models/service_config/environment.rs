@@ -26,7 +26,8 @@
 
 use secstr::SecUtf8;
 use serde::de::Error as SerdeError;
-use serde::{Deserialize, Deserializer};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
@@ -68,6 +69,28 @@ impl Environment {
     }
 }
 
+/// Serializes the variables in their detailed form, i.e. including whether they are templated or
+/// replicated, so that they can be deserialized without losing information.
+impl Serialize for Environment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for variable in &self.values {
+            map.serialize_entry(
+                variable.key(),
+                &serde_json::json!({
+                    "value": variable.value().unsecure(),
+                    "templated": variable.templated(),
+                    "replicate": variable.replicate(),
+                }),
+            )?;
+        }
+        map.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for Environment {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -25,13 +25,17 @@
  */
 use crate::models::service::ContainerType;
 use crate::models::Image;
+pub use dependencies::{deployment_waves, is_dependency, DependencyCycleError};
 pub use environment::{Environment, EnvironmentVariable};
+use regex::{Captures, Regex};
+use serde::ser::{Serialize, Serializer};
 use serde::Deserialize;
 use serde_value::Value;
+use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
-use std::hash::Hash;
 use std::path::PathBuf;
 
+mod dependencies;
 mod environment;
 mod templating;
 
@@ -43,7 +47,6 @@ pub struct ServiceConfig {
     env: Option<Environment>,
     // TODO: rename this field because it does not match to volumes any more (it is file content, cf. issue #8)
     volumes: Option<BTreeMap<PathBuf, String>>,
-    #[serde(skip)]
     labels: Option<BTreeMap<String, String>>,
     #[serde(skip, default = "ContainerType::default")]
     container_type: ContainerType,
@@ -53,6 +56,31 @@ pub struct ServiceConfig {
     router: Option<Router>,
     #[serde(skip)]
     middlewares: Option<BTreeMap<String, Value>>,
+    #[serde(skip)]
+    owner: Option<String>,
+    #[serde(skip)]
+    tickets: Vec<String>,
+    #[serde(skip)]
+    skipped_companions: Vec<String>,
+    #[serde(skip)]
+    companion_definition: Option<String>,
+    #[serde(skip)]
+    basic_auth: Option<String>,
+    depends_on: Option<Vec<String>>,
+    ports: Option<Vec<Port>>,
+    routing: Option<Routing>,
+    command: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    #[serde(skip)]
+    deployed_fingerprint: Option<String>,
+    #[serde(skip)]
+    deployed_image_digest: Option<String>,
+    #[serde(skip)]
+    replicated_from: Option<String>,
+    #[serde(skip)]
+    replicated_image_digest: Option<String>,
+    #[serde(skip)]
+    deployment_strategy: DeploymentStrategy,
 }
 
 impl ServiceConfig {
@@ -67,6 +95,21 @@ impl ServiceConfig {
             port: 80,
             router: None,
             middlewares: None,
+            owner: None,
+            tickets: Vec::new(),
+            skipped_companions: Vec::new(),
+            companion_definition: None,
+            basic_auth: None,
+            depends_on: None,
+            ports: None,
+            routing: None,
+            command: None,
+            entrypoint: None,
+            deployed_fingerprint: None,
+            deployed_image_digest: None,
+            replicated_from: None,
+            replicated_image_digest: None,
+            deployment_strategy: DeploymentStrategy::default(),
         }
     }
 
@@ -83,6 +126,10 @@ impl ServiceConfig {
         &self.image
     }
 
+    pub fn set_image(&mut self, image: Image) {
+        self.image = image;
+    }
+
     pub fn set_service_name(&mut self, service_name: &String) {
         self.service_name = service_name.clone()
     }
@@ -102,12 +149,12 @@ impl ServiceConfig {
         }
     }
 
-    #[deprecated]
     pub fn set_labels(&mut self, labels: Option<BTreeMap<String, String>>) {
         self.labels = labels;
     }
 
-    #[deprecated]
+    /// The labels that will be applied to the container of the service, e.g. for log shippers or
+    /// cost accounting. PREvant's own labels cannot be overwritten by them.
     pub fn labels<'a, 'b: 'a>(&'b self) -> Option<&'a BTreeMap<String, String>> {
         match &self.labels {
             None => None,
@@ -140,8 +187,26 @@ impl ServiceConfig {
         self.port = port;
     }
 
+    /// Returns the port that Traefik routes the HTTP requests to: the port named `http`, if
+    /// there is one, or the port exposed by the image.
     pub fn port(&self) -> u16 {
-        self.port
+        self.ports()
+            .iter()
+            .find(|port| port.name() == HTTP_PORT_NAME)
+            .map_or(self.port, |port| port.port())
+    }
+
+    pub fn set_ports(&mut self, ports: Vec<Port>) {
+        self.ports = Some(ports);
+    }
+
+    /// The explicitly configured ports of the service. All ports, except for the `http` port, are
+    /// only reachable within the network of the app.
+    pub fn ports(&self) -> &[Port] {
+        match &self.ports {
+            Some(ports) => ports,
+            None => &[],
+        }
     }
 
     pub fn set_router(&mut self, router: Router) {
@@ -155,17 +220,225 @@ impl ServiceConfig {
         }
     }
 
+    pub fn set_routing(&mut self, routing: Routing) {
+        self.routing = Some(routing);
+    }
+
+    /// The routing options of the service, i.e. under which path and host Traefik exposes the
+    /// service.
+    pub fn routing(&self) -> Option<&Routing> {
+        self.routing.as_ref()
+    }
+
+    /// The path prefix under which the service is reachable, by default `/{app}/{service}/`.
+    pub fn routing_path(&self, app_name: &str) -> String {
+        match &self.routing {
+            Some(routing) => routing.path(app_name, &self.service_name),
+            None => Routing::default().path(app_name, &self.service_name),
+        }
+    }
+
+    /// The host under which the service is reachable, if the service uses host-based routing.
+    pub fn routing_host(&self, app_name: &str) -> Option<String> {
+        self.routing
+            .as_ref()
+            .and_then(|routing| routing.host(app_name, &self.service_name))
+    }
+
+    /// Whether the path prefix is stripped before the requests are forwarded to the service.
+    pub fn strips_routing_path(&self, app_name: &str) -> bool {
+        match &self.routing {
+            Some(routing) => routing.strip_prefix(app_name, &self.service_name),
+            None => true,
+        }
+    }
+
     pub fn traefik_rule(&self, app_name: &String) -> String {
         match &self.router {
-            None => format!("PathPrefix(`/{}/{}/`)", app_name, &self.service_name),
+            None => {
+                let path_rule = format!("PathPrefix(`{}`)", self.routing_path(app_name));
+                match self.routing_host(app_name) {
+                    Some(host) => format!("Host(`{}`) && {}", host, path_rule),
+                    None => path_rule,
+                }
+            }
             Some(router) => router.rule.clone(),
         }
     }
 
+    /// Moves the routes that refer to the app `from` to the app `to`, e.g. when the app is cloned.
+    /// Only the path prefix `/{from}/` and the host labels that are equal to `from` are rewritten.
+    /// The placeholders of the routing options refer to the new app anyway.
+    pub fn move_routes(&mut self, from: &str, to: &str) {
+        if let Some(router) = &self.router {
+            self.router = Some(router.with_rule(move_rule(router.rule(), from, to)));
+        }
+        if let Some(routing) = &self.routing {
+            self.routing = Some(Routing {
+                path: routing.path.as_ref().map(|path| move_path(path, from, to)),
+                host: routing.host.as_ref().map(|host| move_host(host, from, to)),
+                ..routing.clone()
+            });
+        }
+    }
+
+    pub fn set_command(&mut self, command: Option<Vec<String>>) {
+        self.command = command;
+    }
+
+    /// The arguments that replace the default command of the image.
+    pub fn command(&self) -> Option<&Vec<String>> {
+        self.command.as_ref()
+    }
+
+    pub fn set_entrypoint(&mut self, entrypoint: Option<Vec<String>>) {
+        self.entrypoint = entrypoint;
+    }
+
+    /// The executable and its arguments that replace the entrypoint of the image. An empty
+    /// entrypoint resets the entrypoint of the image.
+    pub fn entrypoint(&self) -> Option<&Vec<String>> {
+        self.entrypoint.as_ref()
+    }
+
     pub fn set_middlewares(&mut self, middlewares: BTreeMap<String, Value>) {
         self.middlewares = Some(middlewares);
     }
 
+    /// Sets the name of the user who deployed the service.
+    pub fn set_owner(&mut self, owner: Option<String>) {
+        self.owner = owner;
+    }
+
+    pub fn owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// Sets the keys of the tickets, e.g. `PROJ-123`, that the service is linked to.
+    pub fn set_tickets(&mut self, tickets: Vec<String>) {
+        self.tickets = tickets;
+    }
+
+    pub fn tickets(&self) -> &[String] {
+        &self.tickets
+    }
+
+    /// Sets the names of the companions that the deployment request of the service skipped.
+    pub fn set_skipped_companions(&mut self, skipped_companions: Vec<String>) {
+        self.skipped_companions = skipped_companions;
+    }
+
+    pub fn skipped_companions(&self) -> &[String] {
+        &self.skipped_companions
+    }
+
+    /// Sets the JSON definition of the user-defined companion that this configuration has been
+    /// created from, so that the companion can be requested again, e.g. for a clone of the app.
+    pub fn set_companion_definition(&mut self, companion_definition: Option<String>) {
+        self.companion_definition = companion_definition;
+    }
+
+    pub fn companion_definition(&self) -> Option<&String> {
+        self.companion_definition.as_ref()
+    }
+
+    /// Sets the htpasswd entry that protects the routes of the service with basic auth.
+    pub fn set_basic_auth(&mut self, basic_auth: Option<String>) {
+        self.basic_auth = basic_auth;
+    }
+
+    pub fn basic_auth(&self) -> Option<&String> {
+        self.basic_auth.as_ref()
+    }
+
+    /// Computes a fingerprint of the rendered configuration that changes whenever the deployed
+    /// container would change. The owner, the tickets, and the deployment strategy are not part
+    /// of the fingerprint.
+    ///
+    /// The fingerprint is the SHA-256 hash of a canonical JSON serialization, so that it stays
+    /// stable across releases of PREvant and of the Rust compiler. Otherwise, all services would
+    /// be considered changed after an update of PREvant.
+    pub fn fingerprint(&self) -> String {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Canonical<'a> {
+            config: &'a ServiceConfig,
+            port: u16,
+            basic_auth: Option<&'a String>,
+            replicated_from: Option<&'a String>,
+            replicated_image_digest: Option<&'a String>,
+        }
+
+        // All maps of the serialization are ordered, thus, the JSON is canonical.
+        let canonical = serde_json::to_vec(&Canonical {
+            config: self,
+            port: self.port,
+            basic_auth: self.basic_auth.as_ref(),
+            replicated_from: self.replicated_from.as_ref(),
+            replicated_image_digest: self.replicated_image_digest.as_ref(),
+        })
+        .expect("The service configuration must be serializable");
+
+        let mut hasher = Sha256::new();
+        hasher.input(&canonical);
+        format!("{:x}", hasher.result())
+    }
+
+    pub fn set_deployed_fingerprint(&mut self, fingerprint: Option<String>) {
+        self.deployed_fingerprint = fingerprint;
+    }
+
+    /// The fingerprint of the configuration that the running service has been deployed with.
+    pub fn deployed_fingerprint(&self) -> Option<&String> {
+        self.deployed_fingerprint.as_ref()
+    }
+
+    pub fn set_deployed_image_digest(&mut self, image_digest: Option<String>) {
+        self.deployed_image_digest = image_digest;
+    }
+
+    /// The digest of the image that the running service has been started with, if the
+    /// infrastructure is able to provide it.
+    pub fn deployed_image_digest(&self) -> Option<&String> {
+        self.deployed_image_digest.as_ref()
+    }
+
+    /// Remembers the app that this replica has been replicated from and the image digest that the
+    /// source service was running with at that time.
+    pub fn set_replicated_from(&mut self, app_name: Option<String>, image_digest: Option<String>) {
+        self.replicated_from = app_name;
+        self.replicated_image_digest = image_digest;
+    }
+
+    pub fn replicated_from(&self) -> Option<&String> {
+        self.replicated_from.as_ref()
+    }
+
+    pub fn replicated_image_digest(&self) -> Option<&String> {
+        self.replicated_image_digest.as_ref()
+    }
+
+    pub fn set_deployment_strategy(&mut self, deployment_strategy: DeploymentStrategy) {
+        self.deployment_strategy = deployment_strategy;
+    }
+
+    /// Defines if a running companion will be redeployed when the app gets updated.
+    pub fn deployment_strategy(&self) -> &DeploymentStrategy {
+        &self.deployment_strategy
+    }
+
+    pub fn set_depends_on(&mut self, depends_on: Vec<String>) {
+        self.depends_on = Some(depends_on);
+    }
+
+    /// The names of the services that have to be ready before this service will be started.
+    pub fn depends_on(&self) -> &[String] {
+        match &self.depends_on {
+            Some(depends_on) => depends_on,
+            None => &[],
+        }
+    }
+
     pub fn middlewares<'a, 'b: 'a>(&'b self) -> Option<&BTreeMap<String, Value>> {
         match &self.middlewares {
             None => None,
@@ -173,20 +446,30 @@ impl ServiceConfig {
         }
     }
 
+    /// The Traefik middlewares of the service. If there are no explicitly configured middlewares,
+    /// the path prefix will be stripped (unless the routing options disable it) and the additional
+    /// middlewares of the routing options will be applied.
     pub fn traefik_middlewares<'a, 'b: 'a>(&'b self, app_name: &String) -> BTreeMap<String, Value> {
         match &self.middlewares {
             None => {
-                let mut prefixes = BTreeMap::new();
-                prefixes.insert(
-                    Value::String("prefixes".to_string()),
-                    Value::Seq(vec![Value::String(format!(
-                        "/{}/{}/",
-                        app_name, self.service_name
-                    ))]),
-                );
-
                 let mut middlewares = BTreeMap::new();
-                middlewares.insert("stripPrefix".to_string(), Value::Map(prefixes));
+
+                if self.strips_routing_path(app_name) {
+                    let mut prefixes = BTreeMap::new();
+                    prefixes.insert(
+                        Value::String("prefixes".to_string()),
+                        Value::Seq(vec![Value::String(self.routing_path(app_name))]),
+                    );
+                    middlewares.insert("stripPrefix".to_string(), Value::Map(prefixes));
+                }
+
+                if let Some(routing_middlewares) = self
+                    .routing
+                    .as_ref()
+                    .and_then(|routing| routing.middlewares.as_ref())
+                {
+                    middlewares.extend(routing_middlewares.clone());
+                }
 
                 middlewares
             }
@@ -237,14 +520,89 @@ impl ServiceConfig {
                 .unwrap_or(BTreeMap::new()),
         );
         self.labels = Some(labels);
+
+        if self.depends_on.is_none() {
+            self.depends_on = other.depends_on.clone();
+        }
+
+        if self.ports.is_none() {
+            self.ports = other.ports.clone();
+        }
+
+        if self.command.is_none() {
+            self.command = other.command.clone();
+        }
+
+        if self.entrypoint.is_none() {
+            self.entrypoint = other.entrypoint.clone();
+        }
+    }
+}
+
+impl Serialize for ServiceConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ServiceConfig<'a> {
+            service_name: &'a String,
+            image: &'a Image,
+            #[serde(rename = "type")]
+            container_type: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            env: Option<BTreeMap<&'a String, &'a str>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            volumes: Option<&'a BTreeMap<PathBuf, String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            labels: Option<&'a BTreeMap<String, String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            router: Option<&'a Router>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            middlewares: Option<&'a BTreeMap<String, Value>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            depends_on: Option<&'a Vec<String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            ports: Option<&'a Vec<Port>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            routing: Option<&'a Routing>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            command: Option<&'a Vec<String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            entrypoint: Option<&'a Vec<String>>,
+        }
+
+        let c = ServiceConfig {
+            service_name: &self.service_name,
+            image: &self.image,
+            container_type: self.container_type.to_string(),
+            env: self.env.as_ref().map(|env| {
+                env.iter()
+                    .map(|variable| (variable.key(), variable.value().unsecure()))
+                    .collect()
+            }),
+            volumes: self.volumes.as_ref(),
+            labels: self.labels.as_ref().filter(|labels| !labels.is_empty()),
+            router: self.router.as_ref(),
+            middlewares: self.middlewares.as_ref(),
+            depends_on: self.depends_on.as_ref(),
+            ports: self.ports.as_ref(),
+            routing: self.routing.as_ref(),
+            command: self.command.as_ref(),
+            entrypoint: self.entrypoint.as_ref(),
+        };
+
+        c.serialize(serializer)
     }
 }
 
 /// Helper that configures the service routing for Traefik (see
 /// [here](https://docs.traefik.io/routing/routers/)).
-#[derive(Clone, Debug, Hash, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Router {
     rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     priority: Option<i32>,
 }
 
@@ -268,6 +626,156 @@ impl Router {
     }
 }
 
+/// Defines how Traefik routes the requests to a service. The path and the host may contain the
+/// placeholders `{app}` and `{service}`, e.g. `{service}.{app}.example.com`. By default, a service
+/// is reachable under `/{app}/{service}/` and the prefix is stripped before the requests reach the
+/// service.
+#[derive(Clone, Debug, Default, Hash, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Routing {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strip_prefix: Option<bool>,
+    /// Additional Traefik middlewares, e.g. `basicAuth`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    middlewares: Option<BTreeMap<String, Value>>,
+}
+
+impl Routing {
+    /// Fills the options that are not set with the options of the other routing, e.g. with the
+    /// globally configured routing.
+    pub fn or(&self, other: &Routing) -> Routing {
+        Routing {
+            path: self.path.clone().or_else(|| other.path.clone()),
+            host: self.host.clone().or_else(|| other.host.clone()),
+            strip_prefix: self.strip_prefix.or(other.strip_prefix),
+            middlewares: self
+                .middlewares
+                .clone()
+                .or_else(|| other.middlewares.clone()),
+        }
+    }
+
+    fn path(&self, app_name: &str, service_name: &str) -> String {
+        let path = match &self.path {
+            Some(path) => replace_placeholders(path, app_name, service_name),
+            None => format!("/{}/{}/", app_name, service_name),
+        };
+
+        if path.starts_with('/') {
+            path
+        } else {
+            format!("/{}", path)
+        }
+    }
+
+    fn host(&self, app_name: &str, service_name: &str) -> Option<String> {
+        self.host
+            .as_ref()
+            .map(|host| replace_placeholders(host, app_name, service_name))
+    }
+
+    /// A service that is reachable under `/` has no prefix that could be stripped.
+    fn strip_prefix(&self, app_name: &str, service_name: &str) -> bool {
+        self.strip_prefix.unwrap_or(true) && self.path(app_name, service_name) != "/"
+    }
+}
+
+fn replace_placeholders(value: &str, app_name: &str, service_name: &str) -> String {
+    value
+        .replace("{app}", app_name)
+        .replace("{service}", service_name)
+}
+
+fn move_rule(rule: &str, from: &str, to: &str) -> String {
+    lazy_static! {
+        static ref MATCHER: Regex = Regex::new(r"(Host|PathPrefix|Path)\(([^)]*)\)").unwrap();
+        static ref ARGUMENT: Regex = Regex::new(r"`([^`]*)`").unwrap();
+    }
+
+    MATCHER
+        .replace_all(rule, |matcher: &Captures| {
+            let arguments = ARGUMENT.replace_all(&matcher[2], |argument: &Captures| {
+                let value = match &matcher[1] {
+                    "Host" => move_host(&argument[1], from, to),
+                    _ => move_path(&argument[1], from, to),
+                };
+                format!("`{}`", value)
+            });
+            format!("{}({})", &matcher[1], arguments)
+        })
+        .into_owned()
+}
+
+fn move_path(path: &str, from: &str, to: &str) -> String {
+    let (slash, relative_path) = match path.strip_prefix('/') {
+        Some(relative_path) => ("/", relative_path),
+        None => ("", path),
+    };
+
+    if relative_path == from {
+        format!("{}{}", slash, to)
+    } else if let Some(rest) = relative_path.strip_prefix(&format!("{}/", from)) {
+        format!("{}{}/{}", slash, to, rest)
+    } else {
+        path.to_string()
+    }
+}
+
+fn move_host(host: &str, from: &str, to: &str) -> String {
+    host.split('.')
+        .map(|label| if label == from { to } else { label })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The name of the port that will be exposed through Traefik.
+pub static HTTP_PORT_NAME: &str = "http";
+
+/// A named port that the container of a service listens on.
+#[derive(Clone, Debug, Hash, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Port {
+    name: String,
+    port: u16,
+}
+
+impl Port {
+    pub fn new(name: String, port: u16) -> Self {
+        Port { name, port }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Defines when a running companion will be redeployed while its app is updated.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeploymentStrategy {
+    /// The companion will be redeployed with every update of the app.
+    RedeployAlways,
+    /// The companion will be redeployed if its image or any other part of its configuration has
+    /// been changed.
+    RedeployOnImageUpdate,
+    /// The companion will only be deployed once and it is kept as is afterwards, e.g. to keep the
+    /// data of a database.
+    RedeployNever,
+}
+
+impl Default for DeploymentStrategy {
+    fn default() -> Self {
+        DeploymentStrategy::RedeployOnImageUpdate
+    }
+}
+
 #[cfg(test)]
 #[macro_export]
 macro_rules! sc {
@@ -343,6 +851,41 @@ mod tests {
     use secstr::SecUtf8;
     use serde_json::from_value;
 
+    /// The client models are maintained separately, thus, this test ensures that the client
+    /// does not lose any field of the payloads of the API.
+    #[test]
+    fn should_round_trip_service_config_through_client_model() {
+        let mut config = crate::sc!(
+            "db",
+            "mariadb:10.3.17",
+            labels = ("com.example.team" => "platform"),
+            env = ("MYSQL_PASSWORD" => "s3cr3t"),
+            volumes = ("/etc/mysql/my.cnf" => "[client-server]")
+        );
+        config.set_depends_on(vec![String::from("cache")]);
+        config.set_ports(vec![
+            Port::new(String::from("http"), 8080),
+            Port::new(String::from("debug"), 5005),
+        ]);
+        config.set_routing(
+            from_value(serde_json::json!({
+                "host": "{service}.{app}.example.com",
+                "path": "/",
+                "stripPrefix": false
+            }))
+            .unwrap(),
+        );
+        config.set_command(Some(vec![String::from("--verbose")]));
+        config.set_entrypoint(Some(vec![String::from("docker-entrypoint.sh")]));
+        let json = serde_json::to_value(&config).unwrap();
+
+        let client_config = from_value::<prevant_client::ServiceConfig>(json.clone()).unwrap();
+        let round_tripped =
+            from_value::<ServiceConfig>(serde_json::to_value(&client_config).unwrap()).unwrap();
+
+        assert_eq!(serde_json::to_value(&round_tripped).unwrap(), json);
+    }
+
     #[test]
     fn should_parse_service_config_json() {
         let config = from_value::<ServiceConfig>(serde_json::json!({
@@ -487,4 +1030,208 @@ mod tests {
             Some(&String::from("5678"))
         );
     }
+
+    /// The fingerprints are stored with the running services, thus, they must not change for the
+    /// same configuration.
+    #[test]
+    fn should_compute_stable_fingerprint() {
+        let config =
+            sc!("mariadb", "mariadb:10.3", labels = (), env = ("USER" => "admin"), volumes = ());
+
+        assert_eq!(
+            config.fingerprint(),
+            "31a5a4fd543396bcb9627e4a1004dd32654f34b0682e89729527a569dc46fd42"
+        );
+    }
+
+    #[test]
+    fn should_compute_fingerprint_independent_of_owner() {
+        let config =
+            sc!("mariadb", "mariadb:10.3", labels = (), env = ("USER" => "admin"), volumes = ());
+        let mut owned_config = config.clone();
+        owned_config.set_owner(Some(String::from("john.doe")));
+
+        assert_eq!(config.fingerprint(), owned_config.fingerprint());
+    }
+
+    #[test]
+    fn should_compute_fingerprint_independent_of_tickets() {
+        let config =
+            sc!("mariadb", "mariadb:10.3", labels = (), env = ("USER" => "admin"), volumes = ());
+        let mut linked_config = config.clone();
+        linked_config.set_tickets(vec![String::from("PROJ-123")]);
+
+        assert_eq!(config.fingerprint(), linked_config.fingerprint());
+    }
+
+    #[test]
+    fn should_compute_different_fingerprint_for_changed_env() {
+        let config =
+            sc!("mariadb", "mariadb:10.3", labels = (), env = ("USER" => "admin"), volumes = ());
+        let changed_config =
+            sc!("mariadb", "mariadb:10.3", labels = (), env = ("USER" => "root"), volumes = ());
+
+        assert_ne!(config.fingerprint(), changed_config.fingerprint());
+    }
+
+    #[test]
+    fn should_route_by_default_path() {
+        let config = sc!("backend", "backend:latest");
+
+        assert_eq!(
+            config.traefik_rule(&String::from("master")),
+            "PathPrefix(`/master/backend/`)"
+        );
+        assert!(config.strips_routing_path("master"));
+    }
+
+    #[test]
+    fn should_route_by_host_and_custom_path() {
+        let config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "routing": {
+                "host": "{service}.{app}.example.com",
+                "path": "/",
+                "middlewares": {
+                    "basicAuth": { "secret": "backend-users" }
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.traefik_rule(&String::from("master")),
+            "Host(`backend.master.example.com`) && PathPrefix(`/`)"
+        );
+        assert!(!config.strips_routing_path("master"));
+        assert_eq!(
+            serde_json::to_value(config.traefik_middlewares(&String::from("master"))).unwrap(),
+            serde_json::json!({
+                "basicAuth": { "secret": "backend-users" }
+            })
+        );
+    }
+
+    #[test]
+    fn should_fill_routing_with_other_routing() {
+        let routing = from_value::<Routing>(serde_json::json!({ "path": "/{app}/api/" })).unwrap();
+        let global_routing = from_value::<Routing>(serde_json::json!({
+            "path": "/{app}/{service}/",
+            "host": "{app}.example.com",
+            "stripPrefix": false
+        }))
+        .unwrap();
+
+        let mut config = sc!("backend", "backend:latest");
+        config.set_routing(routing.or(&global_routing));
+
+        assert_eq!(
+            config.traefik_rule(&String::from("master")),
+            "Host(`master.example.com`) && PathPrefix(`/master/api/`)"
+        );
+        assert!(!config.strips_routing_path("master"));
+    }
+
+    #[test]
+    fn should_move_routes_to_other_app() {
+        let mut config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "routing": {
+                "host": "master.example.com",
+                "path": "/master/api/"
+            }
+        }))
+        .unwrap();
+        config.set_router(Router::new(
+            String::from(
+                "Host(`backend.master.example.com`) && PathPrefix(`/master/backend/`, `/docs/master/`)",
+            ),
+            None,
+        ));
+
+        config.move_routes("master", "fork");
+
+        assert_eq!(
+            config.router().unwrap().rule(),
+            "Host(`backend.fork.example.com`) && PathPrefix(`/fork/backend/`, `/docs/master/`)"
+        );
+        assert_eq!(
+            config.routing_host("fork"),
+            Some(String::from("fork.example.com"))
+        );
+        assert_eq!(config.routing_path("fork"), "/fork/api/");
+    }
+
+    #[test]
+    fn should_not_move_routes_with_placeholders() {
+        let mut config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "routing": {
+                "host": "{service}.{app}.example.com"
+            }
+        }))
+        .unwrap();
+
+        config.move_routes("master", "fork");
+
+        assert_eq!(
+            config.traefik_rule(&String::from("fork")),
+            "Host(`backend.fork.example.com`) && PathPrefix(`/fork/backend/`)"
+        );
+    }
+
+    #[test]
+    fn should_use_http_port_for_routing() {
+        let config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "ports": [
+              { "name": "debug", "port": 5005 },
+              { "name": "http", "port": 8080 }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(config.port(), 8080);
+        assert_eq!(config.ports().len(), 2);
+    }
+
+    #[test]
+    fn should_use_image_port_without_http_port() {
+        let mut config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "ports": [
+              { "name": "debug", "port": 5005 }
+            ]
+        }))
+        .unwrap();
+        config.set_port(4711);
+
+        assert_eq!(config.port(), 4711);
+    }
+
+    #[test]
+    fn should_parse_and_serialize_labels() {
+        let config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "backend:latest",
+            "labels": {
+              "com.example.team": "platform"
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.labels().unwrap().get("com.example.team"),
+            Some(&String::from("platform"))
+        );
+        assert_eq!(
+            serde_json::to_value(&config).unwrap()["labels"],
+            serde_json::json!({ "com.example.team": "platform" })
+        );
+    }
 }
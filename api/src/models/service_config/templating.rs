@@ -117,6 +117,22 @@ impl ServiceConfig {
             templated_config.set_labels(Some(apply_templates(&reg, &parameters, labels)?));
         }
 
+        if let Some(command) = self.command() {
+            templated_config.set_command(Some(apply_templates_to_args(
+                &reg,
+                &parameters,
+                command,
+            )?));
+        }
+
+        if let Some(entrypoint) = self.entrypoint() {
+            templated_config.set_entrypoint(Some(apply_templates_to_args(
+                &reg,
+                &parameters,
+                entrypoint,
+            )?));
+        }
+
         if let Some(router) = self.router() {
             let rule = reg.render_template(router.rule(), &parameters)?;
             templated_config.set_router(router.with_rule(rule));
@@ -231,6 +247,16 @@ where
     Ok(templated_values)
 }
 
+fn apply_templates_to_args(
+    reg: &Handlebars,
+    parameters: &TemplateParameters,
+    args: &[String],
+) -> Result<Vec<String>, TemplateRenderError> {
+    args.iter()
+        .map(|arg| reg.render_template(arg, &parameters))
+        .collect()
+}
+
 fn apply_templating_to_middlewares(
     reg: &Handlebars,
     parameters: &TemplateParameters,
@@ -356,6 +382,35 @@ mod tests {
         assert_eq!(env.value().unsecure(), "service-a,service-b,");
     }
 
+    #[test]
+    fn should_apply_app_companion_templating_with_command() {
+        let mut config = ServiceConfig::new(
+            String::from("migrations"),
+            Image::from_str("flyway").unwrap(),
+        );
+        config.set_command(Some(vec![
+            String::from("-url=jdbc:postgresql://db/{{application.name}}"),
+            String::from("migrate"),
+        ]));
+        config.set_entrypoint(Some(vec![String::from("flyway")]));
+
+        let templated_config = config
+            .apply_templating_for_application_companion(&String::from("master"), &Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            templated_config.command(),
+            Some(&vec![
+                String::from("-url=jdbc:postgresql://db/master"),
+                String::from("migrate")
+            ])
+        );
+        assert_eq!(
+            templated_config.entrypoint(),
+            Some(&vec![String::from("flyway")])
+        );
+    }
+
     #[test]
     fn should_apply_app_companion_templating_with_labels() {
         let mut config = ServiceConfig::new(
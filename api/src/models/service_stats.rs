@@ -0,0 +1,59 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// The resource usage of a single service, e.g. gathered through the Docker stats API.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStats {
+    service_name: String,
+    /// The CPU usage in percent of a single CPU, i.e. the value may exceed 100 on multi-core
+    /// hosts.
+    cpu_percentage: f64,
+    memory_usage: u64,
+    memory_limit: u64,
+    network_rx_bytes: u64,
+    network_tx_bytes: u64,
+}
+
+impl ServiceStats {
+    pub fn new(
+        service_name: String,
+        cpu_percentage: f64,
+        memory_usage: u64,
+        memory_limit: u64,
+        network_rx_bytes: u64,
+        network_tx_bytes: u64,
+    ) -> Self {
+        ServiceStats {
+            service_name,
+            cpu_percentage,
+            memory_usage,
+            memory_limit,
+            network_rx_bytes,
+            network_tx_bytes,
+        }
+    }
+}
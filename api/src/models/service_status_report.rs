@@ -0,0 +1,54 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::service::{Service, ServiceStatus};
+use chrono::{DateTime, Utc};
+
+/// The detailed state of a single service, e.g. in order to find crash-looping containers.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStatusReport {
+    service_name: String,
+    status: ServiceStatus,
+    started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart_count: Option<u64>,
+    /// The exit code of the last run of the service's container, if it has exited at least once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<u64>,
+}
+
+impl From<&Service> for ServiceStatusReport {
+    fn from(service: &Service) -> Self {
+        ServiceStatusReport {
+            service_name: service.service_name().clone(),
+            status: service.status().clone(),
+            started_at: *service.started_at(),
+            restart_count: service.restart_count(),
+            exit_code: service.exit_code(),
+        }
+    }
+}
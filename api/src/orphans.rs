@@ -0,0 +1,56 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::http_result::HttpResult;
+use crate::models::Orphan;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// Lists the resources that carry the labels of PREvant but do not belong to a properly deployed
+/// app, e.g. leftovers of crashes during deployments.
+#[get("/admin/orphans", format = "application/json")]
+pub async fn orphans(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<Vec<Orphan>>> {
+    user?;
+    Ok(Json(apps.get_orphans().await?))
+}
+
+/// Removes the orphans and returns the removed ones.
+#[delete("/admin/orphans")]
+pub async fn remove_orphans(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<Vec<Orphan>>> {
+    user?;
+    let orphans = apps.remove_orphans().await?;
+    info!("Removed orphans {:?}", orphans);
+    Ok(Json(orphans))
+}
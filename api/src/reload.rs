@@ -0,0 +1,58 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::http_result::HttpResult;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
+use rocket::State;
+use std::sync::Arc;
+
+/// Reloads the configuration file so that the changes apply to subsequent deployments. The
+/// running apps and the in-flight deployments are not affected.
+#[post("/admin/reload-config")]
+pub async fn reload_config(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Status> {
+    user?;
+
+    match apps.reload_config().await {
+        Ok(true) => Ok(Status::NoContent),
+        Ok(false) => Err(HttpApiProblem::with_title_and_type(StatusCode::CONFLICT)
+            .detail("The configuration has not been loaded from a file.")
+            .into()),
+        Err(err) => {
+            warn!("Cannot reload configuration: {}", err);
+            Err(
+                HttpApiProblem::with_title_and_type(StatusCode::UNPROCESSABLE_ENTITY)
+                    .detail(err.to_string())
+                    .into(),
+            )
+        }
+    }
+}
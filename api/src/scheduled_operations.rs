@@ -0,0 +1,74 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
+use crate::http_result::HttpResult;
+use crate::services::scheduled_operations::ScheduledOperation;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Lists the deployments and deletions that have been scheduled with `runAt`, ordered by the point
+/// in time at which they will be performed.
+#[get("/scheduled-operations", format = "application/json")]
+pub async fn scheduled_operations(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<Vec<ScheduledOperation>>> {
+    user?;
+    Ok(Json(apps.scheduled_operations().operations()))
+}
+
+/// Cancels the scheduled operation so that it will not be performed.
+#[delete("/scheduled-operations/<id>")]
+pub async fn cancel_scheduled_operation(
+    id: String,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Status> {
+    user?;
+
+    let operation = Uuid::parse_str(&id)
+        .ok()
+        .and_then(|id| apps.scheduled_operations().cancel(&id));
+    match operation {
+        Some(operation) => {
+            info!(
+                "Cancelled scheduled operation {} of {}",
+                id,
+                operation.app_name()
+            );
+            Ok(Status::NoContent)
+        }
+        None => Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!("There is no scheduled operation {}", id))
+            .into()),
+    }
+}
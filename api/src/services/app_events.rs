@@ -0,0 +1,195 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::service::{Service, ServiceStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// The number of events that a subscriber may lag behind before it misses the oldest events.
+const CAPACITY: usize = 64;
+
+/// Publishes the lifecycle events of the apps to all current subscribers, e.g. the clients of
+/// `GET /apps/events`, so that they do not need to poll the list of apps.
+pub struct AppEvents {
+    sender: broadcast::Sender<AppEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppEvent {
+    event: AppEventKind,
+    app_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<ServiceStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    services: Vec<Service>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppEventKind {
+    DeploymentStarted,
+    DeploymentFinished,
+    DeploymentFailed,
+    ServiceStatusChanged,
+    ServiceRestarted,
+    Deleted,
+}
+
+impl AppEvent {
+    pub fn deployment_started(app_name: &str) -> Self {
+        Self::new(AppEventKind::DeploymentStarted, app_name)
+    }
+
+    pub fn deployment_finished(app_name: &str, services: &[Service]) -> Self {
+        AppEvent {
+            services: services.to_vec(),
+            ..Self::new(AppEventKind::DeploymentFinished, app_name)
+        }
+    }
+
+    pub fn deployment_failed(app_name: &str, error: String) -> Self {
+        AppEvent {
+            error: Some(error),
+            ..Self::new(AppEventKind::DeploymentFailed, app_name)
+        }
+    }
+
+    pub fn service_status_changed(
+        app_name: &str,
+        service_name: &str,
+        status: ServiceStatus,
+    ) -> Self {
+        AppEvent {
+            service_name: Some(service_name.to_string()),
+            status: Some(status),
+            ..Self::new(AppEventKind::ServiceStatusChanged, app_name)
+        }
+    }
+
+    pub fn service_restarted(app_name: &str, service_name: &str) -> Self {
+        AppEvent {
+            service_name: Some(service_name.to_string()),
+            ..Self::new(AppEventKind::ServiceRestarted, app_name)
+        }
+    }
+
+    pub fn deleted(app_name: &str, services: &[Service]) -> Self {
+        AppEvent {
+            services: services.to_vec(),
+            ..Self::new(AppEventKind::Deleted, app_name)
+        }
+    }
+
+    fn new(event: AppEventKind, app_name: &str) -> Self {
+        AppEvent {
+            event,
+            app_name: app_name.to_string(),
+            service_name: None,
+            status: None,
+            services: Vec::new(),
+            error: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// The name of the event that server-sent events use as `event` field.
+    pub fn name(&self) -> &'static str {
+        match self.event {
+            AppEventKind::DeploymentStarted => "deployment-started",
+            AppEventKind::DeploymentFinished => "deployment-finished",
+            AppEventKind::DeploymentFailed => "deployment-failed",
+            AppEventKind::ServiceStatusChanged => "service-status-changed",
+            AppEventKind::ServiceRestarted => "service-restarted",
+            AppEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+impl AppEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        AppEvents { sender }
+    }
+
+    /// Sends the event to all current subscribers. Without any subscriber, the event is dropped.
+    pub fn publish(&self, event: AppEvent) {
+        // Sending only fails if there is no subscriber, which is nothing to worry about.
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver of all events that are published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    #[test]
+    fn should_send_events_to_all_subscribers() {
+        let events = AppEvents::new();
+        let mut first = events.subscribe();
+        let mut second = events.subscribe();
+
+        events.publish(AppEvent::deployment_started("master"));
+
+        assert_eq!(first.try_recv().unwrap().name(), "deployment-started");
+        assert_eq!(second.try_recv().unwrap().name(), "deployment-started");
+        assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn should_drop_events_without_subscribers() {
+        let events = AppEvents::new();
+
+        events.publish(AppEvent::deployment_started("master"));
+
+        assert_eq!(events.subscribe().try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn should_serialize_status_change() {
+        let event = AppEvent::service_status_changed("master", "db", ServiceStatus::Paused);
+
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["event"], "service-status-changed");
+        assert_eq!(json["appName"], "master");
+        assert_eq!(json["serviceName"], "db");
+        assert_eq!(json["status"], "paused");
+        assert!(json.get("services").is_none());
+    }
+}
@@ -0,0 +1,239 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use chrono::{DateTime, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    Deployment,
+    Deletion,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// Describes who changed which app at which time and whether the change succeeded.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    action: AuditAction,
+    app_name: String,
+    services: Vec<String>,
+    outcome: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        user: Option<String>,
+        action: AuditAction,
+        app_name: &str,
+        services: Vec<String>,
+    ) -> Self {
+        AuditEntry {
+            timestamp: Utc::now(),
+            user,
+            action,
+            app_name: app_name.to_string(),
+            services,
+            outcome: AuditOutcome::Success,
+            error: None,
+        }
+    }
+
+    pub fn failed(mut self, error: String) -> Self {
+        self.outcome = AuditOutcome::Failure;
+        self.error = Some(error);
+        self
+    }
+}
+
+/// An append-only log of the deployments and deletions of apps. If an audit file is configured,
+/// every entry is appended as a JSON line to that file so that the log survives restarts of
+/// PREvant.
+pub struct AuditLog {
+    file: Mutex<Option<PathBuf>>,
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn load(file: Option<&PathBuf>) -> Self {
+        let entries = match file {
+            Some(file) if file.exists() => match File::open(file) {
+                Ok(f) => BufReader::new(f)
+                    .lines()
+                    .filter_map(|line| line.ok())
+                    .filter(|line| !line.trim().is_empty())
+                    .filter_map(|line| match serde_json::from_str::<AuditEntry>(&line) {
+                        Ok(entry) => Some(entry),
+                        Err(err) => {
+                            warn!("Skipping invalid audit log entry “{}”: {}", line, err);
+                            None
+                        }
+                    })
+                    .collect(),
+                Err(err) => {
+                    warn!("Cannot read audit log from {}: {}", file.display(), err);
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+
+        AuditLog {
+            file: Mutex::new(file.cloned()),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Appends subsequent entries to the given file, e.g. after the configuration has been
+    /// reloaded. The entries recorded so far stay available.
+    pub fn relocate(&self, file: Option<&PathBuf>) {
+        *self.file.lock().unwrap() = file.cloned();
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(file) = &*self.file.lock().unwrap() {
+            let result = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .map_err(failure::Error::from)
+                .and_then(|mut f| {
+                    f.write_all(format!("{}\n", serde_json::to_string(&entry)?).as_bytes())?;
+                    f.sync_all()?;
+                    Ok(())
+                });
+
+            if let Err(err) = result {
+                error!("Cannot append to audit log {}: {}", file.display(), err);
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    /// Returns the entries of the given app (or of all apps) that have been recorded within the
+    /// given time range, ordered from the oldest to the newest entry.
+    pub fn entries(
+        &self,
+        app_name: Option<&str>,
+        from: Option<&DateTime<Utc>>,
+        until: Option<&DateTime<Utc>>,
+    ) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| app_name.map_or(true, |app_name| entry.app_name == app_name))
+            .filter(|entry| from.map_or(true, |from| &entry.timestamp >= from))
+            .filter(|entry| until.map_or(true, |until| &entry.timestamp <= until))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_restore_appended_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("audit.jsonl");
+
+        let log = AuditLog::load(Some(&file));
+        log.record(AuditEntry::new(
+            Some(String::from("alice")),
+            AuditAction::Deployment,
+            "master",
+            vec![String::from("db")],
+        ));
+        log.record(
+            AuditEntry::new(None, AuditAction::Deletion, "branch", vec![])
+                .failed(String::from("App not found")),
+        );
+
+        let restored_log = AuditLog::load(Some(&file));
+
+        let entries = restored_log.entries(None, None, None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].user, Some(String::from("alice")));
+        assert_eq!(entries[1].outcome, AuditOutcome::Failure);
+    }
+
+    #[test]
+    fn should_filter_entries_by_app_and_time_range() {
+        let log = AuditLog::load(None);
+        log.record(AuditEntry::new(
+            None,
+            AuditAction::Deployment,
+            "master",
+            vec![],
+        ));
+        log.record(AuditEntry::new(
+            None,
+            AuditAction::Deletion,
+            "branch",
+            vec![],
+        ));
+
+        assert_eq!(log.entries(Some("master"), None, None).len(), 1);
+        assert_eq!(
+            log.entries(
+                None,
+                Some(&(Utc::now() + chrono::Duration::minutes(1))),
+                None
+            )
+            .len(),
+            0
+        );
+        assert_eq!(
+            log.entries(
+                None,
+                None,
+                Some(&(Utc::now() + chrono::Duration::minutes(1)))
+            )
+            .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,180 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::service::ServiceStatus;
+use std::collections::BTreeMap;
+use std::fs::{rename, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+type DesiredApps = BTreeMap<String, BTreeMap<String, ServiceStatus>>;
+
+/// Records which services of which apps are supposed to run. If a state file is configured, the
+/// record is persisted so that PREvant is able to restart the services that have been stopped
+/// through a restart of the host, e.g. after a power cycle, without restarting services that users
+/// paused deliberately.
+pub struct DesiredState {
+    file: Mutex<Option<PathBuf>>,
+    apps: Mutex<DesiredApps>,
+}
+
+impl DesiredState {
+    pub fn load(file: Option<&PathBuf>) -> Self {
+        let apps = match file {
+            Some(file) if file.exists() => match File::open(file)
+                .map_err(failure::Error::from)
+                .and_then(|f| serde_json::from_reader(f).map_err(failure::Error::from))
+            {
+                Ok(apps) => apps,
+                Err(err) => {
+                    warn!(
+                        "Cannot read desired state from {}, starting with an empty state: {}",
+                        file.display(),
+                        err
+                    );
+                    BTreeMap::new()
+                }
+            },
+            _ => BTreeMap::new(),
+        };
+
+        DesiredState {
+            file: Mutex::new(file.cloned()),
+            apps: Mutex::new(apps),
+        }
+    }
+
+    /// Persists the state to the given file from now on, e.g. after the configuration has been
+    /// reloaded. The current state is written to the new file right away.
+    pub fn relocate(&self, file: Option<&PathBuf>) {
+        let apps = self.apps.lock().unwrap();
+        {
+            let mut current = self.file.lock().unwrap();
+            if current.as_ref() == file {
+                return;
+            }
+            *current = file.cloned();
+        }
+        self.persist(&apps);
+    }
+
+    pub fn apps(&self) -> DesiredApps {
+        self.apps.lock().unwrap().clone()
+    }
+
+    /// Records that the given services of the app have been deployed and, thus, must be running.
+    pub fn record_deployment<'a, I>(&self, app_name: &str, service_names: I)
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let mut apps = self.apps.lock().unwrap();
+        let services = apps.entry(app_name.to_string()).or_default();
+        for service_name in service_names {
+            services.insert(service_name.clone(), ServiceStatus::Running);
+        }
+        self.persist(&apps);
+    }
+
+    pub fn record_status(&self, app_name: &str, service_name: &str, status: ServiceStatus) {
+        let mut apps = self.apps.lock().unwrap();
+        if let Some(services) = apps.get_mut(app_name) {
+            services.insert(service_name.to_string(), status);
+            self.persist(&apps);
+        }
+    }
+
+    pub fn record_deletion(&self, app_name: &str) {
+        let mut apps = self.apps.lock().unwrap();
+        if apps.remove(app_name).is_some() {
+            self.persist(&apps);
+        }
+    }
+
+    /// Writes the state to a temporary file first and replaces the state file afterwards, so that
+    /// a crash while writing does not leave a corrupted state file behind.
+    fn persist(&self, apps: &DesiredApps) {
+        let file = match &*self.file.lock().unwrap() {
+            Some(file) => file.clone(),
+            None => return,
+        };
+
+        let temp_file = file.with_extension("tmp");
+        let result = File::create(&temp_file)
+            .map_err(failure::Error::from)
+            .and_then(|mut f| {
+                f.write_all(serde_json::to_string(apps)?.as_bytes())?;
+                f.sync_all()?;
+                Ok(())
+            })
+            .and_then(|_| rename(&temp_file, &file).map_err(failure::Error::from));
+
+        if let Err(err) = result {
+            error!(
+                "Cannot persist desired state to {}: {}",
+                file.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_restore_persisted_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+
+        let state = DesiredState::load(Some(&file));
+        state.record_deployment("master", &[String::from("db"), String::from("backend")]);
+        state.record_status("master", "backend", ServiceStatus::Paused);
+        state.record_deployment("branch", &[String::from("backend")]);
+        state.record_deletion("branch");
+
+        let restored_state = DesiredState::load(Some(&file));
+
+        let mut expected_services = BTreeMap::new();
+        expected_services.insert(String::from("backend"), ServiceStatus::Paused);
+        expected_services.insert(String::from("db"), ServiceStatus::Running);
+        let mut expected_apps = BTreeMap::new();
+        expected_apps.insert(String::from("master"), expected_services);
+        assert_eq!(restored_state.apps(), expected_apps);
+    }
+
+    #[test]
+    fn should_start_with_empty_state_for_corrupted_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state.json");
+        std::fs::write(&file, "{ not json").unwrap();
+
+        let state = DesiredState::load(Some(&file));
+
+        assert!(state.apps().is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::FreezeWindow;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Keeps track of the freeze windows, configured as well as declared at runtime by
+/// administrators, during which apps must not be changed.
+pub struct Freezes {
+    windows: Mutex<Windows>,
+}
+
+struct Windows {
+    configured: BTreeMap<String, FreezeWindow>,
+    declared: BTreeMap<String, FreezeWindow>,
+}
+
+impl Freezes {
+    pub fn new(windows: BTreeMap<String, FreezeWindow>) -> Self {
+        Freezes {
+            windows: Mutex::new(Windows {
+                configured: windows,
+                declared: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Returns the configured and the declared windows. A declared window replaces the configured
+    /// window with the same name.
+    pub fn windows(&self) -> BTreeMap<String, FreezeWindow> {
+        let windows = self.windows.lock().unwrap();
+        let mut all = windows.configured.clone();
+        all.extend(windows.declared.clone());
+        all
+    }
+
+    /// Replaces the configured windows, e.g. after the configuration has been reloaded, while
+    /// keeping the windows declared at runtime.
+    pub fn reconfigure(&self, configured: BTreeMap<String, FreezeWindow>) {
+        self.windows.lock().unwrap().configured = configured;
+    }
+
+    /// Declares a new freeze window or replaces the window with the same name.
+    pub fn declare(&self, name: String, window: FreezeWindow) {
+        self.windows.lock().unwrap().declared.insert(name, window);
+    }
+
+    /// Lifts the freeze window with the given name and returns `false` if there was none. A
+    /// configured window stays lifted until the configuration is reloaded.
+    pub fn lift(&self, name: &str) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        let declared = windows.declared.remove(name).is_some();
+        let configured = windows.configured.remove(name).is_some();
+        declared || configured
+    }
+
+    /// Returns the freeze window that prevents the app from being changed at the given point in
+    /// time together with the end of the window's current period. If multiple windows are in
+    /// effect, the one ending last will be returned. Windows that allow new apps are ignored if
+    /// the app is new.
+    pub fn active_freeze(
+        &self,
+        app_name: &str,
+        now: &DateTime<Utc>,
+        new_app: bool,
+    ) -> Option<(DateTime<Utc>, FreezeWindow)> {
+        self.windows()
+            .values()
+            .filter(|window| !(new_app && window.allows_new_apps()))
+            .filter_map(|window| {
+                window
+                    .active_until(app_name, now)
+                    .map(|until| (until, window.clone()))
+            })
+            .max_by_key(|(until, _)| *until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(until: &str) -> FreezeWindow {
+        toml::de::from_str::<FreezeWindow>(&format!("until = '{}'", until)).unwrap()
+    }
+
+    #[test]
+    fn should_return_freeze_ending_last() {
+        let freezes = Freezes::new(BTreeMap::new());
+        freezes.declare(String::from("short"), window("2021-07-01T20:00:00Z"));
+        freezes.declare(String::from("long"), window("2021-07-02T06:00:00Z"));
+
+        let freeze = freezes.active_freeze("master", &Utc.ymd(2021, 7, 1).and_hms(18, 0, 0), false);
+
+        assert_eq!(
+            freeze.map(|(until, _)| until),
+            Some(Utc.ymd(2021, 7, 2).and_hms(6, 0, 0))
+        );
+    }
+
+    #[test]
+    fn should_ignore_freeze_allowing_new_apps_for_new_app() {
+        let freezes = Freezes::new(BTreeMap::new());
+        freezes.declare(
+            String::from("demo"),
+            toml::de::from_str::<FreezeWindow>(
+                "until = '2021-07-02T06:00:00Z'\nnewAppsAllowed = true",
+            )
+            .unwrap(),
+        );
+        let now = Utc.ymd(2021, 7, 1).and_hms(18, 0, 0);
+
+        assert!(freezes.active_freeze("master", &now, true).is_none());
+        assert!(freezes.active_freeze("master", &now, false).is_some());
+    }
+
+    #[test]
+    fn should_keep_declared_freeze_when_reconfigured() {
+        let mut configured = BTreeMap::new();
+        configured.insert(String::from("release"), window("2021-07-02T06:00:00Z"));
+        let freezes = Freezes::new(configured);
+        freezes.declare(String::from("incident"), window("2021-07-03T06:00:00Z"));
+
+        let mut reconfigured = BTreeMap::new();
+        reconfigured.insert(String::from("holidays"), window("2021-12-31T06:00:00Z"));
+        freezes.reconfigure(reconfigured);
+
+        assert_eq!(
+            freezes.windows().keys().collect::<Vec<_>>(),
+            vec!["holidays", "incident"]
+        );
+    }
+
+    #[test]
+    fn should_not_return_lifted_freeze() {
+        let freezes = Freezes::new(BTreeMap::new());
+        freezes.declare(String::from("release"), window("2021-07-02T06:00:00Z"));
+
+        assert!(freezes.lift("release"));
+        assert!(!freezes.lift("release"));
+        assert_eq!(
+            freezes
+                .active_freeze("master", &Utc.ymd(2021, 7, 1).and_hms(18, 0, 0), false)
+                .is_none(),
+            true
+        );
+    }
+}
@@ -0,0 +1,164 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::AppStatusChangeId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The outcome of looking up the idempotency key of a request.
+#[derive(Debug, PartialEq)]
+pub enum IdempotentRequest<T> {
+    /// The key is unknown (or has expired) and the request has to be processed.
+    New,
+    /// A request with the same key is still processed under the given status change.
+    InProgress(AppStatusChangeId),
+    /// A request with the same key has been processed with the given result.
+    Completed(T),
+    /// The key has been used for a request of another app.
+    Conflict,
+}
+
+/// Remembers the results of requests by their idempotency keys for a limited time, so that
+/// clients can replay requests, e.g. after network timeouts, without processing them twice.
+pub struct IdempotentRequests<T> {
+    ttl: Duration,
+    requests: Mutex<HashMap<String, KnownRequest<T>>>,
+}
+
+struct KnownRequest<T> {
+    app_name: String,
+    status_id: AppStatusChangeId,
+    received_at: Instant,
+    result: Option<T>,
+}
+
+impl<T: Clone> IdempotentRequests<T> {
+    pub fn new(ttl: Duration) -> Self {
+        IdempotentRequests {
+            ttl,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up the key and registers it for the given app and status change if it is unknown.
+    pub fn begin(
+        &self,
+        key: &str,
+        app_name: &str,
+        status_id: &AppStatusChangeId,
+    ) -> IdempotentRequest<T> {
+        let mut requests = self.requests.lock().unwrap();
+
+        let ttl = self.ttl;
+        requests.retain(|_, request| request.received_at.elapsed() < ttl);
+
+        match requests.get(key) {
+            Some(request) if request.app_name != app_name => IdempotentRequest::Conflict,
+            Some(KnownRequest {
+                result: Some(result),
+                ..
+            }) => IdempotentRequest::Completed(result.clone()),
+            Some(request) => IdempotentRequest::InProgress(request.status_id),
+            None => {
+                requests.insert(
+                    key.to_string(),
+                    KnownRequest {
+                        app_name: app_name.to_string(),
+                        status_id: *status_id,
+                        received_at: Instant::now(),
+                        result: None,
+                    },
+                );
+                IdempotentRequest::New
+            }
+        }
+    }
+
+    /// Records the result that replays of the key will be answered with.
+    pub fn complete(&self, key: &str, result: T) {
+        if let Some(request) = self.requests.lock().unwrap().get_mut(key) {
+            request.result = Some(result);
+        }
+    }
+
+    /// Forgets the key, e.g. because the request failed and a replay should process it again.
+    pub fn forget(&self, key: &str) {
+        self.requests.lock().unwrap().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_return_result_of_replayed_request() {
+        let requests = IdempotentRequests::new(Duration::from_secs(60));
+        let status_id = AppStatusChangeId::new();
+
+        assert_eq!(
+            requests.begin("key", "master", &status_id),
+            IdempotentRequest::New
+        );
+        assert_eq!(
+            requests.begin("key", "master", &AppStatusChangeId::new()),
+            IdempotentRequest::InProgress(status_id)
+        );
+
+        requests.complete("key", 42);
+
+        assert_eq!(
+            requests.begin("key", "master", &AppStatusChangeId::new()),
+            IdempotentRequest::Completed(42)
+        );
+        assert_eq!(
+            requests.begin("key", "branch", &AppStatusChangeId::new()),
+            IdempotentRequest::Conflict
+        );
+    }
+
+    #[test]
+    fn should_process_forgotten_and_expired_requests_again() {
+        let requests = IdempotentRequests::<u32>::new(Duration::from_secs(60));
+        requests.begin("key", "master", &AppStatusChangeId::new());
+        requests.forget("key");
+
+        assert_eq!(
+            requests.begin("key", "master", &AppStatusChangeId::new()),
+            IdempotentRequest::New
+        );
+
+        let requests = IdempotentRequests::new(Duration::from_secs(0));
+        requests.begin("key", "master", &AppStatusChangeId::new());
+        requests.complete("key", 42);
+
+        assert_eq!(
+            requests.begin("key", "master", &AppStatusChangeId::new()),
+            IdempotentRequest::New
+        );
+    }
+}
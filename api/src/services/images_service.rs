@@ -77,6 +77,57 @@ impl ImagesService {
         Ok(port_mappings)
     }
 
+    /// Resolves the size of the remote images, i.e. the sum of the compressed sizes of their layers,
+    /// through the docker registry. Images that cannot be resolved are omitted.
+    pub async fn resolve_image_sizes(
+        &self,
+        images: &HashSet<Image>,
+    ) -> Result<HashMap<Image, u64>, ImagesServiceError> {
+        let futures = images
+            .iter()
+            .filter_map(|image| match image {
+                Image::Named { .. } => Some(image),
+                Image::Digest { .. } => None,
+            })
+            .map(|image| ImagesService::resolve_image_size(&image))
+            .collect::<Vec<_>>();
+        let sizes = join_all(futures).await;
+
+        let mut image_sizes = HashMap::new();
+        for size_result in sizes {
+            match size_result {
+                Ok((image, size)) => {
+                    image_sizes.insert(image.clone(), size);
+                }
+                Err(err) => warn!("Cannot resolve size of image: {}", err),
+            }
+        }
+
+        Ok(image_sizes)
+    }
+
+    async fn resolve_image_size(image: &Image) -> Result<(&Image, u64), ImagesServiceError> {
+        debug!("Resolve image size of {:?}", image);
+
+        let client = dkregistry::v2::Client::configure()
+            .registry(&image.registry().unwrap())
+            .build()?;
+
+        let (image_name, reference) = (image.name().unwrap(), image.reference().unwrap());
+
+        match client.get_manifest(&image_name, &reference).await? {
+            Manifest::S2(schema) => {
+                // The layer descriptors do not expose their sizes, thus, the sizes are read from
+                // the serialized manifest.
+                let manifest = serde_json::to_value(&schema.manifest_spec)?;
+                Ok((image, layers_size(&manifest)))
+            }
+            _ => Err(ImagesServiceError::UnknownManifestFormat {
+                image: image.clone(),
+            }),
+        }
+    }
+
     async fn resolve_image_blob(
         image: &Image,
     ) -> Result<Option<(&Image, ImageBlob)>, ImagesServiceError> {
@@ -86,9 +137,9 @@ impl ImagesService {
             .registry(&image.registry().unwrap())
             .build()?;
 
-        let (image_name, tag) = (image.name().unwrap(), image.tag().unwrap());
+        let (image_name, reference) = (image.name().unwrap(), image.reference().unwrap());
 
-        let digest = match client.get_manifest(&image_name, &tag).await? {
+        let digest = match client.get_manifest(&image_name, &reference).await? {
             Manifest::S2(schema) => schema.manifest_spec.config().digest.clone(),
             _ => {
                 return Err(ImagesServiceError::UnknownManifestFormat {
@@ -108,6 +159,18 @@ impl ImagesService {
     }
 }
 
+fn layers_size(manifest: &serde_json::Value) -> u64 {
+    manifest["layers"]
+        .as_array()
+        .map(|layers| {
+            layers
+                .iter()
+                .filter_map(|layer| layer["size"].as_u64())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
 #[derive(Deserialize)]
 struct ImageBlob {
     config: ImageConfig,
@@ -223,4 +286,31 @@ mod tests {
 
         assert_eq!(blob.get_exposed_port(), None);
     }
+
+    #[test]
+    fn should_sum_layer_sizes() {
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+            "config": {
+                "mediaType": "application/vnd.docker.container.image.v1+json",
+                "size": 7023,
+                "digest": "sha256:b5b2b2c507a0944348e0303114d8d93aaaa081732b86451d9bce1f432a537bc7"
+            },
+            "layers": [
+                {
+                    "mediaType": "application/vnd.docker.image.rootfs.diff.tar.gzip",
+                    "size": 32654,
+                    "digest": "sha256:e692418e4cbaf90ca69d05a66403747baa33ee08806650b51fab815ad7fc331f"
+                },
+                {
+                    "mediaType": "application/vnd.docker.image.rootfs.diff.tar.gzip",
+                    "size": 16724,
+                    "digest": "sha256:3c3a4604a545cdc127456d94e421cd355bca5b528f4a9c1905b15da2eb4a4c6b"
+                }
+            ]
+        });
+
+        assert_eq!(layers_size(&manifest), 49378);
+    }
 }
@@ -24,4 +24,11 @@
  * =========================LICENSE_END==================================
  */
 
+pub mod app_events;
+pub mod audit_log;
+pub mod desired_state;
+pub mod freezes;
+pub mod idempotent_requests;
 pub mod images_service;
+pub mod scheduled_operations;
+pub mod webhook_deliveries;
@@ -0,0 +1,482 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::Companion;
+use crate::models::{AppName, ServiceConfig};
+use chrono::{DateTime, Utc};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::convert::TryFrom;
+use std::fs::{rename, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// The change of an app that has been requested in advance.
+#[derive(Clone)]
+pub enum ScheduledChange {
+    Deployment {
+        replicate_from: Option<AppName>,
+        service_configs: Vec<ServiceConfig>,
+        user_defined_companions: Vec<Companion>,
+        skipped_companions: Vec<String>,
+    },
+    Deletion,
+}
+
+/// A deployment or deletion of an app that will be performed at the given point in time.
+#[derive(Clone)]
+pub struct ScheduledOperation {
+    id: Uuid,
+    app_name: AppName,
+    change: ScheduledChange,
+    run_at: DateTime<Utc>,
+    owner: Option<String>,
+}
+
+impl ScheduledOperation {
+    pub fn new(
+        app_name: AppName,
+        change: ScheduledChange,
+        run_at: DateTime<Utc>,
+        owner: Option<String>,
+    ) -> Self {
+        ScheduledOperation {
+            id: Uuid::new_v4(),
+            app_name,
+            change,
+            run_at,
+            owner,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn app_name(&self) -> &AppName {
+        &self.app_name
+    }
+
+    pub fn change(&self) -> &ScheduledChange {
+        &self.change
+    }
+
+    pub fn run_at(&self) -> &DateTime<Utc> {
+        &self.run_at
+    }
+
+    pub fn owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// Moves the operation to a later point in time, e.g. to the end of a freeze window.
+    pub fn postponed(self, run_at: DateTime<Utc>) -> Self {
+        ScheduledOperation { run_at, ..self }
+    }
+}
+
+impl Serialize for ScheduledOperation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (action, services) = match &self.change {
+            ScheduledChange::Deployment {
+                service_configs, ..
+            } => (
+                "deployment",
+                service_configs
+                    .iter()
+                    .map(|config| config.service_name().clone())
+                    .collect::<Vec<_>>(),
+            ),
+            ScheduledChange::Deletion => ("deletion", Vec::new()),
+        };
+
+        let mut s = serializer.serialize_struct("ScheduledOperation", 6)?;
+        s.serialize_field("id", &self.id.to_hyphenated().to_string())?;
+        s.serialize_field("appName", self.app_name.as_str())?;
+        s.serialize_field("action", action)?;
+        s.serialize_field("services", &services)?;
+        s.serialize_field("runAt", &self.run_at)?;
+        if let Some(owner) = &self.owner {
+            s.serialize_field("owner", owner)?;
+        }
+        s.end()
+    }
+}
+
+/// The representation of a scheduled operation in the file, which, in contrast to the
+/// representation of the REST API, contains everything that is required to perform the operation.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedOperation {
+    id: Uuid,
+    app_name: String,
+    change: PersistedChange,
+    run_at: DateTime<Utc>,
+    owner: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+enum PersistedChange {
+    #[serde(rename_all = "camelCase")]
+    Deployment {
+        replicate_from: Option<String>,
+        service_configs: Vec<serde_json::Value>,
+        user_defined_companions: Vec<Companion>,
+        skipped_companions: Vec<String>,
+    },
+    Deletion,
+}
+
+impl TryFrom<&ScheduledOperation> for PersistedOperation {
+    type Error = serde_json::Error;
+
+    fn try_from(operation: &ScheduledOperation) -> Result<Self, Self::Error> {
+        let change = match &operation.change {
+            ScheduledChange::Deployment {
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            } => PersistedChange::Deployment {
+                replicate_from: replicate_from.as_ref().map(|app_name| app_name.to_string()),
+                service_configs: service_configs
+                    .iter()
+                    .map(persisted_service_config)
+                    .collect::<Result<_, _>>()?,
+                user_defined_companions: user_defined_companions.clone(),
+                skipped_companions: skipped_companions.clone(),
+            },
+            ScheduledChange::Deletion => PersistedChange::Deletion,
+        };
+
+        Ok(PersistedOperation {
+            id: operation.id,
+            app_name: operation.app_name.to_string(),
+            change,
+            run_at: operation.run_at,
+            owner: operation.owner.clone(),
+        })
+    }
+}
+
+/// The serialization of the REST API omits whether environment variables are templated or
+/// replicated, thus, the variables are replaced by their detailed form.
+fn persisted_service_config(
+    config: &ServiceConfig,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let mut value = serde_json::to_value(config)?;
+    if let (Some(env), Some(object)) = (config.env(), value.as_object_mut()) {
+        object.insert(String::from("env"), serde_json::to_value(env)?);
+    }
+    Ok(value)
+}
+
+impl TryFrom<PersistedOperation> for ScheduledOperation {
+    type Error = failure::Error;
+
+    fn try_from(operation: PersistedOperation) -> Result<Self, Self::Error> {
+        let change = match operation.change {
+            PersistedChange::Deployment {
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            } => ScheduledChange::Deployment {
+                replicate_from: match replicate_from {
+                    Some(app_name) => Some(AppName::from_str(&app_name)?),
+                    None => None,
+                },
+                service_configs: service_configs
+                    .into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<Result<_, _>>()?,
+                user_defined_companions,
+                skipped_companions,
+            },
+            PersistedChange::Deletion => ScheduledChange::Deletion,
+        };
+
+        Ok(ScheduledOperation {
+            id: operation.id,
+            app_name: AppName::from_str(&operation.app_name)?,
+            change,
+            run_at: operation.run_at,
+            owner: operation.owner,
+        })
+    }
+}
+
+/// Keeps the operations that have been scheduled for later until they are due. If a file is
+/// configured, the operations are persisted so that they survive a restart of PREvant.
+pub struct ScheduledOperations {
+    file: Mutex<Option<PathBuf>>,
+    operations: Mutex<Vec<ScheduledOperation>>,
+}
+
+impl ScheduledOperations {
+    pub fn load(file: Option<&PathBuf>) -> Self {
+        let operations = match file {
+            Some(file) if file.exists() => match File::open(file)
+                .map_err(failure::Error::from)
+                .and_then(|f| {
+                    serde_json::from_reader::<_, Vec<PersistedOperation>>(f)
+                        .map_err(failure::Error::from)
+                }) {
+                Ok(operations) => operations
+                    .into_iter()
+                    .filter_map(|operation| {
+                        let id = operation.id;
+                        match ScheduledOperation::try_from(operation) {
+                            Ok(operation) => Some(operation),
+                            Err(err) => {
+                                warn!("Cannot restore scheduled operation {}: {}", id, err);
+                                None
+                            }
+                        }
+                    })
+                    .collect(),
+                Err(err) => {
+                    warn!(
+                        "Cannot read scheduled operations from {}, starting without scheduled operations: {}",
+                        file.display(),
+                        err
+                    );
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+
+        ScheduledOperations {
+            file: Mutex::new(file.cloned()),
+            operations: Mutex::new(operations),
+        }
+    }
+
+    /// Persists the operations to the given file from now on, e.g. after the configuration has
+    /// been reloaded. The pending operations are written to the new file right away.
+    pub fn relocate(&self, file: Option<&PathBuf>) {
+        let operations = self.operations.lock().unwrap();
+        {
+            let mut current = self.file.lock().unwrap();
+            if current.as_ref() == file {
+                return;
+            }
+            *current = file.cloned();
+        }
+        self.persist(&operations);
+    }
+
+    pub fn schedule(&self, operation: ScheduledOperation) {
+        let mut operations = self.operations.lock().unwrap();
+        operations.push(operation);
+        self.persist(&operations);
+    }
+
+    /// Returns the pending operations ordered by the point in time at which they will be
+    /// performed.
+    pub fn operations(&self) -> Vec<ScheduledOperation> {
+        let mut operations = self.operations.lock().unwrap().clone();
+        operations.sort_by_key(|operation| operation.run_at);
+        operations
+    }
+
+    /// Removes the operation with the given id and returns `None` if there was none.
+    pub fn cancel(&self, id: &Uuid) -> Option<ScheduledOperation> {
+        let mut operations = self.operations.lock().unwrap();
+        let index = operations
+            .iter()
+            .position(|operation| &operation.id == id)?;
+        let operation = operations.remove(index);
+        self.persist(&operations);
+        Some(operation)
+    }
+
+    /// Removes and returns the operations that are due at the given point in time, ordered by the
+    /// point in time at which they have been scheduled.
+    pub fn take_due(&self, now: &DateTime<Utc>) -> Vec<ScheduledOperation> {
+        let mut operations = self.operations.lock().unwrap();
+        let (mut due, pending) = operations
+            .drain(..)
+            .partition::<Vec<_>, _>(|operation| &operation.run_at <= now);
+        *operations = pending;
+        if !due.is_empty() {
+            self.persist(&operations);
+        }
+
+        due.sort_by_key(|operation| operation.run_at);
+        due
+    }
+
+    /// Writes the operations to a temporary file first and replaces the file afterwards, so that
+    /// a crash while writing does not leave a corrupted file behind.
+    fn persist(&self, operations: &[ScheduledOperation]) {
+        let file = match &*self.file.lock().unwrap() {
+            Some(file) => file.clone(),
+            None => return,
+        };
+
+        let temp_file = file.with_extension("tmp");
+        let result = operations
+            .iter()
+            .map(PersistedOperation::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(failure::Error::from)
+            .and_then(|operations| {
+                let mut f = File::create(&temp_file)?;
+                f.write_all(serde_json::to_string(&operations)?.as_bytes())?;
+                f.sync_all()?;
+                Ok(())
+            })
+            .and_then(|_| rename(&temp_file, &file).map_err(failure::Error::from));
+
+        if let Err(err) = result {
+            error!(
+                "Cannot persist scheduled operations to {}: {}",
+                file.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn deletion(app_name: &str, run_at: DateTime<Utc>) -> ScheduledOperation {
+        ScheduledOperation::new(
+            AppName::from_str(app_name).unwrap(),
+            ScheduledChange::Deletion,
+            run_at,
+            None,
+        )
+    }
+
+    #[test]
+    fn should_take_due_operations_only() {
+        let operations = ScheduledOperations::load(None);
+        operations.schedule(deletion("later", Utc.ymd(2021, 7, 2).and_hms(22, 0, 0)));
+        operations.schedule(deletion("second", Utc.ymd(2021, 7, 1).and_hms(22, 0, 0)));
+        operations.schedule(deletion("first", Utc.ymd(2021, 7, 1).and_hms(20, 0, 0)));
+
+        let due = operations.take_due(&Utc.ymd(2021, 7, 1).and_hms(22, 0, 0));
+
+        assert_eq!(
+            due.iter()
+                .map(|operation| operation.app_name().to_string())
+                .collect::<Vec<_>>(),
+            vec![String::from("first"), String::from("second")]
+        );
+        assert_eq!(operations.operations().len(), 1);
+        assert!(operations
+            .take_due(&Utc.ymd(2021, 7, 1).and_hms(22, 0, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn should_cancel_operation() {
+        let operations = ScheduledOperations::load(None);
+        let operation = deletion("master", Utc.ymd(2021, 7, 1).and_hms(22, 0, 0));
+        let id = *operation.id();
+        operations.schedule(operation);
+
+        assert!(operations.cancel(&id).is_some());
+        assert!(operations.cancel(&id).is_none());
+        assert!(operations.operations().is_empty());
+    }
+
+    #[test]
+    fn should_restore_persisted_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("scheduled-operations.json");
+
+        let operations = ScheduledOperations::load(Some(&file));
+        let service_config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "nginx",
+            "env": { "BASE_URL": { "value": "/{{application.name}}", "templated": true } }
+        }))
+        .unwrap();
+        let companion = serde_json::from_value::<Companion>(serde_json::json!({
+            "serviceName": "mock",
+            "type": "application",
+            "image": "wiremock"
+        }))
+        .unwrap();
+        let deployment = ScheduledOperation::new(
+            AppName::from_str("master").unwrap(),
+            ScheduledChange::Deployment {
+                replicate_from: Some(AppName::from_str("release").unwrap()),
+                service_configs: vec![service_config.clone()],
+                user_defined_companions: vec![companion],
+                skipped_companions: vec![String::from("db")],
+            },
+            Utc.ymd(2021, 7, 1).and_hms(22, 0, 0),
+            Some(String::from("alice")),
+        );
+        let deployment_id = *deployment.id();
+        operations.schedule(deployment);
+        let deletion = deletion("branch", Utc.ymd(2021, 7, 2).and_hms(22, 0, 0));
+        let deletion_id = *deletion.id();
+        operations.schedule(deletion);
+        operations.cancel(&deletion_id);
+
+        let restored_operations = ScheduledOperations::load(Some(&file)).operations();
+
+        assert_eq!(restored_operations.len(), 1);
+        let restored_operation = &restored_operations[0];
+        assert_eq!(restored_operation.id(), &deployment_id);
+        assert_eq!(restored_operation.owner(), Some(&String::from("alice")));
+        match restored_operation.change() {
+            ScheduledChange::Deployment {
+                replicate_from,
+                service_configs,
+                user_defined_companions,
+                skipped_companions,
+            } => {
+                assert_eq!(replicate_from, &Some(AppName::from_str("release").unwrap()));
+                assert_eq!(service_configs, &vec![service_config]);
+                assert!(service_configs[0]
+                    .env()
+                    .and_then(|env| env.variable("BASE_URL"))
+                    .unwrap()
+                    .templated());
+                assert_eq!(user_defined_companions.len(), 1);
+                assert_eq!(skipped_companions, &vec![String::from("db")]);
+            }
+            ScheduledChange::Deletion => panic!("The deployment must be restored"),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{EmailConfig, NotificationSink};
+use crate::models::service::Service;
+use crate::models::ServiceBuilder;
+use chrono::{DateTime, Utc};
+use failure::Error;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use url::Url;
+use uuid::Uuid;
+
+/// Queues deliveries of deployment events to the configured notification sinks, e.g. webhooks,
+/// Slack, or email. Failed deliveries are retried with an exponential backoff and when all attempts
+/// failed, the delivery will be kept as dead letter so that administrators can inspect and
+/// redeliver them.
+pub struct WebhookDeliveries {
+    sinks: Mutex<Vec<NotificationSink>>,
+    base_url: Mutex<Option<Url>>,
+    initial_backoff: Duration,
+    sender: Mutex<Option<UnboundedSender<Delivery>>>,
+    dead_letters: Arc<Mutex<Vec<DeadLetter>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentEvent {
+    event: DeploymentEventKind,
+    app_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    services: Vec<Service>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeploymentEventKind {
+    Deployed,
+    DeploymentFailed,
+    Deleted,
+}
+
+#[derive(Clone, Debug)]
+struct Delivery {
+    id: Uuid,
+    sink: NotificationSink,
+    max_attempts: u32,
+    event: DeploymentEvent,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    id: Uuid,
+    /// The redacted URL of the sink, see [`NotificationSink::redacted_url`].
+    url: String,
+    #[serde(skip)]
+    sink: NotificationSink,
+    attempts: u32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+    event: DeploymentEvent,
+}
+
+impl DeploymentEvent {
+    pub fn deployed(app_name: &str, services: &[Service]) -> Self {
+        Self::new(DeploymentEventKind::Deployed, app_name, services, None)
+    }
+
+    pub fn deployment_failed(app_name: &str, error: String) -> Self {
+        Self::new(
+            DeploymentEventKind::DeploymentFailed,
+            app_name,
+            &[],
+            Some(error),
+        )
+    }
+
+    pub fn deleted(app_name: &str, services: &[Service]) -> Self {
+        Self::new(DeploymentEventKind::Deleted, app_name, services, None)
+    }
+
+    fn new(
+        event: DeploymentEventKind,
+        app_name: &str,
+        services: &[Service],
+        error: Option<String>,
+    ) -> Self {
+        DeploymentEvent {
+            event,
+            app_name: app_name.to_string(),
+            services: services.to_vec(),
+            error,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Adds the base URL to the services so that the event contains the URLs of the services.
+    fn with_base_url(mut self, base_url: &Url) -> Self {
+        self.services = self
+            .services
+            .into_iter()
+            .map(|service| {
+                ServiceBuilder::from(service.clone())
+                    .base_url(base_url.clone())
+                    .build()
+                    .unwrap_or(service)
+            })
+            .collect();
+        self
+    }
+
+    fn subject(&self) -> String {
+        match self.event {
+            DeploymentEventKind::Deployed => format!("{} has been deployed", self.app_name),
+            DeploymentEventKind::DeploymentFailed => {
+                format!("The deployment of {} failed", self.app_name)
+            }
+            DeploymentEventKind::Deleted => format!("{} has been deleted", self.app_name),
+        }
+    }
+
+    /// A human-readable message of the event for chat messages and emails.
+    fn message(&self) -> String {
+        match self.event {
+            DeploymentEventKind::Deployed => {
+                let mut message = format!("The review app {} is live:", self.app_name);
+                for service in &self.services {
+                    match service.service_url() {
+                        Some(url) => {
+                            message.push_str(&format!("\n- {}: {}", service.service_name(), url))
+                        }
+                        None => message.push_str(&format!("\n- {}", service.service_name())),
+                    }
+                }
+                message
+            }
+            DeploymentEventKind::DeploymentFailed => format!(
+                "The deployment of the review app {} failed: {}",
+                self.app_name,
+                self.error.as_deref().unwrap_or("unknown error")
+            ),
+            DeploymentEventKind::Deleted => {
+                format!("The review app {} has been deleted.", self.app_name)
+            }
+        }
+    }
+}
+
+impl DeadLetter {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+}
+
+impl WebhookDeliveries {
+    pub fn new(sinks: Vec<NotificationSink>, base_url: Option<Url>) -> Self {
+        Self::with_initial_backoff(sinks, base_url, Duration::from_secs(1))
+    }
+
+    fn with_initial_backoff(
+        sinks: Vec<NotificationSink>,
+        base_url: Option<Url>,
+        initial_backoff: Duration,
+    ) -> Self {
+        WebhookDeliveries {
+            sinks: Mutex::new(sinks),
+            base_url: Mutex::new(base_url),
+            initial_backoff,
+            sender: Mutex::new(None),
+            dead_letters: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Replaces the sinks, e.g. after the configuration has been reloaded. Queued deliveries and
+    /// dead letters are kept.
+    pub fn reconfigure(&self, sinks: Vec<NotificationSink>, base_url: Option<Url>) {
+        *self.sinks.lock().unwrap() = sinks;
+        *self.base_url.lock().unwrap() = base_url;
+    }
+
+    /// Queues the delivery of the event to all configured sinks.
+    pub fn notify(&self, event: DeploymentEvent) {
+        let event = match &*self.base_url.lock().unwrap() {
+            Some(base_url) => event.with_base_url(base_url),
+            None => event,
+        };
+
+        let sinks = self.sinks.lock().unwrap().clone();
+        for sink in &sinks {
+            self.enqueue(Delivery {
+                id: Uuid::new_v4(),
+                sink: sink.clone(),
+                max_attempts: sink.max_attempts(),
+                event: event.clone(),
+            });
+        }
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().unwrap().clone()
+    }
+
+    /// Removes the dead letter and queues it again with a fresh set of attempts. Returns `false` if
+    /// there is no dead letter for the given id.
+    pub fn redeliver(&self, id: &Uuid) -> bool {
+        let dead_letter = {
+            let mut dead_letters = self.dead_letters.lock().unwrap();
+            match dead_letters.iter().position(|dl| &dl.id == id) {
+                Some(index) => dead_letters.remove(index),
+                None => return false,
+            }
+        };
+
+        let max_attempts = self
+            .sinks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|sink| sink == &&dead_letter.sink)
+            .map(|sink| sink.max_attempts())
+            .unwrap_or(dead_letter.attempts);
+
+        self.enqueue(Delivery {
+            id: dead_letter.id,
+            sink: dead_letter.sink,
+            max_attempts,
+            event: dead_letter.event,
+        });
+        true
+    }
+
+    fn enqueue(&self, delivery: Delivery) {
+        let mut sender = self.sender.lock().unwrap();
+
+        // The worker is started with the first delivery so that there is no worker as long as
+        // no sink is configured.
+        let sender = sender.get_or_insert_with(|| {
+            let (sender, mut receiver) = unbounded_channel::<Delivery>();
+            let dead_letters = self.dead_letters.clone();
+            let initial_backoff = self.initial_backoff;
+            tokio::spawn(async move {
+                let client = reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .user_agent(format!("PREvant/{}", crate_version!()))
+                    .build()
+                    .expect("Should be able to create HTTP client");
+
+                while let Some(delivery) = receiver.recv().await {
+                    // Each delivery retries on its own so that a flaky receiver does not delay the
+                    // deliveries to other receivers.
+                    tokio::spawn(deliver(
+                        client.clone(),
+                        delivery,
+                        initial_backoff,
+                        dead_letters.clone(),
+                    ));
+                }
+            });
+            sender
+        });
+
+        if let Err(err) = sender.send(delivery) {
+            error!("Cannot queue notification delivery: {}", err);
+        }
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    delivery: Delivery,
+    initial_backoff: Duration,
+    dead_letters: Arc<Mutex<Vec<DeadLetter>>>,
+) {
+    let url = delivery.sink.redacted_url();
+    let mut attempt = 1;
+    loop {
+        let result = match &delivery.sink {
+            NotificationSink::Webhook(webhook) => {
+                post(&client, webhook.url(), &delivery.event).await
+            }
+            NotificationSink::Slack(webhook) => {
+                let message = serde_json::json!({ "text": delivery.event.message() });
+                post(&client, webhook.url(), &message).await
+            }
+            NotificationSink::Email(email) => send_email(email, &delivery.event).await,
+        };
+
+        let err = match result {
+            Ok(_) => {
+                debug!("Delivered {:?} to {}", delivery.event.event, url);
+                return;
+            }
+            Err(err) => err,
+        };
+
+        if attempt >= delivery.max_attempts {
+            warn!(
+                "Giving up delivering {:?} to {} after {} attempts: {}",
+                delivery.event.event, url, attempt, err
+            );
+            dead_letters.lock().unwrap().push(DeadLetter {
+                id: delivery.id,
+                url,
+                sink: delivery.sink,
+                attempts: attempt,
+                last_error: err.to_string(),
+                failed_at: Utc::now(),
+                event: delivery.event,
+            });
+            return;
+        }
+
+        let backoff = backoff(initial_backoff, attempt);
+        debug!(
+            "Cannot deliver {:?} to {} (attempt {}), retrying in {:?}: {}",
+            delivery.event.event, url, attempt, backoff, err
+        );
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+async fn post<T: Serialize>(client: &reqwest::Client, url: &Url, payload: &T) -> Result<(), Error> {
+    // The URL is stripped from the errors because they end up in logs and in the dead letters.
+    client
+        .post(url.clone())
+        .json(payload)
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)?
+        .error_for_status()
+        .map_err(reqwest::Error::without_url)?;
+    Ok(())
+}
+
+async fn send_email(email: &EmailConfig, event: &DeploymentEvent) -> Result<(), Error> {
+    let mut message = Message::builder()
+        .from(email.from().parse()?)
+        .subject(format!("[PREvant] {}", event.subject()));
+    for to in email.to() {
+        message = message.to(to.parse()?);
+    }
+    let message = message.body(event.message())?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(email.smtp_host())?
+        .port(email.smtp_port());
+    if let Some((username, password)) = email.credentials() {
+        transport = transport.credentials(Credentials::new(
+            username.clone(),
+            password.unsecure().to_string(),
+        ));
+    }
+
+    transport.build().send(message).await?;
+    Ok(())
+}
+
+/// Doubles the backoff with each attempt but waits at most five minutes.
+fn backoff(initial_backoff: Duration, attempt: u32) -> Duration {
+    let max_backoff = Duration::from_secs(300);
+    initial_backoff
+        .checked_mul(2u32.saturating_pow(attempt - 1))
+        .map_or(max_backoff, |backoff| backoff.min(max_backoff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_double_backoff() {
+        let initial_backoff = Duration::from_secs(1);
+
+        assert_eq!(backoff(initial_backoff, 1), Duration::from_secs(1));
+        assert_eq!(backoff(initial_backoff, 2), Duration::from_secs(2));
+        assert_eq!(backoff(initial_backoff, 4), Duration::from_secs(8));
+        assert_eq!(backoff(initial_backoff, 32), Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn should_move_failed_delivery_to_dead_letters() {
+        let sinks = vec![toml::de::from_str::<NotificationSink>(
+            r#"
+            type = "webhook"
+            url = "http://127.0.0.1:1/unreachable"
+            maxAttempts = 2
+            "#,
+        )
+        .unwrap()];
+        let deliveries =
+            WebhookDeliveries::with_initial_backoff(sinks, None, Duration::from_millis(1));
+
+        deliveries.notify(DeploymentEvent::deleted("master", &[]));
+
+        let mut dead_letters = Vec::new();
+        for _ in 0..100 {
+            dead_letters = deliveries.dead_letters();
+            if !dead_letters.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 2);
+        assert_eq!(dead_letters[0].event.event, DeploymentEventKind::Deleted);
+        assert_eq!(dead_letters[0].url, "http://127.0.0.1:1");
+        assert!(!dead_letters[0].last_error.contains("/unreachable"));
+    }
+
+    #[test]
+    fn should_describe_deployment_failure() {
+        let event = DeploymentEvent::deployment_failed("master", String::from("Image not found"));
+
+        assert_eq!(event.subject(), "The deployment of master failed");
+        assert_eq!(
+            event.message(),
+            "The deployment of the review app master failed: Image not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_redeliver_unknown_dead_letter() {
+        let deliveries = WebhookDeliveries::new(Vec::new(), None);
+
+        assert!(!deliveries.redeliver(&Uuid::new_v4()));
+    }
+}
@@ -25,7 +25,6 @@
  */
 
 use crate::apps::Apps;
-use crate::config::Config;
 use crate::http_result::{HttpApiError, HttpResult};
 use crate::models::ticket_info::TicketInfo;
 use goji::Error as GojiError;
@@ -33,20 +32,20 @@ use goji::{Credentials, Jira, SearchOptions};
 use http_api_problem::{HttpApiProblem, StatusCode};
 use rocket::serde::json::Json;
 use rocket::State;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::convert::From;
 use std::sync::Arc;
 
-/// Analyzes running containers and returns a map of `review-app-name` with the
-/// corresponding `TicketInfo`.
+/// Analyzes running containers and returns a map of ticket keys, i.e. the tickets the apps are
+/// linked to and the app names themselves, with the corresponding `TicketInfo`.
 #[get("/apps/tickets", format = "application/json")]
 pub async fn tickets(
-    config_state: &State<Config>,
     apps_service: &State<Arc<Apps>>,
 ) -> HttpResult<Json<HashMap<String, TicketInfo>>> {
     let mut tickets: HashMap<String, TicketInfo> = HashMap::new();
 
-    match config_state.jira_config() {
+    let config = apps_service.config();
+    match config.jira_config() {
         None => {
             return Err(ListTicketsError::MissingIssueTrackingConfiguration.into());
         }
@@ -62,9 +61,15 @@ pub async fn tickets(
                 Err(e) => return Err(ListTicketsError::from(e).into()),
             };
 
-            let issue_keys = services
-                .keys()
-                .map(|s| format!("{:?}", s))
+            let mut keys = BTreeSet::new();
+            for (app_name, services) in services.iter_all() {
+                keys.insert(app_name.clone());
+                keys.extend(services.iter().flat_map(|s| s.tickets()).cloned());
+            }
+
+            let issue_keys = keys
+                .iter()
+                .map(|key| format!("{:?}", key))
                 .collect::<Vec<String>>()
                 .join(", ");
 
@@ -26,20 +26,27 @@
 
 use crate::apps::delete_app_sync;
 use crate::apps::Apps;
+use crate::auth::{AuthenticationError, User};
 use crate::http_result::HttpResult;
 use crate::models::service::Service;
 use crate::models::web_hook_info::WebHookInfo;
 use crate::models::AppName;
+use crate::services::webhook_deliveries::DeadLetter;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::State;
 use std::str::FromStr;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[post("/webhooks", format = "application/json", data = "<web_hook_info>")]
 pub async fn webhooks(
     apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
     web_hook_info: WebHookInfo,
 ) -> HttpResult<Json<Vec<Service>>> {
+    let user = user?.name().cloned();
     info!(
         "Deleting app {:?} through web hook {:?} with event {:?}",
         web_hook_info.get_app_name(),
@@ -48,5 +55,119 @@ pub async fn webhooks(
     );
 
     let app_name = AppName::from_str(&web_hook_info.get_app_name());
-    delete_app_sync(app_name, apps).await
+    delete_app_sync(app_name, apps, user).await
+}
+
+/// Lists the deliveries of deployment events that failed permanently.
+#[get("/webhooks/dead-letters", format = "application/json")]
+pub async fn dead_letters(
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Json<Vec<DeadLetter>>> {
+    user?;
+    Ok(Json(apps.webhook_deliveries().dead_letters()))
+}
+
+/// Queues a dead letter again for delivery.
+#[post("/webhooks/dead-letters/<id>/redeliver")]
+pub async fn redeliver_dead_letter(
+    id: String,
+    apps: &State<Arc<Apps>>,
+    user: Result<User, AuthenticationError>,
+) -> HttpResult<Status> {
+    user?;
+    let id = Uuid::from_str(&id).map_err(|err| {
+        HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(format!("{}", err))
+    })?;
+
+    if apps.webhook_deliveries().redeliver(&id) {
+        Ok(Status::Accepted)
+    } else {
+        Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!("There is no dead letter with id {}", id))
+            .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use rocket::http::ContentType;
+    use rocket::local::asynchronous::Client;
+
+    #[tokio::test]
+    async fn should_not_delete_app_through_webhook_without_token() {
+        let config = crate::config_from_str!(
+            r#"
+            [[authentication.tokens]]
+            name = "ci"
+            token = "s3cr3t"
+            "#
+        );
+        let apps = Apps::new(config, Box::new(Dummy::new())).unwrap();
+        let rocket = rocket::build()
+            .manage(Arc::new(apps))
+            .mount("/api", routes![webhooks]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client
+            .post("/api/webhooks")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!({
+                    "eventKey": "pr:merged",
+                    "pullRequest": {
+                        "title": "Some feature",
+                        "fromRef": { "displayId": "PREVANT-1" }
+                    }
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_record_user_of_webhook_in_audit_log() {
+        let config = crate::config_from_str!(
+            r#"
+            [[authentication.tokens]]
+            name = "ci"
+            token = "s3cr3t"
+            "#
+        );
+        let apps = Arc::new(Apps::new(config, Box::new(Dummy::new())).unwrap());
+        let rocket = rocket::build()
+            .manage(apps.clone())
+            .mount("/api", routes![webhooks]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        client
+            .post("/api/webhooks")
+            .header(ContentType::JSON)
+            .header(rocket::http::Header::new("Authorization", "Bearer s3cr3t"))
+            .body(
+                serde_json::json!({
+                    "eventKey": "pr:merged",
+                    "pullRequest": {
+                        "title": "Some feature",
+                        "fromRef": { "displayId": "PREVANT-1" }
+                    }
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+
+        let entries = apps.audit_log().entries(Some("PREVANT-1"), None, None);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&entries[0]).unwrap()["user"],
+            serde_json::json!("ci")
+        );
+    }
 }
@@ -0,0 +1,186 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant Client
+ * %%
+ * Copyright (C) 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::{Capabilities, Deployment, Service, ServiceStatus};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use url::Url;
+
+/// An asynchronous client for PREvant's REST API.
+#[derive(Clone, Debug)]
+pub struct Client {
+    base_url: Url,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// Creates a client for the PREvant instance at `base_url`, e.g. `http://localhost:8080`.
+    pub fn new(base_url: Url) -> Self {
+        Client {
+            base_url,
+            token: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Sets the bearer token that will be sent with every request. The token is required if
+    /// authentication is enabled on the server.
+    pub fn with_token<T: Into<String>>(mut self, token: T) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Returns a map of app names and their services.
+    pub async fn apps(&self) -> Result<HashMap<String, Vec<Service>>, ClientError> {
+        self.json(self.http.get(self.url("/api/apps/")?)).await
+    }
+
+    /// Creates or updates the app and waits until the deployment has been finished.
+    pub async fn create_app(
+        &self,
+        app_name: &str,
+        deployment: &Deployment,
+    ) -> Result<Vec<Service>, ClientError> {
+        let request = self
+            .http
+            .post(self.url(&format!("/api/apps/{}", app_name))?)
+            .json(deployment);
+        self.json(request).await
+    }
+
+    /// Creates or updates the app by replicating the services of `replicate_from`.
+    pub async fn replicate_app(
+        &self,
+        app_name: &str,
+        replicate_from: &str,
+        deployment: &Deployment,
+    ) -> Result<Vec<Service>, ClientError> {
+        let mut url = self.url(&format!("/api/apps/{}", app_name))?;
+        url.query_pairs_mut()
+            .append_pair("replicateFrom", replicate_from);
+        self.json(self.http.post(url).json(deployment)).await
+    }
+
+    /// Deletes the app and waits until all services have been stopped.
+    pub async fn delete_app(&self, app_name: &str) -> Result<Vec<Service>, ClientError> {
+        let request = self
+            .http
+            .delete(self.url(&format!("/api/apps/{}", app_name))?);
+        self.json(request).await
+    }
+
+    /// Returns the logs of the given service.
+    pub async fn logs(&self, app_name: &str, service_name: &str) -> Result<String, ClientError> {
+        let request = self
+            .http
+            .get(self.url(&format!("/api/apps/{}/logs/{}", app_name, service_name))?)
+            .header("Accept", "text/plain");
+        Ok(self.send(request).await?.text().await?)
+    }
+
+    /// Starts or pauses the given service.
+    pub async fn change_status(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        status: ServiceStatus,
+    ) -> Result<(), ClientError> {
+        let request = self
+            .http
+            .put(self.url(&format!("/api/apps/{}/states/{}", app_name, service_name))?)
+            .json(&serde_json::json!({ "status": status }));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Returns the features that the infrastructure backend of the server supports.
+    pub async fn capabilities(&self) -> Result<Capabilities, ClientError> {
+        self.json(self.http.get(self.url("/api/infrastructure/capabilities")?))
+            .await
+    }
+
+    fn url(&self, path: &str) -> Result<Url, ClientError> {
+        self.base_url
+            .join(path)
+            .map_err(|err| ClientError::InvalidUrl {
+                err: err.to_string(),
+            })
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let request = request.header("Accept", "application/json");
+        Ok(self.send(request).await?.json::<T>().await?)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+
+        let response = request.send().await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let detail = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|problem| {
+                problem
+                    .get("detail")
+                    .or_else(|| problem.get("title"))
+                    .and_then(|detail| detail.as_str().map(String::from))
+            });
+
+        Err(ClientError::Problem { status, detail })
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    #[fail(display = "Invalid URL: {}", err)]
+    InvalidUrl { err: String },
+    #[fail(display = "Cannot communicate with PREvant: {}", err)]
+    Http { err: String },
+    #[fail(display = "PREvant responded with {}: {:?}", status, detail)]
+    Problem {
+        status: StatusCode,
+        detail: Option<String>,
+    },
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        ClientError::Http {
+            err: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,713 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant Client
+ * %%
+ * Copyright (C) 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! Models of PREvant's REST API. They mirror the JSON payloads described in the OpenAPI
+//! specification.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use url::Url;
+
+/// The configuration of a service that will be deployed as part of an application.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceConfig {
+    service_name: String,
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<BTreeMap<String, EnvironmentVariable>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volumes: Option<BTreeMap<PathBuf, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depends_on: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ports: Option<Vec<Port>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routing: Option<Routing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<Vec<String>>,
+}
+
+/// The server responds with the plain values of the environment variables whereas requests may
+/// contain the detailed form.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "EnvironmentVariableValue")]
+pub struct EnvironmentVariable {
+    value: String,
+    templated: bool,
+    replicate: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EnvironmentVariableValue {
+    Plain(String),
+    Detailed {
+        value: String,
+        #[serde(default)]
+        templated: bool,
+        #[serde(default)]
+        replicate: bool,
+    },
+}
+
+impl From<EnvironmentVariableValue> for EnvironmentVariable {
+    fn from(value: EnvironmentVariableValue) -> Self {
+        match value {
+            EnvironmentVariableValue::Plain(value) => EnvironmentVariable::new(value),
+            EnvironmentVariableValue::Detailed {
+                value,
+                templated,
+                replicate,
+            } => EnvironmentVariable {
+                value,
+                templated,
+                replicate,
+            },
+        }
+    }
+}
+
+/// A port that the service listens on. The port named `http` receives the routed requests.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Port {
+    name: String,
+    port: u16,
+}
+
+impl Port {
+    pub fn new<N: Into<String>>(name: N, port: u16) -> Self {
+        Port {
+            name: name.into(),
+            port,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Defines how the requests are routed to the service. The placeholders `{app}` and `{service}`
+/// are replaced by the names of the app and the service.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Routing {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strip_prefix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    middlewares: Option<BTreeMap<String, Value>>,
+}
+
+impl Routing {
+    pub fn with_path<P: Into<String>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_host<H: Into<String>>(mut self, host: H) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn with_strip_prefix(mut self, strip_prefix: bool) -> Self {
+        self.strip_prefix = Some(strip_prefix);
+        self
+    }
+
+    pub fn with_middleware<K: Into<String>>(mut self, name: K, middleware: Value) -> Self {
+        self.middlewares
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.into(), middleware);
+        self
+    }
+
+    pub fn path(&self) -> Option<&String> {
+        self.path.as_ref()
+    }
+
+    pub fn host(&self) -> Option<&String> {
+        self.host.as_ref()
+    }
+
+    pub fn strip_prefix(&self) -> Option<bool> {
+        self.strip_prefix
+    }
+
+    pub fn middlewares(&self) -> Option<&BTreeMap<String, Value>> {
+        self.middlewares.as_ref()
+    }
+}
+
+impl ServiceConfig {
+    pub fn new<N: Into<String>, I: Into<String>>(service_name: N, image: I) -> Self {
+        ServiceConfig {
+            service_name: service_name.into(),
+            image: image.into(),
+            env: None,
+            volumes: None,
+            labels: None,
+            depends_on: None,
+            ports: None,
+            routing: None,
+            command: None,
+            entrypoint: None,
+        }
+    }
+
+    pub fn service_name(&self) -> &String {
+        &self.service_name
+    }
+
+    pub fn image(&self) -> &String {
+        &self.image
+    }
+
+    pub fn env(&self) -> Option<&BTreeMap<String, EnvironmentVariable>> {
+        self.env.as_ref()
+    }
+
+    pub fn volumes(&self) -> Option<&BTreeMap<PathBuf, String>> {
+        self.volumes.as_ref()
+    }
+
+    pub fn labels(&self) -> Option<&BTreeMap<String, String>> {
+        self.labels.as_ref()
+    }
+
+    pub fn depends_on(&self) -> Option<&Vec<String>> {
+        self.depends_on.as_ref()
+    }
+
+    pub fn ports(&self) -> Option<&Vec<Port>> {
+        self.ports.as_ref()
+    }
+
+    pub fn routing(&self) -> Option<&Routing> {
+        self.routing.as_ref()
+    }
+
+    pub fn command(&self) -> Option<&Vec<String>> {
+        self.command.as_ref()
+    }
+
+    pub fn entrypoint(&self) -> Option<&Vec<String>> {
+        self.entrypoint.as_ref()
+    }
+
+    pub fn with_env<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        self.with_env_variable(key, EnvironmentVariable::new(value.into()))
+    }
+
+    /// Adds an environment variable that will be copied when the application is replicated.
+    pub fn with_replicated_env<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        self.with_env_variable(key, EnvironmentVariable::new(value.into()).replicated())
+    }
+
+    pub fn with_env_variable<K: Into<String>>(
+        mut self,
+        key: K,
+        variable: EnvironmentVariable,
+    ) -> Self {
+        self.env
+            .get_or_insert_with(BTreeMap::new)
+            .insert(key.into(), variable);
+        self
+    }
+
+    /// Adds a file with the given content that will be mounted into the container.
+    pub fn with_file<P: Into<PathBuf>, C: Into<String>>(mut self, path: P, content: C) -> Self {
+        self.volumes
+            .get_or_insert_with(BTreeMap::new)
+            .insert(path.into(), content.into());
+        self
+    }
+
+    /// Adds a label to the container of the service (on Kubernetes, a pod annotation).
+    pub fn with_label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.labels
+            .get_or_insert_with(BTreeMap::new)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Declares that the given service of the same deployment has to be ready before this service
+    /// will be started.
+    pub fn with_dependency<S: Into<String>>(mut self, service_name: S) -> Self {
+        self.depends_on
+            .get_or_insert_with(Vec::new)
+            .push(service_name.into());
+        self
+    }
+
+    pub fn with_port(mut self, port: Port) -> Self {
+        self.ports.get_or_insert_with(Vec::new).push(port);
+        self
+    }
+
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = Some(routing);
+        self
+    }
+
+    pub fn with_command(mut self, command: Vec<String>) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    pub fn with_entrypoint(mut self, entrypoint: Vec<String>) -> Self {
+        self.entrypoint = Some(entrypoint);
+        self
+    }
+}
+
+impl EnvironmentVariable {
+    pub fn new(value: String) -> Self {
+        EnvironmentVariable {
+            value,
+            templated: false,
+            replicate: false,
+        }
+    }
+
+    pub fn templated(mut self) -> Self {
+        self.templated = true;
+        self
+    }
+
+    pub fn replicated(mut self) -> Self {
+        self.replicate = true;
+        self
+    }
+
+    pub fn value(&self) -> &String {
+        &self.value
+    }
+
+    pub fn is_templated(&self) -> bool {
+        self.templated
+    }
+
+    pub fn is_replicated(&self) -> bool {
+        self.replicate
+    }
+}
+
+/// The payload of a deployment request: the services of the application and optionally
+/// companions that will be deployed only for this application.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Deployment {
+    services: Vec<ServiceConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    companions: BTreeMap<String, Companion>,
+}
+
+impl Deployment {
+    pub fn new(services: Vec<ServiceConfig>) -> Self {
+        Deployment {
+            services,
+            companions: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_companion<K: Into<String>>(mut self, key: K, companion: Companion) -> Self {
+        self.companions.insert(key.into(), companion);
+        self
+    }
+
+    pub fn services(&self) -> &Vec<ServiceConfig> {
+        &self.services
+    }
+
+    pub fn companions(&self) -> &BTreeMap<String, Companion> {
+        &self.companions
+    }
+}
+
+/// A user-defined companion. The service name, environment variables, and files may contain
+/// handlebars templates that will be rendered by PREvant.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Companion {
+    service_name: String,
+    #[serde(rename = "type")]
+    companion_type: CompanionType,
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volumes: Option<BTreeMap<PathBuf, String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CompanionType {
+    #[serde(rename = "application")]
+    Application,
+    #[serde(rename = "service")]
+    Service,
+}
+
+impl Companion {
+    pub fn new<N: Into<String>, I: Into<String>>(
+        service_name: N,
+        companion_type: CompanionType,
+        image: I,
+    ) -> Self {
+        Companion {
+            service_name: service_name.into(),
+            companion_type,
+            image: image.into(),
+            env: None,
+            volumes: None,
+        }
+    }
+
+    /// Adds an environment variable in the form `KEY=value`.
+    pub fn with_env<K: Display, V: Display>(mut self, key: K, value: V) -> Self {
+        self.env
+            .get_or_insert_with(Vec::new)
+            .push(format!("{}={}", key, value));
+        self
+    }
+
+    pub fn with_file<P: Into<PathBuf>, C: Into<String>>(mut self, path: P, content: C) -> Self {
+        self.volumes
+            .get_or_insert_with(BTreeMap::new)
+            .insert(path.into(), content.into());
+        self
+    }
+}
+
+/// A service that is running as part of an application.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<Url>,
+    #[serde(rename = "type")]
+    container_type: ContainerType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    open_api_url: Option<Url>,
+    state: State,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tickets: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_restart: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    basic_auth: Option<BasicAuthCredentials>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ContainerType {
+    #[serde(rename = "instance")]
+    Instance,
+    #[serde(rename = "replica")]
+    Replica,
+    #[serde(rename = "app-companion")]
+    ApplicationCompanion,
+    #[serde(rename = "service-companion")]
+    ServiceCompanion,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    software_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct State {
+    status: ServiceStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<u64>,
+}
+
+/// The generated credentials that protect the routes of an app. They are only part of the
+/// response of the deployment that generated them.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BasicAuthCredentials {
+    username: String,
+    password: String,
+}
+
+impl BasicAuthCredentials {
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+
+    pub fn password(&self) -> &String {
+        &self.password
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServiceStatus {
+    Running,
+    Paused,
+}
+
+impl Service {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    pub fn container_type(&self) -> &ContainerType {
+        &self.container_type
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    pub fn open_api_url(&self) -> Option<&Url> {
+        self.open_api_url.as_ref()
+    }
+
+    pub fn status(&self) -> &ServiceStatus {
+        &self.state.status
+    }
+
+    /// The user who deployed the service, if authentication is enabled on the server.
+    pub fn owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// How often the service has been restarted. Only provided on Docker.
+    pub fn restart_count(&self) -> Option<u64> {
+        self.state.restart_count
+    }
+
+    /// The exit code of the last run of the service. Only provided on Docker.
+    pub fn exit_code(&self) -> Option<u64> {
+        self.state.exit_code
+    }
+
+    /// The keys of the tickets the app is linked to.
+    pub fn tickets(&self) -> &Vec<String> {
+        &self.tickets
+    }
+
+    /// The next scheduled restart of the service, if a restart schedule applies to it.
+    pub fn next_restart(&self) -> Option<&DateTime<Utc>> {
+        self.next_restart.as_ref()
+    }
+
+    /// Returns `true` if the replica's source service has been redeployed with a different image
+    /// since the replication.
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// The generated credentials of the app, only present in the response of the deployment that
+    /// generated them.
+    pub fn basic_auth(&self) -> Option<&BasicAuthCredentials> {
+        self.basic_auth.as_ref()
+    }
+}
+
+impl Version {
+    pub fn git_commit(&self) -> Option<&String> {
+        self.git_commit.as_ref()
+    }
+
+    pub fn software_version(&self) -> Option<&String> {
+        self.software_version.as_ref()
+    }
+
+    pub fn date_modified(&self) -> Option<&DateTime<Utc>> {
+        self.date_modified.as_ref()
+    }
+}
+
+/// The features that the infrastructure backend of the server supports.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    volumes: bool,
+    exec: bool,
+    tcp_routing: bool,
+    replicas: bool,
+}
+
+impl Capabilities {
+    pub fn volumes(&self) -> bool {
+        self.volumes
+    }
+
+    pub fn exec(&self) -> bool {
+        self.exec
+    }
+
+    pub fn tcp_routing(&self) -> bool {
+        self.tcp_routing
+    }
+
+    pub fn replicas(&self) -> bool {
+        self.replicas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn should_serialize_service_config() {
+        let config = ServiceConfig::new("db", "mariadb:10.3.17")
+            .with_replicated_env("MYSQL_RANDOM_ROOT_PASSWORD", "yes")
+            .with_file("/etc/mysql/my.cnf", "[client-server]");
+
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "serviceName": "db",
+                "image": "mariadb:10.3.17",
+                "env": {
+                    "MYSQL_RANDOM_ROOT_PASSWORD": {
+                        "value": "yes",
+                        "templated": false,
+                        "replicate": true
+                    }
+                },
+                "volumes": {
+                    "/etc/mysql/my.cnf": "[client-server]"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn should_serialize_deployment_with_companions() {
+        let deployment = Deployment::new(vec![ServiceConfig::new("nginx", "nginx:alpine")])
+            .with_companion(
+                "mock",
+                Companion::new(
+                    "{{service.name}}-mock",
+                    CompanionType::Service,
+                    "mockserver/mockserver:latest",
+                )
+                .with_env("SERVICE", "{{service.name}}"),
+            );
+
+        assert_eq!(
+            serde_json::to_value(&deployment).unwrap(),
+            json!({
+                "services": [{ "serviceName": "nginx", "image": "nginx:alpine" }],
+                "companions": {
+                    "mock": {
+                        "serviceName": "{{service.name}}-mock",
+                        "type": "service",
+                        "image": "mockserver/mockserver:latest",
+                        "env": [ "SERVICE={{service.name}}" ]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn should_deserialize_plain_environment_variable() {
+        let config = serde_json::from_value::<ServiceConfig>(json!({
+            "serviceName": "db",
+            "image": "mariadb:10.3.17",
+            "env": { "MYSQL_RANDOM_ROOT_PASSWORD": "yes" }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config
+                .env()
+                .and_then(|env| env.get("MYSQL_RANDOM_ROOT_PASSWORD"))
+                .map(|variable| variable.value()),
+            Some(&String::from("yes"))
+        );
+    }
+
+    #[test]
+    fn should_deserialize_service() {
+        let service = serde_json::from_value::<Service>(json!({
+            "name": "nginx",
+            "url": "http://localhost/master/nginx/",
+            "type": "instance",
+            "version": {
+                "gitCommit": "43de4c6edf3c7ed93cdf8983f1ea7d73115176cc"
+            },
+            "state": { "status": "running" },
+            "owner": "john.doe"
+        }))
+        .unwrap();
+
+        assert_eq!(service.name(), "nginx");
+        assert_eq!(
+            service.url(),
+            Some(&Url::parse("http://localhost/master/nginx/").unwrap())
+        );
+        assert_eq!(service.container_type(), &ContainerType::Instance);
+        assert_eq!(
+            service.version().and_then(|v| v.git_commit()),
+            Some(&String::from("43de4c6edf3c7ed93cdf8983f1ea7d73115176cc"))
+        );
+        assert_eq!(service.status(), &ServiceStatus::Running);
+        assert_eq!(service.owner(), Some(&String::from("john.doe")));
+    }
+}